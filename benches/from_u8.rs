@@ -1,4 +1,5 @@
-#![feature(test)]
+#![cfg_attr(feature="nightly", feature(test))]
+#[cfg(feature="nightly")]
 mod from_u8 {
     extern crate test;
     use self::test::Bencher;
@@ -1,18 +1,10 @@
-#![feature(test)]
+#![cfg_attr(feature="nightly", feature(test))]
+#[cfg(feature="nightly")]
 mod match_u8 {
     extern crate test;
     use self::test::Bencher;
     extern crate tree_magic;
 
-    #[cfg(not(feature="staticmime"))]
-    macro_rules! convmime {
-        ($x:expr) => {$x.to_string()}
-    }
-    #[cfg(feature="staticmime")]
-    macro_rules! convmime {
-        ($x:expr) => {$x}
-    }
-
     ///Image benchmarks
     #[bench]
     fn image_gif(b: &mut Bencher) {
@@ -0,0 +1,60 @@
+#ifndef TREE_MAGIC_H
+#define TREE_MAGIC_H
+
+/* Generated with cbindgen from src/ffi.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Finds the MIME type of a byte buffer.
+//
+// Returns a new string, or NULL if `bytes` is NULL and `len` isn't 0.
+//
+// # Safety
+// `bytes` must point to at least `len` readable bytes, or be NULL.
+char *tree_magic_from_u8(const uint8_t *bytes, size_t len);
+
+// Finds the MIME type of a file.
+//
+// Returns a new string, or NULL if `path` is NULL or the file can't be
+// read.
+//
+// # Safety
+// `path` must be a NUL-terminated string, or NULL.
+char *tree_magic_from_path(const char *path);
+
+// Checks if a byte buffer is of the given MIME type.
+//
+// Returns false if either pointer is NULL, or `mimetype` isn't UTF-8.
+//
+// # Safety
+// `mimetype` must be a NUL-terminated string, or NULL. `bytes` must point
+// to at least `len` readable bytes, or be NULL.
+bool tree_magic_match(const char *mimetype, const uint8_t *bytes, size_t len);
+
+// Checks if a file is of the given MIME type.
+//
+// Returns false if either pointer is NULL, or `mimetype` isn't UTF-8.
+//
+// # Safety
+// `mimetype` and `path` must be NUL-terminated strings, or NULL.
+bool tree_magic_match_path(const char *mimetype, const char *path);
+
+// Frees a string returned by this library. Passing NULL does nothing.
+//
+// # Safety
+// `s` must have come from this library, and must not be used afterwards.
+void tree_magic_free(char *s);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* TREE_MAGIC_H */
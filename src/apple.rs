@@ -0,0 +1,99 @@
+//! Handles Apple property lists
+
+/// Dummy cache type
+pub type Cache = bool;
+
+pub mod init {
+
+    use FnvHashMap;
+    use MIME;
+    use std::prelude::v1::*;
+
+    pub fn get_supported() -> Vec<MIME> {
+        vec![
+            MIME::from("application/x-bplist"),
+            MIME::from("application/x-plist")
+        ]
+    }
+
+    /// Returns Vec of parent->child relations
+    pub fn get_subclasses() -> Vec<(MIME, MIME)> {
+        vec![
+            (MIME::from("application/octet-stream"), MIME::from("application/x-bplist")),
+            (MIME::from("application/xml"), MIME::from("application/x-plist"))
+        ]
+    }
+
+    pub fn get_aliaslist() -> FnvHashMap<MIME, MIME> {
+        FnvHashMap::default()
+    }
+
+}
+
+pub mod check {
+
+    use std::prelude::v1::*;
+    #[cfg(feature="fs")]
+    use std::path::Path;
+    use super::super::CacheItem;
+    #[cfg(feature="fs")]
+    use super::super::slurp_to_cache;
+
+    /// Finds the name of the root element of an XML document, skipping
+    /// the byte order mark, XML declaration, processing instructions,
+    /// comments and doctype that may come before it.
+    fn xml_root(b: &[u8]) -> Option<&[u8]> {
+        let mut b = if b.starts_with(b"\xef\xbb\xbf") {&b[3..]} else {b};
+
+        loop {
+            let start = b.iter().position(|x| !x.is_ascii_whitespace())?;
+            b = &b[start..];
+            if b.first() != Some(&b'<') {
+                return None;
+            }
+
+            let close: &[u8] = if b.starts_with(b"<?") {
+                b"?>"
+            } else if b.starts_with(b"<!--") {
+                b"-->"
+            } else if b.starts_with(b"<!") {
+                b">"
+            } else {
+                let name = &b[1..];
+                let len = name.iter()
+                    .position(|x| x.is_ascii_whitespace() || *x == b'>' || *x == b'/')
+                    .unwrap_or(name.len());
+                return match len {
+                    0 => None,
+                    _ => Some(&name[..len])
+                };
+            };
+
+            let end = b.windows(close.len()).position(|x| x == close)?;
+            b = &b[end + close.len()..];
+        }
+    }
+
+    #[allow(unused_variables)]
+    pub fn from_u8(
+        b: &[u8], mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        match mimetype {
+            "application/x-bplist" => b.starts_with(b"bplist00"),
+            "application/x-plist" => xml_root(b) == Some(b"plist"),
+            _ => false
+        }
+    }
+
+    #[cfg(feature="fs")]
+    #[allow(unused_variables)]
+    pub fn from_filepath(
+        filepath: &Path, mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        let b = match slurp_to_cache(filepath, filecache, 2048) {
+            Ok(x) => x,
+            Err(_) => return false
+        };
+        from_u8(b.as_slice(), mimetype, cache, filecache)
+    }
+}
@@ -0,0 +1,107 @@
+//! Handles ZIP archives, found by the record at their end
+//!
+//! A ZIP archive is read from its end of central directory record, so
+//! anything can come before the archive, such as the program of a
+//! self-extracting one. Magic only finds archives that start with a local
+//! file header.
+
+/// Dummy cache type
+pub type Cache = bool;
+
+pub mod init {
+
+    use FnvHashMap;
+    use MIME;
+    use std::prelude::v1::*;
+
+    pub fn get_supported() -> Vec<MIME> {
+        vec![MIME::from("application/zip")]
+    }
+
+    /// Returns Vec of parent->child relations
+    pub fn get_subclasses() -> Vec<(MIME, MIME)> {
+        vec![(MIME::from("application/octet-stream"), MIME::from("application/zip"))]
+    }
+
+    pub fn get_aliaslist() -> FnvHashMap<MIME, MIME> {
+        FnvHashMap::default()
+    }
+
+}
+
+pub mod check {
+
+    use std;
+    use std::prelude::v1::*;
+    #[cfg(feature="fs")]
+    use std::path::Path;
+    use super::super::{CacheItem, input_tail};
+    #[cfg(feature="fs")]
+    use super::super::slurp_to_cache;
+
+    /// Signature of a local file header, which most archives start with
+    const LOCAL_HEADER: &[u8] = b"PK\x03\x04";
+    /// Signature of the end of central directory record
+    const EOCD: &[u8] = b"PK\x05\x06";
+    /// Length of the end of central directory record, without its comment
+    const EOCD_LEN: usize = 22;
+
+    fn read_u16_le(b: &[u8]) -> u16 {
+        b[0] as u16 | (b[1] as u16) << 8
+    }
+
+    fn read_u32_le(b: &[u8]) -> u32 {
+        b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24
+    }
+
+    /// Whether the input ends in an end of central directory record: its
+    /// signature, then a comment that runs exactly to the end, no more
+    /// entries on this disk than in total, and a central directory that
+    /// fits before the record. `tail` is the end of an input `len` long.
+    fn has_eocd(tail: &[u8], len: u64) -> bool {
+        if tail.len() < EOCD_LEN {
+            return false;
+        }
+        let longest = std::cmp::min(tail.len() - EOCD_LEN, 0xffff);
+        (0..=longest).any(|comment| {
+            let pos = tail.len() - EOCD_LEN - comment;
+            let x = &tail[pos..pos + EOCD_LEN];
+            if &x[..4] != EOCD || read_u16_le(&x[20..22]) as usize != comment {
+                return false;
+            }
+            // ZIP64 archives keep the real counts and sizes elsewhere
+            let (size, offset) = (read_u32_le(&x[12..16]), read_u32_le(&x[16..20]));
+            if size == 0xffffffff || offset == 0xffffffff {
+                return true;
+            }
+            let start = len.saturating_sub((tail.len() - pos) as u64);
+            read_u16_le(&x[8..10]) <= read_u16_le(&x[10..12]) &&
+            size as u64 + offset as u64 <= start
+        })
+    }
+
+    /// Checks the given buffer. The end of central directory record is
+    /// looked for at the end of the file, if the buffer is the start of one.
+    #[allow(unused_variables)]
+    pub fn from_u8(
+        b: &[u8], mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        match mimetype {
+            "application/zip" => b.starts_with(LOCAL_HEADER) || input_tail(b, filecache, has_eocd),
+            _ => false
+        }
+    }
+
+    #[cfg(feature="fs")]
+    #[allow(unused_variables)]
+    pub fn from_filepath(
+        filepath: &Path, mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        let b = match slurp_to_cache(filepath, filecache, 2048) {
+            Ok(x) => x,
+            Err(_) => return false
+        };
+
+        from_u8(b.as_slice(), mimetype, cache, filecache)
+    }
+}
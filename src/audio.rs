@@ -0,0 +1,174 @@
+//! Handles lossless audio formats
+
+const TYPES: [&str; 5] =
+[
+    "audio/flac",
+    "audio/x-ape",
+    "audio/x-wavpack",
+    "audio/x-wavpack-correction",
+    "audio/x-tta"
+];
+
+/// Dummy cache type
+pub type Cache = bool;
+
+pub mod init {
+
+    use FnvHashMap;
+    use MIME;
+    use std::prelude::v1::*;
+
+    pub fn get_supported() -> Vec<MIME> {
+        super::TYPES.iter().map(|x| MIME::from(*x)).collect()
+    }
+
+    /// Returns Vec of parent->child relations
+    ///
+    /// WavPack correction files only make sense next to the main file,
+    /// so they sit under it.
+    pub fn get_subclasses() -> Vec<(MIME, MIME)> {
+        vec![
+            (MIME::from("application/octet-stream"), MIME::from("audio/flac")),
+            (MIME::from("application/octet-stream"), MIME::from("audio/x-ape")),
+            (MIME::from("application/octet-stream"), MIME::from("audio/x-wavpack")),
+            (MIME::from("audio/x-wavpack"), MIME::from("audio/x-wavpack-correction")),
+            (MIME::from("application/octet-stream"), MIME::from("audio/x-tta"))
+        ]
+    }
+
+    pub fn get_aliaslist() -> FnvHashMap<MIME, MIME> {
+        FnvHashMap::default()
+    }
+
+}
+
+pub mod check {
+
+    use std;
+    use std::prelude::v1::*;
+    #[cfg(feature="fs")]
+    use std::path::Path;
+    use super::super::CacheItem;
+    #[cfg(feature="fs")]
+    use super::super::slurp_to_cache;
+
+    /// Length of an ID3v2 tag header
+    const ID3_HEADER_LEN: usize = 10;
+    /// WavPack metadata sub-block IDs for the audio bitstreams
+    const ID_WV_BITSTREAM: u8 = 0x0a;
+    const ID_WVC_BITSTREAM: u8 = 0x0b;
+
+    fn read_u16_le(b: &[u8]) -> u16 {
+        b[0] as u16 | (b[1] as u16) << 8
+    }
+
+    fn read_u32_le(b: &[u8]) -> u32 {
+        b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24
+    }
+
+    /// Length of the ID3v2 tag at the start of the buffer, including its
+    /// footer, or 0 if there is none. Taggers put these in front of many
+    /// formats that have no room for tags of their own.
+    fn id3_len(b: &[u8]) -> usize {
+        if b.len() < ID3_HEADER_LEN || !b.starts_with(b"ID3") ||
+            b[6..10].iter().any(|&x| x & 0x80 != 0)
+        {
+            return 0;
+        }
+        let size = b[6..10].iter().fold(0usize, |acc, &x| acc << 7 | x as usize);
+        let footer = if b[5] & 0x10 != 0 {ID3_HEADER_LEN} else {0};
+        ID3_HEADER_LEN + size + footer
+    }
+
+    /// `fLaC`, then a STREAMINFO block, which must come first and is
+    /// always 34 bytes long.
+    fn is_flac(b: &[u8]) -> bool {
+        b.len() >= 8 && b.starts_with(b"fLaC") && b[4] & 0x7f == 0 &&
+        b[5..8] == [0, 0, 34]
+    }
+
+    /// Monkey's Audio: `MAC ` and a plausible version number
+    fn is_ape(b: &[u8]) -> bool {
+        b.len() >= 6 && b.starts_with(b"MAC ") &&
+        (3800..=4100).contains(&read_u16_le(&b[4..6]))
+    }
+
+    /// WavPack block: `wvpk`, the block size and a version 4 stream.
+    /// Returns whether the first block holds the main bitstream or the
+    /// correction bitstream, judging by its metadata sub-blocks.
+    fn wavpack_bitstream(b: &[u8]) -> Option<u8> {
+        if b.len() < 32 || !b.starts_with(b"wvpk") ||
+            !(0x402..=0x410).contains(&read_u16_le(&b[8..10]))
+        {
+            return None;
+        }
+        let end = std::cmp::min(b.len(), read_u32_le(&b[4..8]) as usize + 8);
+
+        let mut i = 32;
+        while i + 2 <= end {
+            let id = b[i];
+            let (header, size) = if id & 0x80 != 0 {
+                if i + 4 > end {
+                    break;
+                }
+                (4, (b[i + 1] as usize | (b[i + 2] as usize) << 8 | (b[i + 3] as usize) << 16) * 2)
+            } else {
+                (2, b[i + 1] as usize * 2)
+            };
+            match id & 0x3f {
+                x @ ID_WV_BITSTREAM | x @ ID_WVC_BITSTREAM => return Some(x),
+                _ => i += header + size
+            }
+        }
+        // Main blocks carry the bitstream, but the buffer may be too
+        // short to reach it
+        Some(ID_WV_BITSTREAM)
+    }
+
+    /// True Audio: `TTA1`, then the format, channel count, sample size and
+    /// sample rate.
+    fn is_tta(b: &[u8]) -> bool {
+        if b.len() < 22 || !b.starts_with(b"TTA1") {
+            return false;
+        }
+        let format = read_u16_le(&b[4..6]);
+        let channels = read_u16_le(&b[6..8]);
+        let bits = read_u16_le(&b[8..10]);
+        let rate = read_u32_le(&b[10..14]);
+        (1..=2).contains(&format) && channels > 0 &&
+        [8, 16, 24].contains(&bits) && rate > 0
+    }
+
+    #[allow(unused_variables)]
+    pub fn from_u8(
+        b: &[u8], mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        let start = std::cmp::min(id3_len(b), b.len());
+        let b = &b[start..];
+        match mimetype {
+            "audio/flac" => is_flac(b),
+            "audio/x-ape" => is_ape(b),
+            "audio/x-wavpack" => wavpack_bitstream(b).is_some(),
+            "audio/x-wavpack-correction" => wavpack_bitstream(b) == Some(ID_WVC_BITSTREAM),
+            "audio/x-tta" => is_tta(b),
+            _ => false
+        }
+    }
+
+    #[cfg(feature="fs")]
+    #[allow(unused_variables)]
+    pub fn from_filepath(
+        filepath: &Path, mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        // Look past any ID3v2 tag, which may hold cover art
+        let header = match slurp_to_cache(filepath, filecache, ID3_HEADER_LEN) {
+            Ok(x) => x,
+            Err(_) => return false
+        };
+        let b = match slurp_to_cache(filepath, filecache, id3_len(&header) + 2048) {
+            Ok(x) => x,
+            Err(_) => return false
+        };
+        from_u8(b.as_slice(), mimetype, cache, filecache)
+    }
+}
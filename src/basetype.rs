@@ -1,58 +1,74 @@
 //! Handles "base types" such as inode/* and text/plain
-extern crate std;
+#[cfg(feature="fs")]
+use std;
 
-const TYPES: [&'static str; 5] =
+const TYPES: [&str; 9] =
 [
     "all/all",
     "all/allfiles",
     "inode/directory",
+    "inode/blockdevice",
+    "inode/chardevice",
+    "inode/fifo",
+    "inode/socket",
     "text/plain",
     "application/octet-stream"
 ];
 
+/// The `inode/*` type of something that is neither a regular file nor a
+/// directory, going by its metadata. Only Unix has such things.
+#[cfg(feature="fs")]
+#[allow(unused_variables)]
+pub fn special_type(meta: &std::fs::Metadata) -> Option<&'static str> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        let kind = meta.file_type();
+        if kind.is_block_device() {
+            return Some("inode/blockdevice");
+        }
+        if kind.is_char_device() {
+            return Some("inode/chardevice");
+        }
+        if kind.is_fifo() {
+            return Some("inode/fifo");
+        }
+        if kind.is_socket() {
+            return Some("inode/socket");
+        }
+    }
+    None
+}
+
 /// Hold metadata in cache
+#[cfg(feature="fs")]
 pub type Cache = std::fs::Metadata;
+/// Dummy cache type, as there is no metadata without a filesystem
+#[cfg(not(feature="fs"))]
+pub type Cache = bool;
 
 pub mod init {
 
-    extern crate fnv;
-    use fnv::FnvHashMap;
+    use FnvHashMap;
     use MIME;
+    use std::prelude::v1::*;
     
-    #[cfg(feature="staticmime")]
     pub fn get_supported() -> Vec<MIME> {
-        super::TYPES.to_vec().iter().map(|x| *x).collect()
-    }
-    
-    #[cfg(not(feature="staticmime"))]
-    pub fn get_supported() -> Vec<MIME> {
-        super::TYPES.to_vec().iter().map(|x| x.to_string()).collect()
+        super::TYPES.iter().map(|x| MIME::from(*x)).collect()
     }
     
     /// Returns Vec of parent->child relations
-    #[cfg(feature="staticmime")]
     pub fn get_subclasses() -> Vec<(MIME, MIME)> {
-        let mut res = Vec::<(MIME, MIME)>::new();
-
-        res.push( ("all/all", "all/allfiles") );
-        res.push( ("all/all", "inode/directory") );
-        res.push( ("all/allfiles", "application/octet-stream") );
-        res.push( ("application/octet-stream", "text/plain") );
-        
-        res
-    }
-    
-    #[cfg(not(feature="staticmime"))]
-    pub fn get_subclasses() -> Vec<(MIME, MIME)> {
-        let mut res = Vec::<(MIME, MIME)>::new();
-
-        // There's probably a better way to do this.
-        res.push( ("all/all".to_string(), "all/allfiles".to_string()) );
-        res.push( ("all/all".to_string(), "inode/directory".to_string()) );
-        res.push( ("all/allfiles".to_string(), "application/octet-stream".to_string()) );
-        res.push( ("application/octet-stream".to_string(), "text/plain".to_string()) );
-        
-        res
+        [
+            ("all/all", "all/allfiles"),
+            ("all/all", "inode/directory"),
+            ("all/all", "inode/blockdevice"),
+            ("all/all", "inode/chardevice"),
+            ("all/all", "inode/fifo"),
+            ("all/all", "inode/socket"),
+            ("all/allfiles", "application/octet-stream"),
+            ("application/octet-stream", "text/plain")
+        ].iter().map(|&(x, y)| (MIME::from(x), MIME::from(y))).collect()
     }
     
     pub fn get_aliaslist() -> FnvHashMap<MIME, MIME> {
@@ -63,17 +79,138 @@ pub mod init {
 
 pub mod check {
 
-    extern crate std;
-    extern crate parking_lot;
+    use std;
+    use std::prelude::v1::*;
+    #[cfg(feature="fs")]
     use std::path::Path;
-    use super::super::{Cache, CacheItem, slurp_to_cache};
+    use super::super::{CacheItem, input_samples};
+    #[cfg(feature="fs")]
+    use super::super::{Cache, slurp_to_cache};
     
-    /// If there are any null bytes, return False. Otherwise return True.
+    /// How far to look for control characters, as in shared-mime-info
+    const CONTROL_SCAN_LEN: usize = 32;
+
+    /// ASCII control characters other than whitespace
+    fn is_control(c: u32) -> bool {
+        c < 0x20 && !(0x09..=0x0d).contains(&c)
+    }
+
+    /// Whether decoded text passes the same test as text without a byte
+    /// order mark
+    fn is_clean(x: &str) -> bool {
+        !x.chars().take(CONTROL_SCAN_LEN).any(|c| is_control(c as u32)) &&
+        !x.contains('\0')
+    }
+
+    /// Decodes text that starts with a UTF-8, UTF-16 or UTF-32 byte order
+    /// mark, and returns it as UTF-8 without the mark.
+    ///
+    /// Returns None if there is no byte order mark, if the rest isn't valid
+    /// in the encoding it announces, or if it decodes to control characters.
+    /// Binary data can start with the same bytes as a mark, `FF FE` above
+    /// all. A code unit or surrogate pair cut short by the end of the buffer
+    /// is dropped.
+    pub fn decode_bom(b: &[u8]) -> Option<String> {
+        decode_marked(b).filter(|x| is_clean(x))
+    }
+
+    /// Decodes text after a byte order mark, for `decode_bom`
+    fn decode_marked(b: &[u8]) -> Option<String> {
+        if b.starts_with(b"\xef\xbb\xbf") {
+            let body = &b[3..];
+            return match std::str::from_utf8(body) {
+                Ok(x) => Some(x.to_string()),
+                Err(e) if e.error_len().is_none() => {
+                    Some(String::from_utf8_lossy(&body[..e.valid_up_to()]).to_string())
+                },
+                Err(_) => None
+            };
+        }
+
+        // UTF-32LE must be checked before UTF-16LE, as it starts the same way
+        if b.starts_with(b"\xff\xfe\0\0") || b.starts_with(b"\0\0\xfe\xff") {
+            let little = b[0] == 0xff;
+            return b[4..].chunks(4)
+                .filter(|x| x.len() == 4)
+                .map(|x| {
+                    let unit = if little {
+                        x[0] as u32 | (x[1] as u32) << 8 | (x[2] as u32) << 16 | (x[3] as u32) << 24
+                    } else {
+                        (x[0] as u32) << 24 | (x[1] as u32) << 16 | (x[2] as u32) << 8 | x[3] as u32
+                    };
+                    std::char::from_u32(unit)
+                })
+                .collect();
+        }
+
+        if b.starts_with(b"\xff\xfe") || b.starts_with(b"\xfe\xff") {
+            let little = b[0] == 0xff;
+            let mut units: Vec<u16> = b[2..].chunks(2)
+                .filter(|x| x.len() == 2)
+                .map(|x| if little {
+                    x[0] as u16 | (x[1] as u16) << 8
+                } else {
+                    (x[0] as u16) << 8 | x[1] as u16
+                })
+                .collect();
+            // Drop a high surrogate whose partner was cut off
+            if units.last().is_some_and(|x| (0xd800..0xdc00).contains(x)) {
+                units.pop();
+            }
+            return std::char::decode_utf16(units).collect::<Result<String, _>>().ok();
+        }
+
+        None
+    }
+
+    /// Guesses the character set of text, for use in a `charset` parameter.
+    ///
+    /// A byte order mark decides the encoding. Otherwise, text that is valid
+    /// UTF-8 (allowing for a character cut off by the end of the buffer) is
+    /// `utf-8`, and anything else is taken to be `windows-1252`.
+    pub fn charset(b: &[u8]) -> &'static str {
+        if b.starts_with(b"\xff\xfe\0\0") {
+            "utf-32le"
+        } else if b.starts_with(b"\0\0\xfe\xff") {
+            "utf-32be"
+        } else if b.starts_with(b"\xff\xfe") {
+            "utf-16le"
+        } else if b.starts_with(b"\xfe\xff") {
+            "utf-16be"
+        } else {
+            match std::str::from_utf8(b) {
+                Ok(_) => "utf-8",
+                Err(e) if e.error_len().is_none() => "utf-8",
+                Err(_) => "windows-1252"
+            }
+        }
+    }
+
+    /// Follows the shared-mime-info rule: no control characters other than
+    /// whitespace in the first 32 characters. On top of that, there must be
+    /// no null characters anywhere in the buffer.
+    ///
+    /// Text with a byte order mark is decoded first, and must decode cleanly.
+    pub fn is_text(b: &[u8]) -> bool {
+        decode_bom(b).is_some() || (
+            !b.iter().take(CONTROL_SCAN_LEN).any(|&c| is_control(c as u32)) &&
+            !b.contains(&0)
+        )
+    }
+
     fn is_text_plain_from_u8(b: &[u8]) -> bool {
-        b.iter().filter(|&x| *x == 0).count() == 0
+        is_text(b)
+    }
+
+    /// With sampling on, a file that looks like text at the start also needs
+    /// to look like it further in. Text with a byte order mark may be UTF-16
+    /// or UTF-32, which is full of nulls, so it's left alone.
+    fn samples_are_text(b: &[u8], filecache: &CacheItem) -> bool {
+        decode_bom(b).is_some() || input_samples(filecache).iter().all(|x| !x.contains(&0))
     }
 
     // TODO: Hoist the main logic here somewhere else. This'll get redundant fast!
+    #[cfg(feature="fs")]
     fn is_text_plain_from_filepath(filepath: &Path, filecache: &CacheItem) -> bool {
     
         let b = match slurp_to_cache(filepath, filecache, 512) {
@@ -89,15 +226,16 @@ pub mod check {
     ) -> bool {
         if mimetype == "application/octet-stream" || mimetype == "all/allfiles" {
             // Both of these are the case if we have a bytestream at all
-            return true;
-        } if mimetype == "text/plain" {
-            return is_text_plain_from_u8(b);
+            true
+        } else if mimetype == "text/plain" {
+            is_text_plain_from_u8(b) && samples_are_text(b, filecache)
         } else {
             // ...how did we get bytes for this?
-            return false;
+            false
         }
     }
     
+    #[cfg(feature="fs")]
     pub fn from_filepath(
         filepath: &Path, mimetype: &str, cache: &CacheItem, filecache: &CacheItem
     ) -> bool{
@@ -120,11 +258,14 @@ pub mod check {
         };
         
         match mimetype {
-            "all/all" => return true,
-            "all/allfiles" | "application/octet-stream" => return meta.is_file(),
-            "inode/directory" => return meta.is_dir(),
-            "text/plain" => return is_text_plain_from_filepath(filepath, filecache),
-            _ => return false
+            "all/all" => true,
+            "all/allfiles" | "application/octet-stream" => meta.is_file(),
+            "inode/directory" => meta.is_dir(),
+            // Only regular files are read, so nothing waits on a FIFO's
+            // writer or a device
+            "text/plain" => meta.is_file() && is_text_plain_from_filepath(filepath, filecache),
+            x if x.starts_with("inode/") => super::special_type(&meta) == Some(x),
+            _ => false
         }
     }
 }
@@ -0,0 +1,191 @@
+//! Handles bioinformatics formats: sequence text files and alignment maps
+
+/// Dummy cache type
+pub type Cache = bool;
+
+pub mod init {
+
+    use FnvHashMap;
+    use MIME;
+    use std::prelude::v1::*;
+
+    pub fn get_supported() -> Vec<MIME> {
+        vec![
+            MIME::from("text/x-fasta"),
+            MIME::from("text/x-fastq"),
+            MIME::from("application/x-bgzf"),
+            MIME::from("application/x-bam"),
+            MIME::from("application/x-cram")
+        ]
+    }
+
+    /// Returns Vec of parent->child relations
+    pub fn get_subclasses() -> Vec<(MIME, MIME)> {
+        vec![
+            (MIME::from("text/plain"), MIME::from("text/x-fasta")),
+            (MIME::from("text/plain"), MIME::from("text/x-fastq")),
+            (MIME::from("application/gzip"), MIME::from("application/x-bgzf")),
+            (MIME::from("application/x-bgzf"), MIME::from("application/x-bam")),
+            (MIME::from("application/octet-stream"), MIME::from("application/x-cram"))
+        ]
+    }
+
+    pub fn get_aliaslist() -> FnvHashMap<MIME, MIME> {
+        FnvHashMap::default()
+    }
+
+}
+
+pub mod check {
+
+    use std::prelude::v1::*;
+    extern crate miniz_oxide;
+    #[cfg(feature="fs")]
+    use std::path::Path;
+    use self::miniz_oxide::inflate::core::{decompress, DecompressorOxide};
+    use self::miniz_oxide::inflate::core::inflate_flags::{
+        TINFL_FLAG_HAS_MORE_INPUT, TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF
+    };
+    use super::super::CacheItem;
+    #[cfg(feature="fs")]
+    use super::super::slurp_to_cache;
+
+    /// Splits the buffer into lines, dropping the last one if the
+    /// buffer cut it short.
+    fn complete_lines(b: &[u8]) -> Vec<&[u8]> {
+        let mut lines: Vec<&[u8]> = b.split(|&x| x == b'\n')
+            .map(|x| match x.last() {
+                Some(&b'\r') => &x[..x.len() - 1],
+                _ => x
+            })
+            .collect();
+        lines.pop();
+        lines
+    }
+
+    /// Nucleotide and amino acid codes, plus gaps and stop codons
+    fn is_sequence(line: &[u8]) -> bool {
+        !line.is_empty() &&
+        line.iter().all(|&x| x.is_ascii_alphabetic() || x == b'*' || x == b'-' || x == b'.')
+    }
+
+    /// `>` header lines, each followed by one or more lines of sequence
+    fn is_fasta(b: &[u8]) -> bool {
+        let lines = complete_lines(b);
+        if lines.len() < 2 || lines[0].len() < 2 || lines[0][0] != b'>' {
+            return false;
+        }
+
+        let mut seen_sequence = false;
+        for line in lines.iter().skip(1) {
+            if line.first() == Some(&b'>') {
+                if !seen_sequence {
+                    return false;
+                }
+                seen_sequence = false;
+            } else if line.is_empty() {
+                continue;
+            } else if is_sequence(line) {
+                seen_sequence = true;
+            } else {
+                return false;
+            }
+        }
+        seen_sequence
+    }
+
+    /// Four line records: `@` header, sequence, `+` separator, and
+    /// a quality string as long as the sequence
+    fn is_fastq(b: &[u8]) -> bool {
+        let lines = complete_lines(b);
+        if lines.len() < 4 {
+            return false;
+        }
+
+        for record in lines.chunks(4).filter(|x| x.len() == 4) {
+            if record[0].len() < 2 || record[0][0] != b'@' ||
+               !is_sequence(record[1]) ||
+               record[2].first() != Some(&b'+') ||
+               record[3].len() != record[1].len() ||
+               !record[3].iter().all(|&x| (33..=126).contains(&x))
+            {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Returns the start of the compressed data if the buffer starts with a
+    /// BGZF block: a gzip member whose extra field carries a `BC` subfield
+    fn bgzf_data(b: &[u8]) -> Option<&[u8]> {
+        // ID1, ID2, CM (deflate), FLG (FEXTRA)
+        if b.len() < 18 || b[0..4] != [0x1f, 0x8b, 0x08, 0x04] {
+            return None;
+        }
+        let xlen = b[10] as usize | (b[11] as usize) << 8;
+        let extra = b.get(12..12 + xlen)?;
+
+        let mut i = 0;
+        while i + 4 <= extra.len() {
+            let slen = extra[i + 2] as usize | (extra[i + 3] as usize) << 8;
+            if extra[i] == b'B' && extra[i + 1] == b'C' && slen == 2 {
+                return b.get(12 + xlen..);
+            }
+            i += 4 + slen;
+        }
+        None
+    }
+
+    /// Inflates the start of the first BGZF block, as far as the
+    /// buffer allows
+    fn bgzf_head(b: &[u8], len: usize) -> Vec<u8> {
+        let data = match bgzf_data(b) {
+            Some(x) => x,
+            None => return Vec::new()
+        };
+
+        let mut out = vec![0; len];
+        let mut decomp = DecompressorOxide::new();
+        let (_, _, written) = decompress(
+            &mut decomp, data, &mut out, 0,
+            TINFL_FLAG_HAS_MORE_INPUT | TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF
+        );
+        out.truncate(written);
+        out
+    }
+
+    fn is_bam(b: &[u8]) -> bool {
+        bgzf_head(b, 4) == b"BAM\x01"
+    }
+
+    /// `CRAM` followed by the major and minor format version
+    fn is_cram(b: &[u8]) -> bool {
+        b.len() >= 6 && b.starts_with(b"CRAM") && (1..=4).contains(&b[4])
+    }
+
+    #[allow(unused_variables)]
+    pub fn from_u8(
+        b: &[u8], mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        match mimetype {
+            "text/x-fasta" => is_fasta(b),
+            "text/x-fastq" => is_fastq(b),
+            "application/x-bgzf" => bgzf_data(b).is_some(),
+            "application/x-bam" => is_bam(b),
+            "application/x-cram" => is_cram(b),
+            _ => false
+        }
+    }
+
+    #[cfg(feature="fs")]
+    #[allow(unused_variables)]
+    pub fn from_filepath(
+        filepath: &Path, mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        let b = match slurp_to_cache(filepath, filecache, 2048) {
+            Ok(x) => x,
+            Err(_) => return false
+        };
+        from_u8(b.as_slice(), mimetype, cache, filecache)
+    }
+}
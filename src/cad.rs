@@ -0,0 +1,115 @@
+//! Handles CAD drawing formats
+
+/// Dummy cache type
+pub type Cache = bool;
+
+pub mod init {
+
+    use FnvHashMap;
+    use MIME;
+    use std::prelude::v1::*;
+
+    pub fn get_supported() -> Vec<MIME> {
+        vec![
+            MIME::from("image/vnd.dxf"),
+            MIME::from("image/vnd.dwg")
+        ]
+    }
+
+    /// Returns Vec of parent->child relations
+    pub fn get_subclasses() -> Vec<(MIME, MIME)> {
+        vec![
+            (MIME::from("text/plain"), MIME::from("image/vnd.dxf")),
+            (MIME::from("application/octet-stream"), MIME::from("image/vnd.dxf")),
+            (MIME::from("application/octet-stream"), MIME::from("image/vnd.dwg"))
+        ]
+    }
+
+    pub fn get_aliaslist() -> FnvHashMap<MIME, MIME> {
+        FnvHashMap::default()
+    }
+
+}
+
+pub mod check {
+
+    use std;
+    use std::prelude::v1::*;
+    #[cfg(feature="fs")]
+    use std::path::Path;
+    use super::super::CacheItem;
+    #[cfg(feature="fs")]
+    use super::super::slurp_to_cache;
+
+    const BINARY_DXF_MAGIC: &[u8] = b"AutoCAD Binary DXF\r\n\x1a\0";
+
+    /// Sections a DXF file can open with
+    const DXF_SECTIONS: [&str; 6] =
+        ["HEADER", "CLASSES", "TABLES", "BLOCKS", "ENTITIES", "OBJECTS"];
+
+    /// Version strings of DWG releases, from R1.4 up to 2018
+    const DWG_VERSIONS: [&[u8]; 18] = [
+        b"AC1.40", b"AC1.50", b"AC2.10", b"AC1001", b"AC1002", b"AC1003",
+        b"AC1004", b"AC1006", b"AC1009", b"AC1012", b"AC1014", b"AC1015",
+        b"AC1018", b"AC1021", b"AC1024", b"AC1027", b"AC1032", b"AC1033"
+    ];
+
+    /// ASCII DXF is a list of group code and value line pairs. After any
+    /// `999` comments, the file must open a section: `0 SECTION 2 <name>`.
+    fn is_ascii_dxf(b: &[u8]) -> bool {
+        let text = match std::str::from_utf8(b) {
+            Ok(x) => x,
+            // The buffer may end in the middle of a character
+            Err(e) => match std::str::from_utf8(&b[..e.valid_up_to()]) {
+                Ok(x) => x,
+                Err(_) => return false
+            }
+        };
+
+        let mut lines = text.lines().map(|x| x.trim());
+        loop {
+            let code = match lines.next() {
+                Some(x) => x,
+                None => return false
+            };
+            let value = match lines.next() {
+                Some(x) => x,
+                None => return false
+            };
+
+            match code {
+                "999" => continue,
+                "0" if value == "SECTION" => break,
+                _ => return false
+            }
+        }
+
+        lines.next() == Some("2") && match lines.next() {
+            Some(x) => DXF_SECTIONS.contains(&x),
+            None => false
+        }
+    }
+
+    #[allow(unused_variables)]
+    pub fn from_u8(
+        b: &[u8], mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        match mimetype {
+            "image/vnd.dxf" => b.starts_with(BINARY_DXF_MAGIC) || is_ascii_dxf(b),
+            "image/vnd.dwg" => DWG_VERSIONS.iter().any(|x| b.starts_with(x)),
+            _ => false
+        }
+    }
+
+    #[cfg(feature="fs")]
+    #[allow(unused_variables)]
+    pub fn from_filepath(
+        filepath: &Path, mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        let b = match slurp_to_cache(filepath, filecache, 512) {
+            Ok(x) => x,
+            Err(_) => return false
+        };
+        from_u8(b.as_slice(), mimetype, cache, filecache)
+    }
+}
@@ -0,0 +1,89 @@
+//! Handles columnar and row-oriented data engineering formats
+
+const TYPES: [&str; 4] =
+[
+    "application/vnd.apache.parquet",
+    "application/vnd.apache.arrow.file",
+    "application/x-avro",
+    "application/x-orc"
+];
+
+/// Dummy cache type
+pub type Cache = bool;
+
+pub mod init {
+
+    use FnvHashMap;
+    use MIME;
+    use std::prelude::v1::*;
+
+    pub fn get_supported() -> Vec<MIME> {
+        super::TYPES.iter().map(|x| MIME::from(*x)).collect()
+    }
+
+    /// Returns Vec of parent->child relations
+    pub fn get_subclasses() -> Vec<(MIME, MIME)> {
+        super::TYPES.iter()
+            .map(|x| (MIME::from("application/octet-stream"), MIME::from(*x)))
+            .collect()
+    }
+
+    pub fn get_aliaslist() -> FnvHashMap<MIME, MIME> {
+        FnvHashMap::default()
+    }
+
+}
+
+pub mod check {
+
+    use std::prelude::v1::*;
+    #[cfg(feature="fs")]
+    use std::path::Path;
+    use super::super::{CacheItem, input_tail};
+    #[cfg(feature="fs")]
+    use super::super::slurp_to_cache;
+
+    const PARQUET_MAGIC: &[u8] = b"PAR1";
+
+    /// Parquet files start and end with `PAR1`, with the footer length
+    /// in between the footer and the trailing magic.
+    fn is_parquet(head: &[u8], tail: &[u8]) -> bool {
+        head.starts_with(PARQUET_MAGIC) && tail.ends_with(PARQUET_MAGIC)
+    }
+
+    /// Avro object container files start with `Obj\x01`, followed by
+    /// the file metadata map, which always holds the schema.
+    fn is_avro(b: &[u8]) -> bool {
+        b.starts_with(b"Obj\x01") &&
+        b.windows(11).take(512).any(|x| x == b"avro.schema")
+    }
+
+    /// Checks the given buffer. The trailing Parquet magic is looked for at
+    /// the end of the file, if the buffer is the start of one.
+    #[allow(unused_variables)]
+    pub fn from_u8(
+        b: &[u8], mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        match mimetype {
+            "application/vnd.apache.parquet" =>
+                b.len() >= 12 && b.starts_with(PARQUET_MAGIC) && input_tail(b, filecache, |tail, _| is_parquet(b, tail)),
+            "application/vnd.apache.arrow.file" => b.starts_with(b"ARROW1\0\0"),
+            "application/x-avro" => is_avro(b),
+            "application/x-orc" => b.starts_with(b"ORC"),
+            _ => false
+        }
+    }
+
+    #[cfg(feature="fs")]
+    #[allow(unused_variables)]
+    pub fn from_filepath(
+        filepath: &Path, mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        let b = match slurp_to_cache(filepath, filecache, 2048) {
+            Ok(x) => x,
+            Err(_) => return false
+        };
+
+        from_u8(b.as_slice(), mimetype, cache, filecache)
+    }
+}
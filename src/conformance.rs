@@ -0,0 +1,190 @@
+//! Runs the shared-mime-info test suite, so how well detection follows the
+//! spec can be measured against the same cases the database is tested with.
+//!
+//! The suite is a directory of sample files and a `list` naming each one,
+//! the type it should be, and which of the three checks are expected to
+//! fail. Each file is checked by name, by its contents, and as a file, and
+//! each check passes if it gives the expected type or an alias of it.
+//!
+//! Requires the `fs` and `checker-fdo-magic` features.
+//!
+//! # Examples
+//! ```rust,no_run
+//! use std::path::Path;
+//! use tree_magic::conformance::{run_list, Detector};
+//!
+//! let results = run_list(Path::new("shared-mime-info/tests/mime-detection/list"), Detector::TreeMagic).unwrap();
+//! for x in results.iter().filter(|x| !x.outcome.is_ok()) {
+//!     println!("{} by {:?}: wanted {}, got {:?}", x.file, x.check, x.expected, x.found);
+//! }
+//! ```
+use std::prelude::v1::*;
+use std::io;
+use std::path::Path;
+use Mime;
+
+/// One line of the list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Case {
+    /// Path to the sample, relative to the list
+    pub file: String,
+    pub expected: Mime,
+    /// Whether the check by name is expected to fail
+    pub xfail_name: bool,
+    /// Whether the check by contents is expected to fail
+    pub xfail_data: bool,
+    /// Whether the check as a file is expected to fail
+    pub xfail_file: bool
+}
+
+impl Case {
+    /// Whether `check` is expected to fail for this case
+    pub fn xfail(&self, check: Check) -> bool {
+        match check {
+            Check::Name => self.xfail_name,
+            Check::Data => self.xfail_data,
+            Check::File => self.xfail_file
+        }
+    }
+}
+
+/// The three ways each sample is checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Check {
+    /// From the file name alone
+    Name,
+    /// From the file's contents alone
+    Data,
+    /// From the file on disk, name and all
+    File
+}
+
+/// Which of the crate's detectors to measure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Detector {
+    /// `from_u8` and `from_filepath`
+    TreeMagic,
+    /// `xdg::from_u8` and `xdg::from_filepath`
+    Xdg
+}
+
+/// How a check went
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Pass,
+    Fail,
+    /// Failed, as the list says it will
+    ExpectedFail,
+    /// Passed, though the list says it will fail
+    UnexpectedPass
+}
+
+impl Outcome {
+    /// Whether the list got this right: a pass, or an expected failure
+    pub fn is_ok(self) -> bool {
+        matches!(self, Outcome::Pass | Outcome::ExpectedFail)
+    }
+}
+
+/// The result of one check of one case
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseResult {
+    pub file: String,
+    pub check: Check,
+    pub expected: Mime,
+    /// What the detector gave, or None if the sample couldn't be read
+    pub found: Option<Mime>,
+    pub outcome: Outcome
+}
+
+/// Reads a list in the suite's format: one case per line, as the file
+/// name, its type, and optionally three flags for the name, data and file
+/// checks, each `x` if it's expected to fail and `o` if not. Blank lines
+/// and lines starting with `#` are skipped.
+///
+/// Fails with `InvalidData` on a line without a type.
+///
+/// # Examples
+/// ```rust
+/// let cases = tree_magic::conformance::parse_list("# Comment\ntest.png image/png\ntest.c text/x-csrc oxo\n").unwrap();
+/// assert_eq!(cases.len(), 2);
+/// assert!(!cases[0].xfail_data);
+/// assert!(cases[1].xfail_data);
+/// ```
+pub fn parse_list(list: &str) -> io::Result<Vec<Case>> {
+    let mut out = Vec::new();
+    for (i, line) in list.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let (file, expected) = match (fields.next(), fields.next()) {
+            (Some(x), Some(y)) => (x, y),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("line {}: no type given", i + 1)))
+        };
+        let flags = fields.next().unwrap_or("").as_bytes();
+        out.push(Case {
+            file: file.to_string(),
+            expected: Mime::from(expected.to_string()),
+            xfail_name: flags.first() == Some(&b'x'),
+            xfail_data: flags.get(1) == Some(&b'x'),
+            xfail_file: flags.get(2) == Some(&b'x')
+        });
+    }
+    Ok(out)
+}
+
+/// Whether two types are the same, going by aliases
+fn same(a: &str, b: &str) -> bool {
+    ::get_alias(a).eq_ignore_ascii_case(::get_alias(b))
+}
+
+/// Runs each case through the three checks, with samples in `dir`.
+///
+/// The name check uses the best match from `xdg::from_filename`, or
+/// `application/octet-stream` if nothing matches, for either detector. A
+/// sample that can't be read fails the other two checks.
+///
+/// Results are in the order of the cases, with the three checks of each in
+/// the order of `Check`.
+pub fn run(dir: &Path, cases: &[Case], detector: Detector) -> Vec<CaseResult> {
+    let mut out = Vec::with_capacity(cases.len() * 3);
+    for case in cases {
+        let path = dir.join(&case.file);
+        let name = path.file_name().map_or(String::new(), |x| x.to_string_lossy().into_owned());
+        let by_name = ::xdg::from_filename(&name).into_iter().next().unwrap_or_else(|| Mime::from("application/octet-stream"));
+        let by_data = std::fs::read(&path).ok().map(|x| match detector {
+            Detector::TreeMagic => ::from_u8(&x),
+            Detector::Xdg => ::xdg::from_u8(&x)
+        });
+        let by_file = match detector {
+            Detector::TreeMagic => ::from_filepath(&path),
+            Detector::Xdg => ::xdg::from_filepath(&path)
+        };
+
+        for (check, found) in [(Check::Name, Some(by_name)), (Check::Data, by_data), (Check::File, by_file)] {
+            let passed = found.as_ref().is_some_and(|x| same(x, &case.expected));
+            let outcome = match (passed, case.xfail(check)) {
+                (true, false) => Outcome::Pass,
+                (true, true) => Outcome::UnexpectedPass,
+                (false, false) => Outcome::Fail,
+                (false, true) => Outcome::ExpectedFail
+            };
+            out.push(CaseResult {
+                file: case.file.clone(),
+                check,
+                expected: case.expected.clone(),
+                found,
+                outcome
+            });
+        }
+    }
+    out
+}
+
+/// Reads the list at `list` and runs it, with samples next to it.
+pub fn run_list(list: &Path, detector: Detector) -> io::Result<Vec<CaseResult>> {
+    let cases = parse_list(&std::fs::read_to_string(list)?)?;
+    let dir = list.parent().unwrap_or_else(|| Path::new(""));
+    Ok(run(dir, &cases, detector))
+}
@@ -0,0 +1,96 @@
+//! Encodes and decodes `data:` URIs (RFC 2397)
+use std;
+use std::prelude::v1::*;
+
+const BASE64_CHARS: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Type assumed when a data URI doesn't give one
+const DEFAULT_TYPE: &str = "text/plain";
+
+pub fn base64_encode(b: &[u8]) -> String {
+    let mut out = String::with_capacity(b.len().div_ceil(3) * 4);
+    for chunk in b.chunks(3) {
+        let n = (chunk[0] as u32) << 16 |
+            (*chunk.get(1).unwrap_or(&0) as u32) << 8 |
+            *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_CHARS[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decodes base64, ignoring whitespace. Padding is optional.
+fn base64_decode(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::<u8>::with_capacity(s.len() / 4 * 3);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    let mut padding = false;
+
+    for c in s.bytes().filter(|x| !x.is_ascii_whitespace()) {
+        if c == b'=' {
+            padding = true;
+            continue;
+        }
+        if padding {
+            return None;
+        }
+        let value = BASE64_CHARS.iter().position(|&x| x == c)? as u32;
+        acc = acc << 6 | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    Some(out)
+}
+
+fn percent_decode(s: &str) -> Option<Vec<u8>> {
+    let b = s.as_bytes();
+    let mut out = Vec::<u8>::with_capacity(b.len());
+    let mut i = 0;
+    while i < b.len() {
+        if b[i] == b'%' {
+            let hex = std::str::from_utf8(b.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(b[i]);
+            i += 1;
+        }
+    }
+    Some(out)
+}
+
+/// Splits a data URI into its declared type and decoded payload.
+///
+/// The declared type is the media type without its parameters, in
+/// lowercase, and defaults to text/plain. Returns None if the URI is
+/// malformed.
+pub fn decode(uri: &str) -> Option<(String, Vec<u8>)> {
+    let uri = uri.trim();
+    if uri.len() < 5 || !uri[..5].eq_ignore_ascii_case("data:") {
+        return None;
+    }
+    let (header, data) = uri[5..].split_at(uri[5..].find(',')?);
+    let data = &data[1..];
+
+    let mut params = header.split(';');
+    let declared = params.next().unwrap_or("").trim().to_lowercase();
+    let declared = if declared.is_empty() {DEFAULT_TYPE.to_string()} else {declared};
+    let is_base64 = params.any(|x| x.trim().eq_ignore_ascii_case("base64"));
+
+    let payload = if is_base64 {
+        base64_decode(std::str::from_utf8(&percent_decode(data)?).ok()?)?
+    } else {
+        percent_decode(data)?
+    };
+    Some((declared, payload))
+}
@@ -0,0 +1,86 @@
+//! Handles DICOM medical images
+
+/// Dummy cache type
+pub type Cache = bool;
+
+pub mod init {
+
+    use FnvHashMap;
+    use MIME;
+    use std::prelude::v1::*;
+
+    pub fn get_supported() -> Vec<MIME> {
+        vec![MIME::from("application/dicom")]
+    }
+
+    /// Returns Vec of parent->child relations
+    pub fn get_subclasses() -> Vec<(MIME, MIME)> {
+        vec![(MIME::from("application/octet-stream"), MIME::from("application/dicom"))]
+    }
+
+    pub fn get_aliaslist() -> FnvHashMap<MIME, MIME> {
+        FnvHashMap::default()
+    }
+
+}
+
+pub mod check {
+
+    #[cfg(feature="fs")]
+    use std::prelude::v1::*;
+    #[cfg(feature="fs")]
+    use std::path::Path;
+    use super::super::CacheItem;
+    #[cfg(feature="fs")]
+    use super::super::slurp_to_cache;
+
+    /// Length of the preamble before the `DICM` prefix (PS3.10, section 7.1)
+    const PREAMBLE_LEN: usize = 128;
+
+    /// Bytes needed to see the prefix and the first file meta element header
+    #[cfg(feature="fs")]
+    const SCAN_LEN: usize = PREAMBLE_LEN + 4 + 6;
+
+    /// Checks the data element following the `DICM` prefix. It must belong
+    /// to the File Meta Information group (0002), which is always encoded as
+    /// Explicit VR Little Endian, so a two-letter VR follows the tag.
+    ///
+    /// If the buffer ends before the element, the prefix alone is trusted.
+    fn is_file_meta_element(b: &[u8]) -> bool {
+        if b.len() < 6 {
+            return true;
+        }
+        b[0] == 0x02 && b[1] == 0x00 &&
+        b[4].is_ascii_uppercase() && b[5].is_ascii_uppercase()
+    }
+
+    fn is_dicom(b: &[u8]) -> bool {
+        // Normal files, with the 128 byte preamble
+        if b.len() >= PREAMBLE_LEN + 4 && &b[PREAMBLE_LEN..PREAMBLE_LEN + 4] == b"DICM" {
+            return is_file_meta_element(&b[PREAMBLE_LEN + 4..]);
+        }
+
+        // Some writers leave the preamble out entirely
+        b.starts_with(b"DICM") && b.len() >= 10 && is_file_meta_element(&b[4..])
+    }
+
+    #[allow(unused_variables)]
+    pub fn from_u8(
+        b: &[u8], mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        mimetype == "application/dicom" && is_dicom(b)
+    }
+
+    /// Reads past the preamble, so the prefix at offset 128 is available
+    #[cfg(feature="fs")]
+    #[allow(unused_variables)]
+    pub fn from_filepath(
+        filepath: &Path, mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        let b = match slurp_to_cache(filepath, filecache, SCAN_LEN) {
+            Ok(x) => x,
+            Err(_) => return false
+        };
+        from_u8(b.as_slice(), mimetype, cache, filecache)
+    }
+}
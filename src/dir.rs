@@ -0,0 +1,148 @@
+//! Classifies every file under a directory, for file managers and indexers,
+//! and totals up what's there by type, for disk usage analyzers.
+//!
+//! Files are detected in batches with `from_filepaths_with`, while the walk
+//! carries on lazily, so a huge tree is never held in memory at once.
+//!
+//! Requires the `parallel` feature.
+//!
+//! # Examples
+//! ```rust
+//! use std::path::Path;
+//! use tree_magic::dir::{classify_dir, WalkOptions};
+//!
+//! for entry in classify_dir(Path::new("tests/image"), &WalkOptions::default()) {
+//!     println!("{}: {:?}", entry.path.display(), entry.mimetype);
+//! }
+//! ```
+extern crate std;
+extern crate walkdir;
+
+use std::path::{Path, PathBuf};
+use FnvHashMap;
+use self::walkdir::WalkDir;
+use DetectOptions;
+use MIME;
+
+/// How many files are detected together
+const BATCH_LEN: usize = 256;
+
+/// How `classify_dir` walks the tree
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WalkOptions {
+    /// How many directories deep to go. Files directly in the starting
+    /// directory are at depth 1. No limit if None.
+    pub max_depth: Option<usize>,
+    /// Whether to follow symbolic links. If not, links are left out, and
+    /// linked directories aren't walked.
+    pub follow_links: bool,
+    /// Options for detecting each file
+    pub detect: DetectOptions
+}
+
+/// One file found by `classify_dir`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entry {
+    pub path: PathBuf,
+    /// The file's type, or None if it couldn't be read
+    pub mimetype: Option<MIME>,
+    /// Size of the file in bytes
+    pub len: u64,
+    /// How many directories deep the file is
+    pub depth: usize
+}
+
+/// Walks a directory tree, giving the type of each file in it.
+///
+/// Only files are given, in the order the walk finds them. Anything the
+/// walk can't get into, such as a directory without read permission or a
+/// symbolic link loop, comes back as an entry with no type. If the path is
+/// a file rather than a directory, that file alone is given.
+pub fn classify_dir(path: &Path, options: &WalkOptions) -> impl Iterator<Item=Entry> {
+    let mut walk = WalkDir::new(path).follow_links(options.follow_links);
+    if let Some(x) = options.max_depth {
+        walk = walk.max_depth(x);
+    }
+    // Files, and whether the walk got to them
+    let mut found = walk.into_iter().filter_map(|x| match x {
+        Ok(x) => match x.file_type().is_file() {
+            true => Some((Entry {
+                len: x.metadata().map(|y| y.len()).unwrap_or(0),
+                path: x.path().to_path_buf(),
+                mimetype: None,
+                depth: x.depth()
+            }, true)),
+            false => None
+        },
+        Err(x) => Some((Entry {
+            path: x.path()?.to_path_buf(),
+            mimetype: None,
+            len: 0,
+            depth: x.depth()
+        }, false))
+    });
+
+    let detect = options.detect.clone();
+    let batches = std::iter::from_fn(move || {
+        let mut batch: Vec<(Entry, bool)> = found.by_ref().take(BATCH_LEN).collect();
+        if batch.is_empty() {
+            return None;
+        }
+        let paths = batch.iter()
+            .filter(|x| x.1)
+            .map(|x| x.0.path.clone());
+        let mut types = ::from_filepaths_with(paths, &detect).into_iter();
+        for entry in batch.iter_mut().filter(|x| x.1) {
+            entry.0.mimetype = types.next().and_then(|x| x.1);
+        }
+        Some(batch.into_iter().map(|x| x.0))
+    });
+    batches.flatten()
+}
+
+/// How much of a tree is of one type, from `type_stats`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypeStats {
+    /// The type, or None for files that couldn't be read
+    pub mimetype: Option<MIME>,
+    /// Number of files
+    pub files: u64,
+    /// Total size of the files in bytes
+    pub bytes: u64
+}
+
+/// Walks a directory tree, and counts the files and bytes of each type.
+///
+/// Types are sorted by total size, largest first, then by name. Files are
+/// counted under the type detected for them, not under its parents, so
+/// `image/png` files don't add to an `image/*` or `application/octet-stream`
+/// total; add those up from the list as needed.
+///
+/// # Examples
+/// ```rust
+/// use std::path::Path;
+/// use tree_magic::dir::{type_stats, WalkOptions};
+///
+/// let stats = type_stats(Path::new("tests"), &WalkOptions::default());
+/// let video: u64 = stats.iter()
+///     .filter(|x| x.mimetype.as_ref().map_or(false, |y| y.starts_with("video/")))
+///     .map(|x| x.bytes)
+///     .sum();
+/// println!("{} bytes of video", video);
+/// ```
+pub fn type_stats(path: &Path, options: &WalkOptions) -> Vec<TypeStats> {
+    let mut totals = FnvHashMap::<Option<MIME>, (u64, u64)>::default();
+    for entry in classify_dir(path, options) {
+        let total = totals.entry(entry.mimetype).or_insert((0, 0));
+        total.0 += 1;
+        total.1 += entry.len;
+    }
+
+    let mut out: Vec<TypeStats> = totals.into_iter()
+        .map(|(mimetype, (files, bytes))| TypeStats {mimetype, files, bytes})
+        .collect();
+    out.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.mimetype.cmp(&b.mimetype)));
+    out
+}
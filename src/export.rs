@@ -0,0 +1,103 @@
+//! Writes out the loaded database as JSON, for tooling outside Rust
+use std::prelude::v1::*;
+use std::fmt::Write;
+use FnvHashMap;
+use MIME;
+
+/// Quotes and escapes a string for JSON
+fn string(x: &str) -> String {
+    let mut out = String::with_capacity(x.len() + 2);
+    out.push('"');
+    for c in x.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => { let _ = write!(out, "\\u{:04x}", c as u32); },
+            c => out.push(c)
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Bytes as a quoted lowercase hex string
+fn hex(b: &[u8]) -> String {
+    let mut out = String::with_capacity(b.len() * 2 + 2);
+    out.push('"');
+    for x in b {
+        let _ = write!(out, "{:02x}", x);
+    }
+    out.push('"');
+    out
+}
+
+/// Writes a JSON object, one member per line, with the given values already
+/// formatted
+fn object<I: IntoIterator<Item=(MIME, String)>>(out: &mut String, members: I) {
+    let members: Vec<String> = members.into_iter()
+        .map(|(k, v)| format!("    {}: {}", string(&k), v))
+        .collect();
+    match members.is_empty() {
+        true => out.push_str("{}"),
+        false => { let _ = write!(out, "{{\n{}\n  }}", members.join(",\n")); }
+    }
+}
+
+fn rule(x: &::MagicRule) -> String {
+    format!(
+        "{{\"indent\": {}, \"offset\": {}, \"range\": {}, \"value\": {}, \"mask\": {}, \"word_size\": {}, \"priority\": {}}}",
+        x.indent, x.offset, x.range, hex(&x.value),
+        x.mask.as_ref().map_or("null".to_string(), |y| hex(y)),
+        x.word_size, x.priority
+    )
+}
+
+/// Renders the database. Keys and lists are sorted, so the output only
+/// changes when the database does.
+pub fn json() -> String {
+    let mut types: Vec<&MIME> = ::TYPES.graph.node_weights().collect();
+    types.sort();
+
+    let mut aliases: Vec<(MIME, MIME)> = ::ALIASES.iter().map(|x| (x.0.clone(), x.1.clone())).collect();
+    aliases.sort();
+
+    let graph = &::TYPES.graph;
+    let mut edges: Vec<(&MIME, &MIME)> = graph.edge_indices()
+        .filter_map(|x| graph.edge_endpoints(x))
+        .map(|x| (&graph[x.0], &graph[x.1]))
+        .collect();
+    edges.sort();
+
+    let mut magic: Vec<MIME> = ::magic_priorities().into_iter().map(|x| x.0).collect();
+    magic.sort();
+
+    let mut globs = FnvHashMap::<MIME, Vec<String>>::default();
+    for (k, v) in ::globs() {
+        globs.entry(k).or_default().push(string(&v));
+    }
+    let mut globs: Vec<(MIME, Vec<String>)> = globs.into_iter().collect();
+    globs.sort();
+
+    let mut out = String::from("{\n  \"types\": [");
+    out.push_str(&types.iter().map(|x| format!("\n    {}", string(x))).collect::<Vec<_>>().join(","));
+    out.push_str("\n  ],\n  \"aliases\": ");
+    object(&mut out, aliases.into_iter().map(|(k, v)| (k, string(&v))));
+    out.push_str(",\n  \"subclasses\": [");
+    out.push_str(&edges.iter()
+        .map(|x| format!("\n    [{}, {}]", string(x.0), string(x.1)))
+        .collect::<Vec<_>>()
+        .join(","));
+    out.push_str("\n  ],\n  \"magic\": ");
+    object(&mut out, magic.into_iter().map(|x| {
+        let rules: Vec<String> = ::rules_for(&x).iter().map(rule).collect();
+        let rules = format!("[\n      {}\n    ]", rules.join(",\n      "));
+        (x, rules)
+    }));
+    out.push_str(",\n  \"globs\": ");
+    object(&mut out, globs.into_iter().map(|(k, v)| (k, format!("[{}]", v.join(", ")))));
+    out.push_str("\n}\n");
+    out
+}
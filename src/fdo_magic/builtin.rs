@@ -1,100 +1,137 @@
-//! Read magic file bundled in crate
+//! Read magic file bundled in the `tree_magic_db` crate
 
-extern crate std;
-extern crate petgraph;
-extern crate fnv;
-use petgraph::prelude::*;
-use fnv::FnvHashMap;
+use std::prelude::v1::*;
+use FnvHashMap;
 use MIME;
-use super::MagicRule;
+use super::LazyRules;
+use super::prefilter::Prefilter;
+use tree_magic_db;
 
-/// Dummy cache type
-pub type Cache = bool;
+/// Types the pre-filter let through for the file being checked
+pub type Cache = super::prefilter::State;
 
-/// Preload alias list
 lazy_static! {
+	/// Preload alias list
 	static ref ALIASES: FnvHashMap<MIME, MIME> = {
 		init::get_aliaslist()
 	};
 }
 
-/// Load magic file before anything else.
 lazy_static! {
-    static ref ALLRULES: FnvHashMap<MIME, DiGraph<MagicRule, u32>> = {
-        super::ruleset::from_u8(include_bytes!("magic")).unwrap()
+    /// Load magic file before anything else.
+    static ref ALLRULES: FnvHashMap<MIME, LazyRules> = {
+        super::ruleset::from_static(tree_magic_db::RULES)
     };
 }
 
-#[cfg(not(feature="staticmime"))]
-macro_rules! convmime {
-    ($x:expr) => {$x.to_string()}
+/// Describes the embedded magic file
+pub fn db_version() -> ::DbVersion {
+    ::DbVersion {
+        version: tree_magic_db::VERSION.map(|x| x.to_string()),
+        hash: super::hash_magic(tree_magic_db::MAGIC),
+        embedded: true
+    }
+}
+
+lazy_static! {
+    /// Automaton over every type's top-level rules
+    static ref PREFILTER: Prefilter = {
+        Prefilter::new(&ALLRULES)
+    };
+    static ref SCAN_EXTENT: usize = {
+        super::scan_extent(&ALLRULES)
+    };
+}
+
+/// Highest priority of the rules for each type
+pub fn priorities() -> FnvHashMap<MIME, u32> {
+    super::priorities(&ALLRULES)
+}
+
+/// Priority of the best of a type's rules that matches the file
+pub fn matched_priority(mimetype: &str, file: &[u8]) -> Option<u32> {
+    let mimetype = ALIASES.get(mimetype).map(|x| &**x).unwrap_or(mimetype);
+    ALLRULES.get(mimetype).map(|x| x.matched_priority(file))
 }
-#[cfg(feature="staticmime")]
-macro_rules! convmime {
-    ($x:expr) => {$x}
+
+/// Parsed rules for a type, or None if it has none
+pub fn rules_for(mimetype: &str) -> Option<Vec<super::MagicRule>> {
+    let mimetype = ALIASES.get(mimetype).map(|x| &**x).unwrap_or(mimetype);
+    ALLRULES.get(mimetype).map(|x| x.rules())
+}
+
+/// The rule of a type that accepted the file, and where in the file its
+/// value was found
+pub fn fired(mimetype: &str, file: &[u8]) -> Option<(super::MagicRule, usize)> {
+    let mimetype = ALIASES.get(mimetype).map(|x| &**x).unwrap_or(mimetype);
+    ALLRULES.get(mimetype)?.fired(file)
+}
+
+/// Every type with rules that match the file, and the priority of the best
+/// of its rules that does
+pub fn matches(file: &[u8]) -> Vec<(MIME, u32)> {
+    super::matches(&ALLRULES, &PREFILTER, file)
+}
+
+/// Furthest into a file any loaded rule looks
+pub fn scan_extent() -> usize {
+    *SCAN_EXTENT
 }
 
 pub mod init {
-    extern crate std;
-    extern crate fnv;
-    use fnv::FnvHashMap;
+    use std::prelude::v1::*;
+    use FnvHashMap;
     use MIME;
+    use tree_magic_db;
     
     /// Read all subclass lines from file
-    fn read_subclasses() -> Result<Vec<(MIME, MIME)>, std::io::Error> {
+    fn read_subclasses() -> Vec<(MIME, MIME)> {
     
-        let r = include_str!("subclasses");
+        let r = tree_magic_db::SUBCLASSES;
         let mut subclasses = Vec::<(MIME, MIME)>::new();
         
         for line in r.lines() {
-            let child = convmime!(line.split_whitespace().nth(0).unwrap_or(""));
-            let parent = convmime!(line.split_whitespace().nth(1).unwrap_or(""));
+            let child = MIME::from(line.split_whitespace().next().unwrap_or(""));
+            let parent = MIME::from(line.split_whitespace().nth(1).unwrap_or(""));
             
             subclasses.push( (parent, child) );
         }
         
-        Ok(subclasses)
+        subclasses
     }
 
     // Get filetype aliases
-    fn read_aliaslist() -> Result<FnvHashMap<MIME, MIME>, std::io::Error> {
-        let raliases = include_str!("aliases");
+    fn read_aliaslist() -> FnvHashMap<MIME, MIME> {
+        let raliases = tree_magic_db::ALIASES;
         let mut aliaslist = FnvHashMap::<MIME, MIME>::default();
         
         for line in raliases.lines() {
-            let a = convmime!(line.split_whitespace().nth(0).unwrap_or(""));
-            let b = convmime!(line.split_whitespace().nth(1).unwrap_or(""));
+            let a = MIME::from(line.split_whitespace().next().unwrap_or(""));
+            let b = MIME::from(line.split_whitespace().nth(1).unwrap_or(""));
             aliaslist.insert(a,b);
         }
         
-        let aliaslist = aliaslist;
-        Ok(aliaslist)
+        aliaslist
     }
     
     pub fn get_aliaslist() -> FnvHashMap<MIME, MIME> {
-        read_aliaslist().unwrap()
+        read_aliaslist()
     }
     
     /// Get list of supported MIME types
     pub fn get_supported() -> Vec<MIME> {
-        super::ALLRULES.keys().map(|x| *x).collect()
+        super::ALLRULES.keys().cloned().collect()
     }
     
     /// Get list of parent -> child subclass links
     pub fn get_subclasses() -> Vec<(MIME, MIME)> {
     
-        let mut subclasses = read_subclasses().unwrap();
+        let mut subclasses = read_subclasses();
         
         // If child or parent refers to an alias, change it to the real type
-        for x in 0..subclasses.len(){
-            match super::ALIASES.get(&subclasses[x].0) {
-                Some(alias) => {subclasses[x].0 = alias.clone();}
-                None => {}
-            }
-            match super::ALIASES.get(&subclasses[x].1) {
-                Some(alias) => {subclasses[x].1 = alias.clone();}
-                None => {}
-            }
+        for x in subclasses.iter_mut() {
+            if let Some(alias) = super::ALIASES.get(&x.0) {x.0 = alias.clone();}
+            if let Some(alias) = super::ALIASES.get(&x.1) {x.1 = alias.clone();}
         }
         
         subclasses
@@ -103,13 +140,26 @@ pub mod init {
 }
 
 pub mod check {
-    extern crate std;
-    extern crate petgraph;
+    use std::prelude::v1::*;
+    #[cfg(feature="fs")]
     use std::path::Path;
-    use petgraph::prelude::*;
-    use fdo_magic;
-    use super::super::super::{CacheItem, slurp_to_cache};
+    use super::super::super::{Cache, CacheItem};
+    #[cfg(feature="fs")]
+    use super::super::super::slurp_to_cache;
     
+    /// Says whether the type gets through the pre-filter, once the file has
+    /// been checked against enough types for it to run
+    fn passes_prefilter(file: &[u8], mimetype: &str, cache: &CacheItem) -> bool {
+        let mut lock = cache.write();
+        if lock.is_none() {
+            *lock = Some(Cache::FdoMagicBuiltin(Default::default()));
+        }
+        match *lock {
+            Some(Cache::FdoMagicBuiltin(ref mut x)) => x.passes(mimetype, file, || &*super::PREFILTER),
+            _ => true
+        }
+    }
+
     /// Test against all rules
     #[allow(unused_variables)]
     pub fn from_u8(
@@ -122,52 +172,42 @@ pub mod check {
         };
 
         // Get magic ruleset
-        let graph = match super::ALLRULES.get(mimetype) {
-            Some(item) => item,
+        let program = match super::ALLRULES.get(mimetype) {
+            Some(item) => item.program(),
             None => return false // No rule for this mime
         };
-        
-        // Check all rulesets
-        for x in graph.externals(Incoming) {
-            if fdo_magic::check::from_u8_walker(file, mimetype, graph, x, true) {
-                return true;
-            }
+
+        if !passes_prefilter(file, mimetype, cache) {
+            return false;
         }
         
-        false
+        // Check all rulesets
+        program.run(file)
     }
 
     /// This only exists for the case of a direct match_filepath call
     /// and even then we could probably get rid of this...
+    #[cfg(feature="fs")]
     #[allow(unused_variables)]
     pub fn from_filepath(
         filepath: &Path, mimetype: &str, cache: &CacheItem, filecache: &CacheItem
     ) -> bool{
         // Get magic ruleset
-        let magic_rules = match super::ALLRULES.get(mimetype) {
-            Some(item) => item,
+        let program = match super::ALLRULES.get(mimetype) {
+            Some(item) => item.program(),
             None => return false // No rule for this mime
         };
 
         // Get # of bytes to read
-        let mut scanlen = 0;
-        for x in magic_rules.raw_nodes() {
-			let ref y = x.weight;
-            let tmplen = 
-                y.start_off as usize +
-                y.val_len as usize +
-                y.region_len as usize;
-                
-            if tmplen > scanlen {
-                scanlen = tmplen;
-            }
-        }
+        let scanlen = program.scan_len();
         
         let b = match slurp_to_cache(filepath, filecache, scanlen) {
             Ok(x) => x,
             Err(_) => return false
         };
         
-        from_u8(b.as_slice(), mimetype, cache, &CacheItem::default())
+        // Only as much of the file as this type needs was read, so the
+        // pre-filter has to start over
+        from_u8(b.as_slice(), mimetype, &CacheItem::default(), &CacheItem::default())
     }
 }
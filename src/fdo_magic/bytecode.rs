@@ -0,0 +1,307 @@
+//! Magic rules lowered into a flat program
+//!
+//! Each rule becomes one instruction, laid out with its children right after
+//! it. A matching instruction either accepts, if it has no children, or falls
+//! through to its first child; a failing one jumps to the next thing to try.
+//! Values and masks live in one buffer, so checking a type is a loop over two
+//! flat arrays rather than a walk over a graph.
+
+use std;
+use std::prelude::v1::*;
+use std::borrow::Cow;
+use memchr;
+use super::MagicRule;
+
+/// How an instruction compares its value against the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    /// Value is at the offset
+    Eq,
+    /// Masked bytes at the offset are the masked value
+    EqMasked,
+    /// Value starts somewhere in the range
+    Search,
+    /// Masked bytes somewhere in the range are the masked value
+    SearchMasked
+}
+
+/// Puts a value in the byte order of the machine we're running on.
+///
+/// The magic file gives numbers big-endian. `big16`/`little16` and friends
+/// are written out as byte strings already, but `host16` and `host32`
+/// values keep their word size, and have to be swapped a word at a time on
+/// little-endian machines.
+fn host_order(b: &[u8], word_len: u32) -> Cow<'_, [u8]> {
+    let word_len = word_len as usize;
+    if cfg!(target_endian="big") || word_len <= 1 || !b.len().is_multiple_of(word_len) {
+        return Cow::Borrowed(b);
+    }
+    Cow::Owned(b.chunks(word_len).flat_map(|x| x.iter().rev().cloned()).collect())
+}
+
+/// A step of lowering rules into a program
+enum Task {
+    /// Add a rule and its children, noting its instructions whose failure
+    /// means the rule doesn't match in the given list
+    Emit{node: usize, root: bool, list: usize},
+    /// Point the failure of the list's instructions at the next one to be
+    /// added
+    FailHere(usize)
+}
+
+/// One rule of a program
+#[derive(Debug, Clone)]
+pub struct Insn {
+    pub op: Op,
+    /// First offset the value may start at
+    pub offset: u32,
+    /// How far past `offset` the value may start
+    pub range: u32,
+    /// Value, and mask if the op has one, in `Program::bytes`. A masked
+    /// value is stored with the mask already applied.
+    pub val: u32,
+    pub mask: u32,
+    pub len: u32,
+    /// Where to go when the test fails
+    pub on_fail: u32,
+    /// Whether a match finishes the program
+    pub accept: bool,
+    /// Whether this is a top-level rule
+    pub root: bool,
+    /// Priority of the top-level rule this belongs to
+    pub priority: u32,
+    /// Index of the rule this came from, in the rules given to `compile`
+    pub rule: u32
+}
+
+/// The rules for one MIME type
+#[derive(Debug, Clone, Default)]
+pub struct Program {
+    code: Vec<Insn>,
+    bytes: Vec<u8>
+}
+
+impl Program {
+    /// Lowers rules in magic file order, where each rule is a child of the
+    /// nearest rule before it with a lower indent.
+    ///
+    /// A rule matches if its own test passes and, if it has children, any
+    /// one of them matches; siblings are alternatives. Top-level rules are
+    /// tried highest priority first, and otherwise in order. Children are
+    /// tried last-listed first, and a child that fails, at any depth, moves
+    /// on to its next sibling before giving up on its parent.
+    pub fn compile(rules: &[MagicRule]) -> Program {
+        let mut children = vec![Vec::<usize>::new(); rules.len()];
+        let mut roots = Vec::<usize>::new();
+        let mut stack = Vec::<usize>::new();
+        for (i, rule) in rules.iter().enumerate() {
+            while let Some(&x) = stack.last() {
+                if rules[x].indent_level < rule.indent_level {
+                    break;
+                }
+                stack.pop();
+            }
+            match stack.last() {
+                Some(&x) => children[x].push(i),
+                None => roots.push(i)
+            }
+            stack.push(i);
+        }
+        roots.sort_by_key(|&x| std::cmp::Reverse(rules[x].priority));
+
+        let mut out = Program::default();
+        // Lists of instructions waiting to be told where to go on failure,
+        // and what's left to do, last first. Worked through with a stack
+        // rather than recursion, so deep nesting can't overflow it.
+        let mut lists = Vec::<Vec<usize>>::new();
+        let mut tasks = Vec::<Task>::new();
+        for &root in roots.iter().rev() {
+            // Everything that gives up on this root moves on to the next
+            tasks.push(Task::FailHere(lists.len()));
+            tasks.push(Task::Emit{node: root, root: true, list: lists.len()});
+            lists.push(Vec::new());
+        }
+        while let Some(task) = tasks.pop() {
+            match task {
+                Task::Emit{node, root, list} => {
+                    let pc = out.emit(&rules[node], node, root, children[node].is_empty());
+                    lists[list].push(pc);
+
+                    // Children go last-listed first. Anywhere one fails, try
+                    // the next; once out of children, the rule doesn't match.
+                    let kids = &children[node];
+                    let mut todo = Vec::<Task>::new();
+                    for (i, &x) in kids.iter().rev().enumerate() {
+                        if i + 1 == kids.len() {
+                            todo.push(Task::Emit{node: x, root: false, list});
+                        } else {
+                            todo.push(Task::Emit{node: x, root: false, list: lists.len()});
+                            todo.push(Task::FailHere(lists.len()));
+                            lists.push(Vec::new());
+                        }
+                    }
+                    tasks.extend(todo.into_iter().rev());
+                },
+                Task::FailHere(list) => {
+                    let next = out.code.len() as u32;
+                    for x in std::mem::take(&mut lists[list]) {
+                        out.code[x].on_fail = next;
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Adds one rule's instruction, giving where it is
+    fn emit(&mut self, rule: &MagicRule, node: usize, root: bool, accept: bool) -> usize {
+        let pc = self.code.len();
+        let rule_val = host_order(&rule.val, rule.word_len);
+        // A mask that doesn't cover the value keeps the rest of it whole
+        let rule_mask = rule.mask.as_ref().map(|x| {
+            let mut x = host_order(x, rule.word_len).into_owned();
+            x.resize(rule_val.len(), 0xff);
+            x
+        });
+        let val = self.bytes.len() as u32;
+        match rule_mask {
+            // Bits the mask drops don't count on either side, so drop them
+            // from the value now rather than on every test
+            Some(ref x) => self.bytes.extend(rule_val.iter().zip(x.iter()).map(|(a, b)| a & b)),
+            None => self.bytes.extend_from_slice(&rule_val)
+        }
+        let mask = self.bytes.len() as u32;
+        if let Some(ref x) = rule_mask {
+            self.bytes.extend_from_slice(x);
+        }
+        // A range of N lets the value start at any of N offsets
+        let range = rule.region_len.saturating_sub(1);
+        let op = match (range, rule.mask.is_some()) {
+            (0, false) => Op::Eq,
+            (0, true) => Op::EqMasked,
+            (_, false) => Op::Search,
+            (_, true) => Op::SearchMasked
+        };
+        self.code.push(Insn{
+            op,
+            offset: rule.start_off,
+            range,
+            val,
+            mask,
+            len: rule.val.len() as u32,
+            on_fail: 0,
+            accept,
+            root,
+            priority: rule.priority,
+            rule: node as u32
+        });
+        pc
+    }
+
+    /// Instructions, in program order
+    pub fn code(&self) -> &[Insn] {
+        &self.code
+    }
+
+    /// Value an instruction compares against
+    pub fn val(&self, insn: &Insn) -> &[u8] {
+        &self.bytes[insn.val as usize..(insn.val + insn.len) as usize]
+    }
+
+    /// Mask an instruction applies, if it has one
+    pub fn mask(&self, insn: &Insn) -> Option<&[u8]> {
+        match insn.op {
+            Op::EqMasked | Op::SearchMasked =>
+                Some(&self.bytes[insn.mask as usize..(insn.mask + insn.len) as usize]),
+            Op::Eq | Op::Search => None
+        }
+    }
+
+    /// How much of a file the program can look at
+    pub fn scan_len(&self) -> usize {
+        self.code.iter()
+            .map(|x| (x.offset as usize).saturating_add(x.range as usize).saturating_add(x.len as usize))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Runs one instruction's test, giving where in the file the value was
+    /// found
+    fn test(&self, insn: &Insn, file: &[u8]) -> Option<usize> {
+        // The window can run past the end of the file, as long as the value
+        // still fits in what's left of it
+        let start = insn.offset as usize;
+        let end = std::cmp::min(start.saturating_add(insn.range as usize).saturating_add(insn.len as usize), file.len());
+        let area = match file.get(start..end) {
+            Some(x) if x.len() >= insn.len as usize => x,
+            _ => return None
+        };
+        let val = self.val(insn);
+        if val.is_empty() {
+            return Some(start);
+        }
+
+        let found = match insn.op {
+            Op::Eq => (area == val).then_some(0),
+            Op::Search => memchr::memmem::find(area, val),
+            Op::EqMasked | Op::SearchMasked => {
+                let mask = self.mask(insn).unwrap_or(&[]);
+                area.windows(val.len()).position(|x| {
+                    x.iter().zip(mask).map(|(a, b)| a & b).eq(val.iter().copied())
+                })
+            }
+        };
+        found.map(|x| start + x)
+    }
+
+    /// Most bytes an instruction's test compares, in a file of `file_len`
+    /// bytes: the value's length at a fixed offset, the window for a
+    /// search, and every position in the window for a masked search
+    #[cfg(feature="std")]
+    fn work(insn: &Insn, file_len: usize) -> usize {
+        let start = insn.offset as usize;
+        let end = std::cmp::min(start.saturating_add(insn.range as usize).saturating_add(insn.len as usize), file_len);
+        let area = end.saturating_sub(start);
+        let len = insn.len as usize;
+        if area < len {
+            return 0;
+        }
+        match insn.op {
+            Op::Eq | Op::EqMasked => len,
+            Op::Search => area,
+            Op::SearchMasked => (area - len + 1).saturating_mul(len)
+        }
+    }
+
+    /// Checks a file against the rules
+    pub fn run(&self, file: &[u8]) -> bool {
+        self.priority(file).is_some()
+    }
+
+    /// Checks a file against the rules, giving the priority of the best
+    /// top-level rule that matches
+    pub fn priority(&self, file: &[u8]) -> Option<u32> {
+        self.fired(file).map(|x| self.code[x.0].priority)
+    }
+
+    /// Checks a file against the rules, giving the instruction that accepted
+    /// it and where in the file its value was found.
+    ///
+    /// Each test is charged to the thread's work limit, if there is one, and
+    /// the file doesn't match once it runs out.
+    pub fn fired(&self, file: &[u8]) -> Option<(usize, usize)> {
+        let mut pc = 0;
+        while let Some(insn) = self.code.get(pc) {
+            #[cfg(feature="std")]
+            if !::limit::charge(1, Program::work(insn, file.len())) {
+                return None;
+            }
+            match self.test(insn, file) {
+                Some(x) if insn.accept => return Some((pc, x)),
+                Some(_) => pc += 1,
+                None => pc = insn.on_fail as usize
+            }
+        }
+        None
+    }
+}
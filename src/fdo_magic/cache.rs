@@ -0,0 +1,155 @@
+//! On-disk cache of the parsed system magic file
+//!
+//! The rules are kept under `$XDG_CACHE_HOME/tree_magic` (or `~/.cache`), and
+//! used as long as the magic file they came from has the same path, size and
+//! modification time. Any problem with the cache just means the magic file
+//! gets parsed again, so errors are ignored throughout.
+
+use std;
+use std::prelude::v1::*;
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use super::MagicRule;
+
+/// Identifies the cache format. Bump the version whenever the layout or
+/// `MagicRule` changes.
+const HEADER: &[u8] = b"tree_magic cache\0\x02";
+
+/// Where the cache lives, if there's a cache directory at all
+fn cache_path() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(x) => PathBuf::from(x),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".cache")
+    };
+    Some(dir.join("tree_magic").join("magic.cache"))
+}
+
+/// Identifies a version of the magic file: path, size and modification time
+fn stamp(source: &Path) -> Option<Vec<u8>> {
+    let meta = fs::metadata(source).ok()?;
+    let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    let mut out = Vec::new();
+    write_bytes(&mut out, source.to_str()?.as_bytes());
+    out.extend_from_slice(&meta.len().to_le_bytes());
+    out.extend_from_slice(&mtime.as_secs().to_le_bytes());
+    out.extend_from_slice(&mtime.subsec_nanos().to_le_bytes());
+    Some(out)
+}
+
+fn write_u32(out: &mut Vec<u8>, x: u32) {
+    out.extend_from_slice(&x.to_le_bytes());
+}
+
+fn write_bytes(out: &mut Vec<u8>, x: &[u8]) {
+    write_u32(out, x.len() as u32);
+    out.extend_from_slice(x);
+}
+
+/// Reads through a cache file, returning None once it runs out
+struct Reader<'a> {
+    b: &'a [u8],
+    pos: usize
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let out = self.b.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(out)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let x = self.take(4)?;
+        Some(u32::from_le_bytes([x[0], x[1], x[2], x[3]]))
+    }
+
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+}
+
+/// Loads the rules for the given magic file, if the cache has them
+pub fn load(source: &Path) -> Option<Vec<(String, Vec<MagicRule>)>> {
+    let b = fs::read(cache_path()?).ok()?;
+    let stamp = stamp(source)?;
+    let mut r = Reader{b: &b, pos: 0};
+    if r.take(HEADER.len())? != HEADER || r.take(stamp.len())? != &stamp[..] {
+        return None;
+    }
+
+    let mut out = Vec::new();
+    for _ in 0..r.u32()? {
+        let mimetype = String::from_utf8(r.bytes()?.to_vec()).ok()?;
+        let mut rules = Vec::new();
+        for _ in 0..r.u32()? {
+            let indent_level = r.u32()?;
+            let start_off = r.u32()?;
+            let val = r.bytes()?.to_vec();
+            let mask = match r.take(1)?[0] {
+                0 => None,
+                _ => Some(Cow::Owned(r.take(val.len())?.to_vec()))
+            };
+            rules.push(MagicRule{
+                indent_level,
+                start_off,
+                val: Cow::Owned(val),
+                mask,
+                word_len: r.u32()?,
+                region_len: r.u32()?,
+                priority: r.u32()?
+            });
+        }
+        out.push((mimetype, rules));
+    }
+
+    match r.pos == b.len() {
+        true => Some(out),
+        false => None
+    }
+}
+
+/// Saves the rules parsed from the given magic file
+pub fn store(source: &Path, rules: &[(&str, Vec<MagicRule>)]) {
+    let path = match cache_path() {
+        Some(x) => x,
+        None => return
+    };
+    let stamp = match stamp(source) {
+        Some(x) => x,
+        None => return
+    };
+
+    let mut out = HEADER.to_vec();
+    out.extend_from_slice(&stamp);
+    write_u32(&mut out, rules.len() as u32);
+    for (mimetype, rules) in rules {
+        write_bytes(&mut out, mimetype.as_bytes());
+        write_u32(&mut out, rules.len() as u32);
+        for rule in rules {
+            write_u32(&mut out, rule.indent_level);
+            write_u32(&mut out, rule.start_off);
+            write_bytes(&mut out, &rule.val);
+            match rule.mask {
+                Some(ref x) => {
+                    out.push(1);
+                    out.extend_from_slice(x);
+                },
+                None => out.push(0)
+            }
+            write_u32(&mut out, rule.word_len);
+            write_u32(&mut out, rule.region_len);
+            write_u32(&mut out, rule.priority);
+        }
+    }
+
+    // Write next to the cache and move it into place, so a process reading
+    // the cache never sees half of it
+    let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+    let _ = path.parent().map(fs::create_dir_all);
+    if fs::write(&tmp, out).is_ok() && fs::rename(&tmp, &path).is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+}
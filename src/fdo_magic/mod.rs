@@ -1,419 +1,487 @@
 // Common routines for all fdo_magic parsers
 
-extern crate std;
-extern crate petgraph;
-extern crate fnv;
+use std::prelude::v1::*;
+use std::borrow::Cow;
+use self::bytecode::Program;
+use self::prefilter::Prefilter;
+use FnvHashMap;
+use MIME;
 
 
-#[cfg(feature="staticmime")]
+// The system magic files can't be read without a filesystem
+#[cfg(not(feature="fs"))]
 pub mod builtin;
-#[cfg(not(feature="staticmime"))]
+#[cfg(feature="fs")]
 pub mod sys;
+#[cfg(feature="cache")]
+mod cache;
+pub mod bytecode;
+mod prefilter;
+mod trie;
 
-// We can't have staticmime and sys_fdo_magic enabled
-// because we can't statically refer to a file on disk.
-#[cfg(all(feature="staticmime", all(feature="sys_fdo_magic", unix)))]
-const CONF_ERROR_CANNOT_USE_STATICMIME_WITH_SYS_FDO_MAGIC: u32 = ();
+/// The embedded database, which the system loader falls back on. Empty with
+/// the `no-embedded-db` feature.
+#[cfg(all(feature="fs", not(feature="no-embedded-db")))]
+mod embedded {
+    use FnvHashMap;
+    use MIME;
+    pub use tree_magic_db::{MAGIC, ALIASES, SUBCLASSES, VERSION};
+
+    pub fn rules() -> FnvHashMap<MIME, super::LazyRules> {
+        super::ruleset::from_static(::tree_magic_db::RULES)
+    }
+}
+#[cfg(feature="no-embedded-db")]
+mod embedded {
+    use FnvHashMap;
+    use MIME;
+
+    pub fn rules() -> FnvHashMap<MIME, super::LazyRules> {
+        FnvHashMap::default()
+    }
+
+    pub static MAGIC: &[u8] = &[];
+    pub static ALIASES: &str = "";
+    pub static SUBCLASSES: &str = "";
+    pub static VERSION: Option<&str> = None;
+}
+
+/// Hashes a magic file, for `DbVersion`
+fn hash_magic(b: &[u8]) -> u64 {
+    use std::hash::Hasher;
+    let mut hasher = ::fnv::FnvHasher::default();
+    hasher.write(b);
+    hasher.finish()
+}
+
+/// Furthest into a file any of the rules look
+fn scan_extent(rules: &FnvHashMap<MIME, LazyRules>) -> usize {
+    rules.values().map(|x| x.scan_len()).max().unwrap_or(0)
+}
+
+/// Furthest into a file a rule looks: the last offset its value may start
+/// at, plus its length. The same as its compiled instruction's `scan_len`.
+fn rule_extent(start_off: u32, region_len: u32, val_len: usize) -> usize {
+    (start_off as usize)
+        .saturating_add(region_len.saturating_sub(1) as usize)
+        .saturating_add(val_len)
+}
+
+/// Highest priority of the rules for each type
+fn priorities(rules: &FnvHashMap<MIME, LazyRules>) -> FnvHashMap<MIME, u32> {
+    rules.iter().map(|(k, v)| (k.clone(), v.priority())).collect()
+}
+
+/// Every type with rules that match the file, and the priority of the best
+/// of its rules that does
+fn matches(rules: &FnvHashMap<MIME, LazyRules>, prefilter: &Prefilter, file: &[u8]) -> Vec<(MIME, u32)> {
+    let candidates = prefilter.scan(file);
+    rules.iter()
+        .filter(|x| prefilter.passes(x.0, &candidates))
+        .filter_map(|(k, v)| v.program().priority(file).map(|y| (k.clone(), y)))
+        .collect()
+}
 
 #[derive(Debug, Clone)]
 pub struct MagicRule {
     pub indent_level: u32,
     pub start_off: u32,
-    pub val_len: u16,
-    pub val: Vec<u8>,
-    pub mask: Option<Vec<u8>>,
+    pub val: Cow<'static, [u8]>,
+    pub mask: Option<Cow<'static, [u8]>>,
+    /// Size of the words in `val` and `mask` if they're host-endian numbers,
+    /// otherwise 1
     pub word_len: u32,
-    pub region_len: u32
+    /// Number of offsets the value may start at; 0 and 1 both mean just
+    /// `start_off`
+    pub region_len: u32,
+    /// Priority of the section the rule is in
+    pub priority: u32
 }
 
-#[cfg(not(feature="staticmime"))]
-macro_rules! convmime {
-    ($x:expr) => {$x.to_string()}
+/// Where a MIME type's rules come from, before they're compiled. A type can
+/// have more than one section, each with its own priority.
+enum RuleSource {
+    /// Sections of a magic file, parsed on first use
+    #[cfg(feature="fs")]
+    Magic(Vec<(u32, &'static [u8])>),
+    /// Rules that were already parsed, such as ones from the cache
+    #[cfg(feature="cache")]
+    Parsed(Vec<MagicRule>),
+    /// Sections parsed at build time
+    #[cfg(not(feature="no-embedded-db"))]
+    Static(Vec<(u32, &'static [::tree_magic_db::Rule])>)
 }
-#[cfg(feature="staticmime")]
-macro_rules! convmime {
-    ($x:expr) => {$x}
+
+/// The rules for one MIME type. They're only parsed and compiled once
+/// something checks against that type.
+pub struct LazyRules {
+    source: RuleSource,
+    /// Highest priority of any of its sections
+    priority: u32,
+    /// Whether all of its sections have that priority
+    uniform: bool,
+    program: ::spin::Once<Program>
+}
+
+impl LazyRules {
+    fn new(source: RuleSource) -> LazyRules {
+        let priorities: Vec<u32> = match source {
+            #[cfg(feature="fs")]
+            RuleSource::Magic(ref x) => x.iter().map(|y| y.0).collect(),
+            #[cfg(feature="cache")]
+            RuleSource::Parsed(ref x) => x.iter().map(|y| y.priority).collect(),
+            #[cfg(not(feature="no-embedded-db"))]
+            RuleSource::Static(ref x) => x.iter().map(|y| y.0).collect()
+        };
+        let priority = priorities.iter().cloned().max().unwrap_or(::DEFAULT_PRIORITY);
+        let uniform = priorities.iter().all(|&x| x == priority);
+        LazyRules{source, priority, uniform, program: ::spin::Once::new()}
+    }
+
+    /// Highest priority of any of the type's rules
+    pub fn priority(&self) -> u32 {
+        self.priority
+    }
+
+    /// Priority of the best rule that matches the file, or the highest
+    /// priority if none do. Only runs the rules if their priorities differ.
+    pub fn matched_priority(&self, file: &[u8]) -> u32 {
+        match self.uniform {
+            true => self.priority,
+            false => self.program().priority(file).unwrap_or(self.priority)
+        }
+    }
+
+    /// The rule that accepted the file, if any did, and where in the file
+    /// its value was found
+    pub fn fired(&self, file: &[u8]) -> Option<(MagicRule, usize)> {
+        let (pc, offset) = self.program().fired(file)?;
+        let rule = self.rules().swap_remove(self.program().code()[pc].rule as usize);
+        Some((rule, offset))
+    }
+
+    /// The type's rules, parsed but not compiled, in the order they appear
+    pub fn rules(&self) -> Vec<MagicRule> {
+        match self.source {
+            #[cfg(feature="fs")]
+            RuleSource::Magic(ref x) => x.iter()
+                .flat_map(|&(priority, y)| ruleset::parse_section(y, priority))
+                .collect(),
+            #[cfg(feature="cache")]
+            RuleSource::Parsed(ref x) => x.clone(),
+            #[cfg(not(feature="no-embedded-db"))]
+            RuleSource::Static(ref x) => x.iter()
+                .flat_map(|&(priority, y)| y.iter().map(move |z| MagicRule{
+                    indent_level: z.indent_level,
+                    start_off: z.start_off,
+                    val: Cow::Borrowed(z.val),
+                    mask: z.mask.map(Cow::Borrowed),
+                    word_len: z.word_len,
+                    region_len: z.region_len,
+                    priority
+                }))
+                .collect()
+        }
+    }
+
+    /// Rules compiled into a program, where children only apply if their
+    /// parent matched
+    pub fn program(&self) -> &Program {
+        self.program.call_once(|| Program::compile(&self.rules()))
+    }
+
+    /// Furthest into a file any of the type's rules look. This reads the
+    /// offsets from the rules as they are, without compiling them.
+    pub fn scan_len(&self) -> usize {
+        match self.source {
+            #[cfg(feature="fs")]
+            RuleSource::Magic(ref x) => x.iter()
+                .map(|y| ruleset::section_extent(y.1))
+                .max(),
+            #[cfg(feature="cache")]
+            RuleSource::Parsed(ref x) => x.iter()
+                .map(|y| rule_extent(y.start_off, y.region_len, y.val.len()))
+                .max(),
+            #[cfg(not(feature="no-embedded-db"))]
+            RuleSource::Static(ref x) => x.iter()
+                .flat_map(|y| y.1.iter())
+                .map(|y| rule_extent(y.start_off, y.region_len, y.val.len()))
+                .max()
+        }.unwrap_or(0)
+    }
 }
 
 pub mod ruleset {
-    extern crate nom;
-    extern crate std;
-	extern crate petgraph;
-	extern crate fnv;
+    use std::prelude::v1::*;
+    #[cfg(any(feature="fs", feature="fuzz"))]
+    use std::borrow::Cow;
+    #[cfg(any(feature="fs", feature="fuzz"))]
     use std::str;
-	use petgraph::prelude::*;
-	use fnv::FnvHashMap;
+	use FnvHashMap;
     use MIME;
 
-    // Below functions from https://github.com/badboy/iso8601/blob/master/src/helper.rs
-    // but modified to be safe and provide defaults
-    pub fn to_string(s: &[u8]) -> std::result::Result<&str, std::str::Utf8Error> {
-        str::from_utf8(s)
+    /// Reads the decimal number at the cursor, if there is one
+    #[cfg(any(feature="fs", feature="fuzz"))]
+    fn read_number(b: &[u8], pos: &mut usize) -> Option<u32> {
+        let start = *pos;
+        let mut out: u32 = 0;
+        while let Some(x) = b.get(*pos).filter(|x| x.is_ascii_digit()) {
+            out = out.saturating_mul(10).saturating_add((x - b'0') as u32);
+            *pos += 1;
+        }
+        if *pos == start { None } else { Some(out) }
     }
-    pub fn to_u32(s: std::result::Result<&str, std::str::Utf8Error>, def: u32) -> u32 {
-        
-        match s {
-            Ok (t) => {str::FromStr::from_str(t).unwrap_or(def)},
-            Err (_) => def
+
+    /// Takes the given number of bytes at the cursor
+    #[cfg(any(feature="fs", feature="fuzz"))]
+    fn take<'a>(b: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8], String> {
+        match b.get(*pos..*pos + len) {
+            Some(x) => {
+                *pos += len;
+                Ok(x)
+            },
+            None => Err(format!("unexpected end of magic file at byte {}", *pos))
         }
     }
 
-    pub fn buf_to_u32(s: &[u8], def: u32) -> u32 {
-        to_u32(to_string(s), def)
+    /// Consumes the given byte at the cursor, if it's there
+    #[cfg(any(feature="fs", feature="fuzz"))]
+    fn eat(b: &[u8], pos: &mut usize, c: u8) -> bool {
+        if b.get(*pos) == Some(&c) {
+            *pos += 1;
+            true
+        } else {
+            false
+        }
     }
 
-    // Initial mime string
-    // Format: [priority: mime]   
-    #[cfg(not(feature="staticmime"))]
-    named!(mime<&str>,
-        map_res!(
-            delimited!(
-                delimited!(
-                    char!('['),
-                    is_not!(":"),
-                    char!(':')
-                ),
-                is_not!("]"), // the mime
-                tag!("]\n") 
-            ),
-            str::from_utf8
-        )
-    );
-    #[cfg(feature="staticmime")]
-    named!(mime<&'static str>,
-        do_parse!(
-            res: delimited!(
-                delimited!(
-                    char!('['),
-                    is_not!(":"),
-                    char!(':')
-                ),
-                is_not!("]"), // the mime
-                tag!("]\n") 
-            ) >>
-            // Yes I am aware that this is horribly dangerous
-            // but there is no reason this shouldn't be fine
-            // because the source is static and known and really
-            // a string is just a slice of u8s isn't it?
-            (unsafe{
-                std::mem::transmute(res)
-            })
-        )
-    );
-    
-    #[test]
-    // Ensures the transmute used in mime for feature="staticmime"
-    // doesn't blow up.
-    fn str_transmute_sanity() {
-        unsafe {
-            const A: &'static [u8] = b"Hello world!";
-            const B: &'static str = "Hello world!";
-            let c: &'static str = std::mem::transmute(A);
-            assert!(B == c); // 256
+    /// Fails unless the given byte is at the cursor
+    #[cfg(any(feature="fs", feature="fuzz"))]
+    fn expect(b: &[u8], pos: &mut usize, c: u8) -> Result<(), String> {
+        match eat(b, pos, c) {
+            true => Ok(()),
+            false => Err(format!("expected {:?} at byte {} of magic file", c as char, *pos))
         }
     }
 
-    // Indent levels sub-parser for magic_rules
-    // Default value 0
-    named!(magic_rules_indent_level<u32>,
-        do_parse!(
-            ret: take_until!(">") >> 
-            (buf_to_u32(ret, 0))
-        )
-    );
-
-    // Start offset sub-parser for magic_rules
-    named!(magic_rules_start_off<u32>,
-        do_parse!(
-            ret: take_until!("=") >>
-            (buf_to_u32(ret, 0))
-        )
-    );
-
-    // Singular magic ruleset
-    named!(magic_rules<super::MagicRule>,
-        do_parse!(
-            peek!(is_a!("012345689>")) >>
-            _indent_level: magic_rules_indent_level >>
-            tag!(">") >>
-            _start_off: magic_rules_start_off >>
-            tag!("=") >>
-            _val_len: u16!(nom::Endianness::Big) >> // length of value
-            _val: do_parse!(
-                ret: take!(_val_len) >>
-                (ret.iter().map(|&x| x).collect())
-            ) >> // value
-            
-            _mask: opt!(
-                do_parse!(
-                    char!('&') >>
-                    ret: take!(_val_len) >> // mask (default 0xFF)
-                    (ret.iter().map(|&x| x).collect())
-                )
-            ) >>
-            
-            // word size (default 1)
-            _word_len: opt!(
-                do_parse!(
-                    tag!("~") >>
-                    ret: take_until!("+") >>
-                    (buf_to_u32(ret, 1))
-                )
-            ) >>
-            
-            // length of region in file to check (default 1)
-            _region_len: opt!(
-                do_parse!(
-                    tag!("+") >>
-                    ret: take_until!("\n") >>
-                    (buf_to_u32(ret, 0))
-                )
-            ) >>
-            
-            take_until_and_consume!("\n") >>
-            
-            (super::MagicRule{
-                indent_level: _indent_level,
-                start_off: _start_off,
-                val: _val,
-                val_len: _val_len,
-                mask: _mask,
-                word_len: _word_len.unwrap_or(1),
-                region_len: _region_len.unwrap_or(0)
-            })
-        )
-        
-    );
-
-    /// Singular magic entry
-    named!(magic_entry<(MIME, Vec<super::MagicRule>)>,
-        do_parse!(
-            _mime: do_parse!(
-                ret: mime >>
-                (convmime!(ret))
-            ) >>
-            _rules: many0!(magic_rules) >>
-            (_mime, _rules)
-        )
-    );
+    /// Section header, giving the priority and MIME type of the following
+    /// rules. Format: [priority:mime]
+    #[cfg(any(feature="fs", feature="fuzz"))]
+    fn mime<'a>(b: &'a [u8], pos: &mut usize) -> Result<(u32, &'a str), String> {
+        expect(b, pos, b'[')?;
+        let priority = read_number(b, pos).unwrap_or(::DEFAULT_PRIORITY);
+        expect(b, pos, b':')?;
+        let len = match b[*pos..].iter().position(|&x| x == b']') {
+            Some(x) => x,
+            None => return Err(format!("unterminated section at byte {} of magic file", *pos))
+        };
+        let out = str::from_utf8(&b[*pos..*pos + len]).map_err(|e| e.to_string())?;
+        *pos += len;
+        expect(b, pos, b']')?;
+        expect(b, pos, b'\n')?;
+        Ok((priority, out))
+    }
 
-    /// Converts a magic file given as a &[u8] array
-    /// to a vector of MagicEntry structs
-    named!(from_u8_to_tuple_vec<Vec<(MIME, Vec<super::MagicRule>)>>,
-        do_parse!(
-            tag!("MIME-Magic\0\n") >>
-            ret: many0!(magic_entry) >>
-            (ret)
-        )
-    );
-	
-	fn gen_graph(magic_rules: Vec<super::MagicRule>) -> DiGraph<super::MagicRule, u32>
-	{
-		use petgraph::prelude::*;
-		// Whip up a graph real quick
-		let mut graph = DiGraph::<super::MagicRule, u32>::new();
-		let mut rulestack = Vec::<(super::MagicRule, NodeIndex)>::new();
-		
-		for x in magic_rules {
-			let xnode = graph.add_node(x.clone());
-			
-			loop {
-				let y = rulestack.pop();
-				match y {
-					None => {break;},
-					Some(rule) => {
-						if rule.0.indent_level < x.indent_level {
-							graph.add_edge(rule.1, xnode, 1);
-							rulestack.push( rule );
-							break;
-						}
-					}
-				};
-			}
-			rulestack.push( (x, xnode) );
-			
-		}
-		
-		let graph = graph;
-		graph
-	}
-    
-    pub fn from_u8(b: &[u8]) -> Result<FnvHashMap<MIME, DiGraph<super::MagicRule, u32>>, String> {
-        let tuplevec = from_u8_to_tuple_vec(b).to_result().map_err(|e| e.to_string())?;;
-        let mut res = FnvHashMap::<MIME, DiGraph<super::MagicRule, u32>>::default();
-        
-        for x in tuplevec {
-            res.insert(x.0, gen_graph(x.1));
+    /// A rule's fields, borrowed from the magic file
+    #[cfg(any(feature="fs", feature="fuzz"))]
+    struct RawRule<'a> {
+        indent_level: u32,
+        start_off: u32,
+        val: &'a [u8],
+        mask: Option<&'a [u8]>,
+        word_len: u32,
+        region_len: u32
+    }
+
+    /// Singular magic rule
+    /// Format: [indent]>start-offset=value-length value [&mask] [~word-size] [+range-length]
+    #[cfg(any(feature="fs", feature="fuzz"))]
+    fn raw_rule<'a>(b: &'a [u8], pos: &mut usize) -> Result<RawRule<'a>, String> {
+        let indent_level = read_number(b, pos).unwrap_or(0);
+        expect(b, pos, b'>')?;
+        let start_off = read_number(b, pos).unwrap_or(0);
+        expect(b, pos, b'=')?;
+        let len = take(b, pos, 2)?;
+        let val_len = (len[0] as u16) << 8 | len[1] as u16;
+        let val = take(b, pos, val_len as usize)?;
+        let mask = match eat(b, pos, b'&') {
+            true => Some(take(b, pos, val_len as usize)?),
+            false => None
+        };
+        // Word size, default 1
+        let word_len = match eat(b, pos, b'~') {
+            true => read_number(b, pos).unwrap_or(1),
+            false => 1
+        };
+        // Number of offsets the value may start at, from start-offset on.
+        // Left out (0) or 1 means just the offset.
+        let region_len = match eat(b, pos, b'+') {
+            true => read_number(b, pos).unwrap_or(0),
+            false => 0
+        };
+        expect(b, pos, b'\n')?;
+
+        Ok(RawRule{indent_level, start_off, val, mask, word_len, region_len})
+    }
+
+    /// Singular magic rule, borrowing its value and mask from the file
+    #[cfg(feature="fs")]
+    fn magic_rule(b: &'static [u8], pos: &mut usize, priority: u32) -> Result<super::MagicRule, String> {
+        let x = raw_rule(b, pos)?;
+        Ok(super::MagicRule{
+            indent_level: x.indent_level,
+            start_off: x.start_off,
+            val: Cow::Borrowed(x.val),
+            mask: x.mask.map(Cow::Borrowed),
+            word_len: x.word_len,
+            region_len: x.region_len,
+            priority
+        })
+    }
+
+    /// Section of a magic file: MIME type, priority, and the bytes of its rules
+    #[cfg(feature="fs")]
+    pub type Section = (&'static str, u32, &'static [u8]);
+
+    /// Splits a magic file into sections, checking the rules parse without
+    /// keeping them
+    #[cfg(feature="fs")]
+    pub fn index(b: &'static [u8]) -> Result<Vec<Section>, String> {
+        const HEADER: &[u8] = b"MIME-Magic\0\n";
+        if !b.starts_with(HEADER) {
+            return Err("magic file header not found".to_string());
         }
-        
-        Ok(res)
-        
+
+        let mut pos = HEADER.len();
+        let mut out = Vec::<Section>::new();
+        while pos < b.len() {
+            let (priority, mimetype) = mime(b, &mut pos)?;
+            let start = pos;
+            while pos < b.len() && b[pos] != b'[' {
+                magic_rule(b, &mut pos, priority)?;
+            }
+            out.push((mimetype, priority, &b[start..pos]));
+        }
+        Ok(out)
     }
 
-    /// Loads the given magic file and outputs a vector of MagicEntry structs
-    #[cfg(not(feature="staticmime"))]
-    pub fn from_filepath(filepath: &str) -> Result<FnvHashMap<MIME, DiGraph<super::MagicRule, u32>>, String>{
-        use std::io::prelude::*;
-        use std::io::BufReader;
-        use std::fs::File;
-
-        let fmagic = File::open(filepath).map_err(|e| e.to_string())?;
-        let mut rmagic = BufReader::new(fmagic);
-        let mut bmagic = Vec::<u8>::new();
-        rmagic.read_to_end(&mut bmagic).map_err(|e| e.to_string())?;
-        
-        let magic_ruleset = from_u8(
-            bmagic.as_slice()
-        ).map_err(|e| e.to_string())?;
-        
-        Ok(magic_ruleset)
+    /// Parses a whole magic file that isn't kept around, copying each
+    /// rule's value and mask
+    #[cfg(feature="fuzz")]
+    pub fn parse(b: &[u8]) -> Result<Vec<(String, Vec<super::MagicRule>)>, String> {
+        const HEADER: &[u8] = b"MIME-Magic\0\n";
+        if !b.starts_with(HEADER) {
+            return Err("magic file header not found".to_string());
+        }
+
+        let mut pos = HEADER.len();
+        let mut out = Vec::<(String, Vec<super::MagicRule>)>::new();
+        while pos < b.len() {
+            let (priority, mimetype) = mime(b, &mut pos)?;
+            let mut rules = Vec::<super::MagicRule>::new();
+            while pos < b.len() && b[pos] != b'[' {
+                let x = raw_rule(b, &mut pos)?;
+                rules.push(super::MagicRule{
+                    indent_level: x.indent_level,
+                    start_off: x.start_off,
+                    val: Cow::Owned(x.val.to_vec()),
+                    mask: x.mask.map(|y| Cow::Owned(y.to_vec())),
+                    word_len: x.word_len,
+                    region_len: x.region_len,
+                    priority
+                });
+            }
+            out.push((mimetype.to_string(), rules));
+        }
+        Ok(out)
     }
 
-}
+    /// Parses the rules of one section, as found by `index`
+    #[cfg(feature="fs")]
+    pub fn parse_section(b: &'static [u8], priority: u32) -> Vec<super::MagicRule> {
+        let mut pos = 0;
+        let mut rules = Vec::<super::MagicRule>::new();
+        while pos < b.len() {
+            match magic_rule(b, &mut pos, priority) {
+                Ok(x) => rules.push(x),
+                Err(_e) => {
+                    trace_event!(warn, error = %_e, "stopped parsing magic rules");
+                    break;
+                }
+            }
+        }
+        rules
+    }
 
-// Functions to check if a file matches a magic entry
-pub mod check {
+    /// Furthest into a file any rule of a section looks, as found by
+    /// `index`, reading only the rules' offsets and lengths
+    #[cfg(feature="fs")]
+    pub fn section_extent(b: &[u8]) -> usize {
+        let mut pos = 0;
+        let mut out = 0;
+        while pos < b.len() {
+            match raw_rule(b, &mut pos) {
+                Ok(x) => out = std::cmp::max(out, super::rule_extent(x.start_off, x.region_len, x.val.len())),
+                Err(_) => break
+            }
+        }
+        out
+    }
 
-    extern crate std;
-    extern crate petgraph;
-    use petgraph::prelude::*;
+    /// Converts a magic file given as a &[u8] array
+    /// to a vector of MIME types and their rules
+    #[cfg(feature="cache")]
+    pub fn from_u8_to_tuple_vec(b: &'static [u8]) -> Result<Vec<(&'static str, Vec<super::MagicRule>)>, String> {
+        Ok(index(b)?.into_iter().map(|x| (x.0, parse_section(x.2, x.1))).collect())
+    }
+	
     
-    fn from_u8_singlerule(file: &[u8], rule: &super::MagicRule) -> bool {
+    /// Indexes a magic file, leaving each MIME type's rules to be parsed
+    /// the first time they're needed
+    #[cfg(feature="fs")]
+    pub fn from_u8(b: &'static [u8]) -> Result<FnvHashMap<MIME, super::LazyRules>, String> {
+        let mut sections = FnvHashMap::<MIME, Vec<(u32, &'static [u8])>>::default();
         
-        // Check if we're even in bounds
-        let bound_min = //std::cmp::min(
-            rule.start_off as usize;
-            //rule.val.len()
-        //);
-        let bound_max =
-            //std::cmp::min(
-            //(
-                rule.start_off as usize +
-                rule.val_len as usize +
-                rule.region_len as usize;
-            //),
-            //rule.val.len()
-        //);
-
-        if (file.len()) < bound_max {
-            return false;
+        // Skip rules for left out families rather than keeping them around
+        for x in index(b)?.into_iter().filter(|x| ::family_enabled(x.0)) {
+            sections.entry(MIME::from(x.0)).or_default().push((x.1, x.2));
         }
-		
-		if rule.region_len == 0 {
-			
-			//println!("Region == 0");
-			
-			match rule.mask {
-				None => {
-					//println!("\tMask == None");
-					let x: Vec<u8> = file.iter().skip(bound_min).take(bound_max - bound_min).map(|&x| x).collect();
-					//println!("\t{:?} / {:?}", x, rule.val);
-					//println!("\tIndent: {}, Start: {}", rule.indent_level, rule.start_off);
-					return rule.val.iter().eq(x.iter());
-				},
-				Some(ref mask) => {
-					//println!("\tMask == Some, len == {}", mask.len());
-					//println!("\tIndent: {}, Start: {}", rule.indent_level, rule.start_off);
-					let mut x: Vec<u8> = file.iter()
-						.skip(bound_min) // Skip to start of area
-						.take(bound_max - bound_min) // Take until end of area - region length
-						.map(|&x| x).collect(); // Convert to vector
-					let mut val: Vec<u8> = rule.val.iter().map(|&x| x).collect();
-					//println!("\t{:?} / {:?}", x, rule.val);
-					
-					
-					assert_eq!(x.len(), mask.len());
-					for i in 0..std::cmp::min(x.len(), mask.len()) {
-						x[i] &= mask[i];
-						val[i] = val[i] & mask[i];
-					}
-					//println!("\t & {:?} => {:?}", mask, x);
-					
-					return rule.val.iter().eq(x.iter());
-				}
-			}
-		
-		} else {
-			//println!("\tRegion == {}", rule.region_len);
-			//println!("\tIndent: {}, Start: {}", rule.indent_level, rule.start_off);
-					
-			// Define our testing slice
-			let ref x: Vec<u8> = file.iter().take(file.len()).map(|&x| x).collect();
-			let testarea: Vec<u8> = x.iter().skip(bound_min).take(bound_max - bound_min).map(|&x| x).collect();
-			//println!("{:?}, {:?}, {:?}\n", file, testarea, rule.val);
-			
-			// Search down until we find a hit
-			let mut y = Vec::<u8>::with_capacity(testarea.len());
-			for x in testarea.windows(rule.val_len as usize) {
-
-				y.clear();
-				
-				// Apply mask to value
-				let ref rule_mask = rule.mask;
-				match *rule_mask {
-					Some(ref mask) => {
-
-						for i in 0..rule.val_len {
-							y.push(x[i as usize] & mask[i as usize]);
-						}
-					},
-					None => y = x.to_vec(),
-				}
-			
-				if y.iter().eq(rule.val.iter()) {
-					return true;
-				}
-			}
-		}
-
-        false
+        
+        Ok(sections.into_iter()
+            .map(|(k, v)| (k, super::LazyRules::new(super::RuleSource::Magic(v))))
+            .collect())
     }
-    
-    /// Test every given rule by walking graph
-    /// TODO: Not loving the code duplication here.
-    pub fn from_u8_walker(
-        file: &[u8],
-        mimetype: &str,
-        graph: &DiGraph<super::MagicRule, u32>,
-        node: NodeIndex,
-        isroot: bool
-    ) -> bool {
-
-        let n = graph.neighbors_directed(node, Outgoing);
+
+    /// Takes a vector of MIME types and their rules, already parsed
+    #[cfg(feature="cache")]
+    pub fn from_tuple_vec<S: AsRef<str> + Into<MIME>>(
+        tuplevec: Vec<(S, Vec<super::MagicRule>)>
+    ) -> FnvHashMap<MIME, super::LazyRules> {
+        let mut sections = FnvHashMap::<MIME, Vec<super::MagicRule>>::default();
         
-        if isroot {
-            let ref rule = graph[node];
-            
-            // Check root
-            if !from_u8_singlerule(&file, rule) {
-                return false;
-            }
-            
-            // Return if that was the only test
-            if n.clone().count() == 0 {
-                return true;
-            }
-            
-            // Otherwise next indent level is lower, so continue
+        for x in tuplevec.into_iter().filter(|x| ::family_enabled(x.0.as_ref())) {
+            sections.entry(x.0.into()).or_default().extend(x.1);
         }
         
-        // Check subrules recursively
-        for y in n {
-            let ref rule = graph[y];
-            
-            if from_u8_singlerule(&file, rule) {
-                // Check next indent level if needed
-                if graph.neighbors_directed(y, Outgoing).count() != 0 {
-                    return from_u8_walker(file, mimetype, graph, y, false);
-                // Next indent level is lower, so this must be it
-                } else {
-                    return true;
-                }
-            }
+        sections.into_iter()
+            .map(|(k, v)| (k, super::LazyRules::new(super::RuleSource::Parsed(v))))
+            .collect()
+    }
+
+    /// Takes rules parsed at build time, without copying them
+    #[cfg(not(feature="no-embedded-db"))]
+    pub fn from_static(
+        rules: &'static [(&'static str, u32, &'static [::tree_magic_db::Rule])]
+    ) -> FnvHashMap<MIME, super::LazyRules> {
+        let mut sections = FnvHashMap::<MIME, Vec<(u32, &'static [::tree_magic_db::Rule])>>::default();
+
+        for x in rules.iter().filter(|x| ::family_enabled(x.0)) {
+            sections.entry(MIME::from(x.0)).or_default().push((x.1, x.2));
         }
-		
-		false
+
+        sections.into_iter()
+            .map(|(k, v)| (k, super::LazyRules::new(super::RuleSource::Static(v))))
+            .collect()
     }
 
 }
-
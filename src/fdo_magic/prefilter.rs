@@ -0,0 +1,174 @@
+//! Pre-filter over top-level magic rules
+//!
+//! A type's top-level rules are alternatives, and it can't match unless one
+//! of them does. Most of them are plain byte strings, so looking for all of
+//! them at once finds every type with a matching top-level rule, and the walk
+//! can turn the rest down without looking at their rules. Strings anchored at
+//! the start of the file are looked up in a trie; the rest are found by one
+//! pass of an Aho–Corasick automaton. Types with a top-level rule that can't
+//! be searched for this way, such as a masked one, are always let through.
+//!
+//! Building the automaton compiles every type's rules, so it's left until
+//! an input has been checked against more than a few types. Checking one
+//! type, as `match_u8` does, only ever compiles that type.
+
+use std;
+use std::prelude::v1::*;
+use aho_corasick::AhoCorasick;
+use FnvHashMap;
+use MIME;
+use super::LazyRules;
+use super::bytecode::Insn;
+use super::trie::PrefixTrie;
+
+/// Where a pattern has to turn up for a type's rule to match
+struct Place {
+    /// Index of the type in `Prefilter::types`
+    mimetype: usize,
+    /// First and last offsets the pattern may start at
+    first: usize,
+    last: usize,
+    /// The input has to reach at least this far
+    min_len: usize
+}
+
+/// Automaton over the top-level rules of every type it covers
+pub struct Prefilter {
+    /// Patterns that are only ever anchored at offset 0
+    trie: PrefixTrie,
+    /// All other patterns, with our pattern ID for each of the automaton's
+    ac: AhoCorasick,
+    ac_ids: Vec<usize>,
+    /// Places for each pattern, by pattern ID
+    places: Vec<Vec<Place>>,
+    /// Types covered, and their index in a `Candidates`
+    types: FnvHashMap<MIME, usize>,
+    /// No top-level rule looks past this
+    scan_len: usize
+}
+
+/// Which of the covered types could match one input
+#[derive(Debug, Clone)]
+pub struct Candidates(Vec<bool>);
+
+/// How many types an input is checked against by running their rules alone,
+/// before the pre-filter is built and run over it
+const DIRECT_CHECKS: usize = 8;
+
+/// What the pre-filter knows about one input
+#[derive(Debug, Clone, Default)]
+pub struct State {
+    /// Types checked against the input so far
+    checked: usize,
+    /// Which types could match, once the pre-filter has run
+    candidates: Option<Candidates>
+}
+
+impl State {
+    /// Whether the type could match the input. Until `DIRECT_CHECKS` types
+    /// have been asked about, every type could; after that, the pre-filter
+    /// from `prefilter` is run over the input, once, and decides.
+    pub fn passes<F: FnOnce() -> &'static Prefilter>(&mut self, mimetype: &str, file: &[u8], prefilter: F) -> bool {
+        if self.candidates.is_none() {
+            self.checked += 1;
+            if self.checked <= DIRECT_CHECKS {
+                return true;
+            }
+        }
+        let prefilter = prefilter();
+        let candidates = self.candidates.get_or_insert_with(|| prefilter.scan(file));
+        prefilter.passes(mimetype, candidates)
+    }
+}
+
+impl Prefilter {
+    /// Builds the automaton. This compiles every type's rules.
+    pub fn new(rules: &FnvHashMap<MIME, LazyRules>) -> Prefilter {
+        let mut patterns = Vec::<&[u8]>::new();
+        let mut pattern_ids = FnvHashMap::<&[u8], usize>::default();
+        let mut places = Vec::<Vec<Place>>::new();
+        let mut types = FnvHashMap::<MIME, usize>::default();
+
+        'types: for (mimetype, item) in rules.iter() {
+            let program = item.program();
+
+            // Leave the type out unless every top-level rule can be searched for
+            let roots: Vec<&Insn> = program.code().iter().filter(|x| x.root).collect();
+            if roots.is_empty() {
+                continue;
+            }
+            for &x in roots.iter() {
+                if program.mask(x).is_some() || x.len == 0 {
+                    continue 'types;
+                }
+            }
+
+            let index = types.len();
+            types.insert(mimetype.clone(), index);
+            for x in roots {
+                let first = x.offset as usize;
+                let last = first + x.range as usize;
+                let min_len = first + x.len as usize;
+
+                let val = program.val(x);
+                let id = *pattern_ids.entry(val).or_insert_with(|| {
+                    patterns.push(val);
+                    places.push(Vec::new());
+                    patterns.len() - 1
+                });
+                places[id].push(Place{mimetype: index, first, last, min_len});
+            }
+        }
+
+        let mut trie = PrefixTrie::default();
+        let mut unanchored = Vec::<&[u8]>::new();
+        let mut ac_ids = Vec::<usize>::new();
+        let mut scan_len = 0;
+        for (id, &pattern) in patterns.iter().enumerate() {
+            if places[id].iter().all(|x| x.last == 0) {
+                trie.insert(pattern, id);
+                continue;
+            }
+            unanchored.push(pattern);
+            ac_ids.push(id);
+            for x in places[id].iter() {
+                scan_len = std::cmp::max(scan_len, x.last + pattern.len());
+            }
+        }
+
+        let ac = AhoCorasick::new(&unanchored)
+            .expect("magic patterns fit in an automaton");
+
+        Prefilter{trie, ac, ac_ids, places, types, scan_len}
+    }
+
+    /// Finds the covered types that could match the input
+    pub fn scan(&self, file: &[u8]) -> Candidates {
+        let mut out = vec![false; self.types.len()];
+        let mut mark = |id: usize, pos: usize| {
+            for place in self.places[id].iter() {
+                if pos >= place.first && pos <= place.last && file.len() >= place.min_len {
+                    out[place.mimetype] = true;
+                }
+            }
+        };
+
+        self.trie.find(file, |id| mark(id, 0));
+
+        let file_part = &file[..std::cmp::min(file.len(), self.scan_len)];
+        for found in self.ac.find_overlapping_iter(file_part) {
+            mark(self.ac_ids[found.pattern().as_usize()], found.start());
+        }
+
+        Candidates(out)
+    }
+
+    /// Whether the type could match an input with these candidates. Types
+    /// the automaton doesn't cover always could.
+    pub fn passes(&self, mimetype: &str, candidates: &Candidates) -> bool {
+        match self.types.get(mimetype) {
+            Some(&x) => candidates.0[x],
+            None => true
+        }
+    }
+}
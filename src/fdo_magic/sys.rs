@@ -1,114 +1,242 @@
 //! Read magic file(s) on user's computer
 //!
-//! May or may not work, depending on platform, but in that case
-//! this will just say it can't handle any file types, and will
-//! never be invoked.
-
-extern crate std;
-extern crate petgraph;
-extern crate fnv;
-use petgraph::prelude::*;
-use fnv::FnvHashMap;
+//! Falls back on the embedded database when the system one can't be read.
+//! With the `no-embedded-db` feature there is nothing to fall back on, so
+//! this will just say it can't handle any file types, and will never be
+//! invoked. Either way, `tree_magic::database()` reports what went wrong.
+
+use std;
+use std::prelude::v1::*;
+use std::borrow::Cow;
+use once_cell::sync::OnceCell;
+use FnvHashMap;
 use MIME;
-use super::MagicRule;
+use super::LazyRules;
+use super::prefilter::Prefilter;
 
-/// Dummy cache type
-pub type Cache = bool;
+/// Types the pre-filter let through for the file being checked
+pub type Cache = super::prefilter::State;
 
-/// Preload alias list
 lazy_static! {
+	/// Preload alias list
 	static ref ALIASES: FnvHashMap<MIME, MIME> = {
 		init::get_aliaslist()
 	};
 }
 
-/// Load magic file before anything else.
-/// sys_fdo_magic always disabled on Windows.
+/// The system magic file
+const MAGIC_PATH: &str = "/usr/share/mime/magic";
+
+lazy_static! {
+    /// The magic file in use, and whether it's the embedded one
+    static ref MAGIC: (Cow<'static, [u8]>, bool) = {
+        match std::fs::read(MAGIC_PATH) {
+            Ok(x) => (Cow::Owned(x), false),
+            Err(_) => (Cow::Borrowed(super::embedded::MAGIC), true)
+        }
+    };
+}
+
+lazy_static! {
+    /// Index the magic file before anything else. Rules for each type are
+    /// parsed when first used; the embedded ones were parsed at build time.
+    static ref ALLRULES: FnvHashMap<MIME, LazyRules> = {
+        load_rules()
+    };
+}
+
+/// What went wrong loading `ALLRULES`, if anything
+static LOAD_ERROR: OnceCell<::InitError> = OnceCell::new();
+
+/// Parses the system magic file, or takes it from the cache if the `cache`
+/// feature is on and the cache is up to date
+fn load_rules() -> FnvHashMap<MIME, LazyRules> {
+    #[cfg(feature="cache")]
+    {
+        if let Some(x) = super::cache::load(std::path::Path::new(MAGIC_PATH)) {
+            trace_event!(debug, types = x.len(), "loaded magic rules from the cache");
+            return super::ruleset::from_tuple_vec(x);
+        }
+    }
+
+    if MAGIC.1 {
+        if MAGIC.0.is_empty() {
+            trace_event!(warn, path = MAGIC_PATH, "no magic database, so no magic rules are loaded");
+            let _ = LOAD_ERROR.set(::InitError::NoDatabase);
+        } else {
+            trace_event!(debug, path = MAGIC_PATH, "no system magic file, using the embedded one");
+        }
+        return super::embedded::rules();
+    }
+
+    // Writing the cache needs every rule parsed up front
+    #[cfg(feature="cache")]
+    {
+        if let Ok(x) = super::ruleset::from_u8_to_tuple_vec(&MAGIC.0) {
+            super::cache::store(std::path::Path::new(MAGIC_PATH), &x);
+        }
+    }
+    match super::ruleset::from_u8(&MAGIC.0) {
+        Ok(x) => {
+            trace_event!(debug, path = MAGIC_PATH, types = x.len(), "indexed system magic file");
+            x
+        },
+        Err(e) => {
+            trace_event!(warn, path = MAGIC_PATH, error = %e, "couldn't parse system magic file");
+            let _ = LOAD_ERROR.set(::InitError::Magic(e));
+            FnvHashMap::default()
+        }
+    }
+}
+
+/// Loads the rules if they aren't already, and says what went wrong
+pub fn init_error() -> Option<::InitError> {
+    ::Lazy::force(&ALLRULES);
+    LOAD_ERROR.get().cloned()
+}
+
+/// Describes the magic file in use
+pub fn db_version() -> ::DbVersion {
+    let version = match MAGIC.1 {
+        true => super::embedded::VERSION.map(|x| x.to_string()),
+        false => std::fs::read_to_string("/usr/share/mime/version").ok().map(|x| x.trim().to_string())
+    };
+    ::DbVersion {
+        version,
+        hash: super::hash_magic(&MAGIC.0),
+        embedded: MAGIC.1
+    }
+}
+
 lazy_static! {
-    static ref ALLRULES: FnvHashMap<MIME, DiGraph<MagicRule, u32>> = {
-        super::ruleset::from_filepath("/usr/share/mime/magic").unwrap()
+    /// Automaton over every type's top-level rules
+    static ref PREFILTER: Prefilter = {
+        Prefilter::new(&ALLRULES)
+    };
+    static ref SCAN_EXTENT: usize = {
+        super::scan_extent(&ALLRULES)
     };
 }
 
-#[cfg(not(feature="staticmime"))]
-macro_rules! convmime {
-    ($x:expr) => {$x.to_string()}
+/// Highest priority of the rules for each type
+pub fn priorities() -> FnvHashMap<MIME, u32> {
+    super::priorities(&ALLRULES)
 }
-#[cfg(feature="staticmime")]
-macro_rules! convmime {
-    ($x:expr) => {$x}
+
+/// Priority of the best of a type's rules that matches the file
+pub fn matched_priority(mimetype: &str, file: &[u8]) -> Option<u32> {
+    let mimetype = ALIASES.get(mimetype).map(|x| &**x).unwrap_or(mimetype);
+    ALLRULES.get(mimetype).map(|x| x.matched_priority(file))
+}
+
+/// Parsed rules for a type, or None if it has none
+pub fn rules_for(mimetype: &str) -> Option<Vec<super::MagicRule>> {
+    let mimetype = ALIASES.get(mimetype).map(|x| &**x).unwrap_or(mimetype);
+    ALLRULES.get(mimetype).map(|x| x.rules())
+}
+
+/// The rule of a type that accepted the file, and where in the file its
+/// value was found
+pub fn fired(mimetype: &str, file: &[u8]) -> Option<(super::MagicRule, usize)> {
+    let mimetype = ALIASES.get(mimetype).map(|x| &**x).unwrap_or(mimetype);
+    ALLRULES.get(mimetype)?.fired(file)
+}
+
+/// Every type with rules that match the file, and the priority of the best
+/// of its rules that does
+pub fn matches(file: &[u8]) -> Vec<(MIME, u32)> {
+    super::matches(&ALLRULES, &PREFILTER, file)
+}
+
+/// Furthest into a file any loaded rule looks
+pub fn scan_extent() -> usize {
+    *SCAN_EXTENT
 }
 
 pub mod init {
-    extern crate std;
-    extern crate fnv;
-    use fnv::FnvHashMap;
-    use std::io::prelude::*;
-    use std::io::BufReader;
-    use std::fs::File;
+    use std;
+    use std::prelude::v1::*;
+    use FnvHashMap;
     use MIME;
     
+    /// Reads a list from the system database, or the embedded one if the
+    /// system has none
+    fn read_list(name: &str, embedded: &str) -> String {
+        std::fs::read_to_string(std::path::Path::new("/usr/share/mime").join(name))
+            .unwrap_or_else(|_| embedded.to_string())
+    }
+
     /// Read all subclass lines from file
-    fn read_subclasses() -> Result<Vec<(MIME, MIME)>, std::io::Error> {
+    fn read_subclasses() -> Vec<(MIME, MIME)> {
     
-        let f = File::open("/usr/share/mime/subclasses")?;
-        let r = BufReader::new(f);
+        let r = read_list("subclasses", super::super::embedded::SUBCLASSES);
         let mut subclasses = Vec::<(MIME, MIME)>::new();
         
-        for x in r.lines() {
-            let line = x?;
-            
-            let child = convmime!(line.split_whitespace().nth(0).unwrap_or(""));
-            let parent = convmime!(line.split_whitespace().nth(1).unwrap_or(""));
+        for line in r.lines() {
+            let child = MIME::from(line.split_whitespace().next().unwrap_or("").to_string());
+            let parent = MIME::from(line.split_whitespace().nth(1).unwrap_or("").to_string());
             
             subclasses.push( (parent, child) );
         }
         
-        Ok(subclasses)
+        subclasses
     }
     
     // Get filetype aliases
-    fn read_aliaslist() -> Result<FnvHashMap<MIME, MIME>, std::io::Error> {
-        let faliases = File::open("/usr/share/mime/aliases")?;
-        let raliases = BufReader::new(faliases);
+    fn read_aliaslist() -> FnvHashMap<MIME, MIME> {
+        let raliases = read_list("aliases", super::super::embedded::ALIASES);
         let mut aliaslist = FnvHashMap::<MIME, MIME>::default();
         
-        for x in raliases.lines() {
-            let line = x?;
-        
-            let a = convmime!(line.split_whitespace().nth(0).unwrap_or(""));
-            let b = convmime!(line.split_whitespace().nth(1).unwrap_or(""));
+        for line in raliases.lines() {
+            let a = MIME::from(line.split_whitespace().next().unwrap_or("").to_string());
+            let b = MIME::from(line.split_whitespace().nth(1).unwrap_or("").to_string());
             aliaslist.insert(a,b);
         }
         
-        let aliaslist = aliaslist;
-        Ok(aliaslist)
+        aliaslist
     }
     
+    /// File name patterns of each type, in the order the database gives
+    /// them. Only the system database has these; none are embedded.
+    pub fn get_globs() -> Vec<(MIME, String)> {
+        get_weighted_globs().into_iter().map(|x| (x.1, x.2)).collect()
+    }
+
+    /// Like `get_globs`, along with each pattern's weight, and whether it's
+    /// case-sensitive
+    pub fn get_weighted_globs() -> Vec<(u32, MIME, String, bool)> {
+        let globs = read_list("globs2", "");
+        globs.lines()
+            .filter(|x| !x.starts_with('#'))
+            .filter_map(|x| {
+                let mut fields = x.split(':');
+                let weight = fields.next()?.parse().unwrap_or(50);
+                let mimetype = MIME::from(fields.next()?.to_string());
+                let pattern = fields.next()?.to_string();
+                let cs = fields.next().is_some_and(|x| x.split(',').any(|y| y == "cs"));
+                Some((weight, mimetype, pattern, cs))
+            })
+            .collect()
+    }
+
     pub fn get_aliaslist() -> FnvHashMap<MIME, MIME> {
-        read_aliaslist().unwrap()
+        read_aliaslist()
     }
 
     /// Get list of supported MIME types
     pub fn get_supported() -> Vec<MIME> {
-        super::ALLRULES.keys().map(|x| convmime!(x)).collect()
+        super::ALLRULES.keys().cloned().collect()
     }
 
     /// Get list of parent -> child subclass links
     pub fn get_subclasses() -> Vec<(MIME, MIME)> {
     
-        let mut subclasses = read_subclasses().unwrap();
+        let mut subclasses = read_subclasses();
         
         // If child or parent refers to an alias, change it to the real type
-        for x in 0..subclasses.len(){
-            match super::ALIASES.get(&subclasses[x].0) {
-                Some(alias) => {subclasses[x].0 = alias.clone();}
-                None => {}
-            }
-            match super::ALIASES.get(&subclasses[x].1) {
-                Some(alias) => {subclasses[x].1 = alias.clone();}
-                None => {}
-            }
+        for x in subclasses.iter_mut() {
+            if let Some(alias) = super::ALIASES.get(&x.0) {x.0 = alias.clone();}
+            if let Some(alias) = super::ALIASES.get(&x.1) {x.1 = alias.clone();}
         }
         
         subclasses
@@ -116,11 +244,25 @@ pub mod init {
 }
 
 pub mod check {
-    extern crate std;
-    extern crate petgraph;
+    use std::prelude::v1::*;
+    #[cfg(feature="fs")]
     use std::path::Path;
-    use petgraph::prelude::*;
-    use super::super::super::{CacheItem, fdo_magic};
+    use super::super::super::{Cache, CacheItem};
+    #[cfg(feature="fs")]
+    use super::super::super::slurp_to_cache;
+
+    /// Says whether the type gets through the pre-filter, once the file has
+    /// been checked against enough types for it to run
+    fn passes_prefilter(file: &[u8], mimetype: &str, cache: &CacheItem) -> bool {
+        let mut lock = cache.write();
+        if lock.is_none() {
+            *lock = Some(Cache::FdoMagicSys(Default::default()));
+        }
+        match *lock {
+            Some(Cache::FdoMagicSys(ref mut x)) => x.passes(mimetype, file, || &*super::PREFILTER),
+            _ => true
+        }
+    }
 
     /// Test against all rules
     #[allow(unused_variables)]
@@ -135,67 +277,42 @@ pub mod check {
 		};
     
         // Get magic ruleset
-        let graph = match super::ALLRULES.get(mimetype) {
-            Some(item) => item,
+        let program = match super::ALLRULES.get(mimetype) {
+            Some(item) => item.program(),
             None => return false // No rule for this mime
         };
-        
-        // Check all rulesets
-        for x in graph.externals(Incoming) {
-            if fdo_magic::check::from_u8_walker(file, mimetype, graph, x, true) {
-                return true;
-            }
+
+        if !passes_prefilter(file, mimetype, cache) {
+            return false;
         }
         
-        false
+        // Check all rulesets
+        program.run(file)
     }
     
     /// This only exists for the case of a direct match_filepath call
     /// and even then we could probably get rid of this...
+    #[cfg(feature="fs")]
     #[allow(unused_variables)]
     pub fn from_filepath(
         filepath: &Path, mimetype: &str, cache: &CacheItem, filecache: &CacheItem
     ) -> bool{
-        use std::fs::File;
-        use std::io::Read;
-        
         // Get magic ruleset
-        let magic_rules = match super::ALLRULES.get(mimetype) {
-            Some(item) => item,
+        let program = match super::ALLRULES.get(mimetype) {
+            Some(item) => item.program(),
             None => return false // No rule for this mime
         };
 
         // Get # of bytes to read
-        let mut scanlen = 0;
-        for x in magic_rules.raw_nodes() {
-			let ref y = x.weight;
-            let tmplen = 
-                y.start_off as usize +
-                y.val_len as usize +
-                y.region_len as usize;
-                
-            if tmplen > scanlen {
-                scanlen = tmplen;
-            }
-        }
+        let scanlen = program.scan_len();
         
-        let mut f = match File::open(filepath) {
+        let b = match slurp_to_cache(filepath, filecache, scanlen) {
             Ok(x) => x,
             Err(_) => return false
         };
-        let mut b = Vec::<u8>::with_capacity(scanlen);
-        
-        // Fill up vector with something
-        for i in 0..scanlen {
-            let _ = i;
-            b.push(0);
-        }
-//         
-        match f.read_exact(&mut b) {
-            Ok(_) => {},
-            Err(_) => return false
-        }
         
-        from_u8(b.as_slice(), mimetype, cache, &CacheItem::default())
+        // Only as much of the file as this type needs was read, so the
+        // pre-filter has to start over
+        from_u8(b.as_slice(), mimetype, &CacheItem::default(), &CacheItem::default())
     }
 }
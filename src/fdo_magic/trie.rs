@@ -0,0 +1,74 @@
+//! Trie over magic that's anchored at the start of a file
+//!
+//! Most formats are told apart by their first few bytes, so walking those
+//! bytes down a trie leads straight to the handful of patterns the file
+//! could start with.
+
+use std;
+use std::prelude::v1::*;
+
+/// How many bytes of a pattern the trie branches on. Longer patterns hang
+/// off the node at this depth, and the rest is compared directly.
+const DEPTH: usize = 16;
+
+#[derive(Default)]
+struct Node {
+    /// Next byte, and the node it leads to, sorted by byte
+    children: Vec<(u8, usize)>,
+    /// Patterns ending here: what's left of them past `DEPTH`, and their ID
+    ends: Vec<(Vec<u8>, usize)>
+}
+
+/// Patterns anchored at offset 0, keyed by their first `DEPTH` bytes
+pub struct PrefixTrie {
+    nodes: Vec<Node>
+}
+
+impl Default for PrefixTrie {
+    fn default() -> PrefixTrie {
+        PrefixTrie{nodes: vec![Node::default()]}
+    }
+}
+
+impl PrefixTrie {
+    /// Adds a pattern, to be reported by `find` with the given ID
+    pub fn insert(&mut self, pattern: &[u8], id: usize) {
+        let (key, rest) = pattern.split_at(std::cmp::min(pattern.len(), DEPTH));
+        let mut node = 0;
+        for &x in key {
+            node = match self.nodes[node].children.binary_search_by_key(&x, |y| y.0) {
+                Ok(i) => self.nodes[node].children[i].1,
+                Err(i) => {
+                    let next = self.nodes.len();
+                    self.nodes.push(Node::default());
+                    self.nodes[node].children.insert(i, (x, next));
+                    next
+                }
+            };
+        }
+        self.nodes[node].ends.push((rest.to_vec(), id));
+    }
+
+    /// Calls `found` with the ID of every pattern the input starts with
+    pub fn find<F: FnMut(usize)>(&self, input: &[u8], mut found: F) {
+        let mut node = &self.nodes[0];
+        let mut depth = 0;
+        loop {
+            for &(ref rest, id) in node.ends.iter() {
+                if input[depth..].starts_with(rest) {
+                    found(id);
+                }
+            }
+
+            let next = match input.get(depth) {
+                Some(&x) if depth < DEPTH => x,
+                _ => return
+            };
+            node = match node.children.binary_search_by_key(&next, |y| y.0) {
+                Ok(i) => &self.nodes[node.children[i].1],
+                Err(_) => return
+            };
+            depth += 1;
+        }
+    }
+}
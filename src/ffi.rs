@@ -0,0 +1,145 @@
+//! C interface, so C, C++ and anything with a C FFI (such as Python's
+//! `ctypes`) can use `tree_magic` in place of libmagic.
+//!
+//! Requires the `ffi` feature. The matching header is `include/tree_magic.h`.
+//!
+//! Strings returned by this module are owned by the caller, and must be
+//! released with `tree_magic_free`.
+//!
+//! # Examples
+//! ```c
+//! #include "tree_magic.h"
+//!
+//! char *mime = tree_magic_from_path("/tmp/image.gif");
+//! if (mime != NULL) {
+//!     printf("%s\n", mime);
+//!     tree_magic_free(mime);
+//! }
+//! ```
+extern crate std;
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+#[cfg(feature="fs")]
+use std::path::PathBuf;
+
+/// Borrows a byte buffer given as a pointer and a length
+unsafe fn as_bytes<'a>(bytes: *const u8, len: usize) -> Option<&'a [u8]> {
+    if bytes.is_null() {
+        if len == 0 { Some(&[]) } else { None }
+    } else {
+        Some(std::slice::from_raw_parts(bytes, len))
+    }
+}
+
+/// Borrows a NUL-terminated UTF-8 string
+unsafe fn as_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+    CStr::from_ptr(s).to_str().ok()
+}
+
+/// Turns a NUL-terminated path into a `PathBuf`. On Unix, paths don't have
+/// to be valid UTF-8.
+#[cfg(all(feature="fs", unix))]
+unsafe fn as_path(s: *const c_char) -> Option<PathBuf> {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    if s.is_null() {
+        return None;
+    }
+    Some(PathBuf::from(OsStr::from_bytes(CStr::from_ptr(s).to_bytes())))
+}
+
+#[cfg(all(feature="fs", not(unix)))]
+unsafe fn as_path(s: *const c_char) -> Option<PathBuf> {
+    as_str(s).map(PathBuf::from)
+}
+
+/// Hands a string over to the caller
+fn into_raw(s: &str) -> *mut c_char {
+    match CString::new(s) {
+        Ok(x) => x.into_raw(),
+        Err(_) => std::ptr::null_mut()
+    }
+}
+
+/// Finds the MIME type of a byte buffer.
+///
+/// Returns a new string, or NULL if `bytes` is NULL and `len` isn't 0.
+///
+/// # Safety
+/// `bytes` must point to at least `len` readable bytes, or be NULL.
+#[no_mangle]
+pub unsafe extern "C" fn tree_magic_from_u8(bytes: *const u8, len: usize) -> *mut c_char {
+    match as_bytes(bytes, len) {
+        Some(b) => into_raw(&::from_u8(b)),
+        None => std::ptr::null_mut()
+    }
+}
+
+/// Finds the MIME type of a file.
+///
+/// Returns a new string, or NULL if `path` is NULL or the file can't be
+/// read.
+///
+/// # Safety
+/// `path` must be a NUL-terminated string, or NULL.
+#[cfg(feature="fs")]
+#[no_mangle]
+pub unsafe extern "C" fn tree_magic_from_path(path: *const c_char) -> *mut c_char {
+    let path = match as_path(path) {
+        Some(x) => x,
+        None => return std::ptr::null_mut()
+    };
+    match ::from_filepath(&path) {
+        Some(x) => into_raw(&x),
+        None => std::ptr::null_mut()
+    }
+}
+
+/// Checks if a byte buffer is of the given MIME type.
+///
+/// Returns false if either pointer is NULL, or `mimetype` isn't UTF-8.
+///
+/// # Safety
+/// `mimetype` must be a NUL-terminated string, or NULL. `bytes` must point
+/// to at least `len` readable bytes, or be NULL.
+#[no_mangle]
+pub unsafe extern "C" fn tree_magic_match(
+    mimetype: *const c_char, bytes: *const u8, len: usize
+) -> bool {
+    match (as_str(mimetype), as_bytes(bytes, len)) {
+        (Some(m), Some(b)) => ::match_u8(m, b),
+        _ => false
+    }
+}
+
+/// Checks if a file is of the given MIME type.
+///
+/// Returns false if either pointer is NULL, or `mimetype` isn't UTF-8.
+///
+/// # Safety
+/// `mimetype` and `path` must be NUL-terminated strings, or NULL.
+#[cfg(feature="fs")]
+#[no_mangle]
+pub unsafe extern "C" fn tree_magic_match_path(
+    mimetype: *const c_char, path: *const c_char
+) -> bool {
+    match (as_str(mimetype), as_path(path)) {
+        (Some(m), Some(p)) => ::match_filepath(m, &p),
+        _ => false
+    }
+}
+
+/// Frees a string returned by this library. Passing NULL does nothing.
+///
+/// # Safety
+/// `s` must have come from this library, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn tree_magic_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
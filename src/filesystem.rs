@@ -0,0 +1,208 @@
+//! Handles partition tables and filesystems, for disk images and block
+//! devices read with `from_device`
+
+/// Dummy cache type
+pub type Cache = bool;
+
+pub mod init {
+
+    use FnvHashMap;
+    use MIME;
+    use std::prelude::v1::*;
+
+    pub fn get_supported() -> Vec<MIME> {
+        vec![
+            MIME::from("application/x-mbr-partition-table"),
+            MIME::from("application/x-gpt-partition-table"),
+            MIME::from("application/x-ext2-filesystem"),
+            MIME::from("application/x-ext3-filesystem"),
+            MIME::from("application/x-ext4-filesystem"),
+            MIME::from("application/x-ntfs-filesystem"),
+            MIME::from("application/x-amiga-disk-format")
+        ]
+    }
+
+    /// Returns Vec of parent->child relations
+    ///
+    /// A GPT disk starts with a protective MBR, so it's a kind of MBR disk.
+    pub fn get_subclasses() -> Vec<(MIME, MIME)> {
+        vec![
+            (MIME::from("application/octet-stream"), MIME::from("application/x-mbr-partition-table")),
+            (MIME::from("application/x-mbr-partition-table"), MIME::from("application/x-gpt-partition-table")),
+            (MIME::from("application/octet-stream"), MIME::from("application/x-ext2-filesystem")),
+            (MIME::from("application/octet-stream"), MIME::from("application/x-ext3-filesystem")),
+            (MIME::from("application/octet-stream"), MIME::from("application/x-ext4-filesystem")),
+            (MIME::from("application/octet-stream"), MIME::from("application/x-ntfs-filesystem")),
+            (MIME::from("application/octet-stream"), MIME::from("application/x-amiga-disk-format"))
+        ]
+    }
+
+    pub fn get_aliaslist() -> FnvHashMap<MIME, MIME> {
+        FnvHashMap::default()
+    }
+
+}
+
+pub mod check {
+
+    use std::prelude::v1::*;
+    #[cfg(feature="fs")]
+    use std::path::Path;
+    use super::super::{CacheItem, input_tail};
+    #[cfg(feature="fs")]
+    use super::super::slurp_to_cache;
+
+    /// Where the ext2/3/4 superblock starts
+    const EXT_SUPERBLOCK: usize = 1024;
+
+    /// ext `s_feature_compat` flag for a journal
+    const EXT_COMPAT_HAS_JOURNAL: u32 = 0x4;
+    /// ext `s_feature_incompat` flag for an external journal device, which
+    /// holds no filesystem
+    const EXT_INCOMPAT_JOURNAL_DEV: u32 = 0x8;
+    /// `s_feature_incompat` flags ext3 knows: file types in directories,
+    /// journal recovery and meta block groups
+    const EXT3_INCOMPAT: u32 = 0x2 | 0x4 | 0x10;
+    /// `s_feature_ro_compat` flags ext3 knows: sparse superblocks, large
+    /// files and B-tree directories
+    const EXT3_RO_COMPAT: u32 = 0x1 | 0x2 | 0x4;
+
+    /// Partition type of the protective MBR entry in front of a GPT
+    const GPT_PROTECTIVE: u8 = 0xee;
+
+    /// Sector sizes a GPT header may follow the MBR at
+    const SECTOR_SIZES: [usize; 2] = [512, 4096];
+
+    /// Sizes of Amiga floppy images: 80 cylinders of 2 tracks, each of 11
+    /// sectors of 512 bytes on a double density disk, or 22 on high density
+    const ADF_SIZES: [u64; 2] = [901120, 1802240];
+
+    fn read_u16_le(b: &[u8]) -> u16 {
+        b[0] as u16 | (b[1] as u16) << 8
+    }
+
+    fn read_u32_le(b: &[u8]) -> u32 {
+        b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24
+    }
+
+    /// A boot sector: 512 bytes ending in `0x55 0xaa`
+    fn has_boot_signature(b: &[u8]) -> bool {
+        b.len() >= 512 && b[510..512] == [0x55, 0xaa]
+    }
+
+    /// The boot sector of a FAT, exFAT or NTFS volume, rather than of a
+    /// partitioned disk. These have a BIOS parameter block where an MBR has
+    /// boot code, and name their filesystem in it.
+    fn is_volume_boot_record(b: &[u8]) -> bool {
+        &b[3..11] == b"NTFS    " || &b[3..11] == b"EXFAT   " ||
+        &b[54..59] == b"FAT12" || &b[54..59] == b"FAT16" || &b[82..87] == b"FAT32"
+    }
+
+    /// The four partition entries of an MBR
+    fn mbr_entries(b: &[u8]) -> impl Iterator<Item=&[u8]> {
+        b[446..510].chunks(16)
+    }
+
+    /// Master boot record: the boot signature, and four partition entries,
+    /// each either empty or marked bootable or not, with a start and length.
+    /// At least one must be in use.
+    fn is_mbr(b: &[u8]) -> bool {
+        if !has_boot_signature(b) || is_volume_boot_record(b) {
+            return false;
+        }
+        let valid = mbr_entries(b).all(|x| {
+            (x[0] == 0x00 || x[0] == 0x80) &&
+            (x[4] == 0 || (read_u32_le(&x[8..12]) != 0 && read_u32_le(&x[12..16]) != 0))
+        });
+        valid && mbr_entries(b).any(|x| x[4] != 0)
+    }
+
+    /// GUID partition table: a protective MBR, then `EFI PART` in the next
+    /// sector, with revision 1.0 and a header at least 92 bytes long.
+    fn is_gpt(b: &[u8]) -> bool {
+        if !is_mbr(b) || !mbr_entries(b).any(|x| x[4] == GPT_PROTECTIVE) {
+            return false;
+        }
+        SECTOR_SIZES.iter().any(|&x| {
+            b.len() >= x + 92 && &b[x..x + 8] == b"EFI PART" &&
+            read_u32_le(&b[x + 8..x + 12]) == 0x00010000 &&
+            read_u32_le(&b[x + 12..x + 16]) >= 92
+        })
+    }
+
+    /// The features of an ext2/3/4 filesystem, as (compat, incompat,
+    /// ro_compat), if there's a superblock: the magic number `0xef53`, a
+    /// block size of at most 64 KiB, and blocks and inodes in each group.
+    fn ext_features(b: &[u8]) -> Option<(u32, u32, u32)> {
+        let sb = b.get(EXT_SUPERBLOCK..EXT_SUPERBLOCK + 104)?;
+        let valid = read_u16_le(&sb[56..58]) == 0xef53 &&
+            read_u32_le(&sb[24..28]) <= 6 &&
+            read_u32_le(&sb[32..36]) != 0 &&
+            read_u32_le(&sb[40..44]) != 0;
+        let features = (read_u32_le(&sb[92..96]), read_u32_le(&sb[96..100]), read_u32_le(&sb[100..104]));
+        (valid && features.1 & EXT_INCOMPAT_JOURNAL_DEV == 0).then_some(features)
+    }
+
+    /// Which of ext2, ext3 and ext4 a filesystem is, as `blkid` decides:
+    /// ext4 if it uses anything ext3 doesn't know, otherwise ext3 if it has
+    /// a journal, and otherwise ext2
+    fn ext_version(b: &[u8]) -> Option<u8> {
+        let (compat, incompat, ro_compat) = ext_features(b)?;
+        Some(if incompat & !EXT3_INCOMPAT != 0 || ro_compat & !EXT3_RO_COMPAT != 0 {
+            4
+        } else if compat & EXT_COMPAT_HAS_JOURNAL != 0 {
+            3
+        } else {
+            2
+        })
+    }
+
+    /// NTFS boot sector: `NTFS` as the OEM name, a power of two from 256 to
+    /// 4096 bytes per sector, and some sectors per cluster
+    fn is_ntfs(b: &[u8]) -> bool {
+        if !has_boot_signature(b) || &b[3..11] != b"NTFS    " {
+            return false;
+        }
+        let sector_size = read_u16_le(&b[11..13]);
+        sector_size.is_power_of_two() && (256..=4096).contains(&sector_size) && b[13] != 0
+    }
+
+    /// Amiga floppy image: an AmigaDOS boot block, which is `DOS` and a
+    /// byte for the filesystem variant, on an input exactly the size of a
+    /// floppy. The size is all that tells it from other files starting
+    /// `DOS`.
+    fn is_adf(b: &[u8], len: u64) -> bool {
+        b.len() >= 4 && &b[..3] == b"DOS" && b[3] <= 7 && ADF_SIZES.contains(&len)
+    }
+
+    /// Checks the given buffer. An Amiga floppy's size is that of the whole
+    /// file, if the buffer is the start of one.
+    #[allow(unused_variables)]
+    pub fn from_u8(
+        b: &[u8], mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        match mimetype {
+            "application/x-mbr-partition-table" => is_mbr(b),
+            "application/x-gpt-partition-table" => is_gpt(b),
+            "application/x-ext2-filesystem" => ext_version(b) == Some(2),
+            "application/x-ext3-filesystem" => ext_version(b) == Some(3),
+            "application/x-ext4-filesystem" => ext_version(b) == Some(4),
+            "application/x-ntfs-filesystem" => is_ntfs(b),
+            "application/x-amiga-disk-format" => input_tail(b, filecache, |_, len| is_adf(b, len)),
+            _ => false
+        }
+    }
+
+    #[cfg(feature="fs")]
+    #[allow(unused_variables)]
+    pub fn from_filepath(
+        filepath: &Path, mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        // Far enough for a GPT header after a 4096 byte sector
+        let b = match slurp_to_cache(filepath, filecache, 8192) {
+            Ok(x) => x,
+            Err(_) => return false
+        };
+        from_u8(b.as_slice(), mimetype, cache, filecache)
+    }
+}
@@ -0,0 +1,190 @@
+//! Handles firmware images and embedded build artifacts, including
+//! Android boot and sparse images
+
+/// Dummy cache type
+pub type Cache = bool;
+
+pub mod init {
+
+    use FnvHashMap;
+    use MIME;
+    use std::prelude::v1::*;
+
+    pub fn get_supported() -> Vec<MIME> {
+        vec![
+            MIME::from("application/x-dtb"),
+            MIME::from("application/x-uboot-fit-image"),
+            MIME::from("application/x-uboot-image"),
+            MIME::from("application/x-uefi-capsule"),
+            MIME::from("application/x-uefi-firmware-volume"),
+            MIME::from("application/vnd.android.bootimg"),
+            MIME::from("application/vnd.android.sparse-image")
+        ]
+    }
+
+    /// Returns Vec of parent->child relations
+    ///
+    /// FIT images are device trees that describe the images they carry.
+    pub fn get_subclasses() -> Vec<(MIME, MIME)> {
+        vec![
+            (MIME::from("application/octet-stream"), MIME::from("application/x-dtb")),
+            (MIME::from("application/x-dtb"), MIME::from("application/x-uboot-fit-image")),
+            (MIME::from("application/octet-stream"), MIME::from("application/x-uboot-image")),
+            (MIME::from("application/octet-stream"), MIME::from("application/x-uefi-capsule")),
+            (MIME::from("application/octet-stream"), MIME::from("application/x-uefi-firmware-volume")),
+            (MIME::from("application/octet-stream"), MIME::from("application/vnd.android.bootimg")),
+            (MIME::from("application/octet-stream"), MIME::from("application/vnd.android.sparse-image"))
+        ]
+    }
+
+    pub fn get_aliaslist() -> FnvHashMap<MIME, MIME> {
+        FnvHashMap::default()
+    }
+
+}
+
+pub mod check {
+
+    use std::prelude::v1::*;
+    #[cfg(feature="fs")]
+    use std::path::Path;
+    use super::super::CacheItem;
+    #[cfg(feature="fs")]
+    use super::super::slurp_to_cache;
+
+    /// Capsule GUIDs, as stored on disk (mixed endian)
+    const CAPSULE_GUIDS: [[u8; 16]; 2] = [
+        // EFI_CAPSULE_GUID {3B6686BD-0D76-4030-B70E-B5519E2FC5A0}
+        [0xbd, 0x86, 0x66, 0x3b, 0x76, 0x0d, 0x30, 0x40,
+         0xb7, 0x0e, 0xb5, 0x51, 0x9e, 0x2f, 0xc5, 0xa0],
+        // EFI_FIRMWARE_MANAGEMENT_CAPSULE_ID_GUID {6DCBD5ED-E82D-4C44-BDA1-7194199AD92A}
+        [0xed, 0xd5, 0xcb, 0x6d, 0x2d, 0xe8, 0x44, 0x4c,
+         0xbd, 0xa1, 0x71, 0x94, 0x19, 0x9a, 0xd9, 0x2a]
+    ];
+
+    fn read_u32_be(b: &[u8]) -> u32 {
+        (b[0] as u32) << 24 | (b[1] as u32) << 16 | (b[2] as u32) << 8 | b[3] as u32
+    }
+
+    fn read_u32_le(b: &[u8]) -> u32 {
+        b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24
+    }
+
+    /// Flattened device tree header: `0xd00dfeed`, then the total size and
+    /// block offsets, which must all fall inside the tree.
+    fn is_dtb(b: &[u8]) -> bool {
+        if b.len() < 40 || read_u32_be(b) != 0xd00dfeed {
+            return false;
+        }
+        let size = read_u32_be(&b[4..8]);
+        let version = read_u32_be(&b[20..24]);
+        let last_compatible = read_u32_be(&b[24..28]);
+        [8, 12, 16].iter().all(|&x| read_u32_be(&b[x..x + 4]) < size) &&
+        (1..=17).contains(&version) && last_compatible <= version
+    }
+
+    /// A FIT image is a device tree with a top-level `images` node. Node
+    /// names are stored inline in the structure block, right after the
+    /// FDT_BEGIN_NODE token.
+    fn is_fit(b: &[u8]) -> bool {
+        const IMAGES_NODE: &[u8] = b"\x00\x00\x00\x01images\x00";
+        if !is_dtb(b) {
+            return false;
+        }
+        let start = read_u32_be(&b[8..12]) as usize;
+        start < b.len() && b[start..].windows(IMAGES_NODE.len()).any(|x| x == IMAGES_NODE)
+    }
+
+    /// U-Boot legacy image header: `0x27051956`, then a 64 byte header
+    /// whose OS, architecture, image type and compression fields must be
+    /// within the ranges defined by U-Boot.
+    fn is_uboot(b: &[u8]) -> bool {
+        if b.len() < 64 || read_u32_be(b) != 0x27051956 {
+            return false;
+        }
+        let (os, arch, kind, comp) = (b[28], b[29], b[30], b[31]);
+        os < 64 && arch < 64 && kind > 0 && kind < 64 && comp < 16 &&
+        b[32..64].contains(&0)
+    }
+
+    /// UEFI capsule header: a known capsule GUID, then the header size,
+    /// flags and total image size.
+    fn is_capsule(b: &[u8]) -> bool {
+        if b.len() < 28 || !CAPSULE_GUIDS.iter().any(|x| b.starts_with(x)) {
+            return false;
+        }
+        let header_size = read_u32_le(&b[16..20]);
+        let image_size = read_u32_le(&b[24..28]);
+        header_size >= 28 && image_size >= header_size
+    }
+
+    /// UEFI firmware volume header: 16 zero bytes, the file system GUID, the
+    /// volume length, and `_FVH`. The 16-bit words of the header must sum
+    /// to zero.
+    fn is_firmware_volume(b: &[u8]) -> bool {
+        if b.len() < 56 || b[..16].iter().any(|&x| x != 0) || &b[40..44] != b"_FVH" {
+            return false;
+        }
+        let header_len = (b[48] as usize) | (b[49] as usize) << 8;
+        if header_len < 56 || header_len > b.len() || !header_len.is_multiple_of(2) {
+            return false;
+        }
+        b[..header_len].chunks(2)
+            .fold(0u16, |acc, x| acc.wrapping_add(x[0] as u16 | (x[1] as u16) << 8)) == 0
+    }
+
+    /// Android boot image: `ANDROID!` and a known header version. Before
+    /// version 3 the header also records the flash page size.
+    fn is_android_boot(b: &[u8]) -> bool {
+        if b.len() < 44 || !b.starts_with(b"ANDROID!") {
+            return false;
+        }
+        match read_u32_le(&b[40..44]) {
+            0..=2 => {
+                let page_size = read_u32_le(&b[36..40]);
+                page_size.is_power_of_two() && (2048..=16384).contains(&page_size)
+            },
+            3..=4 => true,
+            _ => false
+        }
+    }
+
+    /// Android sparse image: `0xed26ff3a`, format version 1.0 and the
+    /// fixed file and chunk header sizes.
+    fn is_android_sparse(b: &[u8]) -> bool {
+        if b.len() < 28 || read_u32_le(b) != 0xed26ff3a {
+            return false;
+        }
+        let block_size = read_u32_le(&b[12..16]);
+        b[4..12] == [1, 0, 0, 0, 28, 0, 12, 0] &&
+        block_size > 0 && block_size.is_multiple_of(4)
+    }
+
+    #[allow(unused_variables)]
+    pub fn from_u8(
+        b: &[u8], mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        match mimetype {
+            "application/x-dtb" => is_dtb(b),
+            "application/x-uboot-fit-image" => is_fit(b),
+            "application/x-uboot-image" => is_uboot(b),
+            "application/x-uefi-capsule" => is_capsule(b),
+            "application/x-uefi-firmware-volume" => is_firmware_volume(b),
+            "application/vnd.android.bootimg" => is_android_boot(b),
+            "application/vnd.android.sparse-image" => is_android_sparse(b),
+            _ => false
+        }
+    }
+
+    #[cfg(feature="fs")]
+    #[allow(unused_variables)]
+    pub fn from_filepath(
+        filepath: &Path, mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        let b = match slurp_to_cache(filepath, filecache, 2048) {
+            Ok(x) => x,
+            Err(_) => return false
+        };
+        from_u8(b.as_slice(), mimetype, cache, filecache)
+    }
+}
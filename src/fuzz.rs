@@ -0,0 +1,133 @@
+//! Entry points for fuzzing detection and the magic parser
+//!
+//! Each takes whatever bytes a fuzzer comes up with, and never panics or
+//! looks at anything but its input and the loaded database, so the same
+//! input always does the same thing. `MagicRule` implements `Arbitrary`,
+//! for fuzzing rules directly rather than through the magic file format.
+//!
+//! Requires the `fuzz` feature.
+//!
+//! # Examples
+//! A `cargo fuzz` target for detection:
+//! ```rust,ignore
+//! #![no_main]
+//! use libfuzzer_sys::fuzz_target;
+//!
+//! fuzz_target!(|data: &[u8]| {
+//!     tree_magic::fuzz::detect(data);
+//! });
+//! ```
+//! And one for rules:
+//! ```rust,ignore
+//! #![no_main]
+//! use libfuzzer_sys::fuzz_target;
+//! use tree_magic::MagicRule;
+//!
+//! fuzz_target!(|input: (Vec<MagicRule>, Vec<u8>)| {
+//!     tree_magic::fuzz::run_rules(&input.0, &input.1);
+//! });
+//! ```
+extern crate arbitrary;
+
+use std::prelude::v1::*;
+#[cfg(feature="checker-fdo-magic")]
+use std::borrow::Cow;
+use self::arbitrary::{Arbitrary, Result, Unstructured};
+use MagicRule;
+use Mime;
+
+/// Most a value or mask generated for a rule is, which is more than any
+/// rule in shared-mime-info
+const MAX_VALUE_LEN: usize = 256;
+
+/// Runs the byte-based detection functions over `data`, giving what
+/// `from_u8` found.
+///
+/// Walks the type graph from the root, then looks at every magic rule as
+/// `from_u8_priority` does, then checks the result and its parents again
+/// with `match_u8`.
+///
+/// # Examples
+/// ```rust
+/// assert_eq!(tree_magic::fuzz::detect(b"\x89PNG\r\n\x1a\n"), "image/png");
+/// ```
+pub fn detect(data: &[u8]) -> Mime {
+    let out = ::from_u8(data);
+    ::from_u8_priority(data);
+    ::from_u8_detailed(data);
+    ::is_text(data);
+    if let Some(x) = ::TypeId::of(&out) {
+        ::match_u8(x.name(), data);
+        for parent in x.parents() {
+            ::match_u8(parent.name(), data);
+        }
+    }
+    out
+}
+
+/// Parses `data` as a magic file, in the format of shared-mime-info's
+/// `magic`, then compiles each type's rules and runs them against `data`
+/// itself. Gives how many rules there were, or why the file didn't parse.
+///
+/// # Examples
+/// ```rust
+/// let magic = b"MIME-Magic\0\n[50:text/x-greeting]\n>0=\0\x05Hello\n";
+/// assert_eq!(tree_magic::fuzz::parse_magic(magic), Ok(1));
+/// assert!(tree_magic::fuzz::parse_magic(b"[50:text/x-greeting]\n").is_err());
+/// ```
+#[cfg(feature="checker-fdo-magic")]
+pub fn parse_magic(data: &[u8]) -> std::result::Result<usize, String> {
+    let sections = ::fdo_magic::ruleset::parse(data)?;
+    let mut out = 0;
+    for (_, rules) in sections {
+        out += rules.len();
+        ::fdo_magic::bytecode::Program::compile(&rules).run(data);
+    }
+    Ok(out)
+}
+
+/// Compiles rules, in magic file order, the way the database's are, and
+/// checks whether `data` matches them.
+///
+/// # Examples
+/// ```rust
+/// let rules = tree_magic::rules_for("image/png");
+/// assert!(tree_magic::fuzz::run_rules(&rules, b"\x89PNG\r\n\x1a\n"));
+/// assert!(!tree_magic::fuzz::run_rules(&rules, b"GIF89a"));
+/// ```
+#[cfg(feature="checker-fdo-magic")]
+pub fn run_rules(rules: &[MagicRule], data: &[u8]) -> bool {
+    let rules: Vec<::fdo_magic::MagicRule> = rules.iter().map(|x| ::fdo_magic::MagicRule {
+        indent_level: x.indent,
+        start_off: x.offset,
+        val: Cow::Owned(x.value.to_vec()),
+        mask: x.mask.as_ref().map(|y| Cow::Owned(y.to_vec())),
+        word_len: x.word_size,
+        region_len: x.range,
+        priority: x.priority
+    }).collect();
+    ::fdo_magic::bytecode::Program::compile(&rules).run(data)
+}
+
+/// Rules like the magic file can hold: the mask, if there is one, is as
+/// long as the value, and the value is no longer than 256 bytes. Offsets,
+/// ranges, word sizes and indents can be anything.
+impl<'a> Arbitrary<'a> for MagicRule {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<MagicRule> {
+        let len = u.int_in_range(0..=MAX_VALUE_LEN)?;
+        let value = u.bytes(len)?.to_vec();
+        let mask = match bool::arbitrary(u)? {
+            true => Some(u.bytes(len)?.to_vec().into()),
+            false => None
+        };
+        Ok(MagicRule {
+            indent: u.arbitrary()?,
+            offset: u.arbitrary()?,
+            range: u.arbitrary()?,
+            value: value.into(),
+            mask,
+            word_size: u.arbitrary()?,
+            priority: u.int_in_range(0..=100)?
+        })
+    }
+}
@@ -0,0 +1,179 @@
+//! Handles the formats the `infer` crate knows that no other checker does
+//!
+//! Each type sits under the type its format is built on, so the walk only
+//! tries it once that type has matched, and a type the rest of the database
+//! already knows is left to the checker that knows it.
+
+use infer_rs::{app, archive, audio, book, doc, font, image, odf, text, video, Matcher};
+
+/// Dummy cache type
+pub type Cache = bool;
+
+/// Each type `infer` can detect, the type it's under, and `infer`'s matcher
+/// for it. Types with more than one matcher are listed once for each.
+///
+/// Types go by their shared-mime-info names where `infer`'s differ, so the
+/// ones the database has are recognized as taken.
+const MATCHERS: &[(&str, &str, Matcher)] = &[
+    ("application/wasm", "application/octet-stream", app::is_wasm),
+    ("application/x-executable", "application/octet-stream", app::is_elf),
+    ("application/x-executable", "application/octet-stream", app::is_coff),
+    ("application/x-ms-dos-executable", "application/octet-stream", app::is_exe),
+    ("application/x-ms-dos-executable", "application/octet-stream", app::is_dll),
+    ("application/java", "application/octet-stream", app::is_java),
+    ("application/x-llvm", "application/octet-stream", app::is_llvm),
+    ("application/x-mach-binary", "application/octet-stream", app::is_mach),
+    ("application/vnd.android.dex", "application/octet-stream", app::is_dex),
+    ("application/vnd.android.dey", "application/octet-stream", app::is_dey),
+    ("application/x-x509-ca-cert", "application/octet-stream", app::is_der),
+    ("application/x-x509-ca-cert", "text/plain", app::is_pem),
+    ("application/epub+zip", "application/zip", book::is_epub),
+    ("application/x-mobipocket-ebook", "application/octet-stream", book::is_mobi),
+    ("image/jpeg", "application/octet-stream", image::is_jpeg),
+    ("image/jp2", "application/octet-stream", image::is_jpeg2000),
+    ("image/png", "application/octet-stream", image::is_png),
+    ("image/gif", "application/octet-stream", image::is_gif),
+    ("image/webp", "application/octet-stream", image::is_webp),
+    ("image/x-canon-cr2", "image/tiff", image::is_cr2),
+    ("image/tiff", "application/octet-stream", image::is_tiff),
+    ("image/bmp", "application/octet-stream", image::is_bmp),
+    ("image/vnd.ms-photo", "application/octet-stream", image::is_jxr),
+    ("image/vnd.adobe.photoshop", "application/octet-stream", image::is_psd),
+    ("image/vnd.microsoft.icon", "application/octet-stream", image::is_ico),
+    ("image/heif", "application/octet-stream", image::is_heif),
+    ("image/avif", "application/octet-stream", image::is_avif),
+    ("image/jxl", "application/octet-stream", image::is_jxl),
+    ("image/openraster", "application/zip", image::is_ora),
+    ("image/vnd.djvu", "application/octet-stream", image::is_djvu),
+    ("video/mp4", "application/octet-stream", video::is_mp4),
+    ("video/x-m4v", "application/octet-stream", video::is_m4v),
+    ("video/x-matroska", "application/octet-stream", video::is_mkv),
+    ("video/webm", "application/octet-stream", video::is_webm),
+    ("video/quicktime", "application/octet-stream", video::is_mov),
+    ("video/x-msvideo", "application/octet-stream", video::is_avi),
+    ("video/x-ms-wmv", "application/octet-stream", video::is_wmv),
+    ("video/mpeg", "application/octet-stream", video::is_mpeg),
+    ("video/x-flv", "application/octet-stream", video::is_flv),
+    ("audio/midi", "application/octet-stream", audio::is_midi),
+    ("audio/mpeg", "application/octet-stream", audio::is_mp3),
+    ("audio/m4a", "application/octet-stream", audio::is_m4a),
+    ("audio/x-opus+ogg", "audio/ogg", audio::is_ogg_opus),
+    ("audio/ogg", "application/octet-stream", audio::is_ogg),
+    ("audio/x-flac", "application/octet-stream", audio::is_flac),
+    ("audio/x-wav", "application/octet-stream", audio::is_wav),
+    ("audio/AMR", "application/octet-stream", audio::is_amr),
+    ("audio/aac", "application/octet-stream", audio::is_aac),
+    ("audio/x-aiff", "application/octet-stream", audio::is_aiff),
+    ("audio/x-dsf", "application/octet-stream", audio::is_dsf),
+    ("audio/x-ape", "application/octet-stream", audio::is_ape),
+    ("font/woff", "application/octet-stream", font::is_woff),
+    ("font/woff2", "application/octet-stream", font::is_woff2),
+    ("font/ttf", "application/octet-stream", font::is_ttf),
+    ("font/otf", "application/octet-stream", font::is_otf),
+    ("application/msword", "application/x-ole-storage", doc::is_doc),
+    ("application/vnd.openxmlformats-officedocument.wordprocessingml.document", "application/zip", doc::is_docx),
+    ("application/vnd.ms-excel", "application/x-ole-storage", doc::is_xls),
+    ("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet", "application/zip", doc::is_xlsx),
+    ("application/vnd.ms-powerpoint", "application/x-ole-storage", doc::is_ppt),
+    ("application/vnd.openxmlformats-officedocument.presentationml.presentation", "application/zip", doc::is_pptx),
+    ("application/vnd.oasis.opendocument.text", "application/zip", odf::is_odt),
+    ("application/vnd.oasis.opendocument.spreadsheet", "application/zip", odf::is_ods),
+    ("application/vnd.oasis.opendocument.presentation", "application/zip", odf::is_odp),
+    ("application/zip", "application/octet-stream", archive::is_zip),
+    ("application/x-tar", "application/octet-stream", archive::is_tar),
+    ("application/vnd.rar", "application/octet-stream", archive::is_rar),
+    ("application/gzip", "application/octet-stream", archive::is_gz),
+    ("application/x-bzip2", "application/octet-stream", archive::is_bz2),
+    ("application/x-7z-compressed", "application/octet-stream", archive::is_7z),
+    ("application/x-xz", "application/octet-stream", archive::is_xz),
+    ("application/pdf", "application/octet-stream", archive::is_pdf),
+    ("application/x-shockwave-flash", "application/octet-stream", archive::is_swf),
+    ("application/rtf", "text/plain", archive::is_rtf),
+    ("application/vnd.ms-fontobject", "application/octet-stream", archive::is_eot),
+    ("application/postscript", "text/plain", archive::is_ps),
+    ("application/vnd.sqlite3", "application/octet-stream", archive::is_sqlite),
+    ("application/x-nes-rom", "application/octet-stream", archive::is_nes),
+    ("application/x-chrome-extension", "application/octet-stream", archive::is_crx),
+    ("application/vnd.ms-cab-compressed", "application/octet-stream", archive::is_cab),
+    ("application/vnd.debian.binary-package", "application/x-archive", archive::is_deb),
+    ("application/x-archive", "application/octet-stream", archive::is_ar),
+    ("application/x-compress", "application/octet-stream", archive::is_z),
+    ("application/x-lzip", "application/octet-stream", archive::is_lz),
+    ("application/x-rpm", "application/octet-stream", archive::is_rpm),
+    ("application/dicom", "application/octet-stream", archive::is_dcm),
+    ("application/zstd", "application/octet-stream", archive::is_zst),
+    ("application/x-ole-storage", "application/octet-stream", archive::is_msi),
+    ("application/x-cpio", "application/octet-stream", archive::is_cpio),
+    ("text/html", "text/plain", text::is_html),
+    ("text/xml", "text/plain", text::is_xml),
+    ("application/x-shellscript", "text/plain", text::is_shellscript)
+];
+
+pub mod init {
+
+    use FnvHashMap;
+    use FnvHashSet;
+    use MIME;
+    use std::prelude::v1::*;
+
+    /// Types `infer` gets to check, each once: the ones no other checker
+    /// supports, under their own name or an alias
+    fn types() -> Vec<&'static str> {
+        let others = || super::super::CHECKERS.iter().filter(|x| x.name != "infer");
+        let known: FnvHashSet<MIME> = others().flat_map(|x| (x.get_supported)()).collect();
+        let aliases: FnvHashMap<MIME, MIME> = others().flat_map(|x| (x.get_aliaslist)()).collect();
+
+        let mut out: Vec<&'static str> = super::MATCHERS.iter().map(|x| x.0).collect();
+        out.dedup();
+        out.retain(|x| !known.contains(*x) && !aliases.get(*x).is_some_and(|y| known.contains(y)));
+        out
+    }
+
+    pub fn get_supported() -> Vec<MIME> {
+        types().into_iter().map(MIME::from).collect()
+    }
+
+    /// Returns Vec of parent->child relations
+    pub fn get_subclasses() -> Vec<(MIME, MIME)> {
+        let types = types();
+        let mut out: Vec<(MIME, MIME)> = super::MATCHERS.iter()
+            .filter(|x| types.contains(&x.0))
+            .map(|x| (MIME::from(x.1), MIME::from(x.0)))
+            .collect();
+        out.dedup();
+        out
+    }
+
+    pub fn get_aliaslist() -> FnvHashMap<MIME, MIME> {
+        FnvHashMap::default()
+    }
+
+}
+
+pub mod check {
+
+    #[cfg(feature="fs")]
+    use std::path::Path;
+    use super::super::CacheItem;
+    #[cfg(feature="fs")]
+    use super::super::{max_scan_extent, slurp_to_cache};
+
+    #[allow(unused_variables)]
+    pub fn from_u8(
+        b: &[u8], mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        super::MATCHERS.iter().any(|x| x.0 == mimetype && (x.2)(b))
+    }
+
+    #[cfg(feature="fs")]
+    #[allow(unused_variables)]
+    pub fn from_filepath(
+        filepath: &Path, mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        let b = match slurp_to_cache(filepath, filecache, max_scan_extent()) {
+            Ok(x) => x,
+            Err(_) => return false
+        };
+        from_u8(b.as_slice(), mimetype, cache, filecache)
+    }
+}
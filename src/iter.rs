@@ -0,0 +1,73 @@
+//! Iterator adaptors that keep only the paths of a given type, so detection
+//! can sit in the middle of a pipeline without collecting results first.
+//!
+//! # Examples
+//! ```rust
+//! use std::path::Path;
+//! use tree_magic::iter::FilterMime;
+//!
+//! let paths = vec![Path::new("tests/image/gif"), Path::new("tests/image/png")];
+//! let images: Vec<_> = paths.iter().filter_mime("image/gif").collect();
+//! assert_eq!(images, vec![&Path::new("tests/image/gif")]);
+//! ```
+use std::path::Path;
+use std::prelude::v1::*;
+
+/// Iterator over the paths of one type, from `FilterMime`
+#[derive(Debug, Clone)]
+pub struct FilterMimeIter<I> {
+    iter: I,
+    mimetype: String,
+    /// Whether subtypes are left out
+    exact: bool
+}
+
+impl<I> FilterMimeIter<I> {
+    /// Checks if the file's detected type is the one wanted
+    fn wanted(&self, filepath: &Path) -> bool {
+        let found = match ::from_filepath(filepath) {
+            Some(x) => x,
+            None => return false
+        };
+        match self.exact {
+            true => ::get_alias(&found) == ::get_alias(&self.mimetype),
+            false => ::is_subtype_of(&found, &self.mimetype)
+        }
+    }
+}
+
+impl<I> Iterator for FilterMimeIter<I> where I: Iterator, I::Item: AsRef<Path> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        while let Some(x) = self.iter.next() {
+            if self.wanted(x.as_ref()) {
+                return Some(x);
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.iter.size_hint().1)
+    }
+}
+
+/// Adds type filters to any iterator over paths. Files that can't be read
+/// are left out.
+pub trait FilterMime: Iterator + Sized where Self::Item: AsRef<Path> {
+    /// Keeps the files of the given type or one of its subtypes, so
+    /// `text/plain` also keeps JSON and source code. Aliases count as the
+    /// type they stand for.
+    fn filter_mime(self, mimetype: &str) -> FilterMimeIter<Self> {
+        FilterMimeIter {iter: self, mimetype: mimetype.to_string(), exact: false}
+    }
+
+    /// Keeps only the files detected as exactly the given type, or an alias
+    /// of it.
+    fn filter_mime_exact(self, mimetype: &str) -> FilterMimeIter<Self> {
+        FilterMimeIter {iter: self, mimetype: mimetype.to_string(), exact: true}
+    }
+}
+
+impl<I: Iterator> FilterMime for I where I::Item: AsRef<Path> {}
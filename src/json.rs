@@ -0,0 +1,218 @@
+//! Handles JSON documents and formats built on top of JSON
+
+/// What the input was found to be, worked out the first time either type
+/// is checked against it
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cache {
+    json: bool,
+    ipynb: bool
+}
+
+pub mod init {
+
+    use FnvHashMap;
+    use MIME;
+    use std::prelude::v1::*;
+
+    pub fn get_supported() -> Vec<MIME> {
+        vec![
+            MIME::from("application/json"),
+            MIME::from("application/x-ipynb+json")
+        ]
+    }
+
+    /// Returns Vec of parent->child relations
+    pub fn get_subclasses() -> Vec<(MIME, MIME)> {
+        vec![
+            (MIME::from("text/plain"), MIME::from("application/json")),
+            (MIME::from("application/json"), MIME::from("application/x-ipynb+json"))
+        ]
+    }
+
+    pub fn get_aliaslist() -> FnvHashMap<MIME, MIME> {
+        FnvHashMap::default()
+    }
+
+}
+
+pub mod check {
+
+    use std;
+    use std::prelude::v1::*;
+    #[cfg(feature="fs")]
+    use std::path::Path;
+    use super::super::{Cache, CacheItem, CHECKER_SCAN_LEN};
+    #[cfg(feature="fs")]
+    use super::super::slurp_to_cache;
+
+    /// What the scanner expects to see next
+    #[derive(PartialEq, Clone, Copy)]
+    enum Expect {
+        Value,
+        Key,
+        Colon,
+        CommaOrClose,
+        End
+    }
+
+    /// Scans a JSON document (which may be cut short by the end of the
+    /// buffer) and returns the keys of the top-level object, in order.
+    ///
+    /// Returns None if the buffer isn't a JSON object or array. Top-level
+    /// scalars are not accepted, as nearly any text would pass.
+    fn top_level_keys(b: &[u8]) -> Option<Vec<String>> {
+        let mut keys = Vec::<String>::new();
+        let mut stack = Vec::<u8>::new();
+        let mut expect = Expect::Value;
+        let mut i = 0;
+
+        while i < b.len() {
+            let c = b[i];
+            if c.is_ascii_whitespace() {
+                i += 1;
+                continue;
+            }
+            if expect == Expect::End {
+                return None;
+            }
+            // Top level must be a container
+            if stack.is_empty() && c != b'{' && c != b'[' {
+                return None;
+            }
+
+            match c {
+                b'{' | b'[' if expect == Expect::Value => {
+                    stack.push(c);
+                    expect = if c == b'{' {Expect::Key} else {Expect::Value};
+                    i += 1;
+                },
+                b'}' | b']' if expect == Expect::CommaOrClose ||
+                    (c == b'}' && expect == Expect::Key) ||
+                    (c == b']' && expect == Expect::Value) =>
+                {
+                    let open = stack.pop()?;
+                    if (open == b'{') != (c == b'}') {
+                        return None;
+                    }
+                    expect = if stack.is_empty() {Expect::End} else {Expect::CommaOrClose};
+                    i += 1;
+                },
+                b',' if expect == Expect::CommaOrClose => {
+                    expect = if stack.last() == Some(&b'{') {Expect::Key} else {Expect::Value};
+                    i += 1;
+                },
+                b':' if expect == Expect::Colon => {
+                    expect = Expect::Value;
+                    i += 1;
+                },
+                b'"' if expect == Expect::Key || expect == Expect::Value => {
+                    // Find the closing quote, skipping escapes
+                    let start = i + 1;
+                    let mut end = start;
+                    while end < b.len() && b[end] != b'"' {
+                        if b[end] < 0x20 {
+                            return None;
+                        }
+                        end += if b[end] == b'\\' {2} else {1};
+                    }
+                    if expect == Expect::Key {
+                        if stack.len() == 1 && end < b.len() {
+                            keys.push(String::from_utf8_lossy(&b[start..end]).to_string());
+                        }
+                        expect = Expect::Colon;
+                    } else {
+                        expect = Expect::CommaOrClose;
+                    }
+                    i = end + 1;
+                },
+                b'-' | b'0'..=b'9' | b't' | b'f' | b'n' if expect == Expect::Value => {
+                    let len = b[i..].iter()
+                        .position(|x| !(x.is_ascii_alphanumeric() || b"+-.".contains(x)))
+                        .unwrap_or(b.len() - i);
+                    let token = &b[i..i + len];
+                    let literal = c.is_ascii_alphabetic();
+                    // A literal cut short by the end of the buffer is fine
+                    let truncated = i + len == b.len();
+                    if literal && !(
+                        [&b"true"[..], b"false", b"null"].contains(&token) ||
+                        (truncated && [&b"true"[..], b"false", b"null"].iter().any(|x| x.starts_with(token)))
+                    ) {
+                        return None;
+                    }
+                    if !literal && !token.iter().all(|x| x.is_ascii_digit() || b"+-.eE".contains(x)) {
+                        return None;
+                    }
+                    expect = Expect::CommaOrClose;
+                    i += len;
+                },
+                _ => return None
+            }
+        }
+
+        if stack.is_empty() && expect != Expect::End {
+            return None;
+        }
+        Some(keys)
+    }
+
+    /// A notebook is an object with an `nbformat` key, given the keys of
+    /// the top-level object
+    fn is_ipynb(b: &[u8], keys: Option<&[String]>) -> bool {
+        match keys {
+            Some(keys) => {
+                b.iter().find(|x| !x.is_ascii_whitespace()) == Some(&b'{') &&
+                keys.iter().any(|x| x == "nbformat")
+            },
+            None => false
+        }
+    }
+
+    /// Works out which of the types the input is, from as far into it as
+    /// the built-in checkers look
+    fn classify(b: &[u8]) -> super::Cache {
+        let b = &b[..std::cmp::min(b.len(), CHECKER_SCAN_LEN)];
+        #[cfg(feature="std")]
+        if !::limit::charge(0, b.len()) {
+            return super::Cache::default();
+        }
+        let keys = top_level_keys(b);
+        super::Cache {
+            json: keys.is_some(),
+            ipynb: is_ipynb(b, keys.as_deref())
+        }
+    }
+
+    /// Checks the given buffer. It's only parsed the first time one of these
+    /// types is checked against it, and the cache holds what it was found to
+    /// be.
+    #[allow(unused_variables)]
+    pub fn from_u8(
+        b: &[u8], mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        let mut lock = cache.write();
+        if lock.is_none() {
+            *lock = Some(Cache::Json(classify(b)));
+        }
+        let found = match *lock {
+            Some(Cache::Json(x)) => x,
+            _ => return false
+        };
+        match mimetype {
+            "application/json" => found.json,
+            "application/x-ipynb+json" => found.ipynb,
+            _ => false
+        }
+    }
+
+    #[cfg(feature="fs")]
+    #[allow(unused_variables)]
+    pub fn from_filepath(
+        filepath: &Path, mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        let b = match slurp_to_cache(filepath, filecache, CHECKER_SCAN_LEN) {
+            Ok(x) => x,
+            Err(_) => return false
+        };
+        from_u8(b.as_slice(), mimetype, cache, filecache)
+    }
+}
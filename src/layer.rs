@@ -0,0 +1,259 @@
+//! Tower middleware that sets the `Content-Type` of responses by sniffing
+//! their bodies.
+//!
+//! Requires the `tower` feature.
+//!
+//! # Examples
+//! ```rust,ignore
+//! use tree_magic::layer::SniffContentTypeLayer;
+//!
+//! let service = tower::ServiceBuilder::new()
+//!     .layer(SniffContentTypeLayer::new())
+//!     .service(static_files);
+//! ```
+extern crate std;
+extern crate bytes;
+extern crate http;
+extern crate http_body;
+extern crate tower_layer;
+extern crate tower_service;
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use self::bytes::Bytes;
+use self::http::{HeaderValue, Request, Response};
+use self::http::header::CONTENT_TYPE;
+use self::http_body::{Body, Frame, SizeHint};
+use self::tower_layer::Layer;
+use self::tower_service::Service;
+
+/// Number of bytes buffered by default, enough for nearly every type
+const DEFAULT_MAX_BYTES: usize = 2048;
+
+/// Layer that applies `SniffContentType` to a service
+#[derive(Debug, Clone, Copy)]
+pub struct SniffContentTypeLayer {
+    max_bytes: usize,
+    overwrite: bool
+}
+
+impl SniffContentTypeLayer {
+    /// Creates a layer that buffers the first 2048 bytes of each response
+    /// body, and sets `Content-Type` on responses that don't have one.
+    pub fn new() -> Self {
+        SniffContentTypeLayer{max_bytes: DEFAULT_MAX_BYTES, overwrite: false}
+    }
+
+    /// Sets how many bytes of the body to buffer before sniffing
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Sets whether to replace a `Content-Type` the service already set
+    pub fn overwrite(mut self, overwrite: bool) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+}
+
+impl Default for SniffContentTypeLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for SniffContentTypeLayer {
+    type Service = SniffContentType<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SniffContentType{inner, max_bytes: self.max_bytes, overwrite: self.overwrite}
+    }
+}
+
+/// Middleware that sniffs the start of each response body, and sets the
+/// `Content-Type` header from it.
+///
+/// Empty bodies are left alone.
+#[derive(Debug, Clone)]
+pub struct SniffContentType<S> {
+    inner: S,
+    max_bytes: usize,
+    overwrite: bool
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for SniffContentType<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ResBody: Body<Data = Bytes>
+{
+    type Response = Response<SniffBody<ResBody>>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future, ResBody>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        ResponseFuture{
+            state: State::Waiting(Box::pin(self.inner.call(req))),
+            max_bytes: self.max_bytes,
+            overwrite: self.overwrite
+        }
+    }
+}
+
+enum State<F, B: Body> {
+    /// Waiting for the inner service to respond
+    Waiting(Pin<Box<F>>),
+    /// Reading the start of the body
+    Buffering {
+        parts: Box<http::response::Parts>,
+        body: SniffBody<B>
+    },
+    Done
+}
+
+/// Response future of `SniffContentType`
+pub struct ResponseFuture<F, B: Body> {
+    state: State<F, B>,
+    max_bytes: usize,
+    overwrite: bool
+}
+
+// Nothing is pinned structurally: the inner future and body are boxed
+impl<F, B: Body> Unpin for ResponseFuture<F, B> {}
+
+impl<F, B, E> Future for ResponseFuture<F, B>
+where
+    F: Future<Output = Result<Response<B>, E>>,
+    B: Body<Data = Bytes>
+{
+    type Output = Result<Response<SniffBody<B>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        loop {
+            match this.state {
+                State::Waiting(ref mut future) => {
+                    let response = match future.as_mut().poll(cx) {
+                        Poll::Ready(Ok(x)) => x,
+                        Poll::Ready(Err(e)) => {
+                            this.state = State::Done;
+                            return Poll::Ready(Err(e));
+                        },
+                        Poll::Pending => return Poll::Pending
+                    };
+                    let (parts, body) = response.into_parts();
+                    let body = SniffBody::new(body);
+                    if parts.headers.contains_key(CONTENT_TYPE) && !this.overwrite {
+                        this.state = State::Done;
+                        return Poll::Ready(Ok(Response::from_parts(parts, body)));
+                    }
+                    this.state = State::Buffering{parts: Box::new(parts), body};
+                },
+                State::Buffering{ref mut body, ..} => {
+                    if body.poll_prefix(cx, this.max_bytes).is_pending() {
+                        return Poll::Pending;
+                    }
+                    let (mut parts, body) = match std::mem::replace(&mut this.state, State::Done) {
+                        State::Buffering{parts, body} => (*parts, body),
+                        _ => unreachable!()
+                    };
+                    if !body.prefix.is_empty() {
+                        let content_type = ::content_type_for_u8(&body.prefix);
+                        if let Ok(x) = HeaderValue::from_str(&content_type) {
+                            parts.headers.insert(CONTENT_TYPE, x);
+                        }
+                    }
+                    return Poll::Ready(Ok(Response::from_parts(parts, body)));
+                },
+                State::Done => panic!("ResponseFuture polled after completion")
+            }
+        }
+    }
+}
+
+/// Response body of `SniffContentType`
+///
+/// Yields the buffered start of the body, then the rest of the inner body.
+pub struct SniffBody<B: Body> {
+    prefix: Vec<u8>,
+    /// A trailers frame or error that ended buffering early
+    pending: Option<Result<Frame<B::Data>, B::Error>>,
+    inner: Option<Pin<Box<B>>>
+}
+
+impl<B: Body> Unpin for SniffBody<B> {}
+
+impl<B: Body<Data = Bytes>> SniffBody<B> {
+    fn new(inner: B) -> Self {
+        SniffBody{prefix: Vec::new(), pending: None, inner: Some(Box::pin(inner))}
+    }
+
+    /// Reads frames from the inner body until `max_bytes` are buffered, or
+    /// the body or its data ends.
+    fn poll_prefix(&mut self, cx: &mut Context<'_>, max_bytes: usize) -> Poll<()> {
+        while self.prefix.len() < max_bytes && self.pending.is_none() {
+            let inner = match self.inner {
+                Some(ref mut x) => x,
+                None => break
+            };
+            match inner.as_mut().poll_frame(cx) {
+                Poll::Ready(Some(Ok(frame))) => match frame.into_data() {
+                    Ok(data) => self.prefix.extend_from_slice(&data),
+                    Err(frame) => self.pending = Some(Ok(frame))
+                },
+                Poll::Ready(Some(Err(e))) => self.pending = Some(Err(e)),
+                Poll::Ready(None) => self.inner = None,
+                Poll::Pending => return Poll::Pending
+            }
+        }
+        Poll::Ready(())
+    }
+}
+
+impl<B: Body<Data = Bytes>> Body for SniffBody<B> {
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>, cx: &mut Context<'_>
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        if !this.prefix.is_empty() {
+            let prefix = std::mem::take(&mut this.prefix);
+            return Poll::Ready(Some(Ok(Frame::data(Bytes::from(prefix)))));
+        }
+        if let Some(x) = this.pending.take() {
+            return Poll::Ready(Some(x));
+        }
+        match this.inner {
+            Some(ref mut x) => x.as_mut().poll_frame(cx),
+            None => Poll::Ready(None)
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.prefix.is_empty() && self.pending.is_none() &&
+        self.inner.as_ref().is_none_or(|x| x.is_end_stream())
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        let buffered = self.prefix.len() as u64;
+        match self.inner {
+            Some(ref x) => {
+                let inner = x.size_hint();
+                let mut hint = SizeHint::new();
+                hint.set_lower(inner.lower() + buffered);
+                if let Some(upper) = inner.upper() {
+                    hint.set_upper(upper + buffered);
+                }
+                hint
+            },
+            None => SizeHint::with_exact(buffered)
+        }
+    }
+}
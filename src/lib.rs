@@ -21,14 +21,64 @@
 //! # Feature flags
 //! `cli`:        Enable building of `tmagic` binary
 //!
-//! `staticmime`: Change output of all `from_*` functions from `String` to `&'static str`.
-//!               Disables ability to load system magic files. Slightly faster.
+//! `std`:        Build against the standard library. On by default; without it,
+//!               the crate is `no_std` and needs only `alloc`, leaving the
+//!               byte-based functions and the type graph.
+//!
+//! `fs`:         Enable functions that read from the filesystem, and load
+//!               system magic files. On by default, and implies `std`; turn it
+//!               off to build for targets without a filesystem, such as
+//!               `wasm32-unknown-unknown`.
+//!
+//! `parallel-init`: Load each checker's types on its own thread when building
+//!               the type graph. Implies `std`.
+//!
+//! `cache`:      Keep the parsed system magic file under `$XDG_CACHE_HOME`,
+//!               and reuse it while the magic file is unchanged. Implies `fs`.
+//!
+//! `no-embedded-db`: Leave out the magic file bundled in `tree_magic_db`, so
+//!               the only rules are the ones installed on the system. Implies
+//!               `fs`.
+//!
+//! `serde`:      Implement `Serialize` and `Deserialize` for detection results
+//!               and database types, such as `Detection`, `MagicRule` and
+//!               `TypeInfo`.
+//!
+//! `tracing`:    Emit `tracing` events when the database is loaded, when magic
+//!               rules fail to parse, and at `trace` level for each step of
+//!               the walk down the type graph.
+//!
+//! `mime`:       Convert between `Mime` and the `mime` crate's `Mime`, for
+//!               frameworks that take that in their headers. Implies `std`.
+//!
+//! `mime_guess`: Add `from_u8_and_name` and `from_filepath_and_name`, which
+//!               weigh `mime_guess`'s guess from the file name against the
+//!               content. Implies `std`.
+//!
+//! `test-vectors`: Add the `vectors` module, with a minimal header for each
+//!               type this build detects, for testing code built on it.
+//!
+//! `fuzz`:       Add the `fuzz` module, with panic-free entry points for
+//!               fuzzing detection and the magic parser, and implement
+//!               `Arbitrary` for `MagicRule`. Implies `std`.
+//!
+//! `checker-*`:  Include a checker, such as `checker-fdo-magic` or
+//!               `checker-pgp`. `all-checkers` is on by default.
+//!
+//! `checker-infer`: Add the `infer` crate's matchers as a checker, for the
+//!               formats it knows that the other checkers don't. Not part of
+//!               `all-checkers`.
+//!
+//! `family-*`:   Include types of a family, such as `family-image` or
+//!               `family-archive`. Families are named after top-level types,
+//!               with archives split out of `application` and `multipart`
+//!               folded into `message`. `all-families` is on by default.
 //! # Example
 //! ```rust
 //! extern crate tree_magic;
 //! 
 //! // Load a GIF file
-//! let input: &[u8] = include_bytes!("tests/image/gif");
+//! let input: &[u8] = include_bytes!("../tests/image/gif");
 //!
 //! // Find the MIME type of the GIF
 //! let result = tree_magic::from_u8(input);
@@ -39,32 +89,163 @@
 //! assert_eq!(result, true);
 //! ```
 
-#[macro_use] extern crate nom;
-#[macro_use] extern crate lazy_static;
+#![cfg_attr(not(feature="std"), no_std)]
+
+#[cfg(not(feature="std"))] #[macro_use] extern crate alloc;
+#[cfg(feature="std")] extern crate once_cell;
+extern crate spin;
+#[cfg(not(feature="std"))] extern crate hashbrown;
 extern crate petgraph;
 extern crate fnv;
-extern crate parking_lot;
+#[cfg(feature="std")] extern crate parking_lot;
+extern crate miniz_oxide;
+#[cfg(feature="checker-fdo-magic")] extern crate aho_corasick;
+#[cfg(any(feature="checker-fdo-magic", feature="checker-mail", feature="axum"))] extern crate memchr;
+#[cfg(feature="checker-infer")] extern crate infer as infer_rs;
+#[cfg(feature="mmap")] extern crate memmap2;
+#[cfg(all(unix, feature="fs"))] extern crate libc;
+#[cfg(feature="parallel")] extern crate rayon;
+#[cfg(all(feature="checker-fdo-magic", not(feature="no-embedded-db")))] extern crate tree_magic_db;
+#[cfg(feature="serde")] extern crate serde;
+#[cfg(feature="tracing")] extern crate tracing;
+#[cfg(feature="mime")] extern crate mime;
+#[cfg(feature="mime_guess")] extern crate mime_guess;
 
 use petgraph::prelude::*;
-use fnv::FnvHashMap;
-use fnv::FnvHashSet;
-//use petgraph::dot::{Dot, Config};
-use std::io::prelude::*;
-use std::io::BufReader;
-use std::fs::File;
+#[cfg(feature="std")] use fnv::FnvHashMap;
+#[cfg(feature="std")] use fnv::FnvHashSet;
+#[cfg(feature="fs")]
 use std::path::Path;
-use parking_lot::RwLock;
+#[cfg(feature="parallel")]
+use std::path::PathBuf;
+#[cfg(feature="std")] use parking_lot::RwLock;
+#[cfg(not(feature="std"))] use spin::RwLock;
 use std::sync::Arc;
+use std::prelude::v1::*;
+
+/// Stands in for `std` without the `std` feature, so the rest of the crate
+/// can use the same paths either way.
+#[cfg(not(feature="std"))]
+mod std {
+    pub use core::*;
+    pub use alloc::{borrow, str, sync};
+    pub mod prelude {
+        pub mod v1 {
+            pub use core::prelude::v1::*;
+            pub use alloc::string::{String, ToString};
+            pub use alloc::vec::Vec;
+        }
+    }
+}
+
+#[cfg(not(feature="std"))]
+type FnvHashMap<K, V> = hashbrown::HashMap<K, V, fnv::FnvBuildHasher>;
+#[cfg(not(feature="std"))]
+type FnvHashSet<T> = hashbrown::HashSet<T, fnv::FnvBuildHasher>;
+
+#[cfg(feature="std")]
+type Lazy<T> = once_cell::sync::Lazy<T>;
+#[cfg(not(feature="std"))]
+type Lazy<T> = spin::Lazy<T>;
+
+/// Lazily initialized statics, backed by `once_cell`, or `spin` without `std`
+macro_rules! lazy_static {
+    ($(#[$attr:meta])* static ref $name:ident : $t:ty = $init:expr; $($rest:tt)*) => {
+        $(#[$attr])* static $name: ::Lazy<$t> = ::Lazy::new(|| $init);
+        lazy_static!($($rest)*);
+    };
+    ($(#[$attr:meta])* pub static ref $name:ident : $t:ty = $init:expr; $($rest:tt)*) => {
+        $(#[$attr])* pub static $name: ::Lazy<$t> = ::Lazy::new(|| $init);
+        lazy_static!($($rest)*);
+    };
+    () => ()
+}
 
+/// Emits a `tracing` event at the given level if the `tracing` feature is
+/// on, and does nothing otherwise
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)*) => {
+        #[cfg(feature="tracing")]
+        ::tracing::$level!($($arg)*);
+    };
+}
+
+#[cfg(feature="checker-fdo-magic")]
 mod fdo_magic;
 mod basetype;
+#[cfg(feature="checker-pgp")]
+mod pgp;
+#[cfg(feature="checker-mail")]
+mod mail;
+#[cfg(feature="checker-dicom")]
+mod dicom;
+#[cfg(feature="checker-bio")]
+mod bio;
+#[cfg(feature="checker-columnar")]
+mod columnar;
+#[cfg(feature="checker-archive")]
+mod archive;
+#[cfg(feature="checker-scientific")]
+mod scientific;
+#[cfg(feature="checker-model")]
+mod model;
+#[cfg(feature="checker-cad")]
+mod cad;
+#[cfg(feature="checker-torrent")]
+mod torrent;
+#[cfg(feature="checker-windows")]
+mod windows;
+#[cfg(feature="checker-json")]
+mod json;
+#[cfg(feature="checker-apple")]
+mod apple;
+#[cfg(feature="checker-firmware")]
+mod firmware;
+#[cfg(feature="checker-rom")]
+mod rom;
+#[cfg(feature="checker-audio")]
+mod audio;
+#[cfg(feature="checker-filesystem")]
+mod filesystem;
+#[cfg(feature="checker-infer")]
+mod infer;
+mod whatwg;
+mod datauri;
+mod export;
+mod mimetype;
+#[cfg(feature="std")]
+mod limit;
+#[cfg(feature="tower")]
+pub mod layer;
+#[cfg(feature="axum")]
+pub mod upload;
+#[cfg(feature="ffi")]
+pub mod ffi;
+#[cfg(feature="fs")]
+pub mod iter;
+#[cfg(feature="parallel")]
+pub mod dir;
+#[cfg(feature="result-cache")]
+pub mod memo;
+#[cfg(all(feature="checker-fdo-magic", feature="fs"))]
+pub mod xdg;
+#[cfg(all(feature="checker-fdo-magic", feature="fs"))]
+pub mod conformance;
+#[cfg(feature="test-vectors")]
+pub mod vectors;
+#[cfg(feature="fuzz")]
+pub mod fuzz;
+
+pub use mimetype::{Mime, ParseMimeError};
 
-#[cfg(feature="staticmime")] type MIME = &'static str;
-#[cfg(not(feature="staticmime"))] type MIME = String;
+/// A MIME type. The name from before `Mime`, kept so code written against it
+/// still builds.
+#[allow(clippy::upper_case_acronyms)]
+pub type MIME = Mime;
 
 /// Check these types first
 /// TODO: Poll these from the checkers? Feels a bit arbitrary
-const TYPEORDER: [&'static str; 7] =
+const TYPEORDER: [&str; 7] =
 [
 	"image/png",
 	"image/jpeg",
@@ -75,55 +256,270 @@ const TYPEORDER: [&'static str; 7] =
 	"application/pdf"
 ];
 
+/// Archive and compressed formats, which are sorted into the `family-archive`
+/// feature instead of `family-application`, and taken by `is_archive`
+const ARCHIVE_TYPES: [&str; 33] =
+[
+    "application/gzip",
+    "application/vnd.debian.binary-package",
+    "application/vnd.ms-cab-compressed",
+    "application/vnd.rar",
+    "application/vnd.squashfs",
+    "application/x-7z-compressed",
+    "application/x-ace",
+    "application/x-alz",
+    "application/x-arc",
+    "application/x-archive",
+    "application/x-arj",
+    "application/x-bzip",
+    "application/x-bzip2",
+    "application/x-compress",
+    "application/x-cpio",
+    "application/x-dar",
+    "application/x-lha",
+    "application/x-lrzip",
+    "application/x-lz4",
+    "application/x-lzip",
+    "application/x-lzma",
+    "application/x-lzop",
+    "application/x-ms-wim",
+    "application/x-qpress",
+    "application/x-rpm",
+    "application/x-stuffit",
+    "application/x-tar",
+    "application/x-xar",
+    "application/x-xz",
+    "application/x-zoo",
+    "application/zip",
+    "application/zstd",
+    "application/x-iso9660-image"
+];
+
+/// Checks if a MIME type belongs to a family enabled with the `family-*`
+/// features. Families follow the top-level type, except for archives.
+/// The base types and `inode/*` are always enabled, as the graph hangs off
+/// of them.
+fn family_enabled(mimetype: &str) -> bool {
+    match mimetype {
+        "all/all" | "all/allfiles" | "application/octet-stream" | "text/plain" => return true,
+        _ => {}
+    }
+    if ARCHIVE_TYPES.contains(&mimetype) {
+        return cfg!(feature="family-archive");
+    }
+    match mimetype.split('/').next().unwrap_or("") {
+        "inode" => true,
+        "application" => cfg!(feature="family-application"),
+        "audio" => cfg!(feature="family-audio"),
+        "font" => cfg!(feature="family-font"),
+        "image" => cfg!(feature="family-image"),
+        "message" | "multipart" => cfg!(feature="family-message"),
+        "model" => cfg!(feature="family-model"),
+        "text" => cfg!(feature="family-text"),
+        "video" => cfg!(feature="family-video"),
+        _ => cfg!(feature="family-other")
+    }
+}
+
 /// Struct used to define checker functions for the sake of boilerplate reduction
 struct CheckerStruct {
+    /// Name, as in the checker's `checker-*` feature
+    name: &'static str,
     from_u8: fn(&[u8], &str, &CacheItem, &CacheItem) -> bool,
+    #[cfg(feature="fs")]
     from_filepath: fn(&Path, &str, &CacheItem, &CacheItem) -> bool,
     get_supported: fn() -> Vec<MIME>,
     get_subclasses: fn() -> Vec<(MIME, MIME)>,
     get_aliaslist: fn() -> FnvHashMap<MIME, MIME>
 }
 
-/// Maximum number of checkers supported with build config.
-/// TODO: Find any better way to do this!
-#[cfg(not(feature="staticmime"))]
-const CHECKERCOUNT: usize = 2;
-#[cfg(feature="staticmime")]
-const CHECKERCOUNT: usize = 2;
-
-/// List of checker functions
-const CHECKERS: [CheckerStruct; CHECKERCOUNT] = 
-[
-    // Disable sys checker when using staticmime
-    #[cfg(not(feature="staticmime"))] CheckerStruct{
+/// List of checker functions. Each checker besides `basetype` can be left out
+/// with its `checker-*` feature.
+const CHECKERS: &[CheckerStruct] = &[
+    // Use builtin magic when there is no filesystem
+    #[cfg(all(feature="checker-fdo-magic", feature="fs"))] CheckerStruct{
+        name: "fdo-magic",
         from_u8: fdo_magic::sys::check::from_u8,
-        from_filepath: fdo_magic::sys::check::from_filepath,
+        #[cfg(feature="fs")] from_filepath: fdo_magic::sys::check::from_filepath,
         get_supported: fdo_magic::sys::init::get_supported,
         get_subclasses: fdo_magic::sys::init::get_subclasses,
         get_aliaslist: fdo_magic::sys::init::get_aliaslist
     },
-    #[cfg(feature="staticmime")] CheckerStruct{
+    #[cfg(all(feature="checker-fdo-magic", not(feature="fs")))] CheckerStruct{
+        name: "fdo-magic",
         from_u8: fdo_magic::builtin::check::from_u8,
-        from_filepath: fdo_magic::builtin::check::from_filepath,
+        #[cfg(feature="fs")] from_filepath: fdo_magic::builtin::check::from_filepath,
         get_supported: fdo_magic::builtin::init::get_supported,
         get_subclasses: fdo_magic::builtin::init::get_subclasses,
         get_aliaslist: fdo_magic::builtin::init::get_aliaslist
     },
     CheckerStruct{
+        name: "basetype",
         from_u8: basetype::check::from_u8,
-        from_filepath: basetype::check::from_filepath,
+        #[cfg(feature="fs")] from_filepath: basetype::check::from_filepath,
         get_supported: basetype::init::get_supported,
         get_subclasses: basetype::init::get_subclasses,
         get_aliaslist: basetype::init::get_aliaslist
+    },
+    #[cfg(feature="checker-pgp")] CheckerStruct{
+        name: "pgp",
+        from_u8: pgp::check::from_u8,
+        #[cfg(feature="fs")] from_filepath: pgp::check::from_filepath,
+        get_supported: pgp::init::get_supported,
+        get_subclasses: pgp::init::get_subclasses,
+        get_aliaslist: pgp::init::get_aliaslist
+    },
+    #[cfg(feature="checker-mail")] CheckerStruct{
+        name: "mail",
+        from_u8: mail::check::from_u8,
+        #[cfg(feature="fs")] from_filepath: mail::check::from_filepath,
+        get_supported: mail::init::get_supported,
+        get_subclasses: mail::init::get_subclasses,
+        get_aliaslist: mail::init::get_aliaslist
+    },
+    #[cfg(feature="checker-dicom")] CheckerStruct{
+        name: "dicom",
+        from_u8: dicom::check::from_u8,
+        #[cfg(feature="fs")] from_filepath: dicom::check::from_filepath,
+        get_supported: dicom::init::get_supported,
+        get_subclasses: dicom::init::get_subclasses,
+        get_aliaslist: dicom::init::get_aliaslist
+    },
+    #[cfg(feature="checker-bio")] CheckerStruct{
+        name: "bio",
+        from_u8: bio::check::from_u8,
+        #[cfg(feature="fs")] from_filepath: bio::check::from_filepath,
+        get_supported: bio::init::get_supported,
+        get_subclasses: bio::init::get_subclasses,
+        get_aliaslist: bio::init::get_aliaslist
+    },
+    #[cfg(feature="checker-columnar")] CheckerStruct{
+        name: "columnar",
+        from_u8: columnar::check::from_u8,
+        #[cfg(feature="fs")] from_filepath: columnar::check::from_filepath,
+        get_supported: columnar::init::get_supported,
+        get_subclasses: columnar::init::get_subclasses,
+        get_aliaslist: columnar::init::get_aliaslist
+    },
+    #[cfg(feature="checker-archive")] CheckerStruct{
+        name: "archive",
+        from_u8: archive::check::from_u8,
+        #[cfg(feature="fs")] from_filepath: archive::check::from_filepath,
+        get_supported: archive::init::get_supported,
+        get_subclasses: archive::init::get_subclasses,
+        get_aliaslist: archive::init::get_aliaslist
+    },
+    #[cfg(feature="checker-scientific")] CheckerStruct{
+        name: "scientific",
+        from_u8: scientific::check::from_u8,
+        #[cfg(feature="fs")] from_filepath: scientific::check::from_filepath,
+        get_supported: scientific::init::get_supported,
+        get_subclasses: scientific::init::get_subclasses,
+        get_aliaslist: scientific::init::get_aliaslist
+    },
+    #[cfg(feature="checker-model")] CheckerStruct{
+        name: "model",
+        from_u8: model::check::from_u8,
+        #[cfg(feature="fs")] from_filepath: model::check::from_filepath,
+        get_supported: model::init::get_supported,
+        get_subclasses: model::init::get_subclasses,
+        get_aliaslist: model::init::get_aliaslist
+    },
+    #[cfg(feature="checker-cad")] CheckerStruct{
+        name: "cad",
+        from_u8: cad::check::from_u8,
+        #[cfg(feature="fs")] from_filepath: cad::check::from_filepath,
+        get_supported: cad::init::get_supported,
+        get_subclasses: cad::init::get_subclasses,
+        get_aliaslist: cad::init::get_aliaslist
+    },
+    #[cfg(feature="checker-torrent")] CheckerStruct{
+        name: "torrent",
+        from_u8: torrent::check::from_u8,
+        #[cfg(feature="fs")] from_filepath: torrent::check::from_filepath,
+        get_supported: torrent::init::get_supported,
+        get_subclasses: torrent::init::get_subclasses,
+        get_aliaslist: torrent::init::get_aliaslist
+    },
+    #[cfg(feature="checker-windows")] CheckerStruct{
+        name: "windows",
+        from_u8: windows::check::from_u8,
+        #[cfg(feature="fs")] from_filepath: windows::check::from_filepath,
+        get_supported: windows::init::get_supported,
+        get_subclasses: windows::init::get_subclasses,
+        get_aliaslist: windows::init::get_aliaslist
+    },
+    #[cfg(feature="checker-json")] CheckerStruct{
+        name: "json",
+        from_u8: json::check::from_u8,
+        #[cfg(feature="fs")] from_filepath: json::check::from_filepath,
+        get_supported: json::init::get_supported,
+        get_subclasses: json::init::get_subclasses,
+        get_aliaslist: json::init::get_aliaslist
+    },
+    #[cfg(feature="checker-apple")] CheckerStruct{
+        name: "apple",
+        from_u8: apple::check::from_u8,
+        #[cfg(feature="fs")] from_filepath: apple::check::from_filepath,
+        get_supported: apple::init::get_supported,
+        get_subclasses: apple::init::get_subclasses,
+        get_aliaslist: apple::init::get_aliaslist
+    },
+    #[cfg(feature="checker-firmware")] CheckerStruct{
+        name: "firmware",
+        from_u8: firmware::check::from_u8,
+        #[cfg(feature="fs")] from_filepath: firmware::check::from_filepath,
+        get_supported: firmware::init::get_supported,
+        get_subclasses: firmware::init::get_subclasses,
+        get_aliaslist: firmware::init::get_aliaslist
+    },
+    #[cfg(feature="checker-rom")] CheckerStruct{
+        name: "rom",
+        from_u8: rom::check::from_u8,
+        #[cfg(feature="fs")] from_filepath: rom::check::from_filepath,
+        get_supported: rom::init::get_supported,
+        get_subclasses: rom::init::get_subclasses,
+        get_aliaslist: rom::init::get_aliaslist
+    },
+    #[cfg(feature="checker-audio")] CheckerStruct{
+        name: "audio",
+        from_u8: audio::check::from_u8,
+        #[cfg(feature="fs")] from_filepath: audio::check::from_filepath,
+        get_supported: audio::init::get_supported,
+        get_subclasses: audio::init::get_subclasses,
+        get_aliaslist: audio::init::get_aliaslist
+    },
+    #[cfg(feature="checker-filesystem")] CheckerStruct{
+        name: "filesystem",
+        from_u8: filesystem::check::from_u8,
+        #[cfg(feature="fs")] from_filepath: filesystem::check::from_filepath,
+        get_supported: filesystem::init::get_supported,
+        get_subclasses: filesystem::init::get_subclasses,
+        get_aliaslist: filesystem::init::get_aliaslist
+    },
+    // Last, so it only fills in what the others don't know
+    #[cfg(feature="checker-infer")] CheckerStruct{
+        name: "infer",
+        from_u8: infer::check::from_u8,
+        #[cfg(feature="fs")] from_filepath: infer::check::from_filepath,
+        get_supported: infer::init::get_supported,
+        get_subclasses: infer::init::get_subclasses,
+        get_aliaslist: infer::init::get_aliaslist
     }
 ];
 
-/// Mappings between modules and supported mimes (by index in table above)
+/// Number of checkers in this build
+const CHECKERCOUNT: usize = CHECKERS.len();
+
 lazy_static! {
+    /// Mappings between modules and supported mimes (by index in table above)
     static ref CHECKER_SUPPORT: FnvHashMap<MIME, usize> = {
         let mut out = FnvHashMap::<MIME, usize>::default();
-        for i in 0..CHECKERS.len() {
-            for j in (CHECKERS[i].get_supported)() {
+        for (i, checker) in CHECKERS.iter().enumerate() {
+            for j in (checker.get_supported)() {
+                if !family_enabled(&j) {
+                    continue;
+                }
                 out.insert(j, i);
             }
         }
@@ -134,8 +530,8 @@ lazy_static! {
 lazy_static! {
     static ref ALIASES: FnvHashMap<MIME, MIME> = {
         let mut out = FnvHashMap::<MIME, MIME>::default();
-        for i in 0..CHECKERS.len() {
-            out.extend((CHECKERS[i].get_aliaslist)());
+        for checker in CHECKERS.iter() {
+            out.extend((checker.get_aliaslist)());
         }
         out
     };
@@ -147,29 +543,120 @@ lazy_static! {
 /// one for each checker. The checker defines the cache types, and everything
 /// should hopefully be peachy.
 ///
-/// Element 0 is the file cache, a Vec<u8>. This gets filled as more bytes
+/// Element 0 is the file cache, an `OpenFile`. This gets filled as more bytes
 /// need to be loaded. Elements 1+ correspond to each checker, as ordered
 /// in the CHECKERS array above.
 #[derive(Clone)]
 pub enum Cache {
-    FileCache(Vec<u8>),
-    #[cfg(not(feature="staticmime"))] FdoMagicSys(fdo_magic::sys::Cache),
-    #[cfg(feature="staticmime")] FdoMagicBuiltin(fdo_magic::builtin::Cache),
-    Basetype(basetype::Cache)
+    #[cfg(feature="fs")] FileCache(OpenFile),
+    #[cfg(all(feature="checker-fdo-magic", feature="fs"))] FdoMagicSys(fdo_magic::sys::Cache),
+    #[cfg(all(feature="checker-fdo-magic", not(feature="fs")))] FdoMagicBuiltin(fdo_magic::builtin::Cache),
+    Basetype(basetype::Cache),
+    #[cfg(feature="checker-pgp")] Pgp(pgp::Cache),
+    #[cfg(feature="checker-mail")] Mail(mail::Cache),
+    #[cfg(feature="checker-dicom")] Dicom(dicom::Cache),
+    #[cfg(feature="checker-bio")] Bio(bio::Cache),
+    #[cfg(feature="checker-columnar")] Columnar(columnar::Cache),
+    #[cfg(feature="checker-archive")] Archive(archive::Cache),
+    #[cfg(feature="checker-scientific")] Scientific(scientific::Cache),
+    #[cfg(feature="checker-model")] Model(model::Cache),
+    #[cfg(feature="checker-cad")] Cad(cad::Cache),
+    #[cfg(feature="checker-torrent")] Torrent(torrent::Cache),
+    #[cfg(feature="checker-windows")] Windows(windows::Cache),
+    #[cfg(feature="checker-json")] Json(json::Cache),
+    #[cfg(feature="checker-apple")] Apple(apple::Cache),
+    #[cfg(feature="checker-firmware")] Firmware(firmware::Cache),
+    #[cfg(feature="checker-rom")] Rom(rom::Cache),
+    #[cfg(feature="checker-audio")] Audio(audio::Cache),
+    #[cfg(feature="checker-filesystem")] Filesystem(filesystem::Cache),
+    #[cfg(feature="checker-infer")] Infer(infer::Cache)
 }
 type CacheItem = Arc<RwLock<Option<Cache>>>;
+
+/// A file being checked, opened once for the whole detection, along with
+/// the bytes read from its start so far
+#[cfg(feature="fs")]
+#[derive(Clone)]
+pub struct OpenFile {
+    file: Arc<std::fs::File>,
+    head: Vec<u8>,
+    /// The last `TAIL_LEN` bytes of the file, once something asks for them
+    #[cfg(any(feature="checker-columnar", feature="checker-archive", feature="checker-filesystem"))]
+    tail: Option<Vec<u8>>,
+    /// The whole file mapped into memory, once something maps it
+    #[cfg(feature="mmap")]
+    map: Option<Arc<memmap2::Mmap>>,
+    /// Windows sampled from the middle of the file, once something asks
+    samples: Option<Vec<Vec<u8>>>,
+    /// Nothing past this far into the file gets read
+    limit: u64,
+    /// Only read these parts of the file, if set
+    sampling: Option<Sampling>
+}
 type CacheContainer = Vec<CacheItem>; // Max number of supported checkers
 
+/// A fresh cache for each checker, plus the file cache. `vec![x; n]` won't
+/// do, as every element would share the one `Arc`.
+fn new_cache() -> CacheContainer {
+    (0..(CHECKERCOUNT + 1)).map(|_| CacheItem::default()).collect()
+}
+
+/// Everything a detection needs to allocate, kept between calls so checking
+/// a batch of small inputs doesn't allocate it all again for each
+struct Scratch {
+    cache: CacheContainer
+}
+
+impl Scratch {
+    fn new() -> Scratch {
+        Scratch{cache: new_cache()}
+    }
+
+    /// Empties the caches for the next input, keeping their allocations
+    #[cfg(feature="std")]
+    fn reset(&mut self) {
+        for x in self.cache.iter() {
+            *x.write() = None;
+        }
+    }
+}
+
+#[cfg(feature="std")]
+thread_local! {
+    static SCRATCH: std::cell::RefCell<Scratch> = std::cell::RefCell::new(Scratch::new());
+}
+
+/// Runs `f` with this thread's scratch space, emptied. Falls back to new
+/// space if the thread's is in use further up the stack, or without `std`.
+fn with_scratch<R, F: FnOnce(&mut Scratch) -> R>(f: F) -> R {
+    #[cfg(feature="std")]
+    {
+        let mut f = Some(f);
+        let reused = SCRATCH.try_with(|x| {
+            let mut scratch = x.try_borrow_mut().ok()?;
+            scratch.reset();
+            f.take().map(|f| f(&mut scratch))
+        });
+        match (reused, f) {
+            (Ok(Some(x)), _) => x,
+            (_, Some(f)) => f(&mut Scratch::new()),
+            (_, None) => unreachable!("f ran, but gave nothing back")
+        }
+    }
+    #[cfg(not(feature="std"))]
+    f(&mut Scratch::new())
+}
+
 // I'd really love to do this, but it needs unstable rust
 /*struct CacheStruct {
-    #[cfg(not(feature="staticmime"))] fdo_magic_sys: Option<fdo_magic::sys::Cache>,
+    fdo_magic_sys: Option<fdo_magic::sys::Cache>,
     fdo_magic_builtin: Option<fdo_magic::builtin::Cache>,
     basetype: Option<basetype::Cache>
 }
 impl CacheStruct {
     pub fn new() {
         CacheStruct {
-            #[cfg(not(feature="staticmime"))] fdo_magic_sys: None,
+            fdo_magic_sys: None,
             fdo_magic_builtin: None,
             basetype: None
         }
@@ -185,6 +672,9 @@ impl CacheStruct {
 /// The `hash` is a mapping between MIME types and nodes on the graph.
 /// The root of the graph is "all/all", so start traversing there unless
 /// you need to jump to a particular node.
+///
+/// Both tie callers to `petgraph`; `TypeId` gets at the same graph without
+/// that.
 pub struct TypeStruct {
     pub graph: DiGraph<MIME, u32>,
     pub hash: FnvHashMap<MIME, NodeIndex>
@@ -192,66 +682,570 @@ pub struct TypeStruct {
 
 lazy_static! {
     /// The TypeStruct autogenerated at library init, and used by the library.
-    pub static ref TYPE: TypeStruct = {
-        graph_init().unwrap_or( 
-            TypeStruct{
-                graph: DiGraph::new(),
-                hash: FnvHashMap::default()
-            } )
+    static ref TYPES: TypeStruct = {
+        graph_init()
     };
 }
 
-/// Convert a &str to a MIME
-#[cfg(not(feature="staticmime"))]
-macro_rules! convmime {
-    ($x:expr) => {$x.to_string()}
+/// The TypeStruct autogenerated at library init, and used by the library.
+#[deprecated(note="use `TypeId` to look up and walk types, which doesn't depend on how the graph is stored")]
+pub static TYPE: TypeRef = TypeRef;
+
+/// Stands in for the type graph in `TYPE`, loading it on first use
+#[doc(hidden)]
+pub struct TypeRef;
+
+impl std::ops::Deref for TypeRef {
+    type Target = TypeStruct;
+
+    fn deref(&self) -> &TypeStruct {
+        &TYPES
+    }
+}
+
+/// A type in the loaded type graph
+///
+/// Unlike the `NodeIndex`es in `TypeStruct`, this doesn't tie callers to how the
+/// graph is stored. Ids are only good for the process that made them, and
+/// sort in no particular order.
+///
+/// # Examples
+/// ```rust
+/// use tree_magic::TypeId;
+///
+/// let zip = TypeId::of("application/zip").unwrap();
+/// assert_eq!(zip.name(), "application/zip");
+/// assert!(zip.children().any(|x| x.name() == "application/epub+zip"));
+/// assert!(zip.parents().any(|x| x == TypeId::of("application/octet-stream").unwrap()));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TypeId(NodeIndex);
+
+impl TypeId {
+    /// Looks up a type by name. Aliases give the type they stand for.
+    pub fn of(mimetype: &str) -> Option<TypeId> {
+        TYPES.hash.get(get_alias(mimetype)).map(|&x| TypeId(x))
+    }
+
+    /// The root of the type graph, `all/all`, which every type is under
+    pub fn root() -> TypeId {
+        match TYPES.graph.externals(Incoming).next() {
+            Some(x) => TypeId(x),
+            None => panic!("No filetype definitions are loaded.")
+        }
+    }
+
+    /// Every type in the graph, sorted by name
+    pub fn all() -> impl Iterator<Item=TypeId> {
+        sorted_ids(TYPES.graph.node_indices())
+    }
+
+    pub fn name(self) -> &'static str {
+        &TYPES.graph[self.0]
+    }
+
+    /// Types this one is a direct subtype of, sorted by name
+    pub fn parents(self) -> impl Iterator<Item=TypeId> {
+        sorted_ids(TYPES.graph.neighbors_directed(self.0, Incoming))
+    }
+
+    /// Direct subtypes of this type, sorted by name
+    pub fn children(self) -> impl Iterator<Item=TypeId> {
+        sorted_ids(TYPES.graph.neighbors_directed(self.0, Outgoing))
+    }
+
+    /// Whether the type has no subtypes
+    pub fn is_leaf(self) -> bool {
+        TYPES.graph.neighbors_directed(self.0, Outgoing).next().is_none()
+    }
+}
+
+impl std::fmt::Display for TypeId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Nodes as ids, sorted by name
+fn sorted_ids<I: Iterator<Item=NodeIndex>>(nodes: I) -> impl Iterator<Item=TypeId> {
+    let mut out: Vec<TypeId> = nodes.map(TypeId).collect();
+    out.sort_by_key(|x| x.name());
+    out.into_iter()
+}
+
+/// A type that can be detected, and what detects it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature="serde", derive(serde::Serialize))]
+pub struct TypeInfo {
+    pub mimetype: MIME,
+    /// Checker that decides whether an input is of the type, named as in its
+    /// `checker-*` feature, such as `fdo-magic` or `pgp`
+    pub checker: &'static str,
+    /// Whether the freedesktop.org magic has rules for the type. Types with
+    /// magic that another checker handles keep it for `from_u8_priority`.
+    pub magic: bool
+}
+
+/// Checkers are read by name, and only the ones in this build are accepted
+#[cfg(feature="serde")]
+impl<'de> serde::Deserialize<'de> for TypeInfo {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<TypeInfo, D::Error> {
+        #[derive(serde::Deserialize)]
+        struct Fields {
+            mimetype: MIME,
+            checker: String,
+            magic: bool
+        }
+        let x = Fields::deserialize(d)?;
+        let checker = checker_name(&x.checker)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown checker {}", x.checker)))?;
+        Ok(TypeInfo {mimetype: x.mimetype, checker, magic: x.magic})
+    }
+}
+
+/// The name of a checker in this build, as a static string
+#[cfg(feature="serde")]
+fn checker_name(name: &str) -> Option<&'static str> {
+    CHECKERS.iter().map(|x| x.name).find(|&x| x == name)
+}
+
+/// Lists every type this build can detect, sorted by name.
+///
+/// # Examples
+/// ```rust
+/// let png = tree_magic::supported_types().find(|x| x.mimetype == "image/png").unwrap();
+/// assert!(png.magic);
+/// ```
+pub fn supported_types() -> impl Iterator<Item=TypeInfo> {
+    let magic = magic_priorities();
+    let mut out: Vec<TypeInfo> = TYPES.graph.node_weights()
+        .filter_map(|x| Some(TypeInfo {
+            mimetype: x.clone(),
+            checker: CHECKERS[*CHECKER_SUPPORT.get(x)?].name,
+            magic: magic.contains_key(x)
+        }))
+        .collect();
+    out.sort_by(|a, b| a.mimetype.cmp(&b.mimetype));
+    out.into_iter()
+}
+
+/// File name patterns of each type in the system database, with aliases
+/// resolved. Empty without the `fs` feature.
+fn globs() -> Vec<(MIME, String)> {
+    #[cfg(all(feature="checker-fdo-magic", feature="fs"))]
+    return fdo_magic::sys::init::get_globs()
+        .into_iter()
+        .map(|x| (MIME::from(get_alias(&x.0).to_string()), x.1))
+        .collect();
+    #[cfg(not(all(feature="checker-fdo-magic", feature="fs")))]
+    return Vec::new();
+}
+
+/// How far this crate gets with a type, from `coverage`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Coverage {
+    /// Detected from the contents of the input
+    Content,
+    /// Only has file name patterns in the system database, which this crate
+    /// doesn't match on
+    GlobOnly,
+    /// Can't be told apart at all, such as a type only known as the parent
+    /// of others
+    Undetectable
+}
+
+/// Reports, for every type in the graph and every type the system database
+/// has file name patterns for, whether it's detected by content, only has
+/// file name patterns, or can't be recognized at all, sorted by name. Useful
+/// for deciding whether this crate alone covers a list of formats.
+///
+/// File name patterns are only known with the `fs` feature, from the system
+/// database; otherwise every type is in the graph, and is either `Content`
+/// or `Undetectable`.
+///
+/// # Examples
+/// ```rust
+/// use tree_magic::Coverage;
+///
+/// let png = tree_magic::coverage().find(|x| x.0 == "image/png").unwrap();
+/// assert_eq!(png.1, Coverage::Content);
+/// ```
+pub fn coverage() -> impl Iterator<Item=(Mime, Coverage)> {
+    let globbed: FnvHashSet<MIME> = globs().into_iter().map(|x| x.0).collect();
+
+    let magic = magic_priorities();
+    let mut out: Vec<(MIME, Coverage)> = TYPES.graph.node_weights()
+        .map(|x| {
+            // fdo-magic only detects the types it has rules for
+            let content = match CHECKER_SUPPORT.get(x) {
+                Some(&i) => CHECKERS[i].name != "fdo-magic" || magic.contains_key(x),
+                None => false
+            };
+            let coverage = match (content, globbed.contains(x)) {
+                (true, _) => Coverage::Content,
+                (false, true) => Coverage::GlobOnly,
+                (false, false) => Coverage::Undetectable
+            };
+            (x.clone(), coverage)
+        })
+        .collect();
+    out.extend(globbed.into_iter()
+        .filter(|x| !TYPES.hash.contains_key(x))
+        .map(|x| (x, Coverage::GlobOnly)));
+    out.sort_by(|a, b| a.0.cmp(&b.0));
+    out.into_iter()
+}
+
+/// One of the freedesktop.org magic rules for a type, from `rules_for`.
+///
+/// A rule matches if `value`, with `mask` ANDed in, is at `offset` or within
+/// `range` bytes after it. Rules form a tree by `indent`: a rule only counts
+/// if the nearest rule before it one level up matched, and the type matches
+/// once a whole chain down to a rule with no children does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MagicRule {
+    /// How deeply the rule is nested, from 0 for the top level
+    pub indent: u32,
+    /// Where in the file the value may start
+    pub offset: u32,
+    /// Number of offsets the value may start at; 0 and 1 both mean just
+    /// `offset`
+    pub range: u32,
+    pub value: std::borrow::Cow<'static, [u8]>,
+    /// Mask ANDed with the file's bytes before comparing, if any
+    pub mask: Option<std::borrow::Cow<'static, [u8]>>,
+    /// Size of the words in `value` and `mask` if they're host-endian
+    /// numbers, otherwise 1
+    pub word_size: u32,
+    /// Priority of the section the rule is in
+    pub priority: u32
+}
+
+/// Gets the magic rules this build will match a type on, in the order they
+/// appear in the database, so they can be shown, audited or exported.
+///
+/// Aliases give the rules of the type they stand for. Empty if the type has
+/// no magic, even if another checker detects it.
+///
+/// # Examples
+/// ```rust
+/// let rules = tree_magic::rules_for("image/png");
+/// assert_eq!(rules[0].offset, 0);
+/// assert_eq!(&*rules[0].value, b"\x89PNG");
+/// ```
+#[allow(unused_variables)]
+pub fn rules_for(mimetype: &str) -> Vec<MagicRule> {
+    #[cfg(all(feature="checker-fdo-magic", feature="fs"))]
+    let rules = fdo_magic::sys::rules_for(mimetype);
+    #[cfg(all(feature="checker-fdo-magic", not(feature="fs")))]
+    let rules = fdo_magic::builtin::rules_for(mimetype);
+    #[cfg(feature="checker-fdo-magic")]
+    return rules.unwrap_or_default().into_iter().map(MagicRule::from).collect();
+    #[cfg(not(feature="checker-fdo-magic"))]
+    return Vec::new();
+}
+
+#[cfg(feature="checker-fdo-magic")]
+impl From<fdo_magic::MagicRule> for MagicRule {
+    fn from(x: fdo_magic::MagicRule) -> MagicRule {
+        MagicRule {
+            indent: x.indent_level,
+            offset: x.start_off,
+            range: x.region_len,
+            value: x.val,
+            mask: x.mask,
+            word_size: x.word_len,
+            priority: x.priority
+        }
+    }
+}
+
+/// Identifies the magic database in use, so results can be traced back to the
+/// rules that produced them
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DbVersion {
+    /// Version of shared-mime-info that wrote the database, if known
+    pub version: Option<String>,
+    /// FNV-1a hash of the magic file, which changes whenever the rules do
+    pub hash: u64,
+    /// Whether the database is the one embedded from `tree_magic_db`, rather
+    /// than the one installed on the system
+    pub embedded: bool
+}
+
+/// Describes the magic database that was loaded.
+///
+/// Returns None if the `checker-fdo-magic` feature is off, as there is no
+/// database then.
+///
+/// # Examples
+/// ```rust
+/// if let Some(db) = tree_magic::db_version() {
+///     println!("shared-mime-info {:?}, rules {:016x}", db.version, db.hash);
+/// }
+/// ```
+pub fn db_version() -> Option<DbVersion> {
+    #[cfg(all(feature="checker-fdo-magic", feature="fs"))]
+    return Some(fdo_magic::sys::db_version());
+    #[cfg(all(feature="checker-fdo-magic", not(feature="fs")))]
+    return Some(fdo_magic::builtin::db_version());
+    #[cfg(not(feature="checker-fdo-magic"))]
+    return None;
+}
+
+/// How far into a file the built-in checkers look, other than the ones that
+/// read past it themselves. A GPT header can be as far in as 4096, after a
+/// protective MBR on a disk with 4096 byte sectors.
+const CHECKER_SCAN_LEN: usize = 8192;
+
+/// How many bytes from the start of a file detection can look at.
+///
+/// This is the furthest any loaded magic rule reaches, counting its offset,
+/// range and value, or how far the built-in checkers look if that's further.
+/// Reading this much of a file is always enough to get the same answer as
+/// reading all of it, other than for the few checkers that look at the end.
+///
+/// # Examples
+/// ```rust
+/// let len = tree_magic::max_scan_extent();
+/// assert!(len >= 2048);
+/// ```
+pub fn max_scan_extent() -> usize {
+    #[cfg(all(feature="checker-fdo-magic", feature="fs"))]
+    let magic = fdo_magic::sys::scan_extent();
+    #[cfg(all(feature="checker-fdo-magic", not(feature="fs")))]
+    let magic = fdo_magic::builtin::scan_extent();
+    #[cfg(not(feature="checker-fdo-magic"))]
+    let magic = 0;
+    std::cmp::max(magic, CHECKER_SCAN_LEN)
+}
+
+/// Something that went wrong loading the MIME database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InitError {
+    /// The system magic file couldn't be parsed, so none of its rules are
+    /// loaded. Holds the parser's message.
+    Magic(String),
+    /// There's no magic file on the system, and the embedded one was left
+    /// out with the `no-embedded-db` feature
+    NoDatabase
+}
+
+impl std::fmt::Display for InitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            InitError::Magic(ref x) => write!(f, "can't parse magic file: {}", x),
+            InitError::NoDatabase => write!(f, "no magic database found")
+        }
+    }
+}
+
+#[cfg(feature="std")]
+impl std::error::Error for InitError {}
+
+/// Whatever went wrong loading the checkers, once they're loaded
+fn init_error() -> Option<InitError> {
+    #[cfg(all(feature="checker-fdo-magic", feature="fs"))]
+    return fdo_magic::sys::init_error();
+    #[cfg(not(all(feature="checker-fdo-magic", feature="fs")))]
+    return None;
+}
+
+/// Loads the MIME database if it isn't already, and says whether that went
+/// well.
+///
+/// `TypeId` and the `from_*` functions carry on with whatever could be loaded,
+/// so a broken system database just means fewer types are recognized. This
+/// reports that instead. The types that were loaded are listed by
+/// `TypeId::all`.
+///
+/// # Examples
+/// ```rust
+/// match tree_magic::database() {
+///     Ok(()) => println!("{} types loaded", tree_magic::TypeId::all().count()),
+///     Err(e) => eprintln!("MIME database: {}", e)
+/// }
+/// ```
+pub fn database() -> Result<(), InitError> {
+    Lazy::force(&TYPES);
+    match init_error() {
+        Some(e) => Err(e),
+        None => Ok(())
+    }
 }
-#[cfg(feature="staticmime")]
-macro_rules! convmime {
-    ($x:expr) => {$x}
+
+/// A problem with the loaded database, from `validate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Diagnostic {
+    /// Types that are each other's subtypes, sorted by name. The walk can't
+    /// get into them from the root.
+    Cycle {
+        types: Vec<MIME>
+    },
+    /// A type the walk can't get to from the root of the type graph
+    Orphan {
+        mimetype: MIME
+    },
+    /// A subclass link naming a type that's neither supported by a checker
+    /// nor given file name patterns, so it's nowhere else in the database.
+    /// Types outside the enabled families don't count.
+    UnknownType {
+        parent: MIME,
+        child: MIME,
+        /// Whichever of the two is unknown, or the parent if both are
+        missing: MIME
+    },
+    /// An alias that stands for more than one type, depending on the checker
+    DuplicateAlias {
+        alias: MIME,
+        /// The types it stands for, sorted by name
+        types: Vec<MIME>
+    }
 }
 
-/// Convert a MIME to a &str
-#[cfg(not(feature="staticmime"))]
-macro_rules! unconvmime {
-    ($x:expr) => {$x.as_str()}
+/// Checks the loaded database for cycles, types the walk can't reach,
+/// subclass links naming unknown types, and aliases that stand for more
+/// than one type. Returns everything found, cycles first, then orphans,
+/// unknown types and aliases, each sorted by type; an empty list means the
+/// database is sound.
+///
+/// Subclass links are checked as the checkers give them, before aliases
+/// are resolved, so a link through an alias counts as known.
+///
+/// # Examples
+/// ```rust
+/// use tree_magic::Diagnostic;
+///
+/// let problems = tree_magic::validate();
+/// assert!(!problems.iter().any(|x| match x {
+///     Diagnostic::Cycle { .. } => true,
+///     _ => false
+/// }));
+/// ```
+pub fn validate() -> Vec<Diagnostic> {
+    let graph = &TYPES.graph;
+    let mut out = Vec::<Diagnostic>::new();
+
+    let mut cycles: Vec<Vec<MIME>> = petgraph::algo::tarjan_scc(graph)
+        .into_iter()
+        .filter(|x| x.len() > 1 || graph.contains_edge(x[0], x[0]))
+        .map(|x| {
+            let mut types: Vec<MIME> = x.into_iter().map(|y| graph[y].clone()).collect();
+            types.sort();
+            types
+        })
+        .collect();
+    cycles.sort();
+    out.extend(cycles.into_iter().map(|types| Diagnostic::Cycle {types}));
+
+    if let Some(&root) = TYPES.hash.get("all/all") {
+        let reached = subtree_nodes(Some(root));
+        let mut orphans: Vec<MIME> = graph.node_indices()
+            .filter(|x| !reached[x.index()])
+            .map(|x| graph[x].clone())
+            .collect();
+        orphans.sort();
+        out.extend(orphans.into_iter().map(|mimetype| Diagnostic::Orphan {mimetype}));
+    }
+
+    // Types with only file name patterns are known, just not detectable
+    let globbed: FnvHashSet<MIME> = globs().into_iter().map(|x| x.0).collect();
+    let known = |x: &str| {
+        !family_enabled(x) || TYPES.hash.contains_key(x) || ALIASES.contains_key(x) || globbed.contains(x)
+    };
+    let mut unknown: Vec<(MIME, MIME, MIME)> = CHECKERS.iter()
+        .flat_map(|x| (x.get_subclasses)())
+        .filter_map(|(parent, child)| {
+            let missing = match (known(&parent), known(&child)) {
+                (false, _) => parent.clone(),
+                (true, false) => child.clone(),
+                (true, true) => return None
+            };
+            Some((missing, parent, child))
+        })
+        .collect();
+    unknown.sort();
+    unknown.dedup();
+    out.extend(unknown.into_iter().map(|(missing, parent, child)| Diagnostic::UnknownType {parent, child, missing}));
+
+    let mut aliases = FnvHashMap::<MIME, Vec<MIME>>::default();
+    for (alias, mimetype) in CHECKERS.iter().flat_map(|x| (x.get_aliaslist)()) {
+        aliases.entry(alias).or_default().push(mimetype);
+    }
+    let mut duplicates: Vec<(MIME, Vec<MIME>)> = aliases.into_iter()
+        .filter_map(|(alias, mut types)| {
+            types.sort();
+            types.dedup();
+            (types.len() > 1).then_some((alias, types))
+        })
+        .collect();
+    duplicates.sort();
+    out.extend(duplicates.into_iter().map(|(alias, types)| Diagnostic::DuplicateAlias {alias, types}));
+
+    out
 }
-#[cfg(feature="staticmime")]
-macro_rules! unconvmime {
-    ($x:expr) => {$x}
+
+/// Supported types and subclass links of a single checker
+fn checker_list(checker: &CheckerStruct) -> (Vec<MIME>, Vec<(MIME, MIME)>) {
+    (
+        (checker.get_supported)().into_iter().filter(|x| family_enabled(x)).collect(),
+        (checker.get_subclasses)()
+    )
 }
 
-/// Clone a MIME
-#[cfg(not(feature="staticmime"))]
-macro_rules! clonemime {
-    ($x:expr) => {$x.clone()}
+/// Supported types and subclass links of all checkers
+#[cfg(not(feature="parallel-init"))]
+fn checker_lists() -> (Vec<MIME>, Vec<(MIME, MIME)>) {
+    let mut mimelist = Vec::<MIME>::new();
+    let mut edgelist_raw = Vec::<(MIME, MIME)>::new();
+    for checker in CHECKERS.iter() {
+        let (mimes, edges) = checker_list(checker);
+        mimelist.extend(mimes);
+        edgelist_raw.extend(edges);
+    }
+    (mimelist, edgelist_raw)
 }
-#[cfg(feature="staticmime")]
-macro_rules! clonemime {
-    ($x:expr) => {$x}
+
+/// Supported types and subclass links of all checkers, with each checker
+/// loading its database on its own thread
+#[cfg(feature="parallel-init")]
+fn checker_lists() -> (Vec<MIME>, Vec<(MIME, MIME)>) {
+    std::thread::scope(|s| {
+        let handles: Vec<_> = CHECKERS.iter()
+            .map(|checker| s.spawn(move || checker_list(checker)))
+            .collect();
+
+        let mut mimelist = Vec::<MIME>::new();
+        let mut edgelist_raw = Vec::<(MIME, MIME)>::new();
+        for handle in handles {
+            let (mimes, edges) = handle.join().unwrap();
+            mimelist.extend(mimes);
+            edgelist_raw.extend(edges);
+        }
+        (mimelist, edgelist_raw)
+    })
 }
 
 // Initialize filetype graph
-fn graph_init() -> Result<TypeStruct, std::io::Error> {
+fn graph_init() -> TypeStruct {
     
     let mut graph = DiGraph::<MIME, u32>::new();
     let mut added_mimes = FnvHashMap::<MIME, NodeIndex>::default();
     
     // Get list of MIME types and MIME relations
-    let mut mimelist = Vec::<MIME>::new();
-    let mut edgelist_raw = Vec::<(MIME, MIME)>::new();
-    for i in 0..CHECKERS.len() {
-        mimelist.extend((CHECKERS[i].get_supported)());
-        edgelist_raw.extend((CHECKERS[i].get_subclasses)());
-    }
+    let (mut mimelist, edgelist_raw) = checker_lists();
     mimelist.sort();
     mimelist.dedup();
     let mimelist = mimelist;
     
     // Create all nodes
     for mimetype in mimelist.iter() {
-        let node = graph.add_node(clonemime!(mimetype));
-        added_mimes.insert(clonemime!(mimetype), node);
+        let node = graph.add_node(mimetype.clone());
+        added_mimes.insert(mimetype.clone(), node);
     }
         
     let mut edge_list = FnvHashSet::<(NodeIndex, NodeIndex)>::with_capacity_and_hasher(
@@ -282,32 +1276,32 @@ fn graph_init() -> Result<TypeStruct, std::io::Error> {
     let node_text = match added_mimes_tmp.get("text/plain"){
         Some(x) => *x,
         None => {
-            let node = graph.add_node(convmime!("text/plain"));
-            added_mimes.insert(convmime!("text/plain"), node);
+            let node = graph.add_node(MIME::from("text/plain"));
+            added_mimes.insert(MIME::from("text/plain"), node);
             node
         }
     };
     let node_octet = match added_mimes_tmp.get("application/octet-stream"){
         Some(x) => *x,
         None => {
-            let node = graph.add_node(convmime!("application/octet-stream"));
-            added_mimes.insert(convmime!("application/octet-stream"), node);
+            let node = graph.add_node(MIME::from("application/octet-stream"));
+            added_mimes.insert(MIME::from("application/octet-stream"), node);
             node
         }
     };
     let node_allall = match added_mimes_tmp.get("all/all"){
         Some(x) => *x,
         None => {
-            let node = graph.add_node(convmime!("all/all"));
-            added_mimes.insert(convmime!("all/all"), node);
+            let node = graph.add_node(MIME::from("all/all"));
+            added_mimes.insert(MIME::from("all/all"), node);
             node
         }
     };
     let node_allfiles = match added_mimes_tmp.get("all/allfiles"){
         Some(x) => *x,
         None => {
-            let node = graph.add_node(convmime!("all/allfiles"));
-            added_mimes.insert(convmime!("all/allfiles"), node);
+            let node = graph.add_node(MIME::from("all/allfiles"));
+            added_mimes.insert(MIME::from("all/allfiles"), node);
             node
         }
     };
@@ -315,8 +1309,8 @@ fn graph_init() -> Result<TypeStruct, std::io::Error> {
     let mut edge_list_2 = FnvHashSet::<(NodeIndex, NodeIndex)>::default();
     for mimenode in graph.externals(Incoming) {
         
-        let ref mimetype = graph[mimenode];
-        let toplevel = mimetype.split("/").nth(0).unwrap_or("");
+        let mimetype = &graph[mimenode];
+        let toplevel = mimetype.split("/").next().unwrap_or("");
         
         if mimenode == node_text || mimenode == node_octet || 
            mimenode == node_allfiles || mimenode == node_allall 
@@ -337,73 +1331,318 @@ fn graph_init() -> Result<TypeStruct, std::io::Error> {
     
     let graph = graph;
     let added_mimes = added_mimes;
-    //println!("{:?}", Dot::with_config(&graph, &[Config::EdgeNoLabel]));
+    trace_event!(debug, types = graph.node_count(), edges = graph.edge_count(), "built type graph");
 
-    Ok( TypeStruct{graph: graph, hash: added_mimes} )
+    TypeStruct{graph, hash: added_mimes}
 }
 
-/// Just the part of from_*_node that walks the graph
-fn typegraph_walker<T: Clone>(
-    parentnode: NodeIndex,
-    input: T,
-    cache: &CacheContainer,
-    matchfn: fn(&str, T, &CacheContainer) -> bool
-) -> Option<MIME> {
+/// Priority of magic that doesn't give one, and of types with no magic
+const DEFAULT_PRIORITY: u32 = 50;
 
-    // Pull most common types towards top
-    let mut children: Vec<NodeIndex> = TYPE.graph
-        .neighbors_directed(parentnode, Outgoing)
-        .collect();
-        
-    for i in 0..children.len() {
-        let x = children[i];
-        if TYPEORDER.contains(&&*TYPE.graph[x]) {
-            children.remove(i);
-            children.insert(0, x);
-        }
-    }
+/// Highest magic priority of each type with magic rules
+fn magic_priorities() -> FnvHashMap<MIME, u32> {
+    #[cfg(all(feature="checker-fdo-magic", feature="fs"))]
+    return fdo_magic::sys::priorities();
+    #[cfg(all(feature="checker-fdo-magic", not(feature="fs")))]
+    return fdo_magic::builtin::priorities();
+    #[cfg(not(feature="checker-fdo-magic"))]
+    return FnvHashMap::<MIME, u32>::default();
+}
 
-    // Walk graph
-    for childnode in children {
-        let ref mimetype = TYPE.graph[childnode];
-        
-        let result = (matchfn)(mimetype, input.clone(), cache);
-        match result {
-            true => {
-                match typegraph_walker(
-                    childnode, input, cache, matchfn
-                ) {
-                    Some(foundtype) => return Some(foundtype),
-                    None => return Some(clonemime!(mimetype)),
-                }
+lazy_static! {
+    /// Highest magic priority of each type, by node index
+    static ref PRIORITIES: Vec<u32> = {
+        let magic = magic_priorities();
+        TYPES.graph.node_indices()
+            .map(|x| magic.get(&TYPES.graph[x]).cloned().unwrap_or(DEFAULT_PRIORITY))
+            .collect()
+    };
+}
+
+/// Highest magic priority of a type
+fn type_priority(mimetype: &str) -> u32 {
+    TYPES.hash.get(mimetype).map(|x| PRIORITIES[x.index()]).unwrap_or(DEFAULT_PRIORITY)
+}
+
+/// Priority a type matched the input at, given that it did. Only magic has
+/// priorities, so types other checkers handle count at their highest.
+#[allow(unused_variables)]
+fn matched_priority(mimetype: &str, bytes: &[u8]) -> u32 {
+    #[cfg(feature="checker-fdo-magic")]
+    {
+        // The magic checker is always first
+        if CHECKER_SUPPORT.get(mimetype) == Some(&0) {
+            #[cfg(feature="fs")]
+            let found = fdo_magic::sys::matched_priority(mimetype, bytes);
+            #[cfg(not(feature="fs"))]
+            let found = fdo_magic::builtin::matched_priority(mimetype, bytes);
+            if let Some(x) = found {
+                return x;
             }
-            false => continue,
         }
     }
-    
-    None
+    type_priority(mimetype)
 }
 
-/// Transforms an alias into it's real type
-#[cfg(feature="staticmime")]
-fn get_alias(mimetype: &str) -> &str {
-    match ALIASES.get(mimetype) {
-        Some(x) => x,
-        None => mimetype
-    }
+/// Which types to try first, wherever they're among the children of a node
+/// in the type graph. Most inputs are one of a few types, so trying those
+/// before their siblings saves checks.
+///
+/// Siblings are always tried highest magic priority first, and one that
+/// only matched on a weak rule doesn't stop a sibling with stronger rules
+/// from being tried, so a weak heuristic never beats a strong signature. The
+/// order only decides between types of the same priority. Types with no
+/// magic count as the default priority of 50.
+///
+/// The default is a built-in list of common types, such as PNG, JPEG and
+/// ZIP.
+///
+/// An order can also leave out every type but a few, with `restrict`, or
+/// leave out a few types and everything under them, with `exclude`.
+#[derive(Clone)]
+pub struct TypeOrder {
+    types: Vec<MIME>,
+    /// Types walked into, with their ancestors, if not all of them
+    allowed: Option<Vec<MIME>>,
+    /// Types left out, with their subtypes
+    excluded: Vec<MIME>,
+    /// Children of each node, by node index, in the order they're tried
+    children: Arc<Vec<Vec<NodeIndex>>>
 }
-#[cfg(not(feature="staticmime"))]
-fn get_alias(mimetype: &String) -> &String {
-    match ALIASES.get(mimetype) {
-        Some(x) => x,
-        None => mimetype
+
+impl TypeOrder {
+    /// Tries the given types before their siblings, in the order given.
+    /// Types that aren't loaded are ignored.
+    ///
+    /// This sorts the children of every node in the type graph, so build it
+    /// once and reuse it.
+    ///
+    /// # Examples
+    /// ```rust
+    /// // A mail server mostly sees documents and archives
+    /// let order = tree_magic::TypeOrder::new(&["application/pdf", "application/zip"]);
+    /// ```
+    pub fn new<S: AsRef<str>>(types: &[S]) -> TypeOrder {
+        let types: Vec<MIME> = types.iter().map(|x| MIME::from(x.as_ref().to_string())).collect();
+        let children = TYPES.graph.node_indices().map(|x| {
+            let mut children: Vec<NodeIndex> = TYPES.graph
+                .neighbors_directed(x, Outgoing)
+                .collect();
+
+            // Strong signatures first, then pull most common types towards top
+            children.sort_by_key(|&y| {
+                let priority = PRIORITIES[y.index()];
+                let position = types.iter()
+                    .position(|z| *z == TYPES.graph[y])
+                    .unwrap_or(types.len());
+                (std::cmp::Reverse(priority), position)
+            });
+            children
+        }).collect();
+        TypeOrder{types, allowed: None, excluded: Vec::new(), children: Arc::new(children)}
     }
-}
 
-/// Internal function. Checks if an alias exists, and if it does,
-/// then runs match_u8.
-fn match_u8_noalias(mimetype: &str, bytes: &[u8], cache: &CacheContainer) -> bool
+    /// Only walks into the given types and their ancestors, for callers
+    /// that only accept a few types, such as upload validators. Types that
+    /// aren't loaded are ignored, and aliases count as the type they stand
+    /// for.
+    ///
+    /// The walk gives up as soon as none of the given types can be reached,
+    /// so detection ends at one of them, or at an ancestor if the input
+    /// isn't any of them. It doesn't go on to the given types' subtypes:
+    /// allowing `text/plain` gets `text/plain` for JSON.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use tree_magic::{DetectOptions, TypeOrder};
+    ///
+    /// let options = DetectOptions {
+    ///     type_order: TypeOrder::default().restrict(&["image/png", "image/gif"]),
+    ///     ..Default::default()
+    /// };
+    /// let result = tree_magic::from_u8_with(include_bytes!("../tests/image/gif"), &options);
+    /// assert_eq!(result, "image/gif");
+    ///
+    /// // Not worth finding out what it is
+    /// let result = tree_magic::from_u8_with(include_bytes!("../tests/application/zip"), &options);
+    /// assert_eq!(result, "application/octet-stream");
+    /// ```
+    pub fn restrict<S: AsRef<str>>(&self, types: &[S]) -> TypeOrder {
+        let allowed: Vec<MIME> = types.iter()
+            .map(|x| MIME::from(get_alias(x.as_ref()).to_string()))
+            .collect();
+
+        // Mark the given types and everything above them
+        let mut keep = vec![false; TYPES.graph.node_count()];
+        let mut stack: Vec<NodeIndex> = allowed.iter()
+            .filter_map(|x| TYPES.hash.get(x).cloned())
+            .collect();
+        while let Some(node) = stack.pop() {
+            if !keep[node.index()] {
+                keep[node.index()] = true;
+                stack.extend(TYPES.graph.neighbors_directed(node, Incoming));
+            }
+        }
+
+        let children = self.children.iter()
+            .map(|x| x.iter().cloned().filter(|y| keep[y.index()]).collect())
+            .collect();
+        TypeOrder{allowed: Some(allowed), children: Arc::new(children), ..self.clone()}
+    }
+
+    /// Leaves the given types out of the walk, and everything under them,
+    /// such as `application/x-ole-storage` and the Office formats based on
+    /// it. Types that aren't loaded are ignored, and aliases count as the
+    /// type they stand for.
+    ///
+    /// Detection ends at the parent of an excluded type instead. A type
+    /// with more than one parent can still be reached through the others,
+    /// unless it's excluded itself.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use tree_magic::{DetectOptions, TypeOrder};
+    ///
+    /// let options = DetectOptions {
+    ///     type_order: TypeOrder::default().exclude(&["application/zip"]),
+    ///     ..Default::default()
+    /// };
+    /// let result = tree_magic::from_u8_with(include_bytes!("../tests/application/zip"), &options);
+    /// assert_eq!(result, "application/octet-stream");
+    /// ```
+    pub fn exclude<S: AsRef<str>>(&self, types: &[S]) -> TypeOrder {
+        let mut excluded = self.excluded.clone();
+        excluded.extend(types.iter().map(|x| MIME::from(get_alias(x.as_ref()).to_string())));
+
+        let mut drop = vec![false; TYPES.graph.node_count()];
+        for x in excluded.iter().filter_map(|x| TYPES.hash.get(x)) {
+            drop[x.index()] = true;
+        }
+        let children = self.children.iter()
+            .map(|x| x.iter().cloned().filter(|y| !drop[y.index()]).collect())
+            .collect();
+        TypeOrder{excluded, children: Arc::new(children), ..self.clone()}
+    }
+
+    /// Types tried first, as given
+    pub fn types(&self) -> &[Mime] {
+        &self.types
+    }
+
+    /// Types the walk is restricted to, if it is
+    pub fn allowed(&self) -> Option<&[Mime]> {
+        self.allowed.as_deref()
+    }
+
+    /// Types left out of the walk
+    pub fn excluded(&self) -> &[Mime] {
+        &self.excluded
+    }
+}
+
+impl Default for TypeOrder {
+    fn default() -> TypeOrder {
+        DEFAULT_ORDER.clone()
+    }
+}
+
+impl std::fmt::Debug for TypeOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("TypeOrder")
+            .field("types", &self.types)
+            .field("allowed", &self.allowed)
+            .field("excluded", &self.excluded)
+            .finish()
+    }
+}
+
+impl PartialEq for TypeOrder {
+    fn eq(&self, other: &TypeOrder) -> bool {
+        self.types == other.types && self.allowed == other.allowed && self.excluded == other.excluded
+    }
+}
+
+impl Eq for TypeOrder {}
+
+lazy_static! {
+    static ref DEFAULT_ORDER: TypeOrder = {
+        TypeOrder::new(&TYPEORDER)
+    };
+}
+
+/// Just the part of from_*_node that walks the graph
+///
+/// `matchfn` says whether the input is of a type, and if so, the priority
+/// it matched at. It carries whatever it needs to check, such as the input
+/// and caches.
+fn typegraph_walker<F: FnMut(&str) -> Option<u32>>(
+    parentnode: NodeIndex,
+    order: &TypeOrder,
+    matchfn: &mut F
+) -> Option<MIME> {
+    let (node, _priority) = best_child(parentnode, order, matchfn)?;
+    trace_event!(trace, parent = &*TYPES.graph[parentnode], mimetype = &*TYPES.graph[node], priority = _priority, "walk chose type");
+    Some(typegraph_walker(node, order, matchfn).unwrap_or_else(|| TYPES.graph[node].clone()))
+}
+
+/// One step of `typegraph_walker`: the child of a node the walk goes down
+/// into, and the priority it matched at
+fn best_child<F: FnMut(&str) -> Option<u32>>(
+    parentnode: NodeIndex,
+    order: &TypeOrder,
+    matchfn: &mut F
+) -> Option<(NodeIndex, u32)> {
+    // Children come highest priority first, so once one matches, only the
+    // ones that could match at a higher priority are left to try
+    let mut best: Option<(NodeIndex, u32)> = None;
+    for &childnode in order.children[parentnode.index()].iter() {
+        if best.is_some_and(|x| PRIORITIES[childnode.index()] <= x.1) {
+            break;
+        }
+        match (matchfn(&TYPES.graph[childnode]), best) {
+            (Some(x), Some(y)) if x <= y.1 => {},
+            (Some(x), _) => best = Some((childnode, x)),
+            (None, _) => {}
+        }
+    }
+    best
+}
+
+/// Transforms an alias into it's real type
+fn get_alias(mimetype: &str) -> &str {
+    match ALIASES.get(mimetype) {
+        Some(x) => x,
+        None => mimetype
+    }
+}
+
+/// Checks if the given bytestream is text, rather than binary data.
+///
+/// This is the same test used to decide whether a stream is `text/plain`:
+/// no control characters other than whitespace in the first 32 bytes, and
+/// no null bytes at all. Text with a UTF-8, UTF-16 or UTF-32 byte order
+/// mark is decoded before checking.
+///
+/// # Examples
+/// ```rust
+/// assert_eq!(tree_magic::is_text(b"Hello, world!\n"), true);
+/// assert_eq!(tree_magic::is_text(include_bytes!("../tests/image/gif")), false);
+/// ```
+pub fn is_text(bytes: &[u8]) -> bool
 {
+    basetype::check::is_text(bytes)
+}
+
+/// Internal function. Checks if an alias exists, and if it does,
+/// then runs match_u8.
+fn match_u8_noalias(mimetype: &str, bytes: &[u8], cache: &CacheContainer) -> bool
+{
+    // Each type checked counts against the work limit, on top of whatever
+    // magic rules it runs
+    #[cfg(feature="std")]
+    if !limit::charge(1, 0) {
+        return false;
+    }
     match CHECKER_SUPPORT.get(mimetype) {
         None => {false},
         Some(y) => (CHECKERS[*y].from_u8)(bytes, mimetype, &cache[*y + 1], &cache[0])
@@ -419,7 +1658,7 @@ fn match_u8_noalias(mimetype: &str, bytes: &[u8], cache: &CacheContainer) -> boo
 /// # Examples
 /// ```rust
 /// // Load a GIF file
-/// let input: &[u8] = include_bytes!("tests/image/gif");
+/// let input: &[u8] = include_bytes!("../tests/image/gif");
 ///
 /// // Check if the MIME and the file are a match
 /// let result = tree_magic::match_u8("image/gif", input);
@@ -428,17 +1667,57 @@ fn match_u8_noalias(mimetype: &str, bytes: &[u8], cache: &CacheContainer) -> boo
 pub fn match_u8(mimetype: &str, bytes: &[u8]) -> bool
 {
     // Transform alias if needed
-    let oldmime = convmime!(mimetype);
-    let x = unconvmime!(get_alias(&oldmime));
-    let mut cache = Vec::<CacheItem>::with_capacity(CHECKERCOUNT + 1);
-    for _ in 0..(CHECKERCOUNT + 1) {
-        cache.push(CacheItem::default());
+    let x = get_alias(mimetype);
+
+    if with_scratch(|scratch| match_u8_noalias(x, bytes, &scratch.cache)) {
+        return true;
+    }
+
+    // Text with a byte order mark may still match once decoded
+    match basetype::check::decode_bom(bytes) {
+        Some(decoded) => with_scratch(|scratch| {
+            match_u8_noalias(x, decoded.as_bytes(), &scratch.cache)
+        }),
+        None => false
     }
-    
-    match_u8_noalias(x, bytes, &cache)
 }
 
 
+/// Checks which of the given MIME types the bytestream matches.
+///
+/// Gives the same answers as calling `match_u8` for each type, in the same
+/// order, but shares the work between them: the input is scanned for every
+/// type's magic once, rather than once per type. Use this to check an
+/// upload against an allow-list.
+///
+/// # Examples
+/// ```rust
+/// let input: &[u8] = include_bytes!("../tests/image/gif");
+///
+/// let result = tree_magic::match_u8_many(&["image/png", "image/gif", "image/jpeg"], input);
+/// assert_eq!(result, vec![false, true, false]);
+/// ```
+pub fn match_u8_many<T: AsRef<str>>(mimetypes: &[T], bytes: &[u8]) -> Vec<bool>
+{
+    let types: Vec<&str> = mimetypes.iter().map(|x| get_alias(x.as_ref())).collect();
+    let mut out: Vec<bool> = with_scratch(|scratch| {
+        types.iter().map(|x| match_u8_noalias(x, bytes, &scratch.cache)).collect()
+    });
+    if out.iter().all(|&x| x) {
+        return out;
+    }
+
+    // Text with a byte order mark may still match once decoded
+    if let Some(decoded) = basetype::check::decode_bom(bytes) {
+        with_scratch(|scratch| {
+            for (found, x) in out.iter_mut().zip(types.iter()) {
+                *found = *found || match_u8_noalias(x, decoded.as_bytes(), &scratch.cache);
+            }
+        });
+    }
+    out
+}
+
 /// Gets the type of a file from a raw bytestream, starting at a certain node
 /// in the type graph.
 ///
@@ -458,7 +1737,7 @@ pub fn match_u8(mimetype: &str, bytes: &[u8]) -> bool
 /// /// it will return None.
 ///
 /// // Load a ZIP file
-/// let input: &[u8] = include_bytes!("tests/application/zip");
+/// let input: &[u8] = include_bytes!("../tests/application/zip");
 /// 
 /// // Get the graph node for ZIP
 /// let zipnode = tree_magic::TYPE.hash.get("application/zip").unwrap();
@@ -467,9 +1746,115 @@ pub fn match_u8(mimetype: &str, bytes: &[u8]) -> bool
 /// let result = tree_magic::from_u8_node(*zipnode, input);
 /// assert_eq!(result, None);
 /// ```
-pub fn from_u8_node(parentnode: NodeIndex, bytes: &[u8]) -> Option<MIME>
+#[deprecated(note="use `from_u8_under`, which takes a `TypeId`")]
+pub fn from_u8_node(parentnode: NodeIndex, bytes: &[u8]) -> Option<Mime>
+{
+	from_u8_node_ordered(parentnode, bytes, &DEFAULT_ORDER)
+}
+
+/// Gets the type of a byte stream, looking only at subtypes of the given
+/// type. Returns None if none of them match.
+///
+/// # Examples
+/// ```rust
+/// use tree_magic::TypeId;
+///
+/// // We know we have a ZIP, but want to see if it's something like an
+/// // Office document that subclasses a ZIP. This one isn't.
+/// let zip = TypeId::of("application/zip").unwrap();
+/// let result = tree_magic::from_u8_under(zip, include_bytes!("../tests/application/zip"));
+/// assert_eq!(result, None);
+/// ```
+pub fn from_u8_under(parent: TypeId, bytes: &[u8]) -> Option<Mime>
+{
+	from_u8_node_ordered(parent.0, bytes, &DEFAULT_ORDER)
+}
+
+/// Internal function. Does the work of `from_u8_node`, trying types in the
+/// given order.
+fn from_u8_node_ordered(parentnode: NodeIndex, bytes: &[u8], order: &TypeOrder) -> Option<MIME>
+{
+	let found = with_scratch(|x| from_u8_node_cached(parentnode, bytes, &x.cache, order));
+	bom_subtype(found, bytes, order)
+}
+
+/// Internal function. Walks the graph for a byte stream with the given
+/// caches. If `bytes` is the start of a file, the file cache can hold it
+/// open, for checkers that look at its end.
+fn from_u8_node_cached(parentnode: NodeIndex, bytes: &[u8], cache: &CacheContainer, order: &TypeOrder) -> Option<MIME>
+{
+	typegraph_walker(parentnode, order, &mut |y| {
+		match_u8_noalias(y, bytes, cache).then(|| matched_priority(y, bytes))
+	})
+}
+
+/// Internal function. Magic for text subtypes is written against UTF-8 (or
+/// ASCII), so text with a byte order mark stops at text/plain. This looks
+/// for a subtype in the decoded text instead.
+fn bom_subtype(found: Option<MIME>, bytes: &[u8], order: &TypeOrder) -> Option<MIME>
 {
-	typegraph_walker(parentnode, bytes, &vec![CacheItem::default(); CHECKERCOUNT + 1], match_u8_noalias)
+	if found.as_deref() != Some("text/plain") {
+		return found;
+	}
+	let decoded = match basetype::check::decode_bom(bytes) {
+		Some(x) => x,
+		None => return found
+	};
+	let textnode = match TYPES.hash.get("text/plain") {
+		Some(x) => *x,
+		None => return found
+	};
+	with_scratch(|x| {
+		typegraph_walker(textnode, order, &mut |y| {
+			match_u8_noalias(y, decoded.as_bytes(), &x.cache).then(|| matched_priority(y, decoded.as_bytes()))
+		})
+	}).or(found)
+}
+
+/// Walks the type graph from a certain node, with a test of your own for
+/// each type.
+///
+/// `matchfn` is asked about the children of a node, most common types first,
+/// and the walk goes down into the first one it accepts. Returns the deepest
+/// type accepted, or None if no child of `parentnode` is. The test can carry
+/// state, such as its own cache or a count of the checks made.
+///
+/// # Examples
+/// ```rust
+/// let input: &[u8] = include_bytes!("../tests/image/gif");
+/// let root = tree_magic::TYPE.hash["all/all"];
+///
+/// let mut checked = Vec::new();
+/// let result = tree_magic::from_node_with(root, |mimetype| {
+///     checked.push(mimetype.to_string());
+///     tree_magic::match_u8(mimetype, input)
+/// });
+/// assert_eq!(result, Some("image/gif".into()));
+/// assert!(checked.len() < tree_magic::TYPE.hash.len());
+/// ```
+#[deprecated(note="use `from_type_with`, which takes a `TypeId`")]
+pub fn from_node_with<F: FnMut(&str) -> bool>(parentnode: NodeIndex, mut matchfn: F) -> Option<Mime> {
+    typegraph_walker(parentnode, &DEFAULT_ORDER, &mut |x| matchfn(x).then(|| type_priority(x)))
+}
+
+/// Walks the type graph from a type, with a test of your own for each of
+/// its subtypes.
+///
+/// `matchfn` is asked about the children of a type, most common types first,
+/// and the walk goes down into the first one it accepts. Returns the deepest
+/// type accepted, or None if no child of `parent` is. The test can carry
+/// state, such as its own cache or a count of the checks made.
+///
+/// # Examples
+/// ```rust
+/// use tree_magic::TypeId;
+///
+/// let input: &[u8] = include_bytes!("../tests/image/gif");
+/// let result = tree_magic::from_type_with(TypeId::root(), |mimetype| tree_magic::match_u8(mimetype, input));
+/// assert_eq!(result, Some("image/gif".into()));
+/// ```
+pub fn from_type_with<F: FnMut(&str) -> bool>(parent: TypeId, mut matchfn: F) -> Option<Mime> {
+    typegraph_walker(parent.0, &DEFAULT_ORDER, &mut |x| matchfn(x).then(|| type_priority(x)))
 }
 
 /// Gets the type of a file from a byte stream.
@@ -479,30 +1864,303 @@ pub fn from_u8_node(parentnode: NodeIndex, bytes: &[u8]) -> Option<MIME>
 /// # Examples
 /// ```rust
 /// // Load a GIF file
-/// let input: &[u8] = include_bytes!("tests/image/gif");
+/// let input: &[u8] = include_bytes!("../tests/image/gif");
 ///
 /// // Find the MIME type of the GIF
 /// let result = tree_magic::from_u8(input);
 /// assert_eq!(result, "image/gif");
 /// ```
-pub fn from_u8(bytes: &[u8]) -> MIME
+pub fn from_u8(bytes: &[u8]) -> Mime
 {
-    let node = match TYPE.graph.externals(Incoming).next() {
+    let node = match TYPES.graph.externals(Incoming).next() {
         Some(foundnode) => foundnode,
         None => panic!("No filetype definitions are loaded.")
     };
-    from_u8_node(node, bytes).unwrap()
+    from_u8_node_ordered(node, bytes, &DEFAULT_ORDER).unwrap()
+}
+
+/// Types `from_u8_fast` looks for
+const FAST_TYPES: [&str; 33] =
+[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "image/bmp",
+    "image/tiff",
+    "image/svg+xml",
+    "image/avif",
+    "image/heif",
+    "image/vnd.microsoft.icon",
+    "application/zip",
+    "application/gzip",
+    "application/x-tar",
+    "application/x-7z-compressed",
+    "application/vnd.rar",
+    "application/x-bzip",
+    "application/x-xz",
+    "application/zstd",
+    "application/pdf",
+    "audio/mpeg",
+    "audio/flac",
+    "audio/ogg",
+    "audio/x-wav",
+    "audio/mp4",
+    "audio/aac",
+    "video/mp4",
+    "video/webm",
+    "video/x-matroska",
+    "application/x-matroska",
+    "video/quicktime",
+    "video/ogg",
+    "application/json",
+    "text/plain"
+];
+
+lazy_static! {
+    /// Whether `from_u8_fast` checks each node, by node index: the fast
+    /// types, and every type on the way to one
+    static ref FAST_NODES: Vec<bool> = {
+        let mut out = vec![false; TYPES.graph.node_count()];
+        let mut stack: Vec<NodeIndex> = FAST_TYPES.iter()
+            .filter_map(|x| TYPES.hash.get(*x).cloned())
+            .collect();
+        while let Some(x) = stack.pop() {
+            if !out[x.index()] {
+                out[x.index()] = true;
+                stack.extend(TYPES.graph.neighbors_directed(x, Incoming));
+            }
+        }
+        out
+    };
+}
+
+/// Gets the type of a byte stream, if it's one of a few common types.
+///
+/// Only images, archives, PDF, common audio and video containers, JSON and
+/// plain text are looked for, and nothing else is checked on the way, so
+/// this is quicker than `from_u8` for latency-sensitive uses such as
+/// thumbnailers. Returns None if the input isn't one of those. Subtypes
+/// aren't told apart: an Office document is `application/zip`, and a shell
+/// script is `text/plain`.
+///
+/// # Examples
+/// ```rust
+/// let input: &[u8] = include_bytes!("../tests/image/gif");
+/// assert_eq!(tree_magic::from_u8_fast(input), Some("image/gif".into()));
+///
+/// // Not a common type
+/// let input: &[u8] = include_bytes!("../tests/application/x-ms-shortcut");
+/// assert_eq!(tree_magic::from_u8_fast(input), None);
+/// ```
+pub fn from_u8_fast(bytes: &[u8]) -> Option<Mime>
+{
+    let node = TYPES.graph.externals(Incoming).next()?;
+    let found = with_scratch(|x| {
+        typegraph_walker(node, &DEFAULT_ORDER, &mut |y| {
+            let found = TYPES.hash.get(y).is_some_and(|z| FAST_NODES[z.index()]) &&
+                match_u8_noalias(y, bytes, &x.cache);
+            found.then(|| matched_priority(y, bytes))
+        })
+    })?;
+    match FAST_TYPES.contains(&&*found) {
+        true => Some(found),
+        false => None
+    }
+}
+
+/// Gets the type of a byte stream, as far as saying whether it's in a
+/// family: a type and its subtypes, like `text/plain`, or a whole media
+/// type, like `image/*`.
+///
+/// The walk only goes where the family can be reached, and stops at the
+/// first type in it, so this is quicker than `from_u8` for questions like
+/// "is this an image at all?". Returns that type, which may be a parent of
+/// what `from_u8` would give, or None as soon as the input can't be in the
+/// family.
+///
+/// # Examples
+/// ```rust
+/// let input: &[u8] = include_bytes!("../tests/image/gif");
+/// assert_eq!(tree_magic::from_u8_until(input, "image/*"), Some("image/gif".into()));
+/// assert_eq!(tree_magic::from_u8_until(input, "text/plain"), None);
+///
+/// // Stops at text/plain, without working out it's JSON
+/// let input: &[u8] = include_bytes!("../tests/application/json");
+/// assert_eq!(tree_magic::from_u8_until(input, "text/plain"), Some("text/plain".into()));
+/// ```
+pub fn from_u8_until(bytes: &[u8], family: &str) -> Option<Mime>
+{
+    let node = TYPES.graph.externals(Incoming).next()?;
+    let family = family_order(family);
+
+    let walk = |b: &[u8]| -> Option<MIME> {
+        let found = with_scratch(|x| from_u8_node_cached(node, b, &x.cache, &family.order))?;
+        TYPES.hash.get(&*found).is_some_and(|x| family.inside[x.index()]).then_some(found)
+    };
+
+    // Text with a byte order mark may still be in it once decoded
+    walk(bytes).or_else(|| walk(basetype::check::decode_bom(bytes)?.as_bytes()))
+}
+
+/// The walk for `from_u8_until`
+struct FamilyOrder {
+    /// Whether each node is in the family, by node index
+    inside: Vec<bool>,
+    /// The default order, going only towards the family, and no further
+    /// once in it
+    order: TypeOrder
+}
+
+/// Most families `family_order` remembers
+const FAMILY_CACHE_LEN: usize = 64;
+
+lazy_static! {
+    static ref FAMILIES: RwLock<FnvHashMap<String, Arc<FamilyOrder>>> = RwLock::new(FnvHashMap::default());
+}
+
+/// Works out the walk for a family, or remembers it from last time
+fn family_order(family: &str) -> Arc<FamilyOrder> {
+    if let Some(x) = FAMILIES.read().get(family) {
+        return x.clone();
+    }
+
+    // The family and everything under it, then everything above that
+    let inside = match family.strip_suffix("/*") {
+        Some(media) => media_nodes(media),
+        None => subtree_nodes(TYPES.hash.get(get_alias(family)).cloned())
+    };
+    let mut reach = inside.clone();
+    let mut stack: Vec<NodeIndex> = TYPES.graph.node_indices().filter(|x| inside[x.index()]).collect();
+    while let Some(x) = stack.pop() {
+        for y in TYPES.graph.neighbors_directed(x, Incoming) {
+            if !reach[y.index()] {
+                reach[y.index()] = true;
+                stack.push(y);
+            }
+        }
+    }
+
+    let children = DEFAULT_ORDER.children.iter().enumerate().map(|(i, x)| match inside[i] {
+        true => Vec::new(),
+        false => x.iter().cloned().filter(|y| reach[y.index()]).collect()
+    }).collect();
+    let order = TypeOrder{children: Arc::new(children), ..DEFAULT_ORDER.clone()};
+
+    let out = Arc::new(FamilyOrder{inside, order});
+    let mut families = FAMILIES.write();
+    if families.len() < FAMILY_CACHE_LEN {
+        families.insert(family.to_string(), out.clone());
+    }
+    out
+}
+
+/// Gets the type of a byte stream the way xdgmime does.
+///
+/// Every freedesktop.org magic rule is checked, rather than just the ones
+/// on the way down the type graph, and the matching type with the highest
+/// priority wins. Of matching types with the same priority, one that's a
+/// subtype of another wins, and after that the first by name, as in the
+/// order `update-mime-database` writes. A type handled by one of the other
+/// checkers only counts if that checker agrees. If no magic matches, this
+/// falls back on `from_u8`.
+///
+/// # Examples
+/// ```rust
+/// let input: &[u8] = include_bytes!("../tests/image/gif");
+/// assert_eq!(tree_magic::from_u8_priority(input), "image/gif");
+/// ```
+pub fn from_u8_priority(bytes: &[u8]) -> Mime
+{
+    #[cfg(all(feature="checker-fdo-magic", feature="fs"))]
+    let mut found = fdo_magic::sys::matches(bytes);
+    #[cfg(all(feature="checker-fdo-magic", not(feature="fs")))]
+    let mut found = fdo_magic::builtin::matches(bytes);
+    #[cfg(not(feature="checker-fdo-magic"))]
+    let mut found = Vec::<(MIME, u32)>::new();
+
+    // Types another checker took over only count if that checker agrees.
+    // The magic checker is always first.
+    let cache = new_cache();
+    found.retain(|x| CHECKER_SUPPORT.get(&x.0) == Some(&0) || match_u8_noalias(&x.0, bytes, &cache));
+
+    let best = match found.iter().map(|x| x.1).max() {
+        Some(x) => x,
+        None => return from_u8(bytes)
+    };
+    found.retain(|x| x.1 == best);
+    let specific = found.iter()
+        .map(|x| &x.0)
+        .filter(|&x| !found.iter().any(|y| y.0 != *x && is_subtype_of(&y.0, x)))
+        .min();
+    match specific {
+        Some(x) => x.clone(),
+        None => from_u8(bytes)
+    }
+}
+
+/// Algorithm used to determine the type of a bytestream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SniffMode<'a> {
+    /// Walk the freedesktop.org type graph, as `from_u8` does
+    Freedesktop,
+    /// Take the matching magic with the highest priority, as
+    /// `from_u8_priority` does
+    Priority,
+    /// Follow the WHATWG MIME Sniffing Standard, as web browsers do
+    Whatwg {
+        /// Type given in the Content-Type header, if there was one
+        supplied: Option<&'a str>,
+        /// Set if the resource had `X-Content-Type-Options: nosniff`
+        no_sniff: bool
+    }
+}
+
+/// Gets the type of a file from a byte stream, using the given algorithm.
+///
+/// In `Whatwg` mode, the result follows the browser sniffing rules: the
+/// supplied type is kept where the standard says to, scriptable types such
+/// as HTML are never sniffed under `nosniff`, and the result may be a type
+/// that freedesktop.org doesn't know, such as `text/xml` or `audio/wave`.
+///
+/// # Examples
+/// ```rust
+/// use tree_magic::SniffMode;
+///
+/// let html = b"<!DOCTYPE html><title>Hi</title>";
+///
+/// let result = tree_magic::from_u8_mode(html, SniffMode::Whatwg {
+///     supplied: None,
+///     no_sniff: false
+/// });
+/// assert_eq!(result, "text/html");
+///
+/// // Scriptable types are never sniffed under nosniff
+/// let result = tree_magic::from_u8_mode(html, SniffMode::Whatwg {
+///     supplied: None,
+///     no_sniff: true
+/// });
+/// assert_eq!(result, "text/plain");
+/// ```
+pub fn from_u8_mode(bytes: &[u8], mode: SniffMode) -> String
+{
+    match mode {
+        SniffMode::Freedesktop => from_u8(bytes).to_string(),
+        SniffMode::Priority => from_u8_priority(bytes).to_string(),
+        SniffMode::Whatwg{supplied, no_sniff} => whatwg::sniff(bytes, supplied, no_sniff)
+    }
 }
 
 /// Internal function. Checks if an alias exists, and if it does,
 /// then runs `match_u8`.
+#[cfg(feature="fs")]
 fn match_filepath_noalias(mimetype: &str, filepath: &Path, cache: &CacheContainer) -> bool
 {
     match CHECKER_SUPPORT.get(mimetype) {
         None => {false},
         Some(y) => {
-            let ref checkercache = cache[*y + 1];
-            let ref filecache = cache[0];
+            let checkercache = &cache[*y + 1];
+            let filecache = &cache[0];
             //assert_eq!(0, std::sync::Arc::strong_count(&filecache));
             (CHECKERS[*y].from_filepath)(filepath, mimetype, checkercache, filecache)
         }
@@ -525,16 +2183,12 @@ fn match_filepath_noalias(mimetype: &str, filepath: &Path, cache: &CacheContaine
 /// let result = tree_magic::match_filepath("image/gif", path);
 /// assert_eq!(result, true);
 /// ```
+#[cfg(feature="fs")]
 pub fn match_filepath(mimetype: &str, filepath: &Path) -> bool 
 {
     // Transform alias if needed
-    let oldmime = convmime!(mimetype);
-    let x = unconvmime!(get_alias(&oldmime));
-    let mut cache_collection = Vec::<CacheItem>::with_capacity(CHECKERCOUNT + 1);
-    
-    for _ in 0..(CHECKERCOUNT + 1) {
-        cache_collection.push(CacheItem::default());
-    }
+    let x = get_alias(mimetype);
+    let cache_collection = new_cache();
    
     match_filepath_noalias(x, filepath, &cache_collection)
 }
@@ -569,47 +2223,102 @@ pub fn match_filepath(mimetype: &str, filepath: &Path) -> bool
 /// let result = tree_magic::from_filepath_node(*zipnode, path);
 /// assert_eq!(result, None);
 /// ```
-pub fn from_filepath_node(parentnode: NodeIndex, filepath: &Path) -> Option<MIME> 
+#[cfg(feature="fs")]
+#[deprecated(note="use `from_filepath_under`, which takes a `TypeId`")]
+pub fn from_filepath_node(parentnode: NodeIndex, filepath: &Path) -> Option<Mime> 
+{
+    from_filepath_node_cached(parentnode, filepath, &new_cache(), &DEFAULT_ORDER, None)
+}
+
+/// Gets the type of a file, looking only at subtypes of the given type.
+/// Returns None if none of them match, or the file can't be read.
+///
+/// # Examples
+/// ```rust
+/// use std::path::Path;
+/// use tree_magic::TypeId;
+///
+/// let zip = TypeId::of("application/zip").unwrap();
+/// let result = tree_magic::from_filepath_under(zip, Path::new("tests/application/zip"));
+/// assert_eq!(result, None);
+/// ```
+#[cfg(feature="fs")]
+pub fn from_filepath_under(parent: TypeId, filepath: &Path) -> Option<Mime>
+{
+    from_filepath_node_cached(parent.0, filepath, &new_cache(), &DEFAULT_ORDER, None)
+}
+
+/// Results by the start of the file they were found from, for files whose
+/// detection didn't read any further. Files in a batch often start the same
+/// way, such as thousands of JPEGs from one camera, and those can then skip
+/// the rules.
+#[cfg(feature="fs")]
+type PrefixMemo = RwLock<FnvHashMap<Vec<u8>, Option<MIME>>>;
+
+/// Most file starts a `PrefixMemo` keeps
+#[cfg(feature="parallel")]
+const PREFIX_MEMO_LEN: usize = 512;
+
+/// Internal function. Does the work of `from_filepath_node`, with a cache
+/// that the caller can keep reading the file through, and a memo of results
+/// shared with other files if there is one.
+#[cfg(feature="fs")]
+fn from_filepath_node_cached(parentnode: NodeIndex, filepath: &Path, cache: &CacheContainer, order: &TypeOrder, memo: Option<&PrefixMemo>) -> Option<MIME>
 {
     // We're actually just going to thunk this down to a u8
     // unless we're checking via basetype for speed reasons.
     
-    let mut cache = Vec::<CacheItem>::with_capacity(CHECKERCOUNT + 1);
-    for _ in 0..(CHECKERCOUNT + 1) {
-        cache.push(CacheItem::default());
-    }
-    
     // Ensure it's at least a application/octet-stream
-    if !match_filepath("application/octet-stream", filepath){
+    if !match_filepath_noalias("application/octet-stream", filepath, cache){
         // Check the other base types
-        return typegraph_walker(parentnode, filepath, &cache, match_filepath_noalias);
+        return typegraph_walker(parentnode, order, &mut |x| {
+            match_filepath_noalias(x, filepath, cache).then(|| type_priority(x))
+        });
     }
     
-    // Load the first 2K of file and parse as u8
-    // for batch processing like this
-    //
-    // TODO: Use cache to only get what we need to when we need to
-    // and then change code so that we keep calling this function
-    // when walking tree.
-    /*let f = match File::open(filepath) {
-        Ok(x) => x,
-        Err(_) => return None // How?
-    };
-    let r = BufReader::new(f);
-    let mut b = Vec::<u8>::new();
-    match r.take(2048).read_to_end(&mut b) {
-        Ok(_) => {},
-        Err(_) => return None // Also how?
-    }*/
-    
+    // With mmap, check the start of the mapping rather than a copy of the
+    // file's start. It's cut off where a read would be, as the text, mail
+    // and JSON checks would go over all of it otherwise, and no magic rule
+    // looks further. Checks at the end of the file find the rest of the
+    // mapping in the file cache.
+    #[cfg(feature="mmap")]
+    {
+        if let Ok((map, len)) = map_cached(filepath, &cache[0]) {
+            let head = &map[..std::cmp::min(len, max_scan_extent())];
+            let found = from_u8_node_cached(parentnode, head, cache, order);
+            return bom_subtype(found, head, order);
+        }
+    }
 
-    let ref filecache = cache[0];
-    let b = match slurp_to_cache(filepath, filecache, 2048){
+    // Load as much of the file as any rule looks at and parse as u8
+    // for batch processing like this. The file stays open in the cache,
+    // so anything that needs more reads on from there.
+    let filecache = &cache[0];
+    let b = match slurp_to_cache(filepath, filecache, max_scan_extent()){
         Ok(x) => x,
         Err(_) => return None
     };
+    if let Some(x) = memo.and_then(|x| x.read().get(&b).cloned()) {
+        return x;
+    }
     
-    from_u8_node(parentnode, b.as_slice())
+    let found = from_u8_node_cached(parentnode, b.as_slice(), cache, order);
+    let found = bom_subtype(found, b.as_slice(), order);
+
+    // Another file with the same start would take the same path through the
+    // rules, so the result holds for it too, unless some of them read on
+    // into this file
+    #[cfg(feature="parallel")]
+    {
+        if let Some(memo) = memo {
+            let mut memo = memo.write();
+            // A result cut short by a work limit only holds for this file
+            if memo.len() < PREFIX_MEMO_LEN && !read_past_head(filecache) && !limit::exhausted() {
+                memo.insert(b, found.clone());
+            }
+        }
+    }
+    found
 }
 
 /// Gets the type of a file from a filepath.
@@ -618,6 +2327,9 @@ pub fn from_filepath_node(parentnode: NodeIndex, filepath: &Path) -> Option<MIME
 /// Returns MIME as string wrapped in Some if a type matches, or
 /// None if the file is not found or cannot be opened.
 ///
+/// Reads the first `max_scan_extent()` bytes of the file, or with the `mmap`
+/// feature, maps it and checks that much of the mapping.
+///
 /// # Examples
 /// ```rust
 /// use std::path::Path;
@@ -627,93 +2339,1350 @@ pub fn from_filepath_node(parentnode: NodeIndex, filepath: &Path) -> Option<MIME
 ///
 /// // Find the MIME type of the GIF
 /// let result = tree_magic::from_filepath(path);
-/// assert_eq!(result, Some("image/gif"));
+/// assert_eq!(result, Some("image/gif".into()));
 /// ```
-pub fn from_filepath(filepath: &Path) -> Option<MIME> {
+#[cfg(feature="fs")]
+pub fn from_filepath(filepath: &Path) -> Option<Mime> {
 
-    let node = match TYPE.graph.externals(Incoming).next() {
+    let node = match TYPES.graph.externals(Incoming).next() {
         Some(foundnode) => foundnode,
         None => panic!("No filetype definitions are loaded.")
     };
     
-    from_filepath_node(node, filepath)
+    from_filepath_node_cached(node, filepath, &new_cache(), &DEFAULT_ORDER, None)
 }
 
-/// Determines if a MIME is an alias of another MIME
+lazy_static! {
+    /// Steps down from the root of each type, along the longest way, by
+    /// node index
+    static ref DEPTHS: Vec<usize> = {
+        let mut out = vec![0; TYPES.graph.node_count()];
+        if let Ok(sorted) = petgraph::algo::toposort(&TYPES.graph, None) {
+            for x in sorted {
+                out[x.index()] = TYPES.graph.neighbors_directed(x, Incoming)
+                    .map(|y| out[y.index()] + 1)
+                    .max()
+                    .unwrap_or(0);
+            }
+        }
+        out
+    };
+}
+
+/// A detected type, with how specific it is
 ///
-/// If this returns true, that means the two MIME types are equivalent.
-/// If this returns false, either one of the MIME types are missing, or they are different.
-/// If you're using the `staticmime` feature flag, input is a &'static str.
-/// Otherwise it is a String.
+/// A generic fallback like `application/octet-stream` or `text/plain` is
+/// shallow and has subtypes, where a confident identification is usually
+/// deep, and often a leaf.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Detection {
+    pub mimetype: MIME,
+    /// Steps down from the root of the type graph, `all/all`, along the
+    /// longest way. `application/octet-stream` is at 2.
+    pub depth: usize,
+    /// Whether the type has no subtypes
+    pub leaf: bool
+}
+
+impl Detection {
+    /// Looks up where a type sits in the type graph. Unknown types are at
+    /// depth 0, and are leaves.
+    pub fn new(mimetype: Mime) -> Detection {
+        let (depth, leaf) = match TYPES.hash.get(get_alias(&mimetype)) {
+            Some(&x) => (DEPTHS[x.index()], TYPES.graph.neighbors_directed(x, Outgoing).next().is_none()),
+            None => (0, true)
+        };
+        Detection{mimetype, depth, leaf}
+    }
+}
+
+/// Gets the type of a byte stream, as `from_u8` does, with its depth in the
+/// type graph and whether it's a leaf.
 ///
 /// # Examples
-/// ```
-/// let mime1 = "application/zip".to_string();
-/// let mime2 = "application/x-zip-compressed".to_string();
+/// ```rust
+/// let result = tree_magic::from_u8_detailed(include_bytes!("../tests/image/gif"));
+/// assert_eq!(result.mimetype, "image/gif");
+/// assert!(result.leaf);
 ///
-/// assert_eq!( tree_magic::is_alias(mime1, mime2), true );
-pub fn is_alias(mime1: MIME, mime2: MIME) -> bool {
-    let x = get_alias(&mime1);
-    let y = get_alias(&mime2);
-    
-    #[cfg(feature="staticmime")]
-    return x == mime2 || y == mime1;
-    #[cfg(not(feature="staticmime"))]
-    return *x == mime2 || *y == mime1;
+/// // Nothing more specific found
+/// let result = tree_magic::from_u8_detailed(&[0xff, 0x00, 0x13]);
+/// assert_eq!(result.mimetype, "application/octet-stream");
+/// assert_eq!(result.depth, 2);
+/// ```
+pub fn from_u8_detailed(bytes: &[u8]) -> Detection {
+    Detection::new(from_u8(bytes))
 }
 
-/// Slurps the given number of bytes from a file to a FileCache
-/// and returns those bytes as a Vec<u8> for convienence.
-fn slurp_to_cache(filepath: &Path, filecache: &CacheItem, bytecount: usize) -> Result<Vec<u8>, std::io::Error> {
-    use std::io::prelude::*;
-    use std::fs::File;
-    use std::ops::Deref;
+/// Gets the type of a file, as `from_filepath` does, with its depth in the
+/// type graph and whether it's a leaf.
+#[cfg(feature="fs")]
+pub fn from_filepath_detailed(filepath: &Path) -> Option<Detection> {
+    from_filepath(filepath).map(Detection::new)
+}
 
-    let ref lock = *filecache.read();
-    let x = lock.deref();
-    let mut b = Vec::<u8>::with_capacity(bytecount);
-    let mut should_copy: bool = false;
+/// A magic rule that fired, and where, from `DetectionReport`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RuleMatch {
+    /// The rule that accepted the input: the last one in its chain, with no
+    /// children of its own
+    pub rule: MagicRule,
+    /// Where in the input the rule's value was found
+    pub offset: usize,
+    /// Length of the value
+    pub len: usize
+}
 
-    match *x {
-        None => {
-            // Slurp up bytes
-            let f = File::open(filepath)?;
-            f.take(bytecount as u64).read_to_end(&mut b)?;
-            should_copy = true;
-        },
-        Some (ref y) => match *y {
-            Cache::FileCache(ref a) => {
-                if a.len() < bytecount {
-                    let count = bytecount - a.len();
-                    
-                    let mut f = File::open(filepath)?;
-                    f.seek(std::io::SeekFrom::Start(a.len() as u64))?;
-                    f.take(count as u64).read_to_end(&mut b)?;
-                    should_copy = true;
-                }
-            },
-            _ => panic!("Invalid type for FileCache!")
+/// Why a byte stream was given the type it was, from `from_u8_report`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature="serde", derive(serde::Serialize))]
+pub struct DetectionReport {
+    pub mimetype: MIME,
+    /// Checker that matched the type, as in `TypeInfo`
+    pub checker: &'static str,
+    /// Priority the type matched at. Only magic has priorities, so types
+    /// other checkers find count at their highest.
+    pub priority: u32,
+    /// The magic rule that matched, if the type was found by magic. For
+    /// text with a byte order mark, the offset is into the decoded text.
+    pub rule: Option<RuleMatch>,
+    /// Types the walk went down through, from the root of the type graph to
+    /// `mimetype`
+    pub path: Vec<MIME>
+}
+
+/// Checkers are read by name, and only the ones in this build are accepted
+#[cfg(feature="serde")]
+impl<'de> serde::Deserialize<'de> for DetectionReport {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<DetectionReport, D::Error> {
+        #[derive(serde::Deserialize)]
+        struct Fields {
+            mimetype: MIME,
+            checker: String,
+            priority: u32,
+            rule: Option<RuleMatch>,
+            path: Vec<MIME>
         }
+        let x = Fields::deserialize(d)?;
+        let checker = checker_name(&x.checker)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown checker {}", x.checker)))?;
+        Ok(DetectionReport {mimetype: x.mimetype, checker, priority: x.priority, rule: x.rule, path: x.path})
     }
+}
 
-    drop(x);
-    drop(lock);
-    //assert_eq!(0, std::sync::Arc::strong_count(&filecache));
+/// The magic rule of a type that fired on the input, if the type has any
+#[allow(unused_variables)]
+fn fired_rule(mimetype: &str, bytes: &[u8]) -> Option<RuleMatch> {
+    #[cfg(all(feature="checker-fdo-magic", feature="fs"))]
+    let fired = fdo_magic::sys::fired(mimetype, bytes);
+    #[cfg(all(feature="checker-fdo-magic", not(feature="fs")))]
+    let fired = fdo_magic::builtin::fired(mimetype, bytes);
+    #[cfg(feature="checker-fdo-magic")]
+    return fired.map(|(rule, offset)| RuleMatch {len: rule.val.len(), rule: rule.into(), offset});
+    #[cfg(not(feature="checker-fdo-magic"))]
+    return None;
+}
 
-    // THIS SECTION DEADLOCKS ALWAYS, because there is a rougue handle somewhere
-    // that write() is forever stuck waiting for.
-    if should_copy {
-        let ref mut out = filecache.write();
-        match out.clone().unwrap() {
-            Cache::FileCache(ref mut a) => {a.append(&mut b);},
-            _ => {panic!("Could not read file?")}
+/// Gets the type of a byte stream, as `from_u8` does, along with how it was
+/// found: the checker and magic rule that matched, and the types the walk
+/// went through. Meant for forensics and debugging; it's slower than
+/// `from_u8`.
+///
+/// # Examples
+/// ```rust
+/// let report = tree_magic::from_u8_report(include_bytes!("../tests/image/png"));
+/// assert_eq!(report.mimetype, "image/png");
+/// assert_eq!(report.checker, "fdo-magic");
+/// assert_eq!(report.path, ["all/all", "all/allfiles", "application/octet-stream", "image/png"]);
+///
+/// let rule = report.rule.unwrap();
+/// assert_eq!((rule.offset, rule.len), (0, 4));
+/// ```
+pub fn from_u8_report(bytes: &[u8]) -> DetectionReport {
+    from_u8_traced(bytes, |_| {})
+}
+
+/// One step of the walk down the type graph, from `from_u8_traced`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TraceEvent {
+    /// A child of `parent` was checked. `priority` is what it matched at,
+    /// or None if it didn't match.
+    Tried {
+        parent: MIME,
+        mimetype: MIME,
+        priority: Option<u32>
+    },
+    /// The walk went down into the best match among the children of the
+    /// last type chosen
+    Chose {
+        mimetype: MIME,
+        priority: u32
+    },
+    /// The walk stopped at `text/plain` on text with a byte order mark, and
+    /// carries on with the decoded text
+    Decoded
+}
+
+/// Gets the type of a byte stream as `from_u8_report` does, calling `trace`
+/// for every type checked, in order, and every step down the graph. This
+/// answers questions like why a file came back as `text/plain` rather than
+/// `text/x-matlab`: either the type was tried and didn't match, a sibling
+/// won on priority, or the walk never got to its parent.
+///
+/// Children are tried most likely first, and once one matches, only those
+/// that could match at a higher priority are, so not every child of a type
+/// shows up.
+///
+/// # Examples
+/// ```rust
+/// use tree_magic::TraceEvent;
+///
+/// let mut tried = Vec::new();
+/// tree_magic::from_u8_traced(b"Hello, world!\n", |x| {
+///     if let TraceEvent::Tried { mimetype, priority: None, .. } = x {
+///         tried.push(mimetype.clone());
+///     }
+/// });
+/// assert!(tried.iter().any(|x| x == "image/png"));
+/// ```
+pub fn from_u8_traced<F: FnMut(&TraceEvent)>(bytes: &[u8], mut trace: F) -> DetectionReport {
+    let root = match TYPES.graph.externals(Incoming).next() {
+        Some(x) => x,
+        None => panic!("No filetype definitions are loaded.")
+    };
+    let mut path = vec![(root, type_priority(&TYPES.graph[root]))];
+    // Goes down from the last type in the path as far as the input matches
+    let walk = |path: &mut Vec<(NodeIndex, u32)>, input: &[u8], trace: &mut F| with_scratch(|x| {
+        let mut node = path[path.len() - 1].0;
+        while let Some(child) = best_child(node, &DEFAULT_ORDER, &mut |y| {
+            let priority = match_u8_noalias(y, input, &x.cache).then(|| matched_priority(y, input));
+            trace(&TraceEvent::Tried {
+                parent: TYPES.graph[node].clone(),
+                mimetype: MIME::from(y.to_string()),
+                priority
+            });
+            priority
+        }) {
+            trace(&TraceEvent::Chose {mimetype: TYPES.graph[child.0].clone(), priority: child.1});
+            path.push(child);
+            node = child.0;
+        }
+    });
+    walk(&mut path, bytes, &mut trace);
+    // As in `bom_subtype`
+    let mut input = std::borrow::Cow::Borrowed(bytes);
+    if TYPES.graph[path[path.len() - 1].0] == "text/plain" {
+        if let Some(decoded) = basetype::check::decode_bom(bytes) {
+            trace(&TraceEvent::Decoded);
+            walk(&mut path, decoded.as_bytes(), &mut trace);
+            input = std::borrow::Cow::Owned(decoded.into_bytes());
         }
     }
-    
-    let ref lock = *filecache.read();
-    let x = lock.deref().clone();
-    match x.unwrap() {
-        Cache::FileCache(a) => Ok(a),
-        _ => {panic!("Could not read file?")}
+
+    let (node, priority) = path[path.len() - 1];
+    let mimetype = TYPES.graph[node].clone();
+    DetectionReport {
+        checker: CHECKER_SUPPORT.get(&mimetype).map_or("", |&x| CHECKERS[x].name),
+        priority,
+        rule: fired_rule(&mimetype, &input),
+        path: path.into_iter().map(|x| TYPES.graph[x.0].clone()).collect(),
+        mimetype
+    }
+}
+
+/// Options for detecting the type of a file
+///
+/// # Examples
+/// ```rust
+/// let options = tree_magic::DetectOptions {
+///     max_read_bytes: Some(512),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DetectOptions {
+    /// Read at most this many bytes of the file, from the start. Types told
+    /// apart by anything further in, such as rules at deep offsets or the
+    /// end of a Parquet file, may then be missed. No limit if None.
+    pub max_read_bytes: Option<usize>,
+    /// Types to try before their siblings, and which to leave out
+    pub type_order: TypeOrder,
+    /// Read only parts of the file, for bounded IO on very large files.
+    /// Off if None.
+    pub sampling: Option<Sampling>,
+    /// Most work detection may do, for inputs from untrusted sources. No
+    /// limit if None.
+    ///
+    /// Requires the `std` feature.
+    #[cfg(feature="std")]
+    pub work_limit: Option<WorkLimit>
+}
+
+/// Which parts of a file to read, for `DetectOptions::sampling`.
+///
+/// The first and last `window` bytes are read, plus `samples` windows
+/// evenly spaced in between. Signatures at either end of the file are found
+/// as usual, and a file is only taken to be text if none of the samples has
+/// a null byte. Anything else past the first window, such as magic at a deep
+/// offset, is missed.
+///
+/// # Examples
+/// ```rust
+/// use tree_magic::{DetectOptions, Sampling};
+///
+/// let options = DetectOptions {
+///     sampling: Some(Sampling { window: 64 * 1024, samples: 4 }),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sampling {
+    /// Bytes read at the start, at the end, and at each sample
+    pub window: usize,
+    /// Windows read from between the start and end
+    pub samples: usize
+}
+
+/// Most work a detection may do, for `DetectOptions::work_limit`.
+///
+/// Each type checked counts as a rule, and so does each magic rule tested,
+/// along with the bytes of the input it compares: the value's length for a
+/// rule at a fixed offset, and the whole window for one that searches a
+/// range. The JSON and mail checkers parse up to the first 8 KiB of the
+/// input once per detection, and count what they parse; the others look at
+/// a bounded part of the input, and count no bytes. Once either budget runs
+/// out, nothing more matches, and the type found so far is the result.
+///
+/// Requires the `std` feature.
+///
+/// # Examples
+/// ```rust
+/// use tree_magic::{DetectOptions, WorkLimit};
+///
+/// let options = DetectOptions {
+///     work_limit: Some(WorkLimit { max_bytes: 1 << 20, max_rules: 10_000 }),
+///     ..Default::default()
+/// };
+/// ```
+#[cfg(feature="std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkLimit {
+    /// Bytes of the input magic rules may compare
+    pub max_bytes: usize,
+    /// Rules and types that may be checked
+    pub max_rules: usize
+}
+
+/// Detection ran out of its `WorkLimit`, from `try_from_u8_with`
+///
+/// Requires the `std` feature.
+#[cfg(feature="std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitExceeded {
+    /// The type found before the limit ran out. Something that far down the
+    /// type graph, but maybe not the type of the input.
+    pub found: Mime
+}
+
+#[cfg(feature="std")]
+impl std::fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "work limit exceeded while detecting type, after finding {}", self.found)
+    }
+}
+
+#[cfg(feature="std")]
+impl std::error::Error for LimitExceeded {}
+
+/// Gets the type of a file from a byte stream, with the given options.
+///
+/// Works like `from_u8`, which is the same as this with the default options.
+/// `max_read_bytes` and `sampling` have no effect, as the bytes are already
+/// read. If detection runs out of its `work_limit`, gives the type found
+/// before it did; use `try_from_u8_with` to tell when that happens.
+///
+/// # Examples
+/// ```rust
+/// use tree_magic::{DetectOptions, TypeOrder};
+///
+/// let options = DetectOptions {
+///     type_order: TypeOrder::new(&["application/pdf", "application/zip"]),
+///     ..Default::default()
+/// };
+/// let result = tree_magic::from_u8_with(include_bytes!("../tests/image/gif"), &options);
+/// assert_eq!(result, "image/gif");
+/// ```
+pub fn from_u8_with(bytes: &[u8], options: &DetectOptions) -> Mime {
+    #[cfg(feature="std")]
+    if let Some(limit) = options.work_limit {
+        return limit::metered(limit, || from_u8_unlimited(bytes, options)).0;
+    }
+    from_u8_unlimited(bytes, options)
+}
+
+/// Gets the type of a file from a byte stream, with the given options,
+/// failing if detection runs out of its `work_limit`.
+///
+/// Use this to sniff input from untrusted sources, such as uploads to a
+/// server, so input crafted to make detection slow can't tie up the CPU.
+/// Without a work limit, this always succeeds, with what `from_u8_with`
+/// gives.
+///
+/// Requires the `std` feature.
+///
+/// # Examples
+/// ```rust
+/// use tree_magic::{DetectOptions, WorkLimit};
+///
+/// let input: &[u8] = include_bytes!("../tests/image/gif");
+///
+/// let options = DetectOptions {
+///     work_limit: Some(WorkLimit { max_bytes: 1 << 20, max_rules: 10_000 }),
+///     ..Default::default()
+/// };
+/// assert_eq!(tree_magic::try_from_u8_with(input, &options), Ok("image/gif".into()));
+///
+/// let options = DetectOptions {
+///     work_limit: Some(WorkLimit { max_bytes: 1 << 20, max_rules: 1 }),
+///     ..Default::default()
+/// };
+/// assert!(tree_magic::try_from_u8_with(input, &options).is_err());
+/// ```
+#[cfg(feature="std")]
+pub fn try_from_u8_with(bytes: &[u8], options: &DetectOptions) -> Result<Mime, LimitExceeded> {
+    let limit = match options.work_limit {
+        Some(x) => x,
+        None => return Ok(from_u8_unlimited(bytes, options))
+    };
+    match limit::metered(limit, || from_u8_unlimited(bytes, options)) {
+        (found, false) => Ok(found),
+        (found, true) => Err(LimitExceeded{found})
+    }
+}
+
+/// Internal function. Does the work of `from_u8_with`, leaving any work
+/// limit to the caller.
+fn from_u8_unlimited(bytes: &[u8], options: &DetectOptions) -> MIME {
+    let node = match TYPES.graph.externals(Incoming).next() {
+        Some(foundnode) => foundnode,
+        None => panic!("No filetype definitions are loaded.")
+    };
+    // A restricted walk can rule out everything under the root
+    from_u8_node_ordered(node, bytes, &options.type_order).unwrap_or_else(|| TYPES.graph[node].clone())
+}
+
+/// Gets the type of a file from a filepath, with the given options.
+///
+/// Works like `from_filepath`, which is the same as this with the default
+/// options. If detection runs out of its `work_limit`, gives the type found
+/// before it did.
+///
+/// # Examples
+/// ```rust
+/// use std::path::Path;
+/// use tree_magic::DetectOptions;
+///
+/// // Cap the IO spent on each file
+/// let options = DetectOptions { max_read_bytes: Some(512), ..Default::default() };
+/// let result = tree_magic::from_filepath_with(Path::new("tests/image/gif"), &options);
+/// assert_eq!(result, Some("image/gif".into()));
+/// ```
+#[cfg(feature="fs")]
+pub fn from_filepath_with(filepath: &Path, options: &DetectOptions) -> Option<Mime> {
+    from_filepath_memo(filepath, options, None)
+}
+
+/// Internal function. Does the work of `from_filepath_with`, with a memo of
+/// results shared with other files if there is one.
+#[cfg(feature="fs")]
+fn from_filepath_memo(filepath: &Path, options: &DetectOptions, memo: Option<&PrefixMemo>) -> Option<MIME> {
+    let node = TYPES.graph.externals(Incoming).next()?;
+    let cache = new_cache();
+    if options.max_read_bytes.is_some() || options.sampling.is_some() {
+        // Sockets, and devices with nothing behind them, can't be opened,
+        // but still have a type
+        let mut lock = cache[0].write();
+        if let Ok(open) = open_to_cache(filepath, &mut lock) {
+            if let Some(x) = options.max_read_bytes {
+                open.limit = std::cmp::min(open.limit, x as u64);
+            }
+            open.sampling = options.sampling;
+        }
+    }
+    if let Some(limit) = options.work_limit {
+        return limit::metered(limit, || from_filepath_node_cached(node, filepath, &cache, &options.type_order, memo)).0;
+    }
+    from_filepath_node_cached(node, filepath, &cache, &options.type_order, memo)
+}
+
+/// Part of a block device or disk image to read, for `from_device`
+///
+/// # Examples
+/// ```rust
+/// use tree_magic::DeviceWindow;
+///
+/// // A partition starting 1 MiB into the disk
+/// let window = DeviceWindow { offset: 1 << 20, ..Default::default() };
+/// ```
+#[cfg(feature="fs")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceWindow {
+    /// Where to start reading, such as the start of a partition
+    pub offset: u64,
+    /// Most bytes to read. Anything over 64 KiB counts as 64 KiB.
+    pub len: usize
+}
+
+#[cfg(feature="fs")]
+impl Default for DeviceWindow {
+    fn default() -> DeviceWindow {
+        DeviceWindow{offset: 0, len: DEVICE_READ_LEN as usize}
+    }
+}
+
+/// Gets the type of what's on a block device, such as `/dev/sda`, or in a
+/// disk image, from a window of it.
+///
+/// `from_filepath` gives `inode/blockdevice` for a device, without reading
+/// it. This reads the window instead, at most 64 KiB, and gives the type of
+/// what's there as `from_u8` would, so a window at the start of a
+/// partition finds its filesystem. Returns None if the path can't be opened
+/// or read, as when reading a device needs privileges. FIFOs, sockets and
+/// directories aren't read, and get their `inode/*` type.
+///
+/// Partition tables and filesystems are found by the `checker-filesystem`
+/// feature.
+///
+/// # Examples
+/// ```rust,no_run
+/// use std::path::Path;
+/// use tree_magic::DeviceWindow;
+///
+/// let disk = tree_magic::from_device(Path::new("/dev/sda"), DeviceWindow::default());
+/// assert_eq!(disk, Some("application/x-gpt-partition-table".into()));
+///
+/// // The first partition, at the usual 1 MiB
+/// let window = DeviceWindow { offset: 1 << 20, ..Default::default() };
+/// let partition = tree_magic::from_device(Path::new("/dev/sda"), window);
+/// assert_eq!(partition, Some("application/x-ext4-filesystem".into()));
+/// ```
+#[cfg(feature="fs")]
+pub fn from_device(path: &Path, window: DeviceWindow) -> Option<Mime> {
+    let meta = std::fs::metadata(path).ok()?;
+    if meta.is_dir() {
+        return Some(MIME::from("inode/directory"));
+    }
+    match basetype::special_type(&meta) {
+        Some(x @ "inode/fifo") | Some(x @ "inode/socket") => return Some(MIME::from(x)),
+        _ => {}
+    }
+
+    // A FileCache holds no more of a device than this, either
+    let len = std::cmp::min(window.len as u64, DEVICE_READ_LEN) as usize;
+    let file = open_nonblocking(path).ok()?;
+    match file.metadata().ok().as_ref().and_then(basetype::special_type) {
+        Some("inode/fifo") | Some("inode/socket") => None,
+        _ => Some(from_u8(&read_window(&file, window.offset, len).ok()?))
+    }
+}
+
+/// Most files `from_filepaths` has open at once
+#[cfg(feature="parallel")]
+const MAX_OPEN_FILES: usize = 64;
+
+/// Gets the MIME types of many files at once, on a thread pool.
+///
+/// Results are in the order the paths were given, each with its path. A
+/// file that can't be read gets None, as with `from_filepath`. Each thread
+/// has one file open at a time, and there are at most `MAX_OPEN_FILES`
+/// threads, so a long list won't run the process out of file descriptors.
+///
+/// Requires the `parallel` feature.
+///
+/// # Examples
+/// ```rust
+/// use std::path::PathBuf;
+///
+/// let paths = vec![PathBuf::from("tests/image/gif"), PathBuf::from("tests/image/png")];
+/// let result = tree_magic::from_filepaths(paths);
+/// assert_eq!(result[0].1, Some("image/gif".into()));
+/// assert_eq!(result[1].1, Some("image/png".into()));
+/// ```
+#[cfg(feature="parallel")]
+pub fn from_filepaths<I: IntoIterator<Item=PathBuf>>(paths: I) -> Vec<(PathBuf, Option<Mime>)> {
+    from_filepaths_with(paths, &DetectOptions::default())
+}
+
+/// Gets the MIME types of many files at once, on a thread pool, with
+/// options. See `from_filepaths` and `from_filepath_with`.
+///
+/// Requires the `parallel` feature.
+#[cfg(feature="parallel")]
+pub fn from_filepaths_with<I: IntoIterator<Item=PathBuf>>(paths: I, options: &DetectOptions) -> Vec<(PathBuf, Option<Mime>)> {
+    use rayon::prelude::*;
+
+    let paths: Vec<PathBuf> = paths.into_iter().collect();
+    let memo = PrefixMemo::default();
+    let detect = |x: PathBuf| {
+        let mimetype = from_filepath_memo(&x, options, Some(&memo));
+        (x, mimetype)
+    };
+
+    // A pool of our own, so the cap holds whatever the global pool's size
+    let threads = std::cmp::min(rayon::current_num_threads(), MAX_OPEN_FILES);
+    match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+        Ok(pool) => pool.install(|| paths.into_par_iter().map(detect).collect()),
+        Err(_) => paths.into_iter().map(detect).collect()
+    }
+}
+
+/// Checks if a type is the parent type or one of its descendants in the
+/// type graph, resolving aliases on both. A type is a subtype of itself.
+///
+/// Returns false if either type isn't known.
+///
+/// # Examples
+/// ```rust
+/// assert!(tree_magic::is_subtype_of("application/json", "text/plain"));
+/// assert!(tree_magic::is_subtype_of("text/plain", "text/plain"));
+/// assert!(!tree_magic::is_subtype_of("text/plain", "application/json"));
+/// ```
+pub fn is_subtype_of(mimetype: &str, parent: &str) -> bool {
+    let resolve = |x: &str| -> Option<NodeIndex> {
+        let x = ALIASES.get(x).map(|y| &**y).unwrap_or(x);
+        TYPES.hash.get(x).cloned()
+    };
+    match (resolve(mimetype), resolve(parent)) {
+        (Some(node), Some(parentnode)) => {
+            petgraph::algo::has_path_connecting(&TYPES.graph, parentnode, node, None)
+        },
+        _ => false
+    }
+}
+
+/// Gets the ancestors of a type, nearest first, up to the root of the type
+/// graph (`all/all`). Aliases are resolved first.
+///
+/// Types with more than one parent have all of them, each once, ordered by
+/// how many steps up they are, then by name. Unknown types have none.
+///
+/// # Examples
+/// ```rust
+/// let result: Vec<_> = tree_magic::ancestors("application/json").collect();
+/// assert_eq!(result, [
+///     "application/javascript",
+///     "text/plain",
+///     "application/octet-stream",
+///     "all/allfiles",
+///     "all/all"
+/// ]);
+/// ```
+pub fn ancestors(mimetype: &str) -> impl Iterator<Item=Mime> {
+    by_distance(mimetype, Incoming).into_iter()
+}
+
+/// Gets every subtype of a type, nearest first, such as every text format
+/// for `text/plain`. Aliases are resolved first.
+///
+/// Types under more than one of them come once, ordered by how many steps
+/// down they are, then by name. Unknown types have none.
+///
+/// # Examples
+/// ```rust
+/// let text: Vec<_> = tree_magic::descendants("text/plain").collect();
+/// assert!(text.contains(&"application/json".into()));
+/// assert!(!text.contains(&"image/png".into()));
+/// ```
+pub fn descendants(mimetype: &str) -> impl Iterator<Item=Mime> {
+    by_distance(mimetype, Outgoing).into_iter()
+}
+
+/// Gets the most specific type that two types are both under, such as
+/// `application/zip` for two Office documents. A type counts as under
+/// itself, so if one is under the other, that's the one given.
+///
+/// Of shared ancestors, one that has another below it is never given. If
+/// that still leaves more than one, the nearest to `a` is given. Unknown
+/// types only share the root of the type graph, `all/all`.
+///
+/// # Examples
+/// ```rust
+/// let result = tree_magic::common_ancestor("application/json", "text/x-python");
+/// assert_eq!(result, "text/plain");
+///
+/// let result = tree_magic::common_ancestor("application/x-zip-compressed", "application/epub+zip");
+/// assert_eq!(result, "application/zip");
+/// ```
+pub fn common_ancestor(a: &str, b: &str) -> Mime {
+    let with_self = |x: &str| -> Vec<MIME> {
+        match TYPES.hash.get(get_alias(x)) {
+            Some(&node) => std::iter::once(TYPES.graph[node].clone()).chain(ancestors(x)).collect(),
+            None => Vec::new()
+        }
+    };
+    let b = with_self(b);
+    let shared: Vec<MIME> = with_self(a).into_iter().filter(|x| b.contains(x)).collect();
+    shared.iter()
+        .find(|&x| !shared.iter().any(|y| y != x && is_subtype_of(y, x)))
+        .cloned()
+        .or_else(|| TYPES.graph.externals(Incoming).next().map(|x| TYPES.graph[x].clone()))
+        .unwrap_or_else(|| MIME::from("all/all"))
+}
+
+/// Walks the type graph from a type in one direction, and gives the types
+/// found, nearest first and then by name, leaving out the type itself
+fn by_distance(mimetype: &str, direction: Direction) -> Vec<MIME> {
+    let mut out = Vec::<MIME>::new();
+    let mut seen = FnvHashSet::<NodeIndex>::default();
+    let mut level: Vec<NodeIndex> = TYPES.hash.get(get_alias(mimetype)).cloned().into_iter().collect();
+    seen.extend(level.iter().cloned());
+    while !level.is_empty() {
+        let mut next: Vec<NodeIndex> = level.iter()
+            .flat_map(|&x| TYPES.graph.neighbors_directed(x, direction))
+            .filter(|&x| seen.insert(x))
+            .collect();
+        next.sort_by(|&a, &b| TYPES.graph[a].cmp(&TYPES.graph[b]));
+        out.extend(next.iter().map(|&x| TYPES.graph[x].clone()));
+        level = next;
+    }
+    out
+}
+
+/// Renders the type graph in Graphviz DOT, with an edge from each type to
+/// each of its subtypes, for documentation and debugging. Types and edges
+/// are sorted by name, so the output only changes when the graph does.
+///
+/// If a type is given, only it and its subtypes are drawn; a type that isn't
+/// loaded gives an empty graph. Aliases count as the type they stand for.
+///
+/// # Examples
+/// ```rust
+/// let dot = tree_magic::export_dot(Some("application/zip"));
+/// assert!(dot.starts_with("digraph"));
+/// assert!(dot.contains("\"application/zip\" -> \"application/epub+zip\""));
+/// ```
+pub fn export_dot(subtree: Option<&str>) -> String {
+    let shown = match subtree {
+        Some(x) => subtree_nodes(TYPES.hash.get(get_alias(x)).cloned()),
+        None => vec![true; TYPES.graph.node_count()]
+    };
+    let quote = |x: NodeIndex| format!("\"{}\"", TYPES.graph[x].replace('\\', "\\\\").replace('"', "\\\""));
+
+    let mut nodes: Vec<NodeIndex> = TYPES.graph.node_indices().filter(|x| shown[x.index()]).collect();
+    nodes.sort_by(|&a, &b| TYPES.graph[a].cmp(&TYPES.graph[b]));
+    let mut edges: Vec<(NodeIndex, NodeIndex)> = TYPES.graph.edge_indices()
+        .filter_map(|x| TYPES.graph.edge_endpoints(x))
+        .filter(|x| shown[x.0.index()] && shown[x.1.index()])
+        .collect();
+    edges.sort_by(|a, b| (&TYPES.graph[a.0], &TYPES.graph[a.1]).cmp(&(&TYPES.graph[b.0], &TYPES.graph[b.1])));
+
+    let mut out = String::from("digraph {\n");
+    for x in nodes {
+        out.push_str(&format!("    {};\n", quote(x)));
+    }
+    for (a, b) in edges {
+        out.push_str(&format!("    {} -> {};\n", quote(a), quote(b)));
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Writes out everything the matcher works from as JSON, so tooling outside
+/// Rust can use the same data: the types in the graph, aliases, subclass
+/// edges, magic rules and file name patterns.
+///
+/// The result is an object with these members, sorted so the output only
+/// changes when the database does:
+/// - `types`: every type in the graph
+/// - `aliases`: the type each alias stands for
+/// - `subclasses`: `[parent, child]` pairs, including the ones added to
+///   hang otherwise parentless types under `application/octet-stream`,
+///   `text/plain` or `all/all`
+/// - `magic`: the rules for each type with any, as `rules_for` gives them,
+///   with values and masks in hex
+/// - `globs`: the file name patterns of each type in the system database.
+///   Empty without the `fs` feature.
+///
+/// # Examples
+/// ```rust
+/// let json = tree_magic::export_json();
+/// assert!(json.contains("\"image/png\""));
+/// ```
+pub fn export_json() -> String {
+    export::json()
+}
+
+/// Checks if the given type is text/plain or one of its descendants
+fn is_text_type(mimetype: &str) -> bool {
+    is_subtype_of(mimetype, "text/plain")
+}
+
+/// Formats a detected type as a `Content-Type` header value, adding the
+/// character set for text types.
+fn format_content_type(mimetype: &str, bytes: &[u8]) -> String {
+    match is_text_type(mimetype) {
+        true => format!("{}; charset={}", mimetype, basetype::check::charset(bytes)),
+        false => mimetype.to_string()
+    }
+}
+
+/// Gets a `Content-Type` header value for a byte stream.
+///
+/// Text types get a `charset` parameter: the one given by a byte order mark,
+/// `utf-8` if the text is valid UTF-8, or `windows-1252` otherwise.
+///
+/// # Examples
+/// ```rust
+/// let result = tree_magic::content_type_for_u8(b"Hello, world!\n");
+/// assert_eq!(result, "text/plain; charset=utf-8");
+///
+/// let result = tree_magic::content_type_for_u8(include_bytes!("../tests/image/gif"));
+/// assert_eq!(result, "image/gif");
+/// ```
+pub fn content_type_for_u8(bytes: &[u8]) -> String {
+    let mimetype = from_u8(bytes);
+    format_content_type(&mimetype, bytes)
+}
+
+/// Gets a `Content-Type` header value for a file.
+///
+/// Works like `content_type_for_u8`, looking at the start of the file.
+/// Returns None if the file is not found or cannot be opened.
+///
+/// # Examples
+/// ```rust
+/// use std::path::Path;
+///
+/// let result = tree_magic::content_type_for_filepath(Path::new("tests/text/plain"));
+/// assert_eq!(result, Some("text/plain; charset=utf-8".to_string()));
+/// ```
+#[cfg(feature="fs")]
+pub fn content_type_for_filepath(filepath: &Path) -> Option<String> {
+    let node = TYPES.graph.externals(Incoming).next()?;
+    let cache = new_cache();
+    let mimetype = from_filepath_node_cached(node, filepath, &cache, &DEFAULT_ORDER, None)?;
+    let bytes = slurp_to_cache(filepath, &cache[0], max_scan_extent()).ok()?;
+    Some(format_content_type(&mimetype, &bytes))
+}
+
+/// Builds a base64 `data:` URI for a byte stream, using its detected type.
+///
+/// # Examples
+/// ```rust
+/// let result = tree_magic::to_data_uri(b"Hello, world!\n");
+/// assert_eq!(result, "data:text/plain;base64,SGVsbG8sIHdvcmxkIQo=");
+/// ```
+pub fn to_data_uri(bytes: &[u8]) -> String {
+    format!("data:{};base64,{}", from_u8(bytes), datauri::base64_encode(bytes))
+}
+
+/// Decodes a `data:` URI and gets the type of its payload.
+///
+/// Returns the type declared in the URI (without parameters, defaulting to
+/// `text/plain`) together with the detected type, so the two can be
+/// cross-checked. Returns None if the URI is malformed.
+///
+/// # Examples
+/// ```rust
+/// let result = tree_magic::from_data_uri("data:image/png;base64,R0lGODlhAQABAAAAACw=");
+/// assert_eq!(result, Some(("image/png".to_string(), "image/gif".into())));
+/// ```
+pub fn from_data_uri(uri: &str) -> Option<(String, Mime)> {
+    let (declared, payload) = datauri::decode(uri)?;
+    let detected = from_u8(&payload);
+    Some((declared, detected))
+}
+
+/// Checks if the payload of a `data:` URI matches the type it declares.
+///
+/// Returns false if the URI is malformed, or if the declared type is unknown.
+///
+/// # Examples
+/// ```rust
+/// assert_eq!(tree_magic::match_data_uri("data:,Hello%2C%20world"), true);
+/// assert_eq!(tree_magic::match_data_uri("data:image/png;base64,R0lGODlhAQABAAAAACw="), false);
+/// ```
+pub fn match_data_uri(uri: &str) -> bool {
+    match datauri::decode(uri) {
+        Some((declared, payload)) => match_u8(&declared, &payload),
+        None => false
+    }
+}
+
+/// Settles on a type from what the content says, and what the file name
+/// suggests. See `from_u8_and_name` for the rules.
+#[cfg(feature="mime_guess")]
+fn reconcile(content: Option<Mime>, filename: &str) -> Option<Mime> {
+    let guesses: Vec<Mime> = mime_guess::from_path(filename).iter_raw().map(Mime::from).collect();
+    let content = match content {
+        Some(x) => x,
+        None => return guesses.into_iter().next()
+    };
+    // A narrower type the content agrees with, by its name in the database
+    let narrower = guesses.iter()
+        .filter(|x| is_subtype_of(x, &content))
+        .find_map(|x| TypeId::of(x));
+    if let Some(x) = narrower {
+        return Some(Mime::from(x.name()));
+    }
+    // Content only got as far as a catch-all the guess would fall under
+    let fits = |x: &Mime| TypeId::of(x).is_none() && (
+        content == "application/octet-stream" ||
+        (content == "text/plain" && x.media_type() == "text") ||
+        x.suffix().is_some_and(|y| y == content.subtype())
+    );
+    match guesses.into_iter().find(fits) {
+        Some(x) => Some(x),
+        None => Some(content)
+    }
+}
+
+/// Gets the type of a byte stream, using its file name to narrow it down.
+///
+/// Content is checked first, and the name's extension is looked up with
+/// `mime_guess`. Then, in order:
+///
+/// 1. A guess that's the detected type or a subtype of it wins, since the
+///    content backs it up. A zip named `.epub` is an EPUB.
+/// 2. A guess the database can't detect from content wins if the detected
+///    type is a catch-all it falls under: anything under
+///    `application/octet-stream`, text types under `text/plain`, and types
+///    with a suffix under the type it names, like `+xml` under
+///    `application/xml`. Text named `.csv` is CSV.
+/// 3. Otherwise the detected type wins. Content the database knows how to
+///    detect would have been found, so a GIF named `.png` is a GIF.
+///
+/// # Examples
+/// ```rust
+/// let text = b"a,b\n1,2\n";
+/// assert_eq!(tree_magic::from_u8_and_name(text, "table.csv"), "text/csv");
+/// assert_eq!(tree_magic::from_u8_and_name(text, "image.png"), "text/plain");
+/// assert_eq!(tree_magic::from_u8_and_name(b"GIF89a", "image.png"), "image/gif");
+/// ```
+#[cfg(feature="mime_guess")]
+pub fn from_u8_and_name(bytes: &[u8], filename: &str) -> Mime {
+    let content = from_u8(bytes);
+    reconcile(Some(content.clone()), filename).unwrap_or(content)
+}
+
+/// Gets the type of a file, using its name to narrow it down.
+///
+/// Weighs the content against the extension like `from_u8_and_name`. If the
+/// file can't be read, the first guess from the extension is used, and
+/// without one this returns None.
+///
+/// # Examples
+/// ```rust
+/// use std::path::Path;
+///
+/// let result = tree_magic::from_filepath_and_name(Path::new("tests/image/gif"));
+/// assert_eq!(result, Some("image/gif".into()));
+/// ```
+#[cfg(all(feature="fs", feature="mime_guess"))]
+pub fn from_filepath_and_name(filepath: &Path) -> Option<Mime> {
+    let filename = filepath.file_name().map_or(Default::default(), |x| x.to_string_lossy());
+    reconcile(from_filepath(filepath), &filename)
+}
+
+/// Marks the given nodes and everything under them, by node index
+fn subtree_nodes<I: IntoIterator<Item=NodeIndex>>(roots: I) -> Vec<bool> {
+    let mut out = vec![false; TYPES.graph.node_count()];
+    let mut stack: Vec<NodeIndex> = roots.into_iter().collect();
+    while let Some(x) = stack.pop() {
+        if !out[x.index()] {
+            out[x.index()] = true;
+            stack.extend(TYPES.graph.neighbors_directed(x, Outgoing));
+        }
+    }
+    out
+}
+
+/// Marks the types of a media type, like `image`, and everything under
+/// them, by node index
+fn media_nodes(media: &str) -> Vec<bool> {
+    subtree_nodes(TYPES.graph.node_indices().filter(|&x| TYPES.graph[x].split('/').next() == Some(media)))
+}
+
+/// Types `is_document` takes, along with their subtypes
+const DOCUMENT_TYPES: [&str; 36] =
+[
+    "application/pdf",
+    "application/postscript",
+    "application/rtf",
+    "application/msword",
+    "application/vnd.ms-excel",
+    "application/vnd.ms-powerpoint",
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+    "application/vnd.oasis.opendocument.text",
+    "application/vnd.oasis.opendocument.text-template",
+    "application/vnd.oasis.opendocument.text-master",
+    "application/vnd.oasis.opendocument.spreadsheet",
+    "application/vnd.oasis.opendocument.spreadsheet-template",
+    "application/vnd.oasis.opendocument.presentation",
+    "application/vnd.oasis.opendocument.presentation-template",
+    "application/vnd.oasis.opendocument.graphics",
+    "application/vnd.oasis.opendocument.graphics-template",
+    "application/vnd.sun.xml.writer",
+    "application/vnd.sun.xml.calc",
+    "application/vnd.sun.xml.impress",
+    "application/vnd.sun.xml.draw",
+    "application/vnd.stardivision.writer",
+    "application/vnd.wordperfect",
+    "application/x-abiword",
+    "application/x-kword",
+    "application/vnd.apple.pages",
+    "application/vnd.apple.numbers",
+    "application/vnd.apple.keynote",
+    "application/epub+zip",
+    "application/x-mobipocket-ebook",
+    "application/x-fictionbook+xml",
+    "application/x-zip-compressed-fb2",
+    "application/x-dvi",
+    "image/vnd.djvu",
+    "application/oxps"
+];
+
+lazy_static! {
+    /// Types each of the family predicates takes, by node index
+    static ref IMAGE_NODES: Vec<bool> = media_nodes("image");
+    static ref AUDIO_NODES: Vec<bool> = media_nodes("audio");
+    static ref VIDEO_NODES: Vec<bool> = media_nodes("video");
+    static ref DOCUMENT_NODES: Vec<bool> = {
+        subtree_nodes(DOCUMENT_TYPES.iter().filter_map(|x| TYPES.hash.get(*x).cloned()))
+    };
+    /// Formats packed in an archive, like EPUB or an Office document in a
+    /// ZIP, aren't archives themselves
+    static ref ARCHIVE_NODES: Vec<bool> = {
+        let mut out = subtree_nodes(ARCHIVE_TYPES.iter().filter_map(|x| TYPES.hash.get(*x).cloned()));
+        for (i, x) in out.iter_mut().enumerate() {
+            *x = *x && !(DOCUMENT_NODES[i] || IMAGE_NODES[i] || AUDIO_NODES[i] || VIDEO_NODES[i]);
+        }
+        out
+    };
+}
+
+/// Looks a type up in a node set. None if the type isn't loaded.
+fn in_nodes(mimetype: &str, nodes: &[bool]) -> Option<bool> {
+    TYPES.hash.get(get_alias(mimetype)).map(|x| nodes[x.index()])
+}
+
+/// Checks if a type is an image: an `image/` type, or a subtype of one.
+/// Types that aren't loaded count if they're `image/`.
+///
+/// # Examples
+/// ```rust
+/// assert!(tree_magic::is_image("image/png"));
+/// assert!(!tree_magic::is_image("application/pdf"));
+/// ```
+pub fn is_image(mimetype: &str) -> bool {
+    in_nodes(mimetype, &IMAGE_NODES).unwrap_or_else(|| mimetype.starts_with("image/"))
+}
+
+/// Checks if a type is audio: an `audio/` type, or a subtype of one.
+/// Types that aren't loaded count if they're `audio/`.
+///
+/// # Examples
+/// ```rust
+/// assert!(tree_magic::is_audio("audio/flac"));
+/// assert!(!tree_magic::is_audio("video/mp4"));
+/// ```
+pub fn is_audio(mimetype: &str) -> bool {
+    in_nodes(mimetype, &AUDIO_NODES).unwrap_or_else(|| mimetype.starts_with("audio/"))
+}
+
+/// Checks if a type is video: a `video/` type, or a subtype of one.
+/// Types that aren't loaded count if they're `video/`.
+///
+/// # Examples
+/// ```rust
+/// assert!(tree_magic::is_video("video/mp4"));
+/// assert!(!tree_magic::is_video("audio/mpeg"));
+/// ```
+pub fn is_video(mimetype: &str) -> bool {
+    in_nodes(mimetype, &VIDEO_NODES).unwrap_or_else(|| mimetype.starts_with("video/"))
+}
+
+/// Checks if a type is an archive or compressed file, such as ZIP, tar or
+/// gzip, or a subtype of one. Documents and media packed in an archive
+/// format, like EPUB or Office files, don't count. Types that aren't loaded
+/// don't count either.
+///
+/// # Examples
+/// ```rust
+/// assert!(tree_magic::is_archive("application/zip"));
+/// assert!(!tree_magic::is_archive("application/epub+zip"));
+/// ```
+pub fn is_archive(mimetype: &str) -> bool {
+    in_nodes(mimetype, &ARCHIVE_NODES).unwrap_or(false)
+}
+
+/// Checks if a type is a document: PDF, PostScript, office formats and
+/// e-books, or a subtype of one. Plain text doesn't count. Types that
+/// aren't loaded don't count either.
+///
+/// # Examples
+/// ```rust
+/// assert!(tree_magic::is_document("application/pdf"));
+/// assert!(!tree_magic::is_document("text/plain"));
+/// ```
+pub fn is_document(mimetype: &str) -> bool {
+    in_nodes(mimetype, &DOCUMENT_NODES).unwrap_or(false)
+}
+
+/// Determines if a MIME is an alias of another MIME
+///
+/// If this returns true, that means the two MIME types are equivalent.
+/// If this returns false, either one of the MIME types are missing, or they are different.
+///
+/// # Examples
+/// ```
+/// let mime1 = "application/zip".into();
+/// let mime2 = "application/x-zip-compressed".into();
+///
+/// assert_eq!( tree_magic::is_alias(mime1, mime2), true );
+pub fn is_alias(mime1: Mime, mime2: Mime) -> bool {
+    let x = get_alias(&mime1);
+    let y = get_alias(&mime2);
+
+    x == mime2 || y == mime1
+}
+
+/// Most of a device a FileCache reads
+#[cfg(feature="fs")]
+const DEVICE_READ_LEN: u64 = 65536;
+
+/// Opens a file for reading. On Unix it's opened without blocking, so
+/// opening a FIFO doesn't wait for a writer, and reading a device that has
+/// nothing to give fails rather than waits.
+#[cfg(feature="fs")]
+fn open_nonblocking(filepath: &Path) -> Result<std::fs::File, std::io::Error> {
+    let mut options = std::fs::OpenOptions::new();
+    options.read(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.custom_flags(libc::O_NONBLOCK);
+    }
+    options.open(filepath)
+}
+
+/// Opens the file for a FileCache, unless it already has been.
+///
+/// Nothing is read from a FIFO or socket, as that would take the data from
+/// whoever it was meant for, and at most `DEVICE_READ_LEN` bytes of a
+/// device.
+#[cfg(feature="fs")]
+fn open_to_cache<'a>(filepath: &Path, lock: &'a mut Option<Cache>) -> Result<&'a mut OpenFile, std::io::Error> {
+    if lock.is_none() {
+        let file = open_nonblocking(filepath)?;
+        // Checked on the open file, so it can't be swapped for something
+        // else in between
+        let limit = match file.metadata().ok().as_ref().and_then(basetype::special_type) {
+            Some("inode/fifo") | Some("inode/socket") => 0,
+            Some(_) => DEVICE_READ_LEN,
+            None => u64::MAX
+        };
+        *lock = Some(Cache::FileCache(OpenFile{
+            file: Arc::new(file),
+            head: Vec::new(),
+            #[cfg(any(feature="checker-columnar", feature="checker-archive", feature="checker-filesystem"))]
+            tail: None,
+            #[cfg(feature="mmap")]
+            map: None,
+            samples: None,
+            limit,
+            sampling: None
+        }));
+    }
+
+    match *lock {
+        Some(Cache::FileCache(ref mut x)) => Ok(x),
+        _ => panic!("Invalid type for FileCache!")
+    }
+}
+
+/// Gets the file a FileCache has open, opening it if it isn't yet, and how
+/// far into it reads may go. Anything reading from it has to seek first, as
+/// others share the position.
+#[cfg(all(feature="fs", any(feature="checker-scientific", feature="checker-model")))]
+fn open_cached(filepath: &Path, filecache: &CacheItem) -> Result<(Arc<std::fs::File>, u64), std::io::Error> {
+    let mut lock = filecache.write();
+    let open = open_to_cache(filepath, &mut lock)?;
+    Ok((open.file.clone(), open.limit))
+}
+
+/// Maps the file a FileCache has open, opening and mapping it if that
+/// isn't done yet, and says how much of the mapping falls within the read
+/// limit, or the first window with sampling on.
+#[cfg(feature="mmap")]
+fn map_cached(filepath: &Path, filecache: &CacheItem) -> Result<(Arc<memmap2::Mmap>, usize), std::io::Error> {
+    let mut lock = filecache.write();
+    let open = open_to_cache(filepath, &mut lock)?;
+
+    let map = match open.map {
+        Some(ref x) => x.clone(),
+        None => {
+            // Safety: the mapping is only read, and dropped with the cache
+            // before detection returns. If another process truncates the
+            // file meanwhile, reads past the new end fault, as with any
+            // mapping; that's the cost of the feature.
+            let x = Arc::new(unsafe { memmap2::Mmap::map(&*open.file)? });
+            open.map = Some(x.clone());
+            x
+        }
+    };
+    let len = std::cmp::min(map.len() as u64, open.limit) as usize;
+    let len = open.sampling.map_or(len, |x| std::cmp::min(len, x.window));
+    Ok((map, len))
+}
+
+/// How much of the end of an input `input_tail` gives: enough for a ZIP end
+/// of central directory record with the longest possible comment
+#[cfg(any(feature="checker-columnar", feature="checker-archive", feature="checker-filesystem"))]
+const TAIL_LEN: usize = 65536 + 22;
+
+/// Runs `f` on the last `TAIL_LEN` bytes of the input and the length of the
+/// whole input, for checks anchored to the end of a file.
+///
+/// `bytes` is the start of the input. If it's a file that goes on past
+/// that, the end is read from the file in the FileCache, once, or taken
+/// from its mapping if it has one; with sampling on, only a window's worth.
+/// The end is empty if it's past the cache's read limit. The file cache
+/// stays locked while `f` runs, so `f` mustn't use it.
+#[cfg(any(feature="checker-columnar", feature="checker-archive", feature="checker-filesystem"))]
+#[allow(unused_variables)]
+fn input_tail<R, F: FnOnce(&[u8], u64) -> R>(bytes: &[u8], filecache: &CacheItem, f: F) -> R {
+    let from_bytes = &bytes[bytes.len().saturating_sub(TAIL_LEN)..];
+
+    #[cfg(feature="fs")]
+    {
+        let mut lock = filecache.write();
+        let open = match *lock {
+            Some(Cache::FileCache(ref mut x)) => x,
+            _ => return f(from_bytes, bytes.len() as u64)
+        };
+
+        let size = match open.file.metadata() {
+            Ok(x) => x.len(),
+            Err(_) => return f(&[], bytes.len() as u64)
+        };
+        if size <= bytes.len() as u64 {
+            return f(from_bytes, bytes.len() as u64);
+        }
+        if size > open.limit {
+            return f(&[], size);
+        }
+        let len = open.sampling.map_or(TAIL_LEN, |x| x.window);
+        let start = size.saturating_sub(len as u64);
+        #[cfg(feature="mmap")]
+        {
+            if let Some(map) = open.map.as_ref().filter(|x| x.len() as u64 == size) {
+                return f(&map[start as usize..], size);
+            }
+        }
+        if open.tail.is_none() {
+            open.tail = Some(read_window(&open.file, start, len).unwrap_or_default());
+        }
+        f(open.tail.as_deref().unwrap_or_default(), size)
+    }
+    #[cfg(not(feature="fs"))]
+    f(from_bytes, bytes.len() as u64)
+}
+
+/// Gets the windows sampled from between the start and end of the input,
+/// with sampling on. Empty if it's off, or the input isn't a file. They're
+/// read once, and any past the cache's read limit are left out.
+#[allow(unused_variables)]
+fn input_samples(filecache: &CacheItem) -> Vec<Vec<u8>> {
+    #[cfg(feature="fs")]
+    {
+        let mut lock = filecache.write();
+        let open = match *lock {
+            Some(Cache::FileCache(ref mut x)) => x,
+            _ => return Vec::new()
+        };
+        let sampling = match open.sampling {
+            Some(x) => x,
+            None => return Vec::new()
+        };
+        if let Some(ref x) = open.samples {
+            return x.clone();
+        }
+
+        let size = open.file.metadata().map(|x| x.len()).unwrap_or(0);
+        let window = sampling.window as u64;
+        let mut samples = Vec::<Vec<u8>>::new();
+        if size > 2 * window {
+            // Evenly spaced between the head and tail windows
+            let between = size - 2 * window;
+            for i in 1..=sampling.samples as u64 {
+                let start = window + between * i / (sampling.samples as u64 + 1) - window / 2;
+                if start + window > open.limit {
+                    break;
+                }
+                if let Ok(x) = read_window(&open.file, start, sampling.window) {
+                    samples.push(x);
+                }
+            }
+        }
+        open.samples = Some(samples.clone());
+        samples
+    }
+    #[cfg(not(feature="fs"))]
+    Vec::new()
+}
+
+/// Reads up to `len` bytes of a file, from the given offset
+#[cfg(feature="fs")]
+fn read_window(file: &std::fs::File, start: u64, len: usize) -> Result<Vec<u8>, std::io::Error> {
+    use std::io::prelude::*;
+
+    let mut f = file;
+    f.seek(std::io::SeekFrom::Start(start))?;
+    let mut out = Vec::<u8>::with_capacity(len);
+    f.take(len as u64).read_to_end(&mut out)?;
+    Ok(out)
+}
+
+/// Checks if anything read the file past its start, rather than only
+/// what `slurp_to_cache` gave it
+#[cfg(feature="parallel")]
+fn read_past_head(filecache: &CacheItem) -> bool {
+    match *filecache.read() {
+        #[cfg(any(feature="checker-columnar", feature="checker-archive", feature="checker-filesystem"))]
+        Some(Cache::FileCache(ref x)) if x.tail.is_some() => true,
+        Some(Cache::FileCache(ref x)) => x.samples.is_some(),
+        _ => false
+    }
+}
+
+/// Slurps the given number of bytes from a file to a FileCache
+/// and returns those bytes as a Vec<u8> for convienence.
+///
+/// The file is only opened the first time, and later calls only read
+/// whatever the earlier ones didn't. Fewer bytes come back if the file is
+/// shorter, or the cache has a read limit.
+#[cfg(feature="fs")]
+fn slurp_to_cache(filepath: &Path, filecache: &CacheItem, bytecount: usize) -> Result<Vec<u8>, std::io::Error> {
+    use std::io::prelude::*;
+
+    // Hold the write lock for the whole operation, so the cache can't
+    // change between checking its length and appending to it.
+    let mut lock = filecache.write();
+    let open = open_to_cache(filepath, &mut lock)?;
+    let bytecount = std::cmp::min(bytecount as u64, open.limit) as usize;
+    let bytecount = open.sampling.map_or(bytecount, |x| std::cmp::min(bytecount, x.window));
+    if open.head.len() < bytecount {
+        let count = bytecount - open.head.len();
+
+        let mut f = &*open.file;
+        f.seek(std::io::SeekFrom::Start(open.head.len() as u64))?;
+        f.take(count as u64).read_to_end(&mut open.head)?;
     }
+    Ok(open.head.clone())
 }
@@ -0,0 +1,64 @@
+//! Keeps count of the work a detection does, against a `WorkLimit`
+//!
+//! The budget lives with the thread, so the checkers and the magic program
+//! can charge it without it being passed down through every one of them.
+//! With no budget set, everything is free.
+
+use std::cell::Cell;
+use WorkLimit;
+
+/// What's left of a budget
+#[derive(Debug, Clone, Copy)]
+struct Meter {
+    bytes: usize,
+    rules: usize,
+    exceeded: bool
+}
+
+thread_local! {
+    static METER: Cell<Option<Meter>> = const { Cell::new(None) };
+}
+
+/// Puts back the budget from before `metered`, even if detection panics
+struct Restore(Option<Meter>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let _ = METER.try_with(|x| x.set(self.0));
+    }
+}
+
+/// Runs `f` on a budget of `limit`, giving what it returned and whether
+/// the budget ran out. Replaces any budget already set until `f` is done.
+pub fn metered<R, F: FnOnce() -> R>(limit: WorkLimit, f: F) -> (R, bool) {
+    let meter = Meter{bytes: limit.max_bytes, rules: limit.max_rules, exceeded: false};
+    let _restore = Restore(METER.with(|x| x.replace(Some(meter))));
+    let out = f();
+    (out, exhausted())
+}
+
+/// Takes `rules` rules and `bytes` bytes from the budget. Gives false, and
+/// takes nothing, if there isn't enough left, and from then on the budget
+/// counts as run out.
+pub fn charge(rules: usize, bytes: usize) -> bool {
+    METER.with(|x| {
+        let mut meter = match x.get() {
+            Some(y) => y,
+            None => return true
+        };
+        if meter.exceeded || meter.rules < rules || meter.bytes < bytes {
+            meter.exceeded = true;
+            x.set(Some(meter));
+            return false;
+        }
+        meter.rules -= rules;
+        meter.bytes -= bytes;
+        x.set(Some(meter));
+        true
+    })
+}
+
+/// Whether the budget has run out
+pub fn exhausted() -> bool {
+    METER.with(|x| x.get()).is_some_and(|x| x.exceeded)
+}
@@ -0,0 +1,329 @@
+//! Handles mail messages, mailboxes and MIME multipart bodies
+
+const TYPES: [&str; 2] =
+[
+    "application/mbox",
+    "message/rfc822"
+];
+
+/// Multipart bodies may hold binary parts, so these sit under both
+/// text/plain and application/octet-stream.
+const MULTIPART_TYPES: [&str; 7] =
+[
+    "multipart/alternative",
+    "multipart/byteranges",
+    "multipart/encrypted",
+    "multipart/form-data",
+    "multipart/mixed",
+    "multipart/related",
+    "multipart/signed"
+];
+
+/// What the input was found to be, worked out the first time any of these
+/// types is checked against it
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cache {
+    mbox: bool,
+    rfc822: bool,
+    multipart: Option<&'static str>
+}
+
+pub mod init {
+
+    use FnvHashMap;
+    use MIME;
+    use std::prelude::v1::*;
+
+    pub fn get_supported() -> Vec<MIME> {
+        super::TYPES.iter()
+            .chain(super::MULTIPART_TYPES.iter())
+            .map(|x| MIME::from(*x))
+            .collect()
+    }
+
+    /// Returns Vec of parent->child relations
+    pub fn get_subclasses() -> Vec<(MIME, MIME)> {
+        let mut res: Vec<(MIME, MIME)> = super::TYPES.iter()
+            .chain(super::MULTIPART_TYPES.iter())
+            .map(|x| (MIME::from("text/plain"), MIME::from(*x)))
+            .collect();
+        res.extend(super::MULTIPART_TYPES.iter()
+            .map(|x| (MIME::from("application/octet-stream"), MIME::from(*x))));
+        res
+    }
+
+    pub fn get_aliaslist() -> FnvHashMap<MIME, MIME> {
+        FnvHashMap::default()
+    }
+
+}
+
+pub mod check {
+
+    use std;
+    use std::prelude::v1::*;
+    #[cfg(feature="fs")]
+    use std::path::Path;
+    use memchr::memmem;
+    use super::super::{Cache, CacheItem, CHECKER_SCAN_LEN};
+    #[cfg(feature="fs")]
+    use super::super::slurp_to_cache;
+
+    /// Header fields that nearly every message carries. At least one of these
+    /// must be present, so that other `key: value` formats aren't mistaken
+    /// for mail.
+    const KNOWN_FIELDS: [&str; 12] =
+    [
+        "from", "to", "cc", "subject", "date", "message-id", "received",
+        "return-path", "mime-version", "reply-to", "sender", "delivered-to"
+    ];
+
+    /// Old-style markers that shared-mime-info also treats as a message
+    const LEGACY_PREFIXES: [&[u8]; 4] =
+    [
+        b"#! rnews", b"N#! rnews", b"Forward to ", b"Pipe to "
+    ];
+
+    /// Splits a line into a `Name: value` header field (RFC 5322,
+    /// section 2.2), with the field name returned in lowercase.
+    fn header_field(line: &[u8]) -> Option<(String, String)> {
+        let colon = line.iter().position(|&x| x == b':')?;
+        let name = &line[..colon];
+        if name.is_empty() || !name.iter().all(|&x| (33..=126).contains(&x)) {
+            return None;
+        }
+        Some((
+            String::from_utf8_lossy(name).to_lowercase(),
+            String::from_utf8_lossy(&line[colon + 1..]).trim().to_string()
+        ))
+    }
+
+    /// Strips the line ending off a line, if there is one
+    fn trim_cr(line: &[u8]) -> &[u8] {
+        match line.last() {
+            Some(&b'\r') => &line[..line.len() - 1],
+            _ => line
+        }
+    }
+
+    /// Parses the block of header fields at the start of the buffer, ended by
+    /// an empty line. Nothing after the empty line is looked at.
+    ///
+    /// Returns None if anything other than a header field is found first, or
+    /// if the buffer ends before the empty line does.
+    fn header_fields(b: &[u8]) -> Option<Vec<(String, String)>> {
+        let mut fields = Vec::<(String, String)>::new();
+
+        let mut lines = b.split(|&x| x == b'\n').peekable();
+        while let Some(line) = lines.next() {
+            // Only a line with an ending is a whole line
+            lines.peek()?;
+            let line = trim_cr(line);
+
+            if line.is_empty() {
+                return Some(fields);
+            }
+
+            // Folded continuation of the previous field
+            if line[0] == b' ' || line[0] == b'\t' {
+                let last = fields.last_mut()?;
+                last.1.push(' ');
+                last.1.push_str(String::from_utf8_lossy(line).trim());
+                continue;
+            }
+
+            fields.push(header_field(line)?);
+        }
+
+        None
+    }
+
+    /// Checks that header fields look like they belong to a message
+    fn is_message_header(fields: &[(String, String)]) -> bool {
+        fields.iter().any(|x| KNOWN_FIELDS.contains(&x.0.as_str()))
+    }
+
+    /// Checks that the buffer starts with a block of header fields
+    /// that looks like it belongs to a message.
+    fn is_header_block(b: &[u8]) -> bool {
+        header_fields(b).is_some_and(|x| is_message_header(&x))
+    }
+
+    /// A single message: header fields followed by an empty line, given the
+    /// fields at the start of the buffer
+    fn is_rfc822(b: &[u8], fields: Option<&[(String, String)]>) -> bool {
+        LEGACY_PREFIXES.iter().any(|x| b.starts_with(x)) || fields.is_some_and(is_message_header)
+    }
+
+    /// A mailbox: a `From ` separator line, followed by a message
+    fn is_mbox(b: &[u8]) -> bool {
+        if !b.starts_with(b"From ") {
+            return false;
+        }
+        match b.iter().position(|&x| x == b'\n') {
+            Some(x) => is_header_block(&b[x + 1..]),
+            None => false
+        }
+    }
+
+    /// Checks a boundary against the rules of RFC 2046, section 5.1.1
+    fn is_boundary(x: &[u8]) -> bool {
+        !x.is_empty() && x.len() <= 70 && x[x.len() - 1] != b' ' &&
+        x.iter().all(|&c| c.is_ascii_alphanumeric() || b"'()+_,-./:=? ".contains(&c))
+    }
+
+    /// Finds the type of a multipart body that is given without its
+    /// headers, judging by the headers of its parts.
+    ///
+    /// The body must start with an optional preamble followed by a
+    /// delimiter line, and the first part must carry at least one
+    /// `Content-*` field. `fields` are the header fields at the start of
+    /// the buffer.
+    fn multipart_type(b: &[u8], fields: Option<&[(String, String)]>) -> Option<&'static str> {
+        // A buffer starting with headers is a whole message, not a body
+        if fields.is_some_and(|x| !x.is_empty()) {
+            return None;
+        }
+
+        // Find the first delimiter line, skipping the preamble
+        let mut offset = 0;
+        let mut boundary: Option<&[u8]> = None;
+        for line in b.split(|&x| x == b'\n') {
+            let end = offset + line.len() + 1;
+            let line = trim_cr(line);
+            if line.starts_with(b"--") && is_boundary(&line[2..]) {
+                boundary = Some(&line[2..]);
+                offset = end;
+                break;
+            }
+            offset = end;
+        }
+        let boundary = boundary?;
+        if offset > b.len() {
+            return None;
+        }
+
+        // Split the rest of the body into parts, and parse each part's headers
+        let mut delimiter = b"\n--".to_vec();
+        delimiter.extend_from_slice(boundary);
+        let finder = memmem::Finder::new(&delimiter);
+        let mut parts = Vec::<Vec<(String, String)>>::new();
+        let mut rest = &b[offset..];
+        loop {
+            let end = finder.find(rest);
+            // Each part keeps the line ending that comes before the delimiter
+            let part = &rest[..end.map_or(rest.len(), |x| x + 1)];
+            match header_fields(part) {
+                Some(x) => parts.push(x),
+                // A part after the first may be cut off by the end of the buffer
+                None if end.is_none() && !parts.is_empty() => break,
+                None => return None
+            }
+
+            match end {
+                Some(x) => {
+                    rest = &rest[x + delimiter.len()..];
+                    // Closing delimiter
+                    if rest.starts_with(b"--") {
+                        break;
+                    }
+                    match rest.iter().position(|&x| x == b'\n') {
+                        Some(x) => rest = &rest[x + 1..],
+                        None => break
+                    }
+                },
+                None => break
+            }
+        }
+
+        if !parts[0].iter().any(|x| x.0.starts_with("content-")) {
+            return None;
+        }
+
+        let field = |part: &Vec<(String, String)>, name: &str| -> String {
+            part.iter()
+                .find(|x| x.0 == name)
+                .map(|x| x.1.to_lowercase())
+                .unwrap_or_default()
+        };
+        let content_types: Vec<String> = parts.iter()
+            .map(|x| field(x, "content-type"))
+            .collect();
+
+        if parts.iter().any(|x| field(x, "content-disposition").starts_with("form-data")) {
+            Some("multipart/form-data")
+        } else if parts.iter().any(|x| !field(x, "content-range").is_empty()) {
+            Some("multipart/byteranges")
+        } else if content_types[0].starts_with("application/pgp-encrypted") {
+            Some("multipart/encrypted")
+        } else if content_types.len() > 1 && (
+            content_types[1].starts_with("application/pgp-signature") ||
+            content_types[1].starts_with("application/pkcs7-signature") ||
+            content_types[1].starts_with("application/x-pkcs7-signature")
+        ) {
+            Some("multipart/signed")
+        } else if content_types.len() > 1 &&
+            content_types[0].starts_with("text/plain") &&
+            content_types[1].starts_with("text/html")
+        {
+            Some("multipart/alternative")
+        } else if content_types[0].starts_with("text/html") &&
+            parts.iter().any(|x| !field(x, "content-id").is_empty())
+        {
+            Some("multipart/related")
+        } else {
+            Some("multipart/mixed")
+        }
+    }
+
+    /// Works out which of the types the input is, from as far into it as
+    /// the built-in checkers look
+    fn classify(b: &[u8]) -> super::Cache {
+        let b = &b[..std::cmp::min(b.len(), CHECKER_SCAN_LEN)];
+        #[cfg(feature="std")]
+        if !::limit::charge(0, b.len()) {
+            return super::Cache::default();
+        }
+        let fields = header_fields(b);
+        super::Cache {
+            mbox: is_mbox(b),
+            rfc822: is_rfc822(b, fields.as_deref()),
+            multipart: multipart_type(b, fields.as_deref())
+        }
+    }
+
+    /// Checks the given buffer. It's only parsed the first time one of these
+    /// types is checked against it, and the cache holds what it was found to
+    /// be.
+    #[allow(unused_variables)]
+    pub fn from_u8(
+        b: &[u8], mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        let mut lock = cache.write();
+        if lock.is_none() {
+            *lock = Some(Cache::Mail(classify(b)));
+        }
+        let found = match *lock {
+            Some(Cache::Mail(x)) => x,
+            _ => return false
+        };
+        match mimetype {
+            "application/mbox" => found.mbox,
+            "message/rfc822" => found.rfc822,
+            x if x.starts_with("multipart/") => found.multipart == Some(x),
+            _ => false
+        }
+    }
+
+    #[cfg(feature="fs")]
+    #[allow(unused_variables)]
+    pub fn from_filepath(
+        filepath: &Path, mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        let b = match slurp_to_cache(filepath, filecache, CHECKER_SCAN_LEN) {
+            Ok(x) => x,
+            Err(_) => return false
+        };
+        from_u8(b.as_slice(), mimetype, cache, filecache)
+    }
+}
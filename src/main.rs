@@ -4,6 +4,8 @@
 //! - Find MIME of a file
 //! - Match file against a set of MIMEs (significantly faster)
 //! - Search a folder recursively
+//! - Output results as JSON
+//! - Draw the type graph with Graphviz
 //!
 //! # Usage
 //!
@@ -12,11 +14,14 @@
 //!
 //! FLAGS:
 //!     -h, --help             Prints help information
+//!         --json             Print results as JSON. With --ugly, print one JSON object per line
 //!     -r, --recursive        Search directories recursively
 //!         --ugly             Print results as they come in, at expense of tab alignment
 //!     -V, --version          Prints version information
 //!
 //! OPTIONS:
+//!         --dot[=<type>]     Print the type graph in Graphviz DOT instead, or just the given type and its subtypes
+//!     -f, --filter=<filter>  Print only files detected as given MIMEs, seperated by commas
 //!     -m, --match=<match>    Print only files that match given MIMEs, seperated by commas
 //!
 //! ARGS:
@@ -36,13 +41,36 @@ use std::path::PathBuf;
 use walkdir::{WalkDir};
 use scoped_threadpool::Pool;
 
-#[cfg(not(feature="staticmime"))]
-macro_rules! convmime {
-    ($x:expr) => {$x.to_string()}
+/// Escapes a string for use in JSON output
+fn json_string(x: &str) -> String {
+    let mut out = String::with_capacity(x.len() + 2);
+    out.push('"');
+    for c in x.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c)
+        }
+    }
+    out.push('"');
+    out
 }
-#[cfg(feature="staticmime")]
-macro_rules! convmime {
-    ($x:expr) => {$x}
+
+/// Formats a single result, either as a JSON object or a tab-separated line
+fn format_result(file: &PathBuf, result: Option<&str>, is_json: bool) -> String {
+    if is_json {
+        let mime = match result {
+            Some(x) => json_string(x),
+            None => "null".to_string()
+        };
+        format!("{{\"path\": {}, \"mime\": {}}}", json_string(&file.to_string_lossy()), mime)
+    } else {
+        format!("{:?}:\t{:?}", file, result)
+    }
 }
 
 fn main() {
@@ -53,7 +81,7 @@ fn main() {
         .version("0.2.0")
         .about("Determines the MIME type of a file by traversing a filetype tree.")
         .arg(Arg::with_name("file")
-            .required(true)
+            .required_unless("dot")
             .index(1)
             .multiple(true)
             .help("List of files or folders to check. Wildcards supported.")
@@ -71,22 +99,53 @@ fn main() {
             .require_equals(true)
             .help("Print only files that match given MIMEs, seperated by commas")
         )
+        .arg(Arg::with_name("filter")
+            .short("f")
+            .long("filter")
+            .use_delimiter(true)
+            .takes_value(true)
+            .require_equals(true)
+            .conflicts_with("match")
+            .help("Print only files detected as given MIMEs, seperated by commas")
+        )
         .arg(Arg::with_name("ugly")
             .long("ugly")
             .help("Print results as they come in, at expense of tab alignment")
         )
+        .arg(Arg::with_name("json")
+            .long("json")
+            .help("Print results as JSON. With --ugly, print one JSON object per line")
+        )
+        .arg(Arg::with_name("dot")
+            .long("dot")
+            .takes_value(true)
+            .min_values(0)
+            .require_equals(true)
+            .value_name("type")
+            .help("Print the type graph in Graphviz DOT instead, or just the given type and its subtypes")
+        )
         .get_matches();
+
+    if args.is_present("dot") {
+        print!("{}", tree_magic::export_dot(args.value_of("dot")));
+        return;
+    }
         
     let mut files: Vec<PathBuf> = args.values_of("file")
         .unwrap()
-        .map(|x| PathBuf::from(x))
+        .map(PathBuf::from)
         .collect();
     let is_ugly = args.is_present("ugly");
     let is_recursive = args.is_present("recursive");
+    let is_json = args.is_present("json");
     let check_against: Vec<String> = match args.values_of("match") {
         Some(y) => {y.map(|x| x.to_string()).collect()}
         None => Vec::<String>::new()
     };
+    let filter: Vec<String> = match args.values_of("filter") {
+        Some(y) => {y.map(|x| x.to_string()).collect()}
+        None => Vec::<String>::new()
+    };
     
     let mut tw = TabWriter::new(vec![]);
     let (tx, rx) = mpsc::channel();
@@ -110,9 +169,18 @@ fn main() {
                 //let file = file.as_str();
                 //let file = Path::new(file);
                 let tx = tx.clone();
+                let filter = filter.clone();
                 scope.execute(move || {
                     let result = tree_magic::from_filepath(file.as_path());
-                    let result = format!("{:?}:\t{:?}", file, result);
+                    if !filter.is_empty() {
+                        let found = match result {
+                            Some(ref x) => filter.iter()
+                                .any(|y| x == y || tree_magic::is_alias(x.clone(), y.clone().into())),
+                            None => false
+                        };
+                        if !found { return; }
+                    }
+                    let result = format_result(&file, result.as_deref(), is_json);
                     if is_ugly {
                         println!("{}", result);
                     } else {
@@ -143,8 +211,7 @@ fn main() {
                     
                     if result.is_none() { return; }
                     
-                    let result = result.unwrap();
-                    let result = format!("{:?}:\t{:?}", file, result);
+                    let result = format_result(&file, result.as_deref(), is_json);
                     if is_ugly {
                         println!("{}", result);
                     } else {
@@ -161,6 +228,17 @@ fn main() {
         let mut list: Vec<_> = rx.iter().collect();
         list.sort();
         list.dedup();
+
+        if is_json {
+            let list: Vec<&str> = list.iter().map(|x| x.trim_end()).collect();
+            if list.is_empty() {
+                println!("[]");
+            } else {
+                println!("[\n  {}\n]", list.join(",\n  "));
+            }
+            return;
+        }
+
         for x in list {
             write!(&mut tw, "{}", x).unwrap();
         }
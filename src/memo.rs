@@ -0,0 +1,158 @@
+//! Remembers the types of files, so asking again about a file that hasn't
+//! changed skips reading and matching it. Meant for file managers refreshing
+//! a view, or watchers seeing the same files over and over.
+//!
+//! A file counts as unchanged while its size and modification time are. Only
+//! a `stat` is done for a file that's remembered.
+//!
+//! Requires the `result-cache` feature.
+//!
+//! # Examples
+//! ```rust
+//! use std::path::Path;
+//! use tree_magic::memo::ResultCache;
+//!
+//! let cache = ResultCache::new(1024);
+//! let path = Path::new("tests/image/gif");
+//! assert_eq!(cache.from_filepath(path), Some("image/gif".into()));
+//! // Doesn't read the file this time
+//! assert_eq!(cache.from_filepath(path), Some("image/gif".into()));
+//! ```
+extern crate std;
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use parking_lot::Mutex;
+use FnvHashMap;
+use MIME;
+
+/// A remembered result
+struct Entry {
+    len: u64,
+    modified: Option<SystemTime>,
+    mimetype: Option<MIME>,
+    /// When it was last used, as a count of lookups
+    used: u64
+}
+
+/// Results, and the order they were used in
+#[derive(Default)]
+struct Lru {
+    entries: FnvHashMap<PathBuf, Entry>,
+    /// Path of each entry, by when it was last used
+    order: BTreeMap<u64, PathBuf>,
+    clock: u64,
+    capacity: usize
+}
+
+impl Lru {
+    /// Drops the least recently used entries until there are at most
+    /// `capacity`
+    fn shrink(&mut self) {
+        while self.entries.len() > self.capacity {
+            let oldest = match self.order.keys().next() {
+                Some(&x) => x,
+                None => return
+            };
+            if let Some(path) = self.order.remove(&oldest) {
+                self.entries.remove(&path);
+            }
+        }
+    }
+}
+
+/// Least recently used cache of detection results, keyed by path, size and
+/// modification time
+pub struct ResultCache {
+    lru: Mutex<Lru>
+}
+
+impl ResultCache {
+    /// Makes a cache that remembers up to `capacity` files
+    pub fn new(capacity: usize) -> ResultCache {
+        ResultCache {lru: Mutex::new(Lru {capacity, ..Default::default()})}
+    }
+
+    /// Gets the MIME type of a file, as `tree_magic::from_filepath` does,
+    /// reusing the last result for it if the file hasn't changed.
+    ///
+    /// Files that can't be `stat`ed aren't remembered.
+    pub fn from_filepath(&self, filepath: &Path) -> Option<MIME> {
+        let meta = match std::fs::metadata(filepath) {
+            Ok(x) => x,
+            Err(_) => return ::from_filepath(filepath)
+        };
+        let len = meta.len();
+        let modified = meta.modified().ok();
+
+        {
+            let mut lru = self.lru.lock();
+            let lru = &mut *lru;
+            lru.clock += 1;
+            if let Some(entry) = lru.entries.get_mut(filepath) {
+                if entry.len == len && entry.modified == modified {
+                    lru.order.remove(&entry.used);
+                    entry.used = lru.clock;
+                    lru.order.insert(entry.used, filepath.to_path_buf());
+                    return entry.mimetype.clone();
+                }
+            }
+        }
+
+        // Not holding the lock while detecting, so other files can be looked
+        // up meanwhile
+        let mimetype = ::from_filepath(filepath);
+        let mut lru = self.lru.lock();
+        if lru.capacity == 0 {
+            return mimetype;
+        }
+        lru.clock += 1;
+        let used = lru.clock;
+        let entry = Entry {len, modified, mimetype: mimetype.clone(), used};
+        if let Some(old) = lru.entries.insert(filepath.to_path_buf(), entry) {
+            lru.order.remove(&old.used);
+        }
+        lru.order.insert(used, filepath.to_path_buf());
+        lru.shrink();
+        mimetype
+    }
+
+    /// Forgets the result for a file
+    pub fn invalidate(&self, filepath: &Path) {
+        let mut lru = self.lru.lock();
+        if let Some(old) = lru.entries.remove(filepath) {
+            lru.order.remove(&old.used);
+        }
+    }
+
+    /// Forgets every result
+    pub fn clear(&self) {
+        let mut lru = self.lru.lock();
+        lru.entries.clear();
+        lru.order.clear();
+    }
+
+    /// Changes how many files are remembered, dropping the least recently
+    /// used results if there are too many
+    pub fn set_capacity(&self, capacity: usize) {
+        let mut lru = self.lru.lock();
+        lru.capacity = capacity;
+        lru.shrink();
+    }
+
+    /// How many files can be remembered
+    pub fn capacity(&self) -> usize {
+        self.lru.lock().capacity
+    }
+
+    /// How many files are remembered
+    pub fn len(&self) -> usize {
+        self.lru.lock().entries.len()
+    }
+
+    /// Whether no files are remembered
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,320 @@
+//! The `Mime` type handed out by the detection functions
+use std;
+use std::prelude::v1::*;
+use std::borrow::{Borrow, Cow};
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// A MIME type, like `image/svg+xml`
+///
+/// Types from the database and the built-in checkers are borrowed from static
+/// data, and the rest are shared, so cloning one never copies the string.
+/// Compares, hashes and sorts the same as its string, and derefs to it.
+///
+/// Types made with `From` are taken as they are, since the database has the
+/// last word on what's a type. `parse` checks them against RFC 6838 first.
+///
+/// # Examples
+/// ```rust
+/// let png = tree_magic::from_u8(b"\x89PNG\r\n\x1a\n");
+/// assert_eq!(png, "image/png");
+/// assert_eq!(png.media_type(), "image");
+/// assert_eq!(png.suffix(), None);
+///
+/// let svg = tree_magic::Mime::parse("image/svg+xml").unwrap();
+/// assert_eq!(svg.subtype(), "svg");
+/// assert_eq!(svg.suffix(), Some("xml"));
+/// ```
+#[derive(Clone)]
+pub struct Mime(Repr);
+
+#[derive(Clone)]
+enum Repr {
+    Static(&'static str),
+    Shared(Arc<str>)
+}
+
+/// Why a string isn't a MIME type, from `Mime::parse`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMimeError {
+    /// There's no `/` between the type and subtype
+    MissingSlash,
+    /// The type or subtype is empty, or longer than 127 characters
+    BadLength,
+    /// A name doesn't start with a letter or digit
+    BadStart,
+    /// There's a character RFC 6838 doesn't allow in names, like a space,
+    /// `;` or a second `/`
+    BadChar(char)
+}
+
+impl std::fmt::Display for ParseMimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            ParseMimeError::MissingSlash => write!(f, "MIME type has no '/'"),
+            ParseMimeError::BadLength => write!(f, "MIME type or subtype is empty or too long"),
+            ParseMimeError::BadStart => write!(f, "MIME type or subtype doesn't start with a letter or digit"),
+            ParseMimeError::BadChar(c) => write!(f, "{:?} isn't allowed in a MIME type", c)
+        }
+    }
+}
+
+#[cfg(feature="std")]
+impl std::error::Error for ParseMimeError {}
+
+/// Checks a type or subtype against restricted-name in RFC 6838
+fn check_name(x: &str) -> Result<(), ParseMimeError> {
+    if x.is_empty() || x.len() > 127 {
+        return Err(ParseMimeError::BadLength);
+    }
+    if !x.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        return Err(ParseMimeError::BadStart);
+    }
+    match x.chars().find(|&c| !c.is_ascii_alphanumeric() && !"!#$&-^_.+".contains(c)) {
+        Some(c) => Err(ParseMimeError::BadChar(c)),
+        None => Ok(())
+    }
+}
+
+impl Mime {
+    /// Checks that `x` is a well-formed `type/subtype`, without parameters
+    ///
+    /// # Examples
+    /// ```rust
+    /// use tree_magic::{Mime, ParseMimeError};
+    ///
+    /// assert_eq!(Mime::parse("text/plain").unwrap(), "text/plain");
+    /// assert_eq!(Mime::parse("text"), Err(ParseMimeError::MissingSlash));
+    /// assert_eq!(Mime::parse("text/plain; charset=utf-8"), Err(ParseMimeError::BadChar(';')));
+    /// ```
+    pub fn parse(x: &str) -> Result<Mime, ParseMimeError> {
+        let (media_type, subtype) = x.split_once('/').ok_or(ParseMimeError::MissingSlash)?;
+        check_name(media_type)?;
+        check_name(subtype)?;
+        Ok(Mime::from(x.to_string()))
+    }
+
+    /// The whole type, like `image/svg+xml`
+    pub fn as_str(&self) -> &str {
+        match self.0 {
+            Repr::Static(x) => x,
+            Repr::Shared(ref x) => x
+        }
+    }
+
+    /// The part before the `/`, like `image` in `image/svg+xml`
+    pub fn media_type(&self) -> &str {
+        self.as_str().split('/').next().unwrap_or("")
+    }
+
+    /// The part after the `/`, without any suffix, like `svg` in
+    /// `image/svg+xml`
+    pub fn subtype(&self) -> &str {
+        let subtype = self.as_str().split_once('/').map_or("", |x| x.1);
+        subtype.rsplit_once('+').map_or(subtype, |x| x.0)
+    }
+
+    /// The structured syntax suffix after the last `+`, like `xml` in
+    /// `image/svg+xml`
+    pub fn suffix(&self) -> Option<&str> {
+        self.as_str().split_once('/')?.1.rsplit_once('+').map(|x| x.1)
+    }
+}
+
+/// An empty string, which isn't a type. Only there so `Mime` can be a graph
+/// weight.
+impl Default for Mime {
+    fn default() -> Mime {
+        Mime::from("")
+    }
+}
+
+impl From<&'static str> for Mime {
+    fn from(x: &'static str) -> Mime {
+        Mime(Repr::Static(x))
+    }
+}
+
+impl From<String> for Mime {
+    fn from(x: String) -> Mime {
+        Mime(Repr::Shared(x.into()))
+    }
+}
+
+impl From<Cow<'static, str>> for Mime {
+    fn from(x: Cow<'static, str>) -> Mime {
+        match x {
+            Cow::Borrowed(x) => Mime::from(x),
+            Cow::Owned(x) => Mime::from(x)
+        }
+    }
+}
+
+impl From<Mime> for String {
+    fn from(x: Mime) -> String {
+        x.as_str().to_string()
+    }
+}
+
+impl std::str::FromStr for Mime {
+    type Err = ParseMimeError;
+
+    fn from_str(x: &str) -> Result<Mime, ParseMimeError> {
+        Mime::parse(x)
+    }
+}
+
+impl Deref for Mime {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Mime {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Borrow<str> for Mime {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl std::fmt::Display for Mime {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::fmt::Debug for Mime {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl PartialEq for Mime {
+    fn eq(&self, other: &Mime) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Mime {}
+
+impl PartialOrd for Mime {
+    fn partial_cmp(&self, other: &Mime) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Mime {
+    fn cmp(&self, other: &Mime) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for Mime {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl PartialEq<str> for Mime {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Mime {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for Mime {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<Mime> for str {
+    fn eq(&self, other: &Mime) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<Mime> for &str {
+    fn eq(&self, other: &Mime) -> bool {
+        *self == other.as_str()
+    }
+}
+
+impl PartialEq<Mime> for String {
+    fn eq(&self, other: &Mime) -> bool {
+        self == other.as_str()
+    }
+}
+
+#[cfg(feature="serde")]
+impl serde::Serialize for Mime {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature="serde")]
+impl<'de> serde::Deserialize<'de> for Mime {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Mime, D::Error> {
+        let x = String::deserialize(d)?;
+        Mime::parse(&x).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Keeps the essence, dropping any parameters such as `charset`. Nothing's
+/// checked again, since `mime` already has.
+#[cfg(feature="mime")]
+impl From<::mime::Mime> for Mime {
+    fn from(x: ::mime::Mime) -> Mime {
+        Mime::from(x.essence_str().to_string())
+    }
+}
+
+/// Fails only for types made with `From` that aren't well-formed. Types from
+/// detection always convert.
+///
+/// # Examples
+/// ```rust
+/// use std::convert::TryFrom;
+///
+/// let png = tree_magic::from_u8(b"\x89PNG\r\n\x1a\n");
+/// assert_eq!(mime::Mime::try_from(&png).unwrap(), mime::IMAGE_PNG);
+/// ```
+#[cfg(feature="mime")]
+impl<'a> std::convert::TryFrom<&'a Mime> for ::mime::Mime {
+    type Error = ::mime::FromStrError;
+
+    fn try_from(x: &'a Mime) -> Result<::mime::Mime, ::mime::FromStrError> {
+        x.as_str().parse()
+    }
+}
+
+#[cfg(feature="mime")]
+impl std::convert::TryFrom<Mime> for ::mime::Mime {
+    type Error = ::mime::FromStrError;
+
+    fn try_from(x: Mime) -> Result<::mime::Mime, ::mime::FromStrError> {
+        ::mime::Mime::try_from(&x)
+    }
+}
+
+#[cfg(feature="mime")]
+impl PartialEq<::mime::Mime> for Mime {
+    fn eq(&self, other: &::mime::Mime) -> bool {
+        self.as_str().eq_ignore_ascii_case(other.essence_str())
+    }
+}
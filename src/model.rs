@@ -0,0 +1,176 @@
+//! Handles 3D model and asset formats
+
+/// Dummy cache type
+pub type Cache = bool;
+
+pub mod init {
+
+    use FnvHashMap;
+    use MIME;
+    use std::prelude::v1::*;
+
+    pub fn get_supported() -> Vec<MIME> {
+        vec![
+            MIME::from("model/stl"),
+            MIME::from("model/gltf+json"),
+            MIME::from("model/gltf-binary"),
+            MIME::from("model/obj"),
+            MIME::from("model/x-ply")
+        ]
+    }
+
+    /// Returns Vec of parent->child relations
+    ///
+    /// STL and PLY come in both text and binary flavors, so they can be
+    /// reached from either text/plain or application/octet-stream.
+    pub fn get_subclasses() -> Vec<(MIME, MIME)> {
+        vec![
+            (MIME::from("text/plain"), MIME::from("model/stl")),
+            (MIME::from("application/octet-stream"), MIME::from("model/stl")),
+            (MIME::from("application/json"), MIME::from("model/gltf+json")),
+            (MIME::from("text/plain"), MIME::from("model/gltf+json")),
+            (MIME::from("application/octet-stream"), MIME::from("model/gltf-binary")),
+            (MIME::from("text/plain"), MIME::from("model/obj")),
+            (MIME::from("text/plain"), MIME::from("model/x-ply")),
+            (MIME::from("application/octet-stream"), MIME::from("model/x-ply"))
+        ]
+    }
+
+    pub fn get_aliaslist() -> FnvHashMap<MIME, MIME> {
+        FnvHashMap::default()
+    }
+
+}
+
+pub mod check {
+
+    use std::prelude::v1::*;
+    #[cfg(feature="fs")]
+    use std::path::Path;
+    use super::super::CacheItem;
+    #[cfg(feature="fs")]
+    use super::super::{open_cached, slurp_to_cache};
+
+    /// Size of the binary STL header and triangle count
+    const STL_HEADER_LEN: u64 = 84;
+    /// Size of each binary STL triangle: normal, 3 vertices, attribute count
+    const STL_TRIANGLE_LEN: u64 = 50;
+
+    fn read_u32_le(b: &[u8]) -> u32 {
+        b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24
+    }
+
+    /// Binary STL has no magic at all. Instead, the triangle count in the
+    /// header must agree with the total size of the file.
+    fn is_binary_stl(b: &[u8], filesize: u64) -> bool {
+        if (b.len() as u64) < STL_HEADER_LEN {
+            return false;
+        }
+        let count = read_u32_le(&b[80..84]) as u64;
+        count > 0 && STL_HEADER_LEN + count * STL_TRIANGLE_LEN == filesize
+    }
+
+    /// ASCII STL starts with `solid`, but so do many binary STL headers,
+    /// so the first facet must be found as well.
+    fn is_ascii_stl(b: &[u8]) -> bool {
+        let start = match b.iter().position(|x| !x.is_ascii_whitespace()) {
+            Some(x) => x,
+            None => return false
+        };
+        b[start..].starts_with(b"solid") &&
+        b.windows(12).any(|x| x == b"facet normal") &&
+        !b.contains(&0)
+    }
+
+    /// A JSON object with the `asset` property that every glTF file requires
+    fn is_gltf_json(b: &[u8]) -> bool {
+        let start = match b.iter().position(|x| !x.is_ascii_whitespace()) {
+            Some(x) => x,
+            None => return false
+        };
+        b[start] == b'{' &&
+        b.windows(7).any(|x| x == b"\"asset\"") &&
+        b.windows(9).any(|x| x == b"\"version\"")
+    }
+
+    /// `glTF` followed by the container version, 1 or 2
+    fn is_glb(b: &[u8]) -> bool {
+        b.len() >= 12 && b.starts_with(b"glTF") && (1..=2).contains(&read_u32_le(&b[4..8]))
+    }
+
+    /// Wavefront OBJ: every line must be a known statement or a comment,
+    /// and there must be at least one vertex.
+    fn is_obj(b: &[u8]) -> bool {
+        const STATEMENTS: [&[u8]; 13] = [
+            b"v ", b"vn ", b"vt ", b"vp ", b"f ", b"l ", b"p ", b"o ", b"g ",
+            b"s ", b"mtllib ", b"usemtl ", b"#"
+        ];
+
+        let mut lines: Vec<&[u8]> = b.split(|&x| x == b'\n').collect();
+        // The last line may be cut short
+        lines.pop();
+
+        let mut has_vertex = false;
+        for line in lines {
+            let line = match line.iter().position(|x| !x.is_ascii_whitespace()) {
+                Some(x) => &line[x..],
+                None => continue
+            };
+            if !STATEMENTS.iter().any(|x| line.starts_with(x)) {
+                return false;
+            }
+            has_vertex |= line.starts_with(b"v ");
+        }
+        has_vertex
+    }
+
+    /// `ply` followed by the format line
+    fn is_ply(b: &[u8]) -> bool {
+        let rest = if b.starts_with(b"ply\n") {
+            &b[4..]
+        } else if b.starts_with(b"ply\r\n") {
+            &b[5..]
+        } else {
+            return false;
+        };
+        [&b"format ascii "[..], b"format binary_little_endian ", b"format binary_big_endian "]
+            .iter()
+            .any(|x| rest.starts_with(x))
+    }
+
+    /// Checks the given buffer. Binary STL is only detected if the
+    /// buffer holds the whole file, as its size must be known.
+    #[allow(unused_variables)]
+    pub fn from_u8(
+        b: &[u8], mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        match mimetype {
+            "model/stl" => is_ascii_stl(b) || is_binary_stl(b, b.len() as u64),
+            "model/gltf+json" => is_gltf_json(b),
+            "model/gltf-binary" => is_glb(b),
+            "model/obj" => is_obj(b),
+            "model/x-ply" => is_ply(b),
+            _ => false
+        }
+    }
+
+    #[cfg(feature="fs")]
+    #[allow(unused_variables)]
+    pub fn from_filepath(
+        filepath: &Path, mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        let b = match slurp_to_cache(filepath, filecache, 2048) {
+            Ok(x) => x,
+            Err(_) => return false
+        };
+
+        if mimetype == "model/stl" && !is_ascii_stl(b.as_slice()) {
+            return match open_cached(filepath, filecache).and_then(|x| x.0.metadata()) {
+                Ok(meta) => is_binary_stl(b.as_slice(), meta.len()),
+                Err(_) => false
+            };
+        }
+
+        from_u8(b.as_slice(), mimetype, cache, filecache)
+    }
+}
@@ -0,0 +1,144 @@
+//! Handles cartridge ROM dumps for game consoles
+//!
+//! Types use the names from shared-mime-info, whose aliases cover the
+//! older `application/x-snes-rom`.
+
+/// Dummy cache type
+pub type Cache = bool;
+
+pub mod init {
+
+    use FnvHashMap;
+    use MIME;
+    use std::prelude::v1::*;
+
+    pub fn get_supported() -> Vec<MIME> {
+        vec![
+            MIME::from("application/x-nes-rom"),
+            MIME::from("application/x-gameboy-rom"),
+            MIME::from("application/x-gameboy-color-rom"),
+            MIME::from("application/vnd.nintendo.snes.rom"),
+            MIME::from("application/x-genesis-rom")
+        ]
+    }
+
+    /// Returns Vec of parent->child relations
+    pub fn get_subclasses() -> Vec<(MIME, MIME)> {
+        get_supported().into_iter()
+            .map(|x| (MIME::from("application/octet-stream"), x))
+            .collect()
+    }
+
+    pub fn get_aliaslist() -> FnvHashMap<MIME, MIME> {
+        FnvHashMap::default()
+    }
+
+}
+
+pub mod check {
+
+    use std::prelude::v1::*;
+    #[cfg(feature="fs")]
+    use std::path::Path;
+    use super::super::CacheItem;
+    #[cfg(feature="fs")]
+    use super::super::slurp_to_cache;
+
+    /// Nintendo logo that the Game Boy boot ROM compares against
+    const GAMEBOY_LOGO: [u8; 48] = [
+        0xce, 0xed, 0x66, 0x66, 0xcc, 0x0d, 0x00, 0x0b, 0x03, 0x73, 0x00, 0x83,
+        0x00, 0x0c, 0x00, 0x0d, 0x00, 0x08, 0x11, 0x1f, 0x88, 0x89, 0x00, 0x0e,
+        0xdc, 0xcc, 0x6e, 0xe6, 0xdd, 0xdd, 0xd9, 0x99, 0xbb, 0xbb, 0x67, 0x63,
+        0x6e, 0x0e, 0xec, 0xcc, 0xdd, 0xdc, 0x99, 0x9f, 0xbb, 0xb9, 0x33, 0x3e
+    ];
+
+    /// Offsets of the SNES internal header for LoROM and HiROM layouts
+    const SNES_HEADERS: [usize; 2] = [0x7fc0, 0xffc0];
+    /// Size of the header that copier devices prepend to SNES dumps
+    const COPIER_HEADER_LEN: usize = 0x200;
+
+    /// iNES and NES 2.0: `NES\x1a`, and at least one PRG ROM bank unless
+    /// the NES 2.0 size exponent is in use.
+    fn is_nes(b: &[u8]) -> bool {
+        b.len() >= 16 && b.starts_with(b"NES\x1a") &&
+        (b[4] > 0 || b[7] & 0x0c == 0x08)
+    }
+
+    /// Game Boy header: the Nintendo logo at 0x104, and the header checksum
+    /// at 0x14D over 0x134..0x14D. Returns the CGB flag if valid.
+    fn gameboy_cgb_flag(b: &[u8]) -> Option<u8> {
+        if b.len() < 0x150 || b[0x104..0x134] != GAMEBOY_LOGO {
+            return None;
+        }
+        let checksum = b[0x134..0x14d].iter()
+            .fold(0u8, |acc, &x| acc.wrapping_sub(x).wrapping_sub(1));
+        if checksum != b[0x14d] {
+            return None;
+        }
+        Some(b[0x143])
+    }
+
+    /// SNES internal header: the checksum and its complement must add up
+    /// to 0xFFFF, and the map mode must be valid. Dumps may carry a copier
+    /// header in front.
+    fn is_snes(b: &[u8]) -> bool {
+        [0, COPIER_HEADER_LEN].iter()
+            .flat_map(|&x| SNES_HEADERS.iter().map(move |&y| x + y))
+            .any(|x| {
+                if b.len() < x + 0x20 {
+                    return false;
+                }
+                let header = &b[x..x + 0x20];
+                let complement = header[0x1c] as u16 | (header[0x1d] as u16) << 8;
+                let checksum = header[0x1e] as u16 | (header[0x1f] as u16) << 8;
+                complement ^ checksum == 0xffff &&
+                header[0x15] & 0xe0 == 0x20 &&
+                header[..21].iter().all(|&x| (0x20..0x7f).contains(&x) || x >= 0xa0)
+            })
+    }
+
+    /// Genesis and Mega Drive: the system name at 0x100, or the
+    /// Electronic Arts markers at 0x280.
+    fn is_genesis(b: &[u8]) -> bool {
+        const NAMES: [&[u8]; 4] = [b"GENESIS", b"MEGA DRIVE", b"MEGA_DRIVE", b"MEGADRIVE"];
+        if b.len() < 0x284 {
+            return false;
+        }
+        let system = &b[0x100..0x110];
+        let start = system.iter().position(|&x| x != b' ').unwrap_or(0);
+        (system[start..].starts_with(b"SEGA") &&
+            NAMES.iter().any(|n| system.windows(n.len()).any(|x| x == *n))) ||
+        &b[0x280..0x284] == b"EAGN" || &b[0x280..0x284] == b"EAMG"
+    }
+
+    #[allow(unused_variables)]
+    pub fn from_u8(
+        b: &[u8], mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        match mimetype {
+            "application/x-nes-rom" => is_nes(b),
+            "application/x-gameboy-rom" => gameboy_cgb_flag(b).is_some_and(|x| x & 0x80 == 0),
+            "application/x-gameboy-color-rom" => gameboy_cgb_flag(b).is_some_and(|x| x & 0x80 != 0),
+            "application/vnd.nintendo.snes.rom" => is_snes(b),
+            "application/x-genesis-rom" => is_genesis(b),
+            _ => false
+        }
+    }
+
+    #[cfg(feature="fs")]
+    #[allow(unused_variables)]
+    pub fn from_filepath(
+        filepath: &Path, mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        // The SNES header sits at the end of the first ROM bank
+        let bytecount = match mimetype {
+            "application/vnd.nintendo.snes.rom" => COPIER_HEADER_LEN + SNES_HEADERS[1] + 0x20,
+            _ => 2048
+        };
+        let b = match slurp_to_cache(filepath, filecache, bytecount) {
+            Ok(x) => x,
+            Err(_) => return false
+        };
+        from_u8(b.as_slice(), mimetype, cache, filecache)
+    }
+}
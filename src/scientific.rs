@@ -0,0 +1,119 @@
+//! Handles scientific data formats: HDF and NetCDF
+
+/// Dummy cache type
+pub type Cache = bool;
+
+pub mod init {
+
+    use FnvHashMap;
+    use MIME;
+    use std::prelude::v1::*;
+
+    pub fn get_supported() -> Vec<MIME> {
+        vec![
+            MIME::from("application/x-hdf"),
+            MIME::from("application/x-hdf5"),
+            MIME::from("application/x-netcdf")
+        ]
+    }
+
+    /// Returns Vec of parent->child relations
+    pub fn get_subclasses() -> Vec<(MIME, MIME)> {
+        vec![
+            (MIME::from("application/octet-stream"), MIME::from("application/x-hdf")),
+            (MIME::from("application/x-hdf"), MIME::from("application/x-hdf5")),
+            (MIME::from("application/octet-stream"), MIME::from("application/x-netcdf"))
+        ]
+    }
+
+    pub fn get_aliaslist() -> FnvHashMap<MIME, MIME> {
+        FnvHashMap::default()
+    }
+
+}
+
+pub mod check {
+
+    #[cfg(feature="fs")]
+    use std;
+    use std::prelude::v1::*;
+    #[cfg(feature="fs")]
+    use std::path::Path;
+    use super::super::CacheItem;
+    #[cfg(feature="fs")]
+    use super::super::{open_cached, slurp_to_cache};
+
+    const HDF4_MAGIC: &[u8] = b"\x0e\x03\x13\x01";
+    const HDF5_MAGIC: &[u8] = b"\x89HDF\r\n\x1a\n";
+
+    /// Offsets the HDF5 superblock may be found at: 0, then 512 and every
+    /// power of two after it, as a user block may come first.
+    fn hdf5_offsets(len: u64) -> Vec<u64> {
+        let mut offsets = vec![0];
+        let mut x = 512;
+        while x < len {
+            offsets.push(x);
+            x *= 2;
+        }
+        offsets
+    }
+
+    fn is_hdf5(b: &[u8]) -> bool {
+        hdf5_offsets(b.len() as u64).iter().any(|&x| b[x as usize..].starts_with(HDF5_MAGIC))
+    }
+
+    /// Classic (`CDF\x01`), 64-bit offset (`CDF\x02`) and
+    /// 64-bit data (`CDF\x05`) NetCDF files
+    fn is_netcdf(b: &[u8]) -> bool {
+        b.len() >= 4 && b.starts_with(b"CDF") && [1, 2, 5].contains(&b[3])
+    }
+
+    #[allow(unused_variables)]
+    pub fn from_u8(
+        b: &[u8], mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        match mimetype {
+            "application/x-hdf" => b.starts_with(HDF4_MAGIC) || is_hdf5(b),
+            "application/x-hdf5" => is_hdf5(b),
+            "application/x-netcdf" => is_netcdf(b),
+            _ => false
+        }
+    }
+
+    /// Probes every possible superblock offset in the file, rather than
+    /// only those that fit in the start of it
+    #[cfg(feature="fs")]
+    fn is_hdf5_file(filepath: &Path, filecache: &CacheItem) -> Result<bool, std::io::Error> {
+        use std::io::prelude::*;
+
+        let (file, limit) = open_cached(filepath, filecache)?;
+        let mut f = &*file;
+        let len = std::cmp::min(f.metadata()?.len(), limit);
+        let mut b = [0; 8];
+
+        for x in hdf5_offsets(len).into_iter().filter(|x| x + 8 <= len) {
+            f.seek(std::io::SeekFrom::Start(x))?;
+            if f.read_exact(&mut b).is_ok() && b == HDF5_MAGIC {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    #[cfg(feature="fs")]
+    #[allow(unused_variables)]
+    pub fn from_filepath(
+        filepath: &Path, mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        let b = match slurp_to_cache(filepath, filecache, 2048) {
+            Ok(x) => x,
+            Err(_) => return false
+        };
+
+        match mimetype {
+            "application/x-hdf" if b.starts_with(HDF4_MAGIC) => true,
+            "application/x-hdf" | "application/x-hdf5" => is_hdf5_file(filepath, filecache).unwrap_or(false),
+            _ => from_u8(b.as_slice(), mimetype, cache, filecache)
+        }
+    }
+}
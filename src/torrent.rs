@@ -0,0 +1,187 @@
+//! Handles BitTorrent metainfo files
+
+/// Dummy cache type
+pub type Cache = bool;
+
+pub mod init {
+
+    use FnvHashMap;
+    use MIME;
+    use std::prelude::v1::*;
+
+    pub fn get_supported() -> Vec<MIME> {
+        vec![MIME::from("application/x-bittorrent")]
+    }
+
+    /// Returns Vec of parent->child relations
+    ///
+    /// The piece hashes are binary, but they can be past the end of
+    /// what gets read, so this can be reached from text/plain too.
+    pub fn get_subclasses() -> Vec<(MIME, MIME)> {
+        vec![
+            (MIME::from("application/octet-stream"), MIME::from("application/x-bittorrent")),
+            (MIME::from("text/plain"), MIME::from("application/x-bittorrent"))
+        ]
+    }
+
+    pub fn get_aliaslist() -> FnvHashMap<MIME, MIME> {
+        FnvHashMap::default()
+    }
+
+}
+
+pub mod check {
+
+    use std;
+    use std::prelude::v1::*;
+    #[cfg(feature="fs")]
+    use std::path::Path;
+    use super::super::CacheItem;
+    #[cfg(feature="fs")]
+    use super::super::slurp_to_cache;
+
+    /// Keys that can come first in a metainfo dictionary. Keys are sorted,
+    /// so this is `announce` unless the torrent is trackerless.
+    const FIRST_KEYS: [&[u8]; 8] = [
+        b"announce", b"announce-list", b"comment", b"created by",
+        b"creation date", b"encoding", b"info", b"url-list"
+    ];
+
+    /// Deepest nesting of lists and dictionaries that will be parsed
+    const MAX_DEPTH: u32 = 32;
+
+    /// Result of parsing one bencoded value
+    enum Parsed<'a> {
+        /// The value was complete, followed by the rest of the buffer
+        Value(&'a [u8]),
+        /// The buffer ended in the middle of the value
+        Truncated,
+        Invalid
+    }
+
+    /// Parses a byte string: `<length>:<bytes>`, returning the bytes
+    /// (possibly cut short) and the rest of the buffer.
+    fn parse_string(b: &[u8]) -> Option<(&[u8], &[u8])> {
+        let colon = match b.iter().position(|&x| x == b':') {
+            Some(x) => x,
+            None => return if b.iter().all(u8::is_ascii_digit) { Some((&[], &[])) } else { None }
+        };
+        if colon == 0 || !b[..colon].iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        let len: usize = std::str::from_utf8(&b[..colon]).ok()?.parse().ok()?;
+        let rest = &b[colon + 1..];
+        if rest.len() < len {
+            Some((rest, &[]))
+        } else {
+            Some((&rest[..len], &rest[len..]))
+        }
+    }
+
+    /// Parses one bencoded value, tolerating the buffer ending inside it
+    fn parse_value<'a>(b: &'a [u8], depth: u32) -> Parsed<'a> {
+        if depth > MAX_DEPTH {
+            return Parsed::Invalid;
+        }
+        match b.first() {
+            None => Parsed::Truncated,
+            Some(&b'i') => {
+                let end = match b.iter().position(|&x| x == b'e') {
+                    Some(x) => x,
+                    None => return if b[1..].iter().all(|&x| x.is_ascii_digit() || x == b'-') {
+                        Parsed::Truncated
+                    } else {
+                        Parsed::Invalid
+                    }
+                };
+                let digits = &b[1..end];
+                let digits = if digits.first() == Some(&b'-') { &digits[1..] } else { digits };
+                if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+                    return Parsed::Invalid;
+                }
+                Parsed::Value(&b[end + 1..])
+            },
+            Some(&b'l') => {
+                let mut rest = &b[1..];
+                loop {
+                    match rest.first() {
+                        None => return Parsed::Truncated,
+                        Some(&b'e') => return Parsed::Value(&rest[1..]),
+                        _ => {}
+                    }
+                    match parse_value(rest, depth + 1) {
+                        Parsed::Value(x) => rest = x,
+                        x => return x
+                    }
+                }
+            },
+            Some(&b'd') => parse_dict(b, depth).0,
+            Some(_) => match parse_string(b) {
+                Some((_, &[])) => Parsed::Truncated,
+                Some((_, rest)) => Parsed::Value(rest),
+                None => Parsed::Invalid
+            }
+        }
+    }
+
+    /// Parses a dictionary, also returning its first key
+    fn parse_dict<'a>(b: &'a [u8], depth: u32) -> (Parsed<'a>, Option<&'a [u8]>) {
+        let mut first_key = None;
+        let mut rest = &b[1..];
+        loop {
+            match rest.first() {
+                None => return (Parsed::Truncated, first_key),
+                Some(&b'e') => return (Parsed::Value(&rest[1..]), first_key),
+                _ => {}
+            }
+
+            let (key, after) = match parse_string(rest) {
+                Some(x) => x,
+                None => return (Parsed::Invalid, first_key)
+            };
+            if first_key.is_none() {
+                first_key = Some(key);
+            }
+            if after.is_empty() {
+                return (Parsed::Truncated, first_key);
+            }
+
+            match parse_value(after, depth + 1) {
+                Parsed::Value(x) => rest = x,
+                x => return (x, first_key)
+            }
+        }
+    }
+
+    /// A dictionary that parses cleanly (as far as the buffer goes),
+    /// starting with a key a metainfo file can start with
+    fn is_torrent(b: &[u8]) -> bool {
+        if !b.starts_with(b"d") {
+            return false;
+        }
+        match parse_dict(b, 0) {
+            (Parsed::Invalid, _) => false,
+            (_, Some(key)) => FIRST_KEYS.contains(&key),
+            (_, None) => false
+        }
+    }
+
+    #[allow(unused_variables)]
+    pub fn from_u8(
+        b: &[u8], mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        mimetype == "application/x-bittorrent" && is_torrent(b)
+    }
+
+    #[cfg(feature="fs")]
+    #[allow(unused_variables)]
+    pub fn from_filepath(
+        filepath: &Path, mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        let b = match slurp_to_cache(filepath, filecache, 2048) {
+            Ok(x) => x,
+            Err(_) => return false
+        };
+        from_u8(b.as_slice(), mimetype, cache, filecache)
+    }
+}
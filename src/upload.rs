@@ -0,0 +1,251 @@
+//! Axum extractor that sniffs the type of each part of a multipart upload,
+//! instead of trusting the type the client declared.
+//!
+//! Requires the `axum` feature.
+//!
+//! # Examples
+//! ```rust,ignore
+//! use tree_magic::upload::{AllowList, SniffedUpload};
+//!
+//! async fn upload(SniffedUpload(parts): SniffedUpload) {
+//!     for part in parts {
+//!         println!("{:?}: {}", part.file_name, part.detected);
+//!     }
+//! }
+//!
+//! let app = Router::new()
+//!     .route("/upload", post(upload))
+//!     .layer(Extension(AllowList::new(&["image/*", "application/pdf"])));
+//! ```
+extern crate std;
+extern crate axum_core;
+extern crate bytes;
+extern crate http;
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use self::axum_core::extract::{FromRequest, Request};
+use self::axum_core::extract::rejection::BytesRejection;
+use self::axum_core::response::{IntoResponse, Response};
+use self::bytes::Bytes;
+use self::http::StatusCode;
+use self::http::header::CONTENT_TYPE;
+use memchr::memmem::{self, find};
+use MIME;
+
+/// Types that file parts are allowed to have
+///
+/// Add this to the request extensions (for example with axum's `Extension`
+/// layer) to have `SniffedUpload` reject other types. A type matches if it
+/// is one of the listed types or a descendant of one, so `text/plain` also
+/// allows `application/json`. An entry like `image/*` allows a whole media
+/// type. Without an allow-list, every type is accepted.
+#[derive(Debug, Clone, Default)]
+pub struct AllowList(Vec<String>);
+
+impl AllowList {
+    pub fn new<T: AsRef<str>>(types: &[T]) -> Self {
+        AllowList(types.iter().map(|x| x.as_ref().to_string()).collect())
+    }
+
+    /// Checks if the given type is allowed
+    pub fn allows(&self, mimetype: &str) -> bool {
+        self.0.iter().any(|x| match x.strip_suffix("/*") {
+            Some(media) => mimetype.split('/').next() == Some(media),
+            None => ::is_subtype_of(mimetype, x)
+        })
+    }
+}
+
+/// One part of a multipart upload
+#[derive(Debug, Clone)]
+pub struct SniffedPart {
+    /// Field name, from `Content-Disposition`
+    pub name: Option<String>,
+    /// File name, from `Content-Disposition`. Only file parts have one.
+    pub file_name: Option<String>,
+    /// Type the client declared in the part's `Content-Type`
+    pub declared: Option<String>,
+    /// Type detected from the contents
+    pub detected: MIME,
+    pub data: Bytes
+}
+
+/// Extracts the parts of a `multipart/form-data` request body, with the
+/// detected type of each.
+///
+/// The whole body is buffered, subject to axum's body size limit. Only file
+/// parts are checked against the `AllowList`; plain form fields are always
+/// accepted.
+#[derive(Debug, Clone)]
+pub struct SniffedUpload(pub Vec<SniffedPart>);
+
+/// Rejection used for `SniffedUpload`
+#[derive(Debug)]
+pub enum UploadRejection {
+    /// The body couldn't be read
+    Body(BytesRejection),
+    /// The request isn't `multipart/form-data`, or has no boundary
+    NotMultipart,
+    /// The body isn't valid multipart data
+    Malformed,
+    /// A file part has a type that isn't in the `AllowList`
+    NotAllowed {
+        file_name: Option<String>,
+        detected: MIME
+    }
+}
+
+impl IntoResponse for UploadRejection {
+    fn into_response(self) -> Response {
+        match self {
+            UploadRejection::Body(x) => x.into_response(),
+            UploadRejection::NotMultipart => (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "Expected request with `Content-Type: multipart/form-data`"
+            ).into_response(),
+            UploadRejection::Malformed => (
+                StatusCode::BAD_REQUEST,
+                "Malformed multipart body"
+            ).into_response(),
+            UploadRejection::NotAllowed{detected, ..} => (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!("Uploaded file type `{}` is not allowed", detected)
+            ).into_response()
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequest<S> for SniffedUpload {
+    type Rejection = UploadRejection;
+
+    fn from_request(
+        req: Request, state: &S
+    ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+        let boundary = req.headers().get(CONTENT_TYPE)
+            .and_then(|x| x.to_str().ok())
+            .and_then(boundary);
+        let allow = req.extensions().get::<AllowList>().cloned();
+        UploadFuture{boundary, allow, body: Box::pin(Bytes::from_request(req, state))}
+    }
+}
+
+type BodyFuture<'a> = Pin<Box<dyn Future<Output = Result<Bytes, BytesRejection>> + Send + 'a>>;
+
+/// Reads the body, then splits it into parts
+struct UploadFuture<'a> {
+    boundary: Option<String>,
+    allow: Option<AllowList>,
+    body: BodyFuture<'a>
+}
+
+impl<'a> Future for UploadFuture<'a> {
+    type Output = Result<SniffedUpload, UploadRejection>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let body = match self.body.as_mut().poll(cx) {
+            Poll::Ready(Ok(x)) => x,
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(UploadRejection::Body(e))),
+            Poll::Pending => return Poll::Pending
+        };
+        let boundary = match self.boundary {
+            Some(ref x) => x,
+            None => return Poll::Ready(Err(UploadRejection::NotMultipart))
+        };
+        Poll::Ready(sniff_parts(&body, boundary, self.allow.as_ref()))
+    }
+}
+
+/// Gets the boundary parameter of a `multipart/form-data` type
+fn boundary(content_type: &str) -> Option<String> {
+    let mut params = content_type.split(';');
+    if !params.next()?.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    params
+        .filter_map(|x| {
+            let (key, value) = x.split_once('=')?;
+            match key.trim().eq_ignore_ascii_case("boundary") {
+                true => Some(value.trim().trim_matches('"').to_string()),
+                false => None
+            }
+        })
+        .find(|x| !x.is_empty())
+}
+
+/// Gets a parameter of a `Content-Disposition` header
+fn disposition_param(disposition: &str, name: &str) -> Option<String> {
+    disposition.split(';').skip(1).find_map(|x| {
+        let (key, value) = x.split_once('=')?;
+        match key.trim().eq_ignore_ascii_case(name) {
+            true => Some(value.trim().trim_matches('"').to_string()),
+            false => None
+        }
+    })
+}
+
+/// Splits a `multipart/form-data` body into parts (RFC 7578), and detects
+/// the type of each.
+fn sniff_parts(
+    body: &Bytes, boundary: &str, allow: Option<&AllowList>
+) -> Result<SniffedUpload, UploadRejection> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let start = find(body, &delimiter).ok_or(UploadRejection::Malformed)?;
+    let mut rest = body.slice(start + delimiter.len()..);
+
+    let mut delimiter = b"\r\n".to_vec();
+    delimiter.extend_from_slice(format!("--{}", boundary).as_bytes());
+    let delimiter = memmem::Finder::new(&delimiter);
+
+    let mut parts = Vec::<SniffedPart>::new();
+    loop {
+        // Closing delimiter
+        if rest.starts_with(b"--") {
+            break;
+        }
+        let line_end = find(&rest, b"\r\n").ok_or(UploadRejection::Malformed)?;
+        rest = rest.slice(line_end + 2..);
+
+        let end = delimiter.find(&rest).ok_or(UploadRejection::Malformed)?;
+        let part = rest.slice(..end);
+        rest = rest.slice(end + delimiter.needle().len()..);
+
+        let header_end = find(&part, b"\r\n\r\n").ok_or(UploadRejection::Malformed)?;
+        let headers = String::from_utf8_lossy(&part[..header_end]).to_string();
+        let data = part.slice(header_end + 4..);
+
+        let mut disposition = None;
+        let mut declared = None;
+        for line in headers.split("\r\n") {
+            let (name, value) = match line.split_once(':') {
+                Some(x) => x,
+                None => continue
+            };
+            if name.trim().eq_ignore_ascii_case("content-disposition") {
+                disposition = Some(value.trim().to_string());
+            } else if name.trim().eq_ignore_ascii_case("content-type") {
+                declared = Some(value.trim().to_string());
+            }
+        }
+        let disposition = disposition.ok_or(UploadRejection::Malformed)?;
+        let file_name = disposition_param(&disposition, "filename");
+        let detected = ::from_u8(&data);
+
+        if let Some(allow) = allow {
+            if file_name.is_some() && !allow.allows(&detected) {
+                return Err(UploadRejection::NotAllowed{file_name, detected});
+            }
+        }
+
+        parts.push(SniffedPart{
+            name: disposition_param(&disposition, "name"),
+            file_name,
+            declared,
+            detected,
+            data
+        });
+    }
+
+    Ok(SniffedUpload(parts))
+}
@@ -0,0 +1,285 @@
+//! Minimal headers for the types the crate detects, so crates that use it
+//! can test their handling of each type without keeping binary samples.
+//!
+//! A header is a short start of a file that `from_u8` gives the type for,
+//! with little more than the checks need. It isn't a whole file, so it's
+//! only good for testing detection, not for whatever reads the file next.
+//!
+//! Types with magic get headers made from their rules. The others have
+//! ones written by hand, below. Either way, a header is only given out once
+//! it's been checked against this build's database, so types the build
+//! can't tell apart, like the Ogg ones that all start `OggS`, have none.
+//!
+//! Requires the `test-vectors` feature.
+//!
+//! # Examples
+//! ```rust
+//! use tree_magic::vectors;
+//!
+//! assert_eq!(tree_magic::from_u8(vectors::PNG), "image/png");
+//!
+//! let gif = vectors::header("image/gif").unwrap();
+//! assert_eq!(tree_magic::from_u8(&gif), "image/gif");
+//! ```
+use std;
+use std::prelude::v1::*;
+use Mime;
+use TypeId;
+
+/// PNG signature and the start of the `IHDR` chunk
+pub const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+/// JPEG start of image and a JFIF `APP0` marker
+pub const JPEG: &[u8] = b"\xff\xd8\xff\xe0\0\x10JFIF\0";
+/// GIF89a signature
+pub const GIF: &[u8] = b"GIF89a";
+/// PDF header
+pub const PDF: &[u8] = b"%PDF-1.7\n";
+/// ZIP local file header for an empty, stored file named `a`
+pub const ZIP: &[u8] = b"PK\x03\x04\x14\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x01\0\0\0a";
+/// Gzip header, with deflate and no flags
+pub const GZIP: &[u8] = b"\x1f\x8b\x08\0\0\0\0\0\0\x03";
+/// ELF identification for a 64-bit little-endian executable
+pub const ELF: &[u8] = b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0\x02\0";
+/// Plain ASCII text
+pub const TEXT: &[u8] = b"Hello, world!\n";
+
+/// Bytes to put at an offset
+type Piece = (usize, &'static [u8]);
+
+/// Headers for the types magic can't make, as (type, length, pieces). Each
+/// piece goes at its offset, and the rest is zeros. Types from the other
+/// checkers are only detected when their checker is built, so a header
+/// that doesn't check out is passed over.
+const WRITTEN: &[(&str, usize, &[Piece])] = &[
+    ("text/plain", 14, &[(0, TEXT)]),
+    ("application/octet-stream", 4, &[(1, b"\x01\x02\x03")]),
+    ("application/json", 9, &[(0, b"{\"a\": 1}\n")]),
+    ("application/pgp-encrypted", 28, &[(0, b"-----BEGIN PGP MESSAGE-----\n")]),
+    ("application/pgp-keys", 37, &[(0, b"-----BEGIN PGP PUBLIC KEY BLOCK-----\n")]),
+    ("application/pgp-signature", 30, &[(0, b"-----BEGIN PGP SIGNATURE-----\n")]),
+    ("message/rfc822", 56, &[(0, b"From: a@example.com\nTo: b@example.com\nSubject: a\n\nHello\n")]),
+    ("application/mbox", 53, &[(0, b"From alice@example.com Mon Mar  2 10:00:00 2020\nFrom:")]),
+    ("multipart/alternative", 84, &[(0, b"--a\nContent-Type: text/plain\n\nHello\n--a\nContent-Type: text/html\n\n<p>Hello</p>\n--a--\n")]),
+    ("multipart/byteranges", 40, &[(0, b"--a\nContent-Range: bytes 0-0/1\n\na\n--a--\n")]),
+    ("multipart/encrypted", 62, &[(0, b"--a\nContent-Type: application/pgp-encrypted\n\nVersion: 1\n--a--\n")]),
+    ("multipart/form-data", 54, &[(0, b"--a\nContent-Disposition: form-data; name=\"a\"\n\n1\n--a--\n")]),
+    ("multipart/mixed", 42, &[(0, b"--a\nContent-Type: text/plain\n\nHello\n--a--\n")]),
+    ("multipart/related", 64, &[(0, b"--a\nContent-Type: text/html\nContent-ID: <a>\n\n<p>Hello</p>\n--a--\n")]),
+    ("multipart/signed", 88, &[(0, b"--a\nContent-Type: text/plain\n\nHello\n--a\nContent-Type: application/pgp-signature\n\n\n--a--\n")]),
+    ("application/x-bittorrent", 34, &[(0, b"d8:announce3:url4:infod4:name1:aee")]),
+    ("text/x-fasta", 15, &[(0, b">seq1\nACGTACGT\n")]),
+    ("text/x-fastq", 18, &[(0, b"@seq1\nACGT\n+\nIIII\n")]),
+    // The empty block BGZF files end with
+    ("application/x-bgzf", 28, &[(0, b"\x1f\x8b\x08\x04\0\0\0\0\0\xff\x06\0BC\x02\0\x1b\0\x03")]),
+    ("application/x-bam", 23, &[(0, b"\x1f\x8b\x08\x04\0\0\0\0\0\0\x06\0BC\x02\0\0\0sr\xf4e4")]),
+    ("application/x-cram", 6, &[(0, b"CRAM\x03")]),
+    ("application/vnd.apache.arrow.file", 8, &[(0, b"ARROW1")]),
+    ("application/vnd.apache.parquet", 46, &[(0, b"PAR1"), (42, b"PAR1")]),
+    ("application/x-avro", 17, &[(0, b"Obj\x01\0\0avro.schema")]),
+    ("application/x-orc", 4, &[(0, b"ORC")]),
+    ("application/x-hdf5", 520, &[(512, b"\x89HDF\r\n\x1a\n")]),
+    ("application/x-netcdf", 4, &[(0, b"CDF\x01")]),
+    ("model/gltf-binary", 12, &[(0, b"glTF\x02")]),
+    ("model/gltf+json", 42, &[(0, b"{\n  \"asset\": {\n    \"version\": \"2.0\"\n  }\n}\n")]),
+    ("model/obj", 78, &[(0, b"# Blender v2.93 OBJ File\nmtllib cube.mtl\no Cube\nv 1.000000 1.000000 -1.000000\n")]),
+    ("model/stl", 184, &[(80, b"\x02")]),
+    ("model/x-ply", 17, &[(0, b"ply\nformat ascii ")]),
+    ("image/vnd.dwg", 7, &[(0, b"AC1015")]),
+    ("image/vnd.dxf", 46, &[(0, b"999\nDXF created by hand\n  0\nSECTION\n  2\nHEADER")]),
+    ("application/x-ms-shortcut", 20, &[(0, b"L\0\0\0\x01\x14\x02\0\0\0\0\0\xc0\0\0\0\0\0\0F")]),
+    ("application/x-ms-registry-hive", 512, &[
+        (0, b"regf\x01\0\0\0\x01"),
+        (20, b"\x01\0\0\0\x05\0\0\0\0\0\0\0\x01\0\0\0 \0\0\0\x01"),
+        (508, b"Vegf")
+    ]),
+    ("application/vnd.ms-cab-compressed", 36, &[(0, b"MSCF\0\0\0\0^\0\0\0\0\0\0\0,\0\0\0\0\0\0\0\x03\x01")]),
+    ("application/x-mswinurl", 73, &[(0, b"[{000214A0-0000-0000-C000-000000000046}]\r\nProp3=19,11\r\n[InternetShortcut]")]),
+    ("application/x-bplist", 8, &[(0, b"bplist00")]),
+    ("application/x-plist", 148, &[(0, b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n<plist")]),
+    ("application/vnd.android.bootimg", 44, &[(0, b"ANDROID!"), (37, b"\x08")]),
+    ("application/vnd.android.sparse-image", 28, &[(0, b":\xff&\xed\x01\0\0\0\x1c\0\x0c\0\0\x10")]),
+    ("application/x-dtb", 40, &[(0, b"\xd0\r\xfe\xed\0\0\0\xd6"), (23, b"\x11")]),
+    ("application/x-uboot-fit-image", 115, &[(0, b"\xd0\r\xfe\xed\0\0\x01y"), (23, b"\x11"), (107, b"\x01images")]),
+    ("application/x-uboot-image", 64, &[(0, b"'\x05\x19V"), (30, b"\x02")]),
+    ("application/x-uefi-capsule", 28, &[(0, b"\xed\xd5\xcbm-\xe8DL\xbd\xa1q\x94\x19\x9a\xd9* \0\0\0\0\0\0\x000")]),
+    ("application/x-uefi-firmware-volume", 72, &[
+        (16, b"z\xc0sT\xcb=\xcaM\xbdo\x1e\x96\x89\xe74\x9a\0\x10\0\0\0\0\0\0_FVH\xff\xfe\x04\0H\0\xe5'\0\0\0\x02\x01\0\0\0\0\x10")
+    ]),
+    ("application/x-nes-rom", 16, &[(0, b"NES\x1a\x01")]),
+    ("application/vnd.nintendo.snes.rom", 32736, &[(32704, b"EXAMPLE CART          \0\0\0\0\0\0\xcb\xed4\x12")]),
+    ("application/x-gameboy-rom", 336, &[
+        (260, b"\xce\xedff\xcc\r\0\x0b\x03s\0\x83\0\x0c\0\r\0\x08\x11\x1f\x88\x89\0\x0e\xdc\xccn\xe6\xdd\xdd\xd9\x99\xbb\xbbgcn\x0e\xec\xcc\xdd\xdc\x99\x9f\xbb\xb93>EXAMPLE"),
+        (330, b"\x013\0\xa7")
+    ]),
+    ("application/x-gameboy-color-rom", 336, &[
+        (260, b"\xce\xedff\xcc\r\0\x0b\x03s\0\x83\0\x0c\0\r\0\x08\x11\x1f\x88\x89\0\x0e\xdc\xccn\xe6\xdd\xdd\xd9\x99\xbb\xbbgcn\x0e\xec\xcc\xdd\xdc\x99\x9f\xbb\xb93>EXAMPLE\0\0\0\0\0\0\0\0\x80\0\0\0\0\0\0\x013\0'")
+    ]),
+    ("audio/flac", 8, &[(0, b"fLaC\0\0\0\"")]),
+    ("audio/x-ape", 6, &[(0, b"MAC \x96\x0f")]),
+    ("audio/x-tta", 22, &[(0, b"TTA1\x01\0\x02\0\x10\0\0\xac")]),
+    ("audio/x-wavpack", 32, &[(0, b"wvpk\0\0\0\0\x10\x04")]),
+    ("audio/x-wavpack-correction", 34, &[(0, b"wvpk*\0\0\0\x10\x04"), (32, b"\x0b")]),
+    ("application/x-mbr-partition-table", 512, &[(450, b"\x83"), (454, b"\0\x08\0\0\0\x40"), (510, b"\x55\xaa")]),
+    ("application/x-gpt-partition-table", 604, &[
+        (450, b"\xee"), (454, b"\x01\0\0\0\xff\xff\xff\xff"), (510, b"\x55\xaa"),
+        (512, b"EFI PART\0\0\x01\0\x5c")
+    ]),
+    ("application/x-ext2-filesystem", 1128, &[(1056, b"\0\x20"), (1064, b"\0\x08"), (1080, b"\x53\xef")]),
+    ("application/x-ext3-filesystem", 1128, &[(1056, b"\0\x20"), (1064, b"\0\x08"), (1080, b"\x53\xef"), (1116, b"\x04")]),
+    ("application/x-ext4-filesystem", 1128, &[(1056, b"\0\x20"), (1064, b"\0\x08"), (1080, b"\x53\xef"), (1120, b"\x40")]),
+    ("application/x-ntfs-filesystem", 512, &[(3, b"NTFS    \0\x02\x08"), (510, b"\x55\xaa")]),
+    // A whole double density floppy, as its size is what gives it away
+    ("application/x-amiga-disk-format", 901120, &[(0, b"DOS\0")])
+];
+
+/// Lays out a header from `WRITTEN`
+fn written(len: usize, pieces: &[Piece]) -> Vec<u8> {
+    let mut out = vec![0; len];
+    for &(offset, b) in pieces {
+        out[offset..offset + b.len()].copy_from_slice(b);
+    }
+    out
+}
+
+/// Ways to fill the gaps between magic values, and whether to end with a
+/// zero byte. Spaces keep text types text, and the zero byte stops binary
+/// types with printable magic, like `fLaC`, from passing for text.
+const FILL: &[(u8, bool)] = &[(0, false), (b' ', false), (0, true)];
+
+/// A header being built, with which of its bytes a value has been put in
+struct Draft {
+    bytes: Vec<u8>,
+    set: Vec<bool>,
+    fill: u8
+}
+
+impl Draft {
+    /// Starts from a header, all of it set
+    fn new(base: &[u8], fill: u8) -> Draft {
+        Draft {bytes: base.to_vec(), set: vec![true; base.len()], fill}
+    }
+
+    /// Whether `value` can go at `offset` without changing a byte that's
+    /// set
+    fn fits(&self, offset: usize, value: &[u8], mask: Option<&[u8]>) -> bool {
+        value.iter().enumerate().all(|(i, &x)| {
+            let m = mask.and_then(|y| y.get(i).cloned()).unwrap_or(0xff);
+            match self.set.get(offset + i) {
+                Some(&true) => self.bytes[offset + i] & m == x & m,
+                _ => true
+            }
+        })
+    }
+
+    /// Puts `value` at the first offset in the rule's range it fits at.
+    /// Returns false if it fits nowhere.
+    fn place(&mut self, offset: usize, range: usize, value: &[u8], mask: Option<&[u8]>) -> bool {
+        let offset = match (offset..offset + std::cmp::max(range, 1)).find(|&x| self.fits(x, value, mask)) {
+            Some(x) => x,
+            None => return false
+        };
+        if self.bytes.len() < offset + value.len() {
+            self.bytes.resize(offset + value.len(), self.fill);
+            self.set.resize(offset + value.len(), false);
+        }
+        for (i, &x) in value.iter().enumerate() {
+            let m = mask.and_then(|y| y.get(i).cloned()).unwrap_or(0xff);
+            self.bytes[offset + i] = (x & m) | (self.bytes[offset + i] & !m);
+            self.set[offset + i] = true;
+        }
+        true
+    }
+}
+
+/// Puts multi-byte words in host order, as the matcher does
+fn host_order(b: &[u8], word_size: u32) -> Vec<u8> {
+    let word_size = word_size as usize;
+    if cfg!(target_endian="big") || word_size <= 1 || !b.len().is_multiple_of(word_size) {
+        return b.to_vec();
+    }
+    b.chunks(word_size).flat_map(|x| x.iter().rev().cloned()).collect()
+}
+
+/// Each way through a type's rules: a top-level rule and one line of its
+/// nested rules, down to a rule with none under it
+fn paths(rules: &[::MagicRule]) -> Vec<Vec<&::MagicRule>> {
+    let mut out = Vec::new();
+    let mut stack: Vec<&::MagicRule> = Vec::new();
+    for (i, rule) in rules.iter().enumerate() {
+        stack.truncate(rule.indent as usize);
+        stack.push(rule);
+        if rules.get(i + 1).is_none_or(|x| x.indent <= rule.indent) {
+            out.push(stack.clone());
+        }
+    }
+    out
+}
+
+/// Whether `from_u8` gives `mimetype` for `b`
+fn detected(mimetype: &str, b: &[u8]) -> bool {
+    TypeId::of(mimetype).is_some_and(|x| TypeId::of(&::from_u8(b)) == Some(x))
+}
+
+/// Gets the shortest header made from the type's magic that's detected as
+/// it. The magic goes on top of a parent's header, since the parent has to
+/// be detected first.
+fn from_rules(mimetype: &str) -> Option<Vec<u8>> {
+    let rules = ::rules_for(mimetype);
+    let mut bases: Vec<Vec<u8>> = TypeId::of(mimetype)?.parents()
+        .filter(|x| !::rules_for(x.name()).is_empty())
+        .filter_map(|x| from_rules(x.name()))
+        .collect();
+    bases.push(Vec::new());
+
+    let mut found: Option<Vec<u8>> = None;
+    for base in &bases {
+        for path in paths(&rules) {
+            for &(fill, binary) in FILL {
+                let mut draft = Draft::new(base, fill);
+                let placed = path.iter().all(|rule| {
+                    let value = host_order(&rule.value, rule.word_size);
+                    let mask = rule.mask.as_ref().map(|x| host_order(x, rule.word_size));
+                    draft.place(rule.offset as usize, rule.range as usize, &value, mask.as_deref())
+                });
+                if binary {
+                    draft.bytes.push(0);
+                }
+                if placed && found.as_ref().is_none_or(|x| draft.bytes.len() < x.len()) && detected(mimetype, &draft.bytes) {
+                    found = Some(draft.bytes);
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Gets a minimal header detected as the given type, or an alias of it.
+///
+/// Returns None if the type isn't known, or this build has no header that
+/// detects as it.
+///
+/// # Examples
+/// ```rust
+/// let header = tree_magic::vectors::header("application/zip").unwrap();
+/// assert_eq!(tree_magic::from_u8(&header), "application/zip");
+///
+/// assert_eq!(tree_magic::vectors::header("application/x-nonexistent"), None);
+/// ```
+pub fn header(mimetype: &str) -> Option<Vec<u8>> {
+    let name = TypeId::of(mimetype)?.name();
+    if let Some(x) = WRITTEN.iter().find(|x| x.0 == name).map(|x| written(x.1, x.2)).filter(|x| detected(name, x)) {
+        return Some(x);
+    }
+    from_rules(name)
+}
+
+/// Gets a header for every type this build can give one for, sorted by type.
+///
+/// This checks every type in the database, so it takes a while.
+pub fn all() -> Vec<(Mime, Vec<u8>)> {
+    let mut out: Vec<(Mime, Vec<u8>)> = TypeId::all()
+        .filter_map(|x| header(x.name()).map(|y| (Mime::from(x.name()), y)))
+        .collect();
+    out.sort();
+    out
+}
@@ -0,0 +1,383 @@
+//! Implements the WHATWG MIME Sniffing Standard
+//!
+//! This is the algorithm web browsers use to pick the type of a resource,
+//! given the bytes of the resource and the type the server supplied. It
+//! gives different answers to the freedesktop.org rules in many cases,
+//! and always prefers the supplied type where the standard says to.
+//!
+//! See <https://mimesniff.spec.whatwg.org/>.
+use std;
+use std::prelude::v1::*;
+
+/// Only the resource header is looked at, which is this long at most
+const RESOURCE_HEADER_LEN: usize = 1445;
+
+/// A byte pattern, with a mask applied to the input before comparing,
+/// and whether leading whitespace may be skipped.
+struct Pattern {
+    pattern: &'static [u8],
+    mask: &'static [u8],
+    ignored: bool,
+    mime: &'static str
+}
+
+macro_rules! pattern {
+    ($pattern:expr, $mask:expr, $ignored:expr, $mime:expr) => {
+        Pattern {pattern: $pattern, mask: $mask, ignored: $ignored, mime: $mime}
+    }
+}
+
+/// Case-insensitive HTML tags, each followed by a tag-terminating byte
+const HTML_TAGS: [&[u8]; 17] = [
+    b"<!DOCTYPE HTML", b"<HTML", b"<HEAD", b"<SCRIPT", b"<IFRAME", b"<H1",
+    b"<DIV", b"<FONT", b"<TABLE", b"<A", b"<STYLE", b"<TITLE", b"<B",
+    b"<BODY", b"<BR", b"<P", b"<!--"
+];
+
+/// Patterns for scriptable types, only sniffed when allowed
+const SCRIPTABLE_PATTERNS: [Pattern; 2] = [
+    pattern!(b"<?xml", b"\xff\xff\xff\xff\xff", true, "text/xml"),
+    pattern!(b"%PDF-", b"\xff\xff\xff\xff\xff", false, "application/pdf")
+];
+
+/// Patterns for PostScript and text with a byte order mark
+const TEXT_PATTERNS: [Pattern; 4] = [
+    pattern!(b"%!PS-Adobe-", b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff", false, "application/postscript"),
+    pattern!(b"\xfe\xff\x00\x00", b"\xff\xff\x00\x00", false, "text/plain"),
+    pattern!(b"\xff\xfe\x00\x00", b"\xff\xff\x00\x00", false, "text/plain"),
+    pattern!(b"\xef\xbb\xbf\x00", b"\xff\xff\xff\x00", false, "text/plain")
+];
+
+/// Image type pattern matching algorithm, section 6.1
+const IMAGE_PATTERNS: [Pattern; 8] = [
+    pattern!(b"\x00\x00\x01\x00", b"\xff\xff\xff\xff", false, "image/x-icon"),
+    pattern!(b"\x00\x00\x02\x00", b"\xff\xff\xff\xff", false, "image/x-icon"),
+    pattern!(b"BM", b"\xff\xff", false, "image/bmp"),
+    pattern!(b"GIF87a", b"\xff\xff\xff\xff\xff\xff", false, "image/gif"),
+    pattern!(b"GIF89a", b"\xff\xff\xff\xff\xff\xff", false, "image/gif"),
+    pattern!(b"RIFF\x00\x00\x00\x00WEBPVP", b"\xff\xff\xff\xff\x00\x00\x00\x00\xff\xff\xff\xff\xff\xff", false, "image/webp"),
+    pattern!(b"\x89PNG\r\n\x1a\n", b"\xff\xff\xff\xff\xff\xff\xff\xff", false, "image/png"),
+    pattern!(b"\xff\xd8\xff", b"\xff\xff\xff", false, "image/jpeg")
+];
+
+/// Audio or video type pattern matching algorithm, section 6.2, apart
+/// from the formats that need parsing.
+const MEDIA_PATTERNS: [Pattern; 6] = [
+    pattern!(b"FORM\x00\x00\x00\x00AIFF", b"\xff\xff\xff\xff\x00\x00\x00\x00\xff\xff\xff\xff", false, "audio/aiff"),
+    pattern!(b"ID3", b"\xff\xff\xff", false, "audio/mpeg"),
+    pattern!(b"OggS\x00", b"\xff\xff\xff\xff\xff", false, "application/ogg"),
+    pattern!(b"MThd\x00\x00\x00\x06", b"\xff\xff\xff\xff\xff\xff\xff\xff", false, "audio/midi"),
+    pattern!(b"RIFF\x00\x00\x00\x00AVI ", b"\xff\xff\xff\xff\x00\x00\x00\x00\xff\xff\xff\xff", false, "video/avi"),
+    pattern!(b"RIFF\x00\x00\x00\x00WAVE", b"\xff\xff\xff\xff\x00\x00\x00\x00\xff\xff\xff\xff", false, "audio/wave")
+];
+
+/// Archive type pattern matching algorithm, section 6.4
+const ARCHIVE_PATTERNS: [Pattern; 3] = [
+    pattern!(b"\x1f\x8b\x08", b"\xff\xff\xff", false, "application/x-gzip"),
+    pattern!(b"PK\x03\x04", b"\xff\xff\xff\xff", false, "application/zip"),
+    pattern!(b"Rar \x1a\x07\x00", b"\xff\xff\xff\xff\xff\xff\xff", false, "application/x-rar-compressed")
+];
+
+/// Whitespace bytes, as defined by the standard
+fn is_whitespace(c: u8) -> bool {
+    [0x09, 0x0a, 0x0c, 0x0d, 0x20].contains(&c)
+}
+
+/// Bytes that never appear in text, as defined by the standard
+fn is_binary_data(c: u8) -> bool {
+    c <= 0x08 || c == 0x0b || (0x0e..=0x1a).contains(&c) || (0x1c..=0x1f).contains(&c)
+}
+
+/// Pattern matching algorithm, section 4.6
+fn pattern_matches(b: &[u8], p: &Pattern) -> bool {
+    let start = if p.ignored {
+        b.iter().position(|&x| !is_whitespace(x)).unwrap_or(b.len())
+    } else {
+        0
+    };
+    let b = &b[start..];
+    b.len() >= p.pattern.len() &&
+    p.pattern.iter().zip(p.mask.iter()).zip(b.iter())
+        .all(|((&pattern, &mask), &x)| x & mask == pattern)
+}
+
+fn match_table(b: &[u8], table: &[Pattern]) -> Option<&'static str> {
+    table.iter().find(|x| pattern_matches(b, x)).map(|x| x.mime)
+}
+
+/// Matches the HTML tags, ignoring case and leading whitespace. Each tag
+/// must be followed by a space or `>`.
+fn is_html(b: &[u8]) -> bool {
+    let start = b.iter().position(|&x| !is_whitespace(x)).unwrap_or(b.len());
+    let b = &b[start..];
+    HTML_TAGS.iter().any(|tag| {
+        b.len() > tag.len() &&
+        b[..tag.len()].eq_ignore_ascii_case(tag) &&
+        (b[tag.len()] == b' ' || b[tag.len()] == b'>')
+    })
+}
+
+fn read_u32_be(b: &[u8]) -> u32 {
+    (b[0] as u32) << 24 | (b[1] as u32) << 16 | (b[2] as u32) << 8 | b[3] as u32
+}
+
+/// Signature for MP4, section 6.2.1
+fn is_mp4(b: &[u8]) -> bool {
+    if b.len() < 12 {
+        return false;
+    }
+    let size = read_u32_be(b) as usize;
+    if b.len() < size || !size.is_multiple_of(4) || size < 12 || &b[4..8] != b"ftyp" {
+        return false;
+    }
+    if &b[8..11] == b"mp4" {
+        return true;
+    }
+    (16..size).step_by(4).any(|x| x + 3 <= size && &b[x..x + 3] == b"mp4")
+}
+
+/// Parses an EBML variable-length integer, returning its length in bytes
+fn vint_len(b: &[u8], i: usize) -> usize {
+    let mut mask = 0x80;
+    let mut n = 1;
+    while n < 8 && i < b.len() && b[i] & mask == 0 {
+        mask >>= 1;
+        n += 1;
+    }
+    n
+}
+
+/// Matches a padded sequence, section 6.2.2: the pattern after any number
+/// of 0x00 bytes from the offset
+fn matches_padded(b: &[u8], i: usize, pattern: &[u8]) -> bool {
+    let start = b[i..].iter().position(|&x| x != 0x00).map_or(b.len(), |x| i + x);
+    b[start..].starts_with(pattern)
+}
+
+/// Signature for WebM, section 6.2.2. A DocType element that isn't `webm`
+/// doesn't end the search.
+fn is_webm(b: &[u8]) -> bool {
+    if b.len() < 4 || b[..4] != [0x1a, 0x45, 0xdf, 0xa3] {
+        return false;
+    }
+    let mut i = 4;
+    while i + 1 < b.len() && i < 38 {
+        if b[i] == 0x42 && b[i + 1] == 0x82 {
+            i += 2;
+            if i >= b.len() {
+                break;
+            }
+            i += vint_len(b, i);
+            if i + 4 >= b.len() {
+                break;
+            }
+            if matches_padded(b, i, b"webm") {
+                return true;
+            }
+        }
+        i += 1;
+    }
+    false
+}
+
+/// MP3 frame header, section 6.2.3. Returns the length of the frame.
+fn mp3_frame_len(b: &[u8], i: usize) -> Option<usize> {
+    const MP25_RATES: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+    const MP3_RATES: [u32; 15] = [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+    const SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+    if i + 4 > b.len() || b[i] != 0xff || b[i + 1] & 0xe0 != 0xe0 {
+        return None;
+    }
+    let version = (b[i + 1] & 0x18) >> 3;
+    let layer = (b[i + 1] & 0x06) >> 1;
+    let bitrate_index = ((b[i + 2] & 0xf0) >> 4) as usize;
+    let rate_index = ((b[i + 2] & 0x0c) >> 2) as usize;
+    let pad = ((b[i + 2] & 0x02) >> 1) as u32;
+    if layer == 0 || bitrate_index == 15 || rate_index == 3 {
+        return None;
+    }
+
+    let bitrate = if version & 0x01 != 0 {
+        MP3_RATES[bitrate_index]
+    } else {
+        MP25_RATES[bitrate_index]
+    } * 1000;
+    let sample_rate = SAMPLE_RATES[rate_index];
+    let scale = if version == 1 {72} else {144};
+    let size = bitrate * scale / sample_rate + pad;
+    if size < 4 {
+        return None;
+    }
+    Some(size as usize)
+}
+
+/// Signature for MP3 without ID3, section 6.2.3: a frame header, followed
+/// by another frame header right where the first frame ends.
+fn is_mp3(b: &[u8]) -> bool {
+    match mp3_frame_len(b, 0) {
+        Some(x) => mp3_frame_len(b, x).is_some(),
+        None => false
+    }
+}
+
+fn match_image(b: &[u8]) -> Option<&'static str> {
+    match_table(b, &IMAGE_PATTERNS)
+}
+
+fn match_media(b: &[u8]) -> Option<&'static str> {
+    match_table(b, &MEDIA_PATTERNS).or_else(|| {
+        if is_mp4(b) {
+            Some("video/mp4")
+        } else if is_webm(b) {
+            Some("video/webm")
+        } else if is_mp3(b) {
+            Some("audio/mpeg")
+        } else {
+            None
+        }
+    })
+}
+
+/// Rules for identifying an unknown MIME type, section 7.1
+fn identify_unknown(b: &[u8], sniff_scriptable: bool) -> &'static str {
+    if sniff_scriptable {
+        if is_html(b) {
+            return "text/html";
+        }
+        if let Some(x) = match_table(b, &SCRIPTABLE_PATTERNS) {
+            return x;
+        }
+    }
+    if let Some(x) = match_table(b, &TEXT_PATTERNS) {
+        return x;
+    }
+    if let Some(x) = match_image(b).or_else(|| match_media(b)) {
+        return x;
+    }
+    if let Some(x) = match_table(b, &ARCHIVE_PATTERNS) {
+        return x;
+    }
+    if b.iter().any(|&x| is_binary_data(x)) {
+        "application/octet-stream"
+    } else {
+        "text/plain"
+    }
+}
+
+/// Rules for distinguishing if a resource is text or binary, section 7.2
+fn text_or_binary(b: &[u8]) -> &'static str {
+    if b.starts_with(b"\xfe\xff") || b.starts_with(b"\xff\xfe") || b.starts_with(b"\xef\xbb\xbf") {
+        return "text/plain";
+    }
+    if !b.iter().any(|&x| is_binary_data(x)) {
+        return "text/plain";
+    }
+    // Scriptable types are never sniffed here
+    match identify_unknown(b, false) {
+        "text/plain" => "application/octet-stream",
+        x => x
+    }
+}
+
+/// Rules for distinguishing if a resource is a feed or HTML, section 7.3
+fn feed_or_html(b: &[u8]) -> Option<&'static str> {
+    let mut i = if b.starts_with(b"\xef\xbb\xbf") {3} else {0};
+
+    loop {
+        while i < b.len() && is_whitespace(b[i]) {
+            i += 1;
+        }
+        if i >= b.len() || b[i] != b'<' {
+            return None;
+        }
+        i += 1;
+
+        let rest = &b[i..];
+        let skip_to = |end: &[u8]| rest.windows(end.len())
+            .position(|x| x == end)
+            .map(|x| x + end.len());
+        let skipped = if rest.starts_with(b"!--") {
+            skip_to(b"-->")
+        } else if rest.starts_with(b"!") {
+            skip_to(b">")
+        } else if rest.starts_with(b"?") {
+            skip_to(b"?>")
+        } else if rest.starts_with(b"rss") {
+            return Some("application/rss+xml");
+        } else if rest.starts_with(b"feed") {
+            return Some("application/atom+xml");
+        } else if rest.starts_with(b"rdf:RDF") {
+            let contains = |x: &[u8]| rest.windows(x.len()).any(|y| y == x);
+            if contains(b"http://purl.org/rss/1.0/") &&
+                contains(b"http://www.w3.org/1999/02/22-rdf-syntax-ns#")
+            {
+                return Some("application/rss+xml");
+            }
+            return None;
+        } else {
+            return None;
+        };
+
+        match skipped {
+            Some(x) => i += x,
+            None => return None
+        }
+    }
+}
+
+/// The essence of a MIME type: its type and subtype, in lowercase
+fn essence(mime: &str) -> String {
+    mime.split(';').next().unwrap_or("").trim().to_lowercase()
+}
+
+fn is_xml(essence: &str) -> bool {
+    essence.ends_with("+xml") || essence == "text/xml" || essence == "application/xml"
+}
+
+/// Determines the computed MIME type of a resource, section 7.1
+///
+/// `supplied` is the type from the Content-Type header, if any, and
+/// `no_sniff` is set if the resource had `X-Content-Type-Options: nosniff`.
+pub fn sniff(b: &[u8], supplied: Option<&str>, no_sniff: bool) -> String {
+    let b = &b[..std::cmp::min(b.len(), RESOURCE_HEADER_LEN)];
+
+    let supplied = match supplied {
+        Some(x) => x,
+        None => return identify_unknown(b, !no_sniff).to_string()
+    };
+    let essence = essence(supplied);
+    if ["unknown/unknown", "application/unknown", "*/*"].contains(&essence.as_str()) {
+        return identify_unknown(b, !no_sniff).to_string();
+    }
+    if no_sniff {
+        return supplied.to_string();
+    }
+
+    // Servers that label everything as text/plain
+    const APACHE_BUG: [&str; 4] = [
+        "text/plain", "text/plain; charset=ISO-8859-1",
+        "text/plain; charset=iso-8859-1", "text/plain; charset=UTF-8"
+    ];
+    if APACHE_BUG.contains(&supplied) {
+        return text_or_binary(b).to_string();
+    }
+
+    if is_xml(&essence) {
+        return supplied.to_string();
+    }
+    if essence == "text/html" {
+        return feed_or_html(b).unwrap_or(supplied).to_string();
+    }
+    if essence.starts_with("image/") {
+        if let Some(x) = match_image(b) {
+            return x.to_string();
+        }
+    }
+    if essence.starts_with("audio/") || essence.starts_with("video/") || essence == "application/ogg" {
+        if let Some(x) = match_media(b) {
+            return x.to_string();
+        }
+    }
+    supplied.to_string()
+}
@@ -0,0 +1,149 @@
+//! Handles Windows-specific file formats
+
+/// Dummy cache type
+pub type Cache = bool;
+
+pub mod init {
+
+    use FnvHashMap;
+    use MIME;
+    use std::prelude::v1::*;
+
+    pub fn get_supported() -> Vec<MIME> {
+        vec![
+            MIME::from("application/x-ms-shortcut"),
+            MIME::from("application/x-mswinurl"),
+            MIME::from("application/vnd.ms-cab-compressed"),
+            MIME::from("application/x-ms-registry-hive")
+        ]
+    }
+
+    /// Returns Vec of parent->child relations
+    pub fn get_subclasses() -> Vec<(MIME, MIME)> {
+        vec![
+            (MIME::from("application/octet-stream"), MIME::from("application/x-ms-shortcut")),
+            (MIME::from("text/plain"), MIME::from("application/x-mswinurl")),
+            (MIME::from("application/octet-stream"), MIME::from("application/vnd.ms-cab-compressed")),
+            (MIME::from("application/octet-stream"), MIME::from("application/x-ms-registry-hive"))
+        ]
+    }
+
+    pub fn get_aliaslist() -> FnvHashMap<MIME, MIME> {
+        FnvHashMap::default()
+    }
+
+}
+
+pub mod check {
+
+    use std::prelude::v1::*;
+    #[cfg(feature="fs")]
+    use std::path::Path;
+    use super::super::CacheItem;
+    #[cfg(feature="fs")]
+    use super::super::slurp_to_cache;
+
+    /// Shell Link header: the header size (0x4C), then the
+    /// LinkCLSID {00021401-0000-0000-C000-000000000046} ([MS-SHLLINK] 2.1)
+    const SHELL_LINK_HEADER: &[u8] = &[
+        0x4c, 0x00, 0x00, 0x00,
+        0x01, 0x14, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46
+    ];
+
+    /// Internet shortcuts are INI files. The first section is usually
+    /// `[InternetShortcut]`, but Explorer may write a property store
+    /// section before it. Old-style files use `[DEFAULT]` and `BASEURL=`.
+    fn is_winurl(b: &[u8]) -> bool {
+        let text = String::from_utf8_lossy(b);
+        let mut lines = text.lines()
+            .map(|x| x.trim().to_lowercase())
+            .filter(|x| !x.is_empty());
+
+        let first = match lines.next() {
+            Some(x) => x,
+            None => return false
+        };
+        if !first.starts_with('[') || !first.ends_with(']') {
+            return false;
+        }
+
+        match first.as_str() {
+            "[internetshortcut]" => true,
+            "[default]" => lines.next().is_some_and(|x| x.starts_with("baseurl=")),
+            _ => lines.any(|x| x == "[internetshortcut]")
+        }
+    }
+
+    /// Offset of the first hive bin, right after the 4096 byte base block
+    const HBIN_OFFSET: usize = 0x1000;
+
+    fn read_u32_le(b: &[u8]) -> u32 {
+        b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24
+    }
+
+    /// Cabinet header ([MS-CAB] 2.1): `MSCF`, then reserved fields that
+    /// must be zero, and format version 1.3.
+    fn is_cab(b: &[u8]) -> bool {
+        if b.len() < 36 || !b.starts_with(b"MSCF\0\0\0\0") {
+            return false;
+        }
+        let size = read_u32_le(&b[8..12]);
+        let files_offset = read_u32_le(&b[16..20]);
+        read_u32_le(&b[12..16]) == 0 &&
+        read_u32_le(&b[20..24]) == 0 &&
+        b[24] == 3 && b[25] == 1 &&
+        files_offset >= 36 && files_offset < size
+    }
+
+    /// Registry hive base block: `regf`, format version 1.x, a primary file
+    /// and the XOR checksum of the first 508 bytes. If the buffer reaches
+    /// that far, the first hive bin must follow the base block.
+    fn is_regf(b: &[u8]) -> bool {
+        if b.len() < 0x200 || !b.starts_with(b"regf") {
+            return false;
+        }
+        let checksum = (0..0x1FC).step_by(4)
+            .fold(0u32, |acc, x| acc ^ read_u32_le(&b[x..x + 4]));
+        let header = read_u32_le(&b[0x14..0x18]) == 1 &&
+            read_u32_le(&b[0x1C..0x20]) == 0 &&
+            read_u32_le(&b[0x20..0x24]) == 1 &&
+            checksum == read_u32_le(&b[0x1FC..0x200]);
+
+        if !header {
+            return false;
+        }
+        if b.len() < HBIN_OFFSET + 8 {
+            return true;
+        }
+        // The hbin header records its own offset from the first bin
+        b[HBIN_OFFSET..].starts_with(b"hbin") &&
+        read_u32_le(&b[HBIN_OFFSET + 4..HBIN_OFFSET + 8]) == 0
+    }
+
+    #[allow(unused_variables)]
+    pub fn from_u8(
+        b: &[u8], mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        match mimetype {
+            "application/x-ms-shortcut" => b.starts_with(SHELL_LINK_HEADER),
+            "application/x-mswinurl" => is_winurl(b),
+            "application/vnd.ms-cab-compressed" => is_cab(b),
+            "application/x-ms-registry-hive" => is_regf(b),
+            _ => false
+        }
+    }
+
+    #[cfg(feature="fs")]
+    #[allow(unused_variables)]
+    pub fn from_filepath(
+        filepath: &Path, mimetype: &str, cache: &CacheItem, filecache: &CacheItem
+    ) -> bool {
+        // Enough to reach the first hive bin of a registry hive
+        let b = match slurp_to_cache(filepath, filecache, HBIN_OFFSET + 8) {
+            Ok(x) => x,
+            Err(_) => return false
+        };
+        from_u8(b.as_slice(), mimetype, cache, filecache)
+    }
+}
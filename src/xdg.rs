@@ -0,0 +1,361 @@
+//! Gives the same answers as GIO and xdgmime, for programs that sit next to
+//! GTK and need to agree with it about what a file is.
+//!
+//! The rest of the crate walks the type graph with every checker, and never
+//! looks at file names. These follow the steps `g_content_type_guess` and
+//! `gio info` take instead: file name patterns from the system database
+//! first, then the single highest-priority magic match, with its fallbacks
+//! to `text/plain` and `application/octet-stream`. Only the system magic
+//! rules are used, and the file name patterns are only in the system
+//! database, so there's no embedded fallback for them.
+//!
+//! Requires the `fs` and `checker-fdo-magic` features.
+//!
+//! # Examples
+//! ```rust
+//! use tree_magic::xdg;
+//!
+//! // A conflict-free file name is taken at its word
+//! assert_eq!(xdg::guess(Some("notes.png"), Some(b"hello\n")), ("image/png".into(), false));
+//! // Without one, the content decides
+//! assert_eq!(xdg::guess(Some("notes"), Some(b"hello\n")), ("text/plain".into(), false));
+//! ```
+use std::prelude::v1::*;
+use std::io::Read;
+use std::path::Path;
+use FnvHashMap;
+use Mime;
+use MIME;
+
+/// What the magic lookup gives when nothing matches
+const UNKNOWN: &str = "application/octet-stream";
+
+/// Magic priority at which a match overrides conflicting file names
+const OVERRIDE_PRIORITY: u32 = 80;
+
+/// Most GIO reads from a file to sniff it
+const SNIFF_LEN: usize = 4096;
+
+/// How much of the data GIO looks at to decide it's text
+const TEXT_LEN: usize = 128;
+
+/// A file name pattern from `globs2`
+struct Glob {
+    weight: u32,
+    mimetype: MIME,
+    /// Lowercased, unless `cs`
+    pattern: String,
+    cs: bool
+}
+
+impl Glob {
+    /// No wildcards, so only matches a name exactly
+    fn is_literal(&self) -> bool {
+        !self.pattern.contains(['*', '?', '['])
+    }
+
+    /// The part after the `*`, for patterns that are only a suffix
+    fn suffix(&self) -> Option<&str> {
+        let rest = self.pattern.strip_prefix('*')?;
+        match rest.contains(['*', '?', '[']) {
+            true => None,
+            false => Some(rest)
+        }
+    }
+}
+
+lazy_static! {
+    static ref GLOBS: Vec<Glob> = {
+        let mut out: Vec<Glob> = Vec::new();
+        for (weight, mimetype, pattern, cs) in ::fdo_magic::sys::init::get_weighted_globs() {
+            // Case-sensitive patterns are written again without the flag,
+            // for older readers, and xdgmime keeps only the first
+            if pattern == "__NOGLOBS__" || out.iter().any(|x| x.mimetype == mimetype && x.pattern == pattern) {
+                continue;
+            }
+            out.push(Glob {
+                weight,
+                mimetype,
+                pattern: if cs { pattern } else { pattern.to_ascii_lowercase() },
+                cs
+            });
+        }
+        out
+    };
+
+    /// Parents of each type, from the system database's `subclasses`
+    static ref PARENTS: FnvHashMap<MIME, Vec<MIME>> = {
+        let mut out = FnvHashMap::<MIME, Vec<MIME>>::default();
+        for (parent, child) in ::fdo_magic::sys::init::get_subclasses() {
+            out.entry(child).or_default().push(parent);
+        }
+        out
+    };
+}
+
+/// Matches a name against a pattern the way `fnmatch` does with no flags:
+/// `*`, `?`, and bracket expressions with ranges, negated by `!` or `^`
+fn fnmatch(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((&'*', rest)) => (0..=name.len()).any(|i| fnmatch(rest, &name[i..])),
+        Some((&'?', rest)) => !name.is_empty() && fnmatch(rest, &name[1..]),
+        Some((&'[', rest)) => {
+            let (&c, name_rest) = match name.split_first() {
+                Some(x) => x,
+                None => return false
+            };
+            let negated = matches!(rest.first(), Some(&'!') | Some(&'^'));
+            let set = if negated { &rest[1..] } else { rest };
+            // A `]` straight after the opening bracket is part of the set
+            let end = match set.iter().skip(1).position(|&x| x == ']') {
+                Some(x) => x + 1,
+                None => return c == '[' && fnmatch(rest, name_rest)
+            };
+            let mut found = false;
+            let mut i = 0;
+            while i < end {
+                if i + 2 < end && set[i + 1] == '-' {
+                    found |= set[i] <= c && c <= set[i + 2];
+                    i += 3;
+                } else {
+                    found |= set[i] == c;
+                    i += 1;
+                }
+            }
+            found != negated && fnmatch(&set[end + 1..], name_rest)
+        },
+        Some((&'\\', rest)) if !rest.is_empty() => {
+            name.first() == Some(&rest[0]) && fnmatch(&rest[1..], &name[1..])
+        },
+        Some((&x, rest)) => name.first() == Some(&x) && fnmatch(rest, &name[1..])
+    }
+}
+
+/// Types matching a file name, the way xdgmime's
+/// `xdg_mime_get_mime_types_from_file_name` finds them.
+///
+/// An exact name wins outright. Otherwise only the longest matching suffix
+/// counts, like `*.tar.gz` over `*.gz`, and other patterns are only tried if
+/// that leaves fewer than two types. Patterns are case-insensitive unless
+/// the database says otherwise. Highest weight first.
+///
+/// # Examples
+/// ```rust
+/// let result = tree_magic::xdg::from_filename("photo.PNG");
+/// assert_eq!(result, ["image/png"]);
+/// ```
+pub fn from_filename(filename: &str) -> Vec<Mime> {
+    let lower = filename.to_ascii_lowercase();
+    let name = |x: &Glob| if x.cs { filename } else { &lower };
+
+    if let Some(x) = GLOBS.iter().find(|x| x.is_literal() && x.pattern == name(x)) {
+        return vec![x.mimetype.clone()];
+    }
+
+    let mut found: Vec<&Glob> = Vec::new();
+    for cs in [false, true] {
+        if found.len() >= 2 {
+            break;
+        }
+        let suffixes: Vec<(&Glob, usize)> = GLOBS.iter()
+            .filter(|x| x.cs == cs)
+            .filter_map(|x| x.suffix().filter(|y| name(x).ends_with(y)).map(|y| (x, y.len())))
+            .collect();
+        let longest = suffixes.iter().map(|x| x.1).max().unwrap_or(0);
+        found.extend(suffixes.into_iter().filter(|x| x.1 == longest).map(|x| x.0));
+    }
+    for cs in [false, true] {
+        if found.len() >= 2 {
+            break;
+        }
+        let chars: Vec<char> = if cs { filename } else { &lower }.chars().collect();
+        found.extend(GLOBS.iter()
+            .filter(|x| x.cs == cs && !x.is_literal() && x.suffix().is_none())
+            .filter(|x| fnmatch(&x.pattern.chars().collect::<Vec<_>>(), &chars)));
+    }
+
+    // Each type once, at its best weight
+    let mut weighted: Vec<(u32, &MIME)> = Vec::new();
+    for x in found {
+        match weighted.iter_mut().find(|y| *y.1 == x.mimetype) {
+            Some(y) => y.0 = std::cmp::max(y.0, x.weight),
+            None => weighted.push((x.weight, &x.mimetype))
+        }
+    }
+    weighted.sort_by_key(|x| std::cmp::Reverse(x.0));
+    weighted.into_iter().map(|x| x.1.clone()).collect()
+}
+
+/// The magic lookup, as xdgmime's `xdg_mime_get_mime_type_for_data` does
+/// it, with the priority it matched at
+fn sniff(bytes: &[u8]) -> (MIME, u32) {
+    if bytes.is_empty() {
+        return (MIME::from("application/x-zerosize"), 100);
+    }
+    // The database is in order of priority, then type, and the first
+    // match is taken
+    let key = |x: &MIME| x.split_once('/').map(|y| (y.0.to_string(), y.1.to_string()));
+    ::fdo_magic::sys::matches(bytes)
+        .into_iter()
+        .min_by(|a, b| b.1.cmp(&a.1).then_with(|| key(&a.0).cmp(&key(&b.0))))
+        .unwrap_or((MIME::from(UNKNOWN), 0))
+}
+
+/// Gets the type of a byte stream from magic alone, as xdgmime does.
+///
+/// That's the highest-priority match in the system database, without
+/// walking down to subtypes, or `application/octet-stream` if nothing
+/// matches. Empty input is `application/x-zerosize`.
+///
+/// # Examples
+/// ```rust
+/// assert_eq!(tree_magic::xdg::from_u8(b"\x89PNG\r\n\x1a\n"), "image/png");
+/// // No fallback to text/plain here
+/// assert_eq!(tree_magic::xdg::from_u8(b"hello\n"), "application/octet-stream");
+/// ```
+pub fn from_u8(bytes: &[u8]) -> Mime {
+    sniff(bytes).0
+}
+
+/// Whether `mimetype` is `base` or under it, as xdgmime's
+/// `xdg_mime_mime_type_subclass` sees it: through aliases, `type/*`
+/// wildcards, every `text/*` under `text/plain`, and everything but
+/// `inode/*` under `application/octet-stream`
+fn is_subclass(mimetype: &str, base: &str, depth: u32) -> bool {
+    let mimetype = ::get_alias(mimetype);
+    let base = ::get_alias(base);
+    if mimetype == base {
+        return true;
+    }
+    if let Some(media) = base.strip_suffix("/*") {
+        if mimetype.split('/').next() == Some(media) {
+            return true;
+        }
+    }
+    if base == "text/plain" && mimetype.starts_with("text/") {
+        return true;
+    }
+    if base == UNKNOWN && !mimetype.starts_with("inode/") {
+        return true;
+    }
+    // Broken databases can have cycles
+    depth < 32 && PARENTS.get(mimetype)
+        .is_some_and(|x| x.iter().any(|y| is_subclass(y, base, depth + 1)))
+}
+
+/// Whether GIO would call data text: no control characters but whitespace
+/// and backspace near the start
+fn looks_like_text(bytes: &[u8]) -> bool {
+    bytes.iter().take(TEXT_LEN).all(|&x| x >= 0x20 || b"\t\n\x0b\x0c\r\x08".contains(&x))
+}
+
+/// Guesses a type from a file name, its contents, or both, as GIO's
+/// `g_content_type_guess` does. Also says whether the guess is uncertain.
+///
+/// A file name that matches exactly one type settles it. Otherwise the
+/// content is sniffed with `from_u8`, and unrecognized text counts as
+/// `text/plain`. When the name matched several types, the sniffed type
+/// picks between them if it matched at priority 80 or more, or if one of
+/// them is under it; failing that, the first is taken, uncertainly.
+///
+/// A name ending in `/` is a directory. With a file name, sniffed desktop
+/// entries count as `text/plain`, so they can't pass for something else.
+///
+/// # Examples
+/// ```rust
+/// use tree_magic::xdg::guess;
+///
+/// assert_eq!(guess(Some("photo"), Some(b"\x89PNG\r\n\x1a\n")), ("image/png".into(), false));
+/// assert_eq!(guess(Some("photo"), None), ("application/octet-stream".into(), true));
+/// assert_eq!(guess(Some("photos/"), None), ("inode/directory".into(), false));
+/// ```
+pub fn guess(filename: Option<&str>, bytes: Option<&[u8]>) -> (Mime, bool) {
+    let names = match filename {
+        Some(x) if x.ends_with('/') => return (Mime::from("inode/directory"), false),
+        Some(x) => from_filename(x.rsplit('/').next().unwrap_or(x)),
+        None => Vec::new()
+    };
+    if names.len() == 1 {
+        return (names[0].clone(), false);
+    }
+
+    let mut sniffed = None;
+    if let Some(bytes) = bytes {
+        let (mut mimetype, priority) = sniff(bytes);
+        if mimetype == UNKNOWN && looks_like_text(bytes) {
+            mimetype = MIME::from("text/plain");
+        }
+        if filename.is_some() && mimetype == "application/x-desktop" {
+            mimetype = MIME::from("text/plain");
+        }
+        sniffed = Some((mimetype, priority)).filter(|x| x.0 != UNKNOWN);
+    }
+
+    if names.is_empty() {
+        return match sniffed {
+            Some(x) => (x.0, false),
+            None => (Mime::from(UNKNOWN), true)
+        };
+    }
+    let picked = sniffed.and_then(|(mimetype, priority)| match priority >= OVERRIDE_PRIORITY {
+        true => Some(mimetype),
+        false => names.iter().find(|x| is_subclass(x, &mimetype, 0)).cloned()
+    });
+    match picked {
+        Some(x) => (x, false),
+        None => (names[0].clone(), true)
+    }
+}
+
+/// Gets the type of a file as `gio info` reports it.
+///
+/// Directories, devices, FIFOs and sockets get their `inode/*` types, a
+/// broken link is `inode/symlink`, and an empty file is `text/plain`.
+/// Otherwise the file name is tried with `guess`, and only if that's
+/// uncertain is the start of the file read, up to 4096 bytes.
+///
+/// Returns None if the file doesn't exist.
+///
+/// # Examples
+/// ```rust
+/// use std::path::Path;
+///
+/// let result = tree_magic::xdg::from_filepath(Path::new("tests/image/gif"));
+/// assert_eq!(result, Some("image/gif".into()));
+/// ```
+pub fn from_filepath(filepath: &Path) -> Option<Mime> {
+    let meta = match std::fs::metadata(filepath) {
+        Ok(x) => x,
+        Err(_) => return match std::fs::symlink_metadata(filepath) {
+            Ok(_) => Some(Mime::from("inode/symlink")),
+            Err(_) => None
+        }
+    };
+    if meta.is_dir() {
+        return Some(Mime::from("inode/directory"));
+    }
+    if let Some(x) = ::basetype::special_type(&meta) {
+        return Some(Mime::from(x));
+    }
+    // Not read, since files in /proc and /sys look empty
+    if meta.is_file() && meta.len() == 0 {
+        return Some(Mime::from("text/plain"));
+    }
+
+    let filename = filepath.file_name().map(|x| x.to_string_lossy());
+    let (mimetype, uncertain) = guess(filename.as_deref(), None);
+    if !uncertain {
+        return Some(mimetype);
+    }
+    let len = match ::fdo_magic::sys::scan_extent() {
+        0 => SNIFF_LEN,
+        x => std::cmp::min(x, SNIFF_LEN)
+    };
+    let mut bytes = Vec::with_capacity(len);
+    let read = ::open_nonblocking(filepath).and_then(|x| x.take(len as u64).read_to_end(&mut bytes));
+    match read {
+        Ok(_) => Some(guess(filename.as_deref(), Some(&bytes)).0),
+        Err(_) => Some(mimetype)
+    }
+}
@@ -0,0 +1,57 @@
+#![cfg(feature="cache")]
+// The database is loaded once per process, so reading the cache back is
+// checked by running this binary again with the cache already in place.
+mod cache {
+
+    extern crate tree_magic;
+
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+    use std::process::Command;
+
+    /// Runs one of these tests in a fresh process, with the given cache dir
+    fn run_child(test: &str, dir: &Path) {
+        let status = Command::new(env::current_exe().unwrap())
+            .args(["--exact", test, "--test-threads=1"])
+            .env("XDG_CACHE_HOME", dir)
+            .env("TREE_MAGIC_CACHE_CHILD", "1")
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[test]
+    fn detect() {
+        if env::var_os("TREE_MAGIC_CACHE_CHILD").is_none() {
+            return;
+        }
+        assert_eq!(tree_magic::from_u8(include_bytes!("image/gif")), "image/gif");
+    }
+
+    #[test]
+    fn written_and_reused() {
+        if !Path::new("/usr/share/mime/magic").exists() {
+            return;
+        }
+        let dir = env::temp_dir().join(format!("tree_magic-cache-{}", std::process::id()));
+        let cache = dir.join("tree_magic").join("magic.cache");
+
+        // First run parses the magic file and writes the cache
+        run_child("cache::detect", &dir);
+        let written = fs::read(&cache).unwrap();
+        assert!(!written.is_empty());
+
+        // Second run reads it back
+        run_child("cache::detect", &dir);
+        assert_eq!(fs::read(&cache).unwrap(), written);
+
+        // A damaged cache is ignored, and replaced
+        fs::write(&cache, &written[..written.len() / 2]).unwrap();
+        run_child("cache::detect", &dir);
+        assert_eq!(fs::read(&cache).unwrap(), written);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+}
@@ -0,0 +1,55 @@
+#![cfg(all(feature="fs", feature="checker-fdo-magic"))]
+mod conformance {
+
+    extern crate tree_magic;
+
+    use self::tree_magic::conformance::{parse_list, run, run_list, Check, Detector, Outcome};
+    use std::path::Path;
+
+    #[test]
+    fn parse() {
+        let cases = parse_list("# file type flags\n\ntest.gif image/gif\ntest.c text/x-csrc oxx\n").unwrap();
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].file, "test.gif");
+        assert_eq!(cases[0].expected, "image/gif");
+        assert!(!cases[0].xfail(Check::Name) && !cases[0].xfail(Check::Data) && !cases[0].xfail(Check::File));
+        assert!(!cases[1].xfail(Check::Name) && cases[1].xfail(Check::Data) && cases[1].xfail(Check::File));
+
+        let err = parse_list("test.gif image/gif\ntest.png\n").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn outcomes() {
+        // The samples have no extensions, so checks by name fail
+        let cases = parse_list("image/gif image/gif xoo\nimage/png image/png\ntext/plain image/gif xxo\nmissing image/gif\n").unwrap();
+        let results = run(Path::new("tests"), &cases, Detector::TreeMagic);
+        let outcomes: Vec<Outcome> = results.iter().map(|x| x.outcome).collect();
+        assert_eq!(outcomes, [
+            Outcome::ExpectedFail, Outcome::Pass, Outcome::Pass,
+            Outcome::Fail, Outcome::Pass, Outcome::Pass,
+            Outcome::ExpectedFail, Outcome::ExpectedFail, Outcome::Fail,
+            Outcome::Fail, Outcome::Fail, Outcome::Fail
+        ]);
+        assert_eq!(results[7].check, Check::Data);
+        assert_eq!(results[7].found, Some("text/plain".into()));
+        assert_eq!(results[10].found, None);
+    }
+
+    #[test]
+    fn list() {
+        let dir = std::env::temp_dir().join(format!("tree_magic-conformance-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("test.png"), include_bytes!("image/png")).unwrap();
+        std::fs::write(dir.join("test.bmp"), include_bytes!("image/bmp")).unwrap();
+        // Aliases count as the same type
+        std::fs::write(dir.join("list"), "test.png image/png\ntest.bmp image/x-bmp\n").unwrap();
+        for detector in [Detector::TreeMagic, Detector::Xdg] {
+            let results = run_list(&dir.join("list"), detector).unwrap();
+            assert!(results.iter().all(|x| x.outcome == Outcome::Pass), "{:?}", results);
+        }
+        assert!(run_list(&dir.join("nonexistent"), Detector::Xdg).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,232 @@
+mod database {
+
+    extern crate tree_magic;
+
+    #[test]
+    fn loaded() {
+        assert_eq!(tree_magic::database(), Ok(()));
+        assert!(tree_magic::TypeId::of("application/octet-stream").is_some());
+    }
+
+    #[test]
+    fn error_message() {
+        let e = tree_magic::InitError::Magic("expected '>' at byte 12".to_string());
+        assert_eq!(e.to_string(), "can't parse magic file: expected '>' at byte 12");
+    }
+
+    #[test]
+    fn family_predicates() {
+        assert!(tree_magic::is_image("image/svg+xml"));
+        assert!(tree_magic::is_image("image/x-not-loaded"));
+        assert!(!tree_magic::is_image("application/xml"));
+        assert!(tree_magic::is_audio("audio/x-vorbis+ogg"));
+        assert!(!tree_magic::is_audio("application/ogg"));
+        assert!(tree_magic::is_video("video/x-theora+ogg"));
+        assert!(tree_magic::is_archive("application/x-zip-compressed"));
+        assert!(tree_magic::is_archive("application/x-tar"));
+        assert!(!tree_magic::is_archive("application/epub+zip"));
+        assert!(!tree_magic::is_archive("application/x-not-loaded"));
+        assert!(tree_magic::is_document("application/epub+zip"));
+        assert!(tree_magic::is_document("application/vnd.oasis.opendocument.text"));
+        assert!(!tree_magic::is_document("application/zip"));
+    }
+
+    #[test]
+    fn is_subtype_of() {
+        assert!(tree_magic::is_subtype_of("application/json", "text/plain"));
+        assert!(tree_magic::is_subtype_of("application/json", "application/octet-stream"));
+        assert!(tree_magic::is_subtype_of("application/x-zip-compressed", "application/zip"));
+        assert!(!tree_magic::is_subtype_of("text/plain", "application/json"));
+        assert!(!tree_magic::is_subtype_of("image/png", "nonexistent/type"));
+    }
+
+    #[test]
+    fn ancestors() {
+        let gif: Vec<_> = tree_magic::ancestors("image/gif").collect();
+        assert_eq!(gif, ["application/octet-stream", "all/allfiles", "all/all"]);
+        // Aliases are resolved, and the type itself isn't included
+        let zip: Vec<_> = tree_magic::ancestors("application/x-zip-compressed").collect();
+        assert_eq!(zip, tree_magic::ancestors("application/zip").collect::<Vec<_>>());
+        assert!(!zip.contains(&"application/zip".into()));
+        assert_eq!(tree_magic::ancestors("all/all").count(), 0);
+        assert_eq!(tree_magic::ancestors("nonexistent/type").count(), 0);
+    }
+
+    #[test]
+    fn descendants() {
+        let zip: Vec<_> = tree_magic::descendants("application/x-zip-compressed").collect();
+        assert!(zip.contains(&"application/epub+zip".into()));
+        assert!(!zip.contains(&"application/zip".into()));
+        // Every one is a subtype, once
+        let text: Vec<_> = tree_magic::descendants("text/plain").collect();
+        assert!(text.iter().all(|x| tree_magic::is_subtype_of(x, "text/plain")));
+        let mut unique = text.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), text.len());
+        assert_eq!(tree_magic::descendants("nonexistent/type").count(), 0);
+    }
+
+    #[test]
+    fn common_ancestor() {
+        assert_eq!(tree_magic::common_ancestor("image/png", "image/gif"), "application/octet-stream");
+        assert_eq!(tree_magic::common_ancestor("application/json", "text/plain"), "text/plain");
+        assert_eq!(tree_magic::common_ancestor("text/plain", "application/json"), "text/plain");
+        assert_eq!(tree_magic::common_ancestor("image/png", "image/png"), "image/png");
+        assert_eq!(tree_magic::common_ancestor("application/zip", "application/x-zip-compressed"), "application/zip");
+        assert_eq!(tree_magic::common_ancestor("inode/directory", "image/png"), "all/all");
+        assert_eq!(tree_magic::common_ancestor("nonexistent/type", "image/png"), "all/all");
+    }
+
+    #[test]
+    fn detection_depth() {
+        use self::tree_magic::Detection;
+
+        let root = Detection::new("all/all".into());
+        assert_eq!((root.depth, root.leaf), (0, false));
+        let json = Detection::new("application/json".into());
+        let text = Detection::new("text/plain".into());
+        assert!(json.depth > text.depth);
+        assert!(!text.leaf);
+        let unknown = Detection::new("nonexistent/type".into());
+        assert_eq!((unknown.depth, unknown.leaf), (0, true));
+    }
+
+    #[test]
+    fn supported_types() {
+        let types: Vec<_> = tree_magic::supported_types().collect();
+        assert_eq!(types.len(), tree_magic::TypeId::all().count());
+        assert!(types.windows(2).all(|x| x[0].mimetype < x[1].mimetype));
+
+        let find = |x: &str| types.iter().find(|y| y.mimetype == x).unwrap();
+        assert_eq!(find("image/png").checker, "fdo-magic");
+        assert!(find("image/png").magic);
+        assert_eq!(find("application/octet-stream").checker, "basetype");
+        assert!(!find("application/octet-stream").magic);
+        assert_eq!(find("application/pgp-keys").checker, "pgp");
+    }
+
+    #[test]
+    fn rules_for() {
+        let png = tree_magic::rules_for("image/png");
+        assert_eq!(png[0].indent, 0);
+        assert_eq!(png[0].offset, 0);
+        assert_eq!(&*png[0].value, b"\x89PNG");
+        assert_eq!(png[0].priority, 50);
+        // Aliases give the rules of their type
+        let ico = tree_magic::rules_for("image/vnd.microsoft.icon");
+        assert!(!ico.is_empty());
+        assert_eq!(tree_magic::rules_for("image/ico"), ico);
+        assert!(tree_magic::rules_for("application/octet-stream").is_empty());
+        assert!(tree_magic::rules_for("nonexistent/type").is_empty());
+    }
+
+    #[test]
+    fn coverage() {
+        use self::tree_magic::Coverage;
+
+        let coverage: Vec<_> = tree_magic::coverage().collect();
+        assert!(coverage.windows(2).all(|x| x[0].0 < x[1].0));
+        for x in tree_magic::TypeId::all() {
+            assert!(coverage.iter().any(|y| y.0 == x.name()), "{}", x);
+        }
+        let find = |x: &str| coverage.iter().find(|y| y.0 == x).unwrap().1;
+        assert_eq!(find("image/png"), Coverage::Content);
+        assert_eq!(find("application/pgp-keys"), Coverage::Content);
+        assert_eq!(find("text/plain"), Coverage::Content);
+        // Types outside the graph can only be known by their file names
+        assert!(coverage.iter()
+            .filter(|x| tree_magic::TypeId::of(&x.0).is_none())
+            .all(|x| x.1 == Coverage::GlobOnly));
+    }
+
+    #[test]
+    fn export_dot() {
+        let all = tree_magic::export_dot(None);
+        assert!(all.starts_with("digraph {\n"));
+        assert!(all.ends_with("}\n"));
+        let edges = all.lines().filter(|x| x.contains(" -> ")).count();
+        assert_eq!(edges, tree_magic::TypeId::all().map(|x| x.children().count()).sum());
+
+        let zip = tree_magic::export_dot(Some("application/zip"));
+        assert!(zip.contains("    \"application/zip\" -> \"application/epub+zip\";\n"));
+        assert!(!zip.contains("\"image/png\""));
+        assert_eq!(tree_magic::export_dot(Some("nonexistent/type")), "digraph {\n}\n");
+    }
+
+    #[test]
+    fn export_json() {
+        let json = tree_magic::export_json();
+        assert!(json.starts_with("{\n  \"types\": ["));
+        assert!(json.ends_with("}\n"));
+        for x in &["\"aliases\": {", "\"subclasses\": [", "\"magic\": {", "\"globs\": "] {
+            assert!(json.contains(x), "{}", x);
+        }
+        assert!(json.contains("\n    [\"application/zip\", \"application/epub+zip\"]"));
+        assert!(json.contains("\"image/png\": [\n      {\"indent\": 0, \"offset\": 0, \"range\": 0, \"value\": \"89504e47\", \"mask\": null, \"word_size\": 1, \"priority\": 50}"));
+        assert_eq!(json, tree_magic::export_json());
+    }
+
+    #[test]
+    fn validate() {
+        use self::tree_magic::Diagnostic;
+
+        for x in tree_magic::validate() {
+            match x {
+                Diagnostic::UnknownType { parent, child, missing } => {
+                    assert!(missing == parent || missing == child);
+                    assert_eq!(tree_magic::TypeId::of(&missing), None);
+                },
+                Diagnostic::DuplicateAlias { types, .. } => assert!(types.len() > 1),
+                x => panic!("{:?}", x)
+            }
+        }
+    }
+
+    #[test]
+    fn type_id() {
+        use self::tree_magic::TypeId;
+
+        let root = TypeId::root();
+        assert_eq!(root.name(), "all/all");
+        assert_eq!(root.parents().count(), 0);
+        assert!(TypeId::all().any(|x| x == root));
+
+        let icon = TypeId::of("image/vnd.microsoft.icon").unwrap();
+        assert_eq!(TypeId::of("image/ico"), Some(icon));
+        assert_eq!(icon.to_string(), "image/vnd.microsoft.icon");
+        assert!(icon.is_leaf());
+        assert_eq!(TypeId::of("nonexistent/type"), None);
+
+        for x in TypeId::all() {
+            assert!(x.children().all(|y| y.parents().any(|z| z == x)));
+        }
+        let octet = TypeId::of("application/octet-stream").unwrap();
+        assert_eq!(tree_magic::from_u8_under(octet, include_bytes!("image/png")), Some("image/png".into()));
+    }
+
+    #[test]
+    fn mime() {
+        use self::tree_magic::{Mime, ParseMimeError};
+
+        let svg = Mime::parse("image/svg+xml").unwrap();
+        assert_eq!((svg.media_type(), svg.subtype(), svg.suffix()), ("image", "svg", Some("xml")));
+        let ld = Mime::from("application/vnd.api+ld+json");
+        assert_eq!((ld.subtype(), ld.suffix()), ("vnd.api+ld", Some("json")));
+        assert_eq!(svg.clone(), "image/svg+xml");
+        assert_eq!(svg, Mime::from("image/svg+xml".to_string()));
+
+        assert_eq!(Mime::parse("image"), Err(ParseMimeError::MissingSlash));
+        assert_eq!(Mime::parse("image/"), Err(ParseMimeError::BadLength));
+        assert_eq!(Mime::parse("/svg"), Err(ParseMimeError::BadLength));
+        assert_eq!(Mime::parse("image/.svg"), Err(ParseMimeError::BadStart));
+        assert_eq!(Mime::parse("image/svg xml"), Err(ParseMimeError::BadChar(' ')));
+        assert_eq!(Mime::parse("image/svg/xml"), Err(ParseMimeError::BadChar('/')));
+        assert_eq!("text/plain".parse::<Mime>(), Ok(Mime::from("text/plain")));
+
+        // Everything the database hands out is well-formed
+        for x in tree_magic::TypeId::all() {
+            assert!(Mime::parse(x.name()).is_ok(), "{}", x);
+        }
+    }
+}
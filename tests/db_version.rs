@@ -0,0 +1,30 @@
+mod db_version {
+
+    extern crate tree_magic;
+
+    #[cfg(feature="checker-fdo-magic")]
+    #[test]
+    fn loaded() {
+        let db = tree_magic::db_version().unwrap();
+        assert_ne!(db.hash, 0);
+        // Loading twice gives the same answer
+        assert_eq!(tree_magic::db_version(), Some(db));
+    }
+
+    #[cfg(all(feature="checker-fdo-magic", feature="fs"))]
+    #[test]
+    fn system() {
+        let db = tree_magic::db_version().unwrap();
+        if let Ok(version) = std::fs::read_to_string("/usr/share/mime/version") {
+            assert!(!db.embedded);
+            assert_eq!(db.version.as_deref(), Some(version.trim()));
+        }
+    }
+
+    #[cfg(not(feature="checker-fdo-magic"))]
+    #[test]
+    fn none() {
+        assert_eq!(tree_magic::db_version(), None);
+    }
+
+}
@@ -0,0 +1,49 @@
+// Checks for builds that leave out checkers or MIME families. These only run
+// with non-default features, e.g.
+// `cargo test --no-default-features --features std,fs,checker-fdo-magic,family-image,family-archive`
+mod features {
+
+    extern crate tree_magic;
+
+    #[cfg(all(feature="checker-fdo-magic", feature="family-image"))]
+    #[test]
+    fn image_family_kept() {
+        assert_eq!(tree_magic::from_u8(include_bytes!("image/gif")), "image/gif");
+    }
+
+    #[cfg(all(feature="checker-fdo-magic", feature="family-archive"))]
+    #[test]
+    fn archive_family_kept() {
+        assert_eq!(tree_magic::from_u8(include_bytes!("application/zip")), "application/zip");
+    }
+
+    #[cfg(not(feature="family-image"))]
+    #[test]
+    fn image_family_dropped() {
+        assert!(tree_magic::TypeId::of("image/gif").is_none());
+        assert!(!tree_magic::match_u8("image/gif", include_bytes!("image/gif")));
+    }
+
+    #[cfg(not(feature="family-application"))]
+    #[test]
+    fn application_family_dropped() {
+        assert!(tree_magic::TypeId::of("application/pdf").is_none());
+        assert!(tree_magic::TypeId::of("application/octet-stream").is_some());
+    }
+
+    #[cfg(not(any(feature="checker-pgp", feature="checker-fdo-magic")))]
+    #[test]
+    fn pgp_checker_dropped() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/pgp-keys")),
+            "text/plain"
+        );
+    }
+
+    #[cfg(not(feature="checker-fdo-magic"))]
+    #[test]
+    fn fdo_magic_checker_dropped() {
+        assert_eq!(tree_magic::from_u8(include_bytes!("image/gif")), "application/octet-stream");
+    }
+
+}
@@ -0,0 +1,64 @@
+#![cfg(feature="ffi")]
+mod ffi {
+    extern crate tree_magic;
+    use std::ffi::{CStr, CString};
+    use std::ptr;
+    use self::tree_magic::ffi::*;
+
+    /// Takes ownership of a string returned by the library
+    fn take(s: *mut std::os::raw::c_char) -> Option<String> {
+        if s.is_null() {
+            return None;
+        }
+        let out = unsafe { CStr::from_ptr(s) }.to_str().unwrap().to_string();
+        unsafe { tree_magic_free(s) };
+        Some(out)
+    }
+
+    #[test]
+    fn from_u8() {
+        let b = include_bytes!("image/gif");
+        let out = take(unsafe { tree_magic_from_u8(b.as_ptr(), b.len()) });
+        assert_eq!(out, Some("image/gif".to_string()));
+    }
+
+    #[test]
+    fn from_u8_null() {
+        assert_eq!(take(unsafe { tree_magic_from_u8(ptr::null(), 4) }), None);
+        assert!(take(unsafe { tree_magic_from_u8(ptr::null(), 0) }).is_some());
+    }
+
+    #[cfg(feature="fs")]
+    #[test]
+    fn from_path() {
+        let path = CString::new("tests/image/png").unwrap();
+        let out = take(unsafe { tree_magic_from_path(path.as_ptr()) });
+        assert_eq!(out, Some("image/png".to_string()));
+        assert_eq!(take(unsafe { tree_magic_from_path(ptr::null()) }), None);
+    }
+
+    #[test]
+    fn matching() {
+        let b = include_bytes!("image/gif");
+        let gif = CString::new("image/gif").unwrap();
+        let png = CString::new("image/png").unwrap();
+        unsafe {
+            assert!(tree_magic_match(gif.as_ptr(), b.as_ptr(), b.len()));
+            assert!(!tree_magic_match(png.as_ptr(), b.as_ptr(), b.len()));
+            assert!(!tree_magic_match(ptr::null(), b.as_ptr(), b.len()));
+        }
+    }
+
+    #[cfg(feature="fs")]
+    #[test]
+    fn matching_path() {
+        let path = CString::new("tests/image/png").unwrap();
+        let png = CString::new("image/png").unwrap();
+        assert!(unsafe { tree_magic_match_path(png.as_ptr(), path.as_ptr()) });
+    }
+
+    #[test]
+    fn free_null() {
+        unsafe { tree_magic_free(ptr::null_mut()) };
+    }
+}
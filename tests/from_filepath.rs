@@ -1,3 +1,4 @@
+#![cfg(feature="fs")]
 mod from_filepath {
 
     extern crate tree_magic;
@@ -12,4 +13,249 @@ mod from_filepath {
         );
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn application_dicom() {
+        assert_eq!(
+            tree_magic::from_filepath(Path::new("tests/application/dicom")),
+            Some("application/dicom".into())
+        );
+        assert!(tree_magic::match_filepath("application/dicom", Path::new("tests/application/dicom")));
+    }
+
+    #[test]
+    fn application_parquet() {
+        assert!(tree_magic::match_filepath(
+            "application/vnd.apache.parquet",
+            Path::new("tests/application/vnd.apache.parquet")
+        ));
+    }
+
+    // The footer is past anything read from the start, so it's read from
+    // the end of the file
+    #[test]
+    fn application_parquet_footer() {
+        let path = std::env::temp_dir().join(format!("tree_magic-parquet-{}", std::process::id()));
+        let mut b = b"PAR1".to_vec();
+        b.resize(tree_magic::max_scan_extent() + 4096, 0);
+        b.extend_from_slice(b"PAR1");
+        std::fs::write(&path, &b).unwrap();
+
+        let result = tree_magic::from_filepath(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(result, Some("application/vnd.apache.parquet".into()));
+    }
+
+    #[test]
+    fn application_x_hdf5() {
+        assert_eq!(
+            tree_magic::from_filepath(Path::new("tests/application/x-hdf5")),
+            Some("application/x-hdf5".into())
+        );
+    }
+
+    #[test]
+    fn application_x_ms_registry_hive() {
+        assert!(tree_magic::match_filepath(
+            "application/x-ms-registry-hive",
+            Path::new("tests/application/x-ms-registry-hive")
+        ));
+    }
+
+    #[test]
+    fn application_x_snes_rom() {
+        assert!(tree_magic::match_filepath(
+            "application/x-snes-rom",
+            Path::new("tests/application/vnd.nintendo.snes.rom")
+        ));
+    }
+
+    #[test]
+    fn audio_flac_id3() {
+        assert!(tree_magic::match_filepath("audio/flac", Path::new("tests/audio/flac-id3")));
+    }
+
+    #[test]
+    fn read_limit() {
+        use self::tree_magic::DetectOptions;
+
+        let path = Path::new("tests/application/x-hdf5");
+        let with_limit = |x| tree_magic::from_filepath_with(path, &DetectOptions {
+            max_read_bytes: Some(x),
+            ..Default::default()
+        });
+        // The superblock is at 512, after a user block
+        assert_eq!(with_limit(1024), Some("application/x-hdf5".into()));
+        assert_ne!(with_limit(256), Some("application/x-hdf5".into()));
+        assert_eq!(
+            tree_magic::from_filepath_with(path, &DetectOptions::default()),
+            tree_magic::from_filepath(path)
+        );
+    }
+
+    #[test]
+    fn sampling() {
+        use self::tree_magic::{DetectOptions, Sampling};
+
+        let sampled = DetectOptions {
+            sampling: Some(Sampling { window: 4096, samples: 1 }),
+            ..Default::default()
+        };
+
+        // Text, but for some nulls halfway through
+        let path = std::env::temp_dir().join(format!("tree_magic-sampling-{}", std::process::id()));
+        let mut b = b"hello world\n".repeat(1 << 16);
+        let middle = b.len() / 2;
+        b[middle - 64..middle + 64].iter_mut().for_each(|x| *x = 0);
+        std::fs::write(&path, &b).unwrap();
+        let plain = tree_magic::from_filepath_with(&path, &DetectOptions::default());
+        let with_samples = tree_magic::from_filepath_with(&path, &sampled);
+
+        // Parquet's footer is still read
+        let mut b = b"PAR1".to_vec();
+        b.resize(1 << 20, 0);
+        b.extend_from_slice(b"PAR1");
+        std::fs::write(&path, &b).unwrap();
+        let parquet = tree_magic::from_filepath_with(&path, &sampled);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(plain, Some("text/plain".into()));
+        assert_eq!(with_samples, Some("application/octet-stream".into()));
+        assert_eq!(parquet, Some("application/vnd.apache.parquet".into()));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn fifo() {
+        use self::tree_magic::DetectOptions;
+
+        let path = std::env::temp_dir().join(format!("tree_magic-fifo-{}", std::process::id()));
+        let made = std::process::Command::new("mkfifo").arg(&path).status();
+        if !made.is_ok_and(|x| x.success()) {
+            return;
+        }
+        // None of these may wait for a writer
+        let found = tree_magic::from_filepath(&path);
+        let limited = tree_magic::from_filepath_with(&path, &DetectOptions {
+            max_read_bytes: Some(512),
+            ..Default::default()
+        });
+        let text = tree_magic::match_filepath("text/plain", &path);
+        let png = tree_magic::match_filepath("image/png", &path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(found, Some("inode/fifo".into()));
+        assert_eq!(limited, Some("inode/fifo".into()));
+        assert!(!text);
+        assert!(!png);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn socket() {
+        use std::os::unix::net::UnixListener;
+
+        let path = std::env::temp_dir().join(format!("tree_magic-socket-{}", std::process::id()));
+        let listener = UnixListener::bind(&path).unwrap();
+        let found = tree_magic::from_filepath(&path);
+        drop(listener);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(found, Some("inode/socket".into()));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn char_device() {
+        // Endless, so only a capped read of it finishes
+        let path = Path::new("/dev/zero");
+        assert_eq!(tree_magic::from_filepath(path), Some("inode/chardevice".into()));
+        assert!(tree_magic::match_filepath("inode/chardevice", path));
+        assert!(!tree_magic::match_filepath("image/png", path));
+        assert!(!tree_magic::match_filepath("application/octet-stream", path));
+    }
+
+    #[test]
+    #[cfg(all(feature="checker-archive", feature="checker-filesystem"))]
+    fn end_of_file() {
+        use self::tree_magic::DetectOptions;
+
+        // A ZIP archive after 1 MiB of something else, found by its end
+        let path = std::env::temp_dir().join(format!("tree_magic-end-{}", std::process::id()));
+        let mut b = vec![0; 1 << 20];
+        b.extend_from_slice(include_bytes!("application/zip"));
+        std::fs::write(&path, &b).unwrap();
+        let zip = tree_magic::from_filepath(&path);
+        let zip_limited = tree_magic::from_filepath_with(&path, &DetectOptions { max_read_bytes: Some(1 << 16), ..Default::default() });
+
+        // An Amiga floppy is known by its size
+        let mut b = vec![0; 901120];
+        b[..4].copy_from_slice(b"DOS\0");
+        std::fs::write(&path, &b).unwrap();
+        let adf = tree_magic::from_filepath(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(zip, Some("application/zip".into()));
+        // The end is past the read limit
+        assert_eq!(zip_limited, Some("application/octet-stream".into()));
+        assert_eq!(adf, Some("application/x-amiga-disk-format".into()));
+    }
+
+    #[test]
+    #[cfg(feature="checker-filesystem")]
+    fn from_device() {
+        use self::tree_magic::DeviceWindow;
+
+        // A disk image with an ext4 filesystem on a partition 1 MiB in
+        let path = std::env::temp_dir().join(format!("tree_magic-disk-{}", std::process::id()));
+        let mut b = include_bytes!("application/x-mbr-partition-table").to_vec();
+        b.resize(1 << 20, 0);
+        b.extend_from_slice(include_bytes!("application/x-ext4-filesystem"));
+        b.resize(2 << 20, 0);
+        std::fs::write(&path, &b).unwrap();
+
+        let disk = tree_magic::from_device(&path, DeviceWindow::default());
+        let partition = tree_magic::from_device(&path, DeviceWindow { offset: 1 << 20, ..Default::default() });
+        // The cap holds, however much is asked for
+        let huge = tree_magic::from_device(&path, DeviceWindow { offset: 1 << 20, len: usize::MAX });
+        let past_end = tree_magic::from_device(&path, DeviceWindow { offset: 4 << 20, ..Default::default() });
+        let as_file = tree_magic::from_filepath(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(disk, Some("application/x-mbr-partition-table".into()));
+        assert_eq!(partition, Some("application/x-ext4-filesystem".into()));
+        assert_eq!(huge, partition);
+        assert_eq!(past_end, Some(tree_magic::from_u8(&[])));
+        assert_eq!(as_file, disk);
+        assert_eq!(tree_magic::from_device(Path::new("this/file/does/not/exist"), DeviceWindow::default()), None);
+    }
+
+    #[test]
+    fn filter_mime() {
+        use self::tree_magic::iter::FilterMime;
+
+        let paths = ["tests/application/json", "tests/image/gif", "tests/text/plain", "nonexistent"];
+        let text: Vec<_> = paths.iter().filter_mime("text/plain").collect();
+        let plain: Vec<_> = paths.iter().filter_mime_exact("text/plain").collect();
+        let gif: Vec<_> = paths.iter().filter_mime_exact("image/gif").collect();
+        assert_eq!(text, vec![&"tests/application/json", &"tests/text/plain"]);
+        assert_eq!(plain, vec![&"tests/text/plain"]);
+        assert_eq!(gif, vec![&"tests/image/gif"]);
+    }
+
+    #[test]
+    fn detailed() {
+        let result = tree_magic::from_filepath_detailed(Path::new("tests/image/gif")).unwrap();
+        assert_eq!(result, tree_magic::Detection::new("image/gif".into()));
+        assert_eq!(result.depth, 3);
+        assert!(tree_magic::from_filepath_detailed(Path::new("nonexistent")).is_none());
+    }
+
+    #[test]
+    fn content_type() {
+        assert_eq!(
+            tree_magic::content_type_for_filepath(Path::new("tests/text/vcard-utf16be")),
+            Some("text/vcard; charset=utf-16be".to_string())
+        );
+        assert_eq!(tree_magic::content_type_for_filepath(Path::new("tests/missing")), None);
+    }
+
+}
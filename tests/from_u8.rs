@@ -2,28 +2,19 @@ mod from_u8 {
 
     extern crate tree_magic;
 
-    #[cfg(not(feature="staticmime"))]
-    macro_rules! convmime {
-        ($x:expr) => {$x.to_string()}
-    }
-    #[cfg(feature="staticmime")]
-    macro_rules! convmime {
-        ($x:expr) => {$x}
-    }
-
     ///Image tests
     #[test]
     fn image_gif() {
         assert_eq!(
             tree_magic::from_u8(include_bytes!("image/gif")),
-            convmime!("image/gif")
+            "image/gif"
         );
     }
     #[test]
     fn image_png() {
         assert_eq!(
             tree_magic::from_u8(include_bytes!("image/png")),
-            convmime!("image/png")
+            "image/png"
         );
     }
     #[test]
@@ -31,35 +22,43 @@ mod from_u8 {
     fn image_bmp() {
         assert_eq!(
             tree_magic::from_u8(include_bytes!("image/bmp")),
-            convmime!("image/bmp")
+            "image/bmp"
         );
     }
     #[test]
     fn image_tiff() {
         assert_eq!(
             tree_magic::from_u8(include_bytes!("image/tiff")),
-            convmime!("image/tiff")
+            "image/tiff"
         );
     }
     #[test]
     fn image_x_portable_bitmap() {
         assert_eq!(
             tree_magic::from_u8(include_bytes!("image/x-portable-bitmap")),
-            convmime!("image/x-portable-bitmap")
+            "image/x-portable-bitmap"
         );
     }
     #[test]
     fn image_x_pcx() {
         assert_eq!(
             tree_magic::from_u8(include_bytes!("image/x-pcx")),
-            convmime!("image/vnd.zbrush.pcx")
+            "image/vnd.zbrush.pcx"
         );
     }
     #[test]
     fn image_x_tga() {
         assert_eq!(
             tree_magic::from_u8(include_bytes!("image/x-tga")),
-            convmime!("image/x-tga")
+            "image/x-tga"
+        );
+    }
+    // Only matched by a masked rule
+    #[test]
+    fn image_vnd_adobe_photoshop() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("image/vnd.adobe.photoshop")),
+            "image/vnd.adobe.photoshop"
         );
     }
 
@@ -69,30 +68,41 @@ mod from_u8 {
     fn application_tar() {
         assert_eq!(
             tree_magic::from_u8(include_bytes!("application/x-tar")),
-            convmime!("application/x-tar")
+            "application/x-tar"
         );
     }
     #[test]
     fn application_x_7z() {
         assert_eq!(
             tree_magic::from_u8(include_bytes!("application/x-7z-compressed")),
-            convmime!("application/x-7z-compressed")
+            "application/x-7z-compressed"
         );
     }
     #[test]
     fn application_zip() {
         assert_eq!(
             tree_magic::from_u8(include_bytes!("application/zip")),
-            convmime!("application/zip")
+            "application/zip"
         );
     }
+    #[test]
+    #[cfg(feature="checker-archive")]
+    fn application_zip_after_other_data() {
+        // As in a self-extracting archive, found by the record at its end
+        let mut b = vec![0; 4096];
+        b.extend_from_slice(include_bytes!("application/zip"));
+        assert_eq!(tree_magic::from_u8(&b), "application/zip");
+        // Cut off before its end, there's nothing to go on
+        b.truncate(b.len() - 1);
+        assert_eq!(tree_magic::from_u8(&b), "application/octet-stream");
+    }
 
     /// Text tests
     #[test]
     fn text_plain() {
         assert_eq!(
             tree_magic::from_u8(include_bytes!("text/plain")),
-            convmime!("text/plain")
+            "text/plain"
         );
     }
 
@@ -101,7 +111,7 @@ mod from_u8 {
 	fn audio_flac() {
 		assert_eq!(
             tree_magic::from_u8(include_bytes!("audio/flac")),
-            convmime!("audio/flac")
+            "audio/flac"
         );
 	}
 	
@@ -109,7 +119,7 @@ mod from_u8 {
 	fn audio_mpeg() {
 		assert_eq!(
             tree_magic::from_u8(include_bytes!("audio/mpeg")),
-            convmime!("audio/mpeg")
+            "audio/mpeg"
         );
 	}
 	
@@ -117,7 +127,7 @@ mod from_u8 {
 	fn audio_ogg() {
 		assert_eq!(
             tree_magic::from_u8(include_bytes!("audio/ogg")),
-            convmime!("audio/ogg")
+            "audio/ogg"
         );
 	}
 	
@@ -125,7 +135,7 @@ mod from_u8 {
 	fn audio_opus() {
 		assert_eq!(
             tree_magic::from_u8(include_bytes!("audio/opus")),
-            convmime!("audio/opus")
+            "audio/opus"
         );
 	}
 	
@@ -133,7 +143,742 @@ mod from_u8 {
 	fn audio_wav() {
 		assert_eq!(
             tree_magic::from_u8(include_bytes!("audio/wav")),
-            convmime!("audio/wav")
+            "audio/wav"
         );
 	}
+
+    // OpenPGP tests
+    #[test]
+    fn application_pgp_encrypted() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/pgp-encrypted")),
+            "application/pgp-encrypted"
+        );
+    }
+    #[test]
+    fn application_pgp_keys() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/pgp-keys")),
+            "application/pgp-keys"
+        );
+    }
+    #[test]
+    fn application_pgp_signature() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/pgp-signature")),
+            "application/pgp-signature"
+        );
+    }
+
+    // Mail tests
+    #[test]
+    fn application_mbox() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/mbox")),
+            "application/mbox"
+        );
+    }
+    #[test]
+    fn message_rfc822() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("message/rfc822")),
+            "message/rfc822"
+        );
+    }
+    #[test]
+    fn text_plain_header_shaped() {
+        // YAML front matter has known field names, but no empty line after them
+        assert_eq!(
+            tree_magic::from_u8(b"date: 2024-01-01\ntitle: Notes\nsubject: mail\n"),
+            "text/plain"
+        );
+    }
+
+    // Multipart tests
+    #[test]
+    fn multipart_alternative() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("multipart/alternative")),
+            "multipart/alternative"
+        );
+    }
+    #[test]
+    fn multipart_form_data() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("multipart/form-data")),
+            "multipart/form-data"
+        );
+    }
+    #[test]
+    fn multipart_mixed() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("multipart/mixed")),
+            "multipart/mixed"
+        );
+    }
+
+    // Medical imaging tests
+    #[test]
+    fn application_dicom() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/dicom")),
+            "application/dicom"
+        );
+    }
+
+    // Bioinformatics tests
+    #[test]
+    fn application_x_bam() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-bam")),
+            "application/x-bam"
+        );
+    }
+    #[test]
+    fn application_x_cram() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-cram")),
+            "application/x-cram"
+        );
+    }
+    #[test]
+    fn text_x_fasta() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("text/x-fasta")),
+            "text/x-fasta"
+        );
+    }
+    #[test]
+    fn text_x_fastq() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("text/x-fastq")),
+            "text/x-fastq"
+        );
+    }
+
+    // Columnar data tests
+    #[test]
+    fn application_parquet() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/vnd.apache.parquet")),
+            "application/vnd.apache.parquet"
+        );
+    }
+    #[test]
+    fn application_arrow_file() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/vnd.apache.arrow.file")),
+            "application/vnd.apache.arrow.file"
+        );
+    }
+    #[test]
+    fn application_x_avro() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-avro")),
+            "application/x-avro"
+        );
+    }
+    #[test]
+    fn application_x_orc() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-orc")),
+            "application/x-orc"
+        );
+    }
+
+    // Scientific data tests
+    #[test]
+    fn application_x_hdf5() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-hdf5")),
+            "application/x-hdf5"
+        );
+    }
+    #[test]
+    fn application_x_netcdf() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-netcdf")),
+            "application/x-netcdf"
+        );
+    }
+
+    // 3D model tests
+    #[test]
+    fn model_stl() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("model/stl")),
+            "model/stl"
+        );
+    }
+    #[test]
+    fn model_gltf_json() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("model/gltf+json")),
+            "model/gltf+json"
+        );
+    }
+    #[test]
+    fn model_gltf_binary() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("model/gltf-binary")),
+            "model/gltf-binary"
+        );
+    }
+    #[test]
+    fn model_obj() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("model/obj")),
+            "model/obj"
+        );
+    }
+    #[test]
+    fn model_x_ply() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("model/x-ply")),
+            "model/x-ply"
+        );
+    }
+
+    // CAD tests
+    #[test]
+    fn image_vnd_dxf() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("image/vnd.dxf")),
+            "image/vnd.dxf"
+        );
+    }
+    #[test]
+    fn image_vnd_dwg() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("image/vnd.dwg")),
+            "image/vnd.dwg"
+        );
+    }
+
+    // BitTorrent tests
+    #[test]
+    fn application_x_bittorrent() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-bittorrent")),
+            "application/x-bittorrent"
+        );
+    }
+
+    // Windows shortcut tests
+    #[test]
+    fn application_x_ms_shortcut() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-ms-shortcut")),
+            "application/x-ms-shortcut"
+        );
+    }
+    #[test]
+    fn application_x_mswinurl() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-mswinurl")),
+            "application/x-mswinurl"
+        );
+    }
+
+    // JSON tests
+    #[test]
+    fn application_json() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/json")),
+            "application/json"
+        );
+    }
+    #[test]
+    fn application_x_ipynb_json() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-ipynb+json")),
+            "application/x-ipynb+json"
+        );
+    }
+    #[test]
+    fn application_json_cells() {
+        // Only an `nbformat` key makes a notebook
+        assert_eq!(
+            tree_magic::from_u8(b"{\"cells\": [{\"id\": 1}], \"count\": 1}"),
+            "application/json"
+        );
+    }
+
+    // Property list tests
+    #[test]
+    fn application_x_bplist() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-bplist")),
+            "application/x-bplist"
+        );
+    }
+    #[test]
+    fn application_x_plist() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-plist")),
+            "application/x-plist"
+        );
+    }
+
+    // Windows CAB and registry tests
+    #[test]
+    fn application_vnd_ms_cab_compressed() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/vnd.ms-cab-compressed")),
+            "application/vnd.ms-cab-compressed"
+        );
+    }
+    #[test]
+    fn application_x_ms_registry_hive() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-ms-registry-hive")),
+            "application/x-ms-registry-hive"
+        );
+    }
+
+    // Firmware tests
+    #[test]
+    fn application_x_dtb() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-dtb")),
+            "application/x-dtb"
+        );
+    }
+    #[test]
+    fn application_x_uboot_fit_image() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-uboot-fit-image")),
+            "application/x-uboot-fit-image"
+        );
+    }
+    #[test]
+    fn application_x_uboot_image() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-uboot-image")),
+            "application/x-uboot-image"
+        );
+    }
+    #[test]
+    fn application_x_uefi_capsule() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-uefi-capsule")),
+            "application/x-uefi-capsule"
+        );
+    }
+    #[test]
+    fn application_x_uefi_firmware_volume() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-uefi-firmware-volume")),
+            "application/x-uefi-firmware-volume"
+        );
+    }
+
+    // Android image tests
+    #[test]
+    fn application_vnd_android_bootimg() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/vnd.android.bootimg")),
+            "application/vnd.android.bootimg"
+        );
+    }
+    #[test]
+    fn application_vnd_android_sparse_image() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/vnd.android.sparse-image")),
+            "application/vnd.android.sparse-image"
+        );
+    }
+
+    // ROM tests
+    #[test]
+    fn application_x_nes_rom() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-nes-rom")),
+            "application/x-nes-rom"
+        );
+    }
+    #[test]
+    fn application_x_gameboy_rom() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-gameboy-rom")),
+            "application/x-gameboy-rom"
+        );
+    }
+    #[test]
+    fn application_x_gameboy_color_rom() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-gameboy-color-rom")),
+            "application/x-gameboy-color-rom"
+        );
+    }
+    #[test]
+    fn application_vnd_nintendo_snes_rom() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/vnd.nintendo.snes.rom")),
+            "application/vnd.nintendo.snes.rom"
+        );
+    }
+    #[test]
+    fn application_x_genesis_rom() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-genesis-rom")),
+            "application/x-genesis-rom"
+        );
+    }
+
+    // Lossless audio tests
+    #[test]
+    fn audio_x_ape() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("audio/x-ape")),
+            "audio/x-ape"
+        );
+    }
+    #[test]
+    fn audio_x_wavpack() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("audio/x-wavpack")),
+            "audio/x-wavpack"
+        );
+    }
+    #[test]
+    fn audio_x_wavpack_correction() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("audio/x-wavpack-correction")),
+            "audio/x-wavpack-correction"
+        );
+    }
+    #[test]
+    fn audio_x_tta() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("audio/x-tta")),
+            "audio/x-tta"
+        );
+    }
+
+    // Partition table and filesystem tests
+    #[test]
+    fn application_x_mbr_partition_table() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-mbr-partition-table")),
+            "application/x-mbr-partition-table"
+        );
+    }
+    #[test]
+    fn application_x_gpt_partition_table() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-gpt-partition-table")),
+            "application/x-gpt-partition-table"
+        );
+    }
+    #[test]
+    fn application_x_ext2_filesystem() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-ext2-filesystem")),
+            "application/x-ext2-filesystem"
+        );
+    }
+    #[test]
+    fn application_x_ext3_filesystem() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-ext3-filesystem")),
+            "application/x-ext3-filesystem"
+        );
+    }
+    #[test]
+    fn application_x_ext4_filesystem() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-ext4-filesystem")),
+            "application/x-ext4-filesystem"
+        );
+    }
+    #[test]
+    fn application_x_ntfs_filesystem() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("application/x-ntfs-filesystem")),
+            "application/x-ntfs-filesystem"
+        );
+    }
+    #[test]
+    fn application_x_amiga_disk_format() {
+        let mut b = vec![0; 901120];
+        b[..4].copy_from_slice(b"DOS\x01");
+        assert_eq!(tree_magic::from_u8(&b), "application/x-amiga-disk-format");
+        // Only the size of a floppy will do
+        b.truncate(4096);
+        assert_ne!(tree_magic::from_u8(&b), "application/x-amiga-disk-format");
+    }
+
+    // Byte order mark tests
+    #[test]
+    fn text_plain_utf16le() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("text/plain-utf16le")),
+            "text/plain"
+        );
+    }
+    #[test]
+    fn text_plain_utf32be() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("text/plain-utf32be")),
+            "text/plain"
+        );
+    }
+    #[test]
+    fn text_vcard_utf16be() {
+        assert_eq!(
+            tree_magic::from_u8(include_bytes!("text/vcard-utf16be")),
+            "text/vcard"
+        );
+    }
+    #[test]
+    fn application_octet_stream_utf16_mark() {
+        // Binary data that happens to start like a UTF-16LE byte order mark
+        assert_eq!(
+            tree_magic::from_u8(b"\xff\xfe\x01\x00\x00\x00\x10\x00\x00\x00\x03\x00\x7f\x02"),
+            "application/octet-stream"
+        );
+    }
+
+    // Text classification tests
+    #[test]
+    fn is_text() {
+        assert!(tree_magic::is_text(include_bytes!("text/plain")));
+        assert!(tree_magic::is_text(include_bytes!("text/plain-utf16le")));
+        assert!(tree_magic::is_text(b""));
+        assert!(!tree_magic::is_text(include_bytes!("image/png")));
+        assert!(!tree_magic::is_text(b"\x1b[1mbold\x1b[0m"));
+        assert!(!tree_magic::is_text(b"0123456789abcdef0123456789abcdef\x00"));
+    }
+
+    // Content-Type tests
+    #[test]
+    fn content_type() {
+        assert_eq!(
+            tree_magic::content_type_for_u8(include_bytes!("text/plain-utf16le")),
+            "text/plain; charset=utf-16le"
+        );
+        assert_eq!(
+            tree_magic::content_type_for_u8(b"caf\xe9 au lait\n"),
+            "text/plain; charset=windows-1252"
+        );
+        assert_eq!(
+            tree_magic::content_type_for_u8(include_bytes!("application/json")),
+            "application/json; charset=utf-8"
+        );
+        assert_eq!(
+            tree_magic::content_type_for_u8(include_bytes!("image/png")),
+            "image/png"
+        );
+    }
+
+    // Nothing cached for one input carries over to the next on the same thread
+    #[test]
+    fn back_to_back() {
+        for _ in 0..2 {
+            assert_eq!(tree_magic::from_u8(include_bytes!("image/gif")), "image/gif");
+            assert_eq!(tree_magic::from_u8(include_bytes!("image/png")), "image/png");
+            assert_eq!(tree_magic::from_u8(b"Hello, world!\n"), "text/plain");
+            assert!(!tree_magic::match_u8("image/gif", include_bytes!("image/png")));
+        }
+    }
+
+    // FLAC behind an ID3 tag also passes for MP3, so the order decides
+    #[test]
+    fn type_order() {
+        use self::tree_magic::{DetectOptions, TypeOrder};
+
+        let input = include_bytes!("audio/flac-id3");
+        for &x in ["audio/flac", "audio/mpeg"].iter() {
+            let options = DetectOptions {
+                type_order: TypeOrder::new(&[x]),
+                ..Default::default()
+            };
+            assert_eq!(tree_magic::from_u8_with(input, &options), x);
+        }
+        assert_eq!(
+            tree_magic::from_u8_with(input, &DetectOptions::default()),
+            tree_magic::from_u8(input)
+        );
+    }
+
+    // Only the allowed types and their ancestors are walked into
+    #[test]
+    fn restricted_types() {
+        use self::tree_magic::{DetectOptions, TypeOrder};
+
+        let options = DetectOptions {
+            type_order: TypeOrder::default().restrict(&["image/png", "text/plain", "application/x-zip-compressed"]),
+            ..Default::default()
+        };
+        let detect = |x: &[u8]| tree_magic::from_u8_with(x, &options);
+        assert_eq!(detect(include_bytes!("image/png")), "image/png");
+        assert_eq!(detect(include_bytes!("application/zip")), "application/zip");
+        assert_eq!(detect(include_bytes!("image/gif")), "application/octet-stream");
+        assert_eq!(detect(include_bytes!("application/json")), "text/plain");
+        assert_eq!(options.type_order.allowed().map(|x| x.len()), Some(3));
+    }
+
+    // Excluded types and everything under them are left out of the walk
+    #[test]
+    fn excluded_types() {
+        use self::tree_magic::{DetectOptions, TypeOrder};
+
+        let order = TypeOrder::default().exclude(&["application/json", "application/x-zip-compressed"]);
+        let options = DetectOptions { type_order: order.clone(), ..Default::default() };
+        let detect = |x: &[u8]| tree_magic::from_u8_with(x, &options);
+        assert_eq!(detect(include_bytes!("application/json")), "text/plain");
+        assert_eq!(detect(include_bytes!("application/zip")), "application/octet-stream");
+        assert_eq!(detect(include_bytes!("image/gif")), "image/gif");
+
+        // Both ways of cutting down the walk go together
+        let options = DetectOptions { type_order: order.restrict(&["image/gif", "application/zip"]), ..Default::default() };
+        assert_eq!(tree_magic::from_u8_with(include_bytes!("image/gif"), &options), "image/gif");
+        assert_eq!(tree_magic::from_u8_with(include_bytes!("application/zip"), &options), "application/octet-stream");
+    }
+
+    // Stops at the first type in the family
+    #[test]
+    fn until_family() {
+        let gif = include_bytes!("image/gif");
+        let json = include_bytes!("application/json");
+        assert_eq!(tree_magic::from_u8_until(gif, "image/*"), Some("image/gif".into()));
+        assert_eq!(tree_magic::from_u8_until(gif, "image/gif"), Some("image/gif".into()));
+        assert_eq!(tree_magic::from_u8_until(gif, "image/png"), None);
+        assert_eq!(tree_magic::from_u8_until(gif, "audio/*"), None);
+        assert_eq!(tree_magic::from_u8_until(json, "text/plain"), Some("text/plain".into()));
+        assert_eq!(tree_magic::from_u8_until(json, "application/json"), Some("application/json".into()));
+        assert_eq!(tree_magic::from_u8_until(include_bytes!("application/zip"), "application/x-zip-compressed"), Some("application/zip".into()));
+        assert_eq!(tree_magic::from_u8_until(include_bytes!("text/vcard-utf16be"), "text/vcard"), Some("text/vcard".into()));
+        assert_eq!(tree_magic::from_u8_until(gif, "nonexistent/type"), None);
+    }
+
+    // HTML magic can start anywhere in the first 256 bytes, including in a
+    // file shorter than that
+    #[test]
+    fn offset_range() {
+        let input = b"\n\n  <html><body>Hi</body></html>\n";
+        assert_eq!(tree_magic::from_u8(input), "text/html");
+        let mut padded = input.to_vec();
+        padded.resize(400, b' ');
+        assert_eq!(tree_magic::from_u8(&padded), "text/html");
+    }
+
+    // PostScript starts with `%`, which TeX takes at a low priority
+    #[test]
+    fn weak_magic() {
+        use self::tree_magic::{DetectOptions, TypeOrder};
+
+        let input = b"%!PS-Adobe-3.0\n%%Title: x\n";
+        assert!(tree_magic::match_u8("text/x-tex", input));
+        let options = DetectOptions {
+            type_order: TypeOrder::new(&["text/x-tex"]),
+            ..Default::default()
+        };
+        assert_eq!(tree_magic::from_u8_with(input, &options), "application/postscript");
+        assert_eq!(tree_magic::from_u8(input), "application/postscript");
+    }
+
+    // Only magic is looked at, so BAM is just the gzip it's packed in
+    #[test]
+    fn priority() {
+        use self::tree_magic::SniffMode;
+
+        assert_eq!(tree_magic::from_u8_priority(include_bytes!("image/gif")), "image/gif");
+        assert_eq!(tree_magic::from_u8_priority(include_bytes!("application/x-bam")), "application/gzip");
+        assert_eq!(tree_magic::from_u8_priority(include_bytes!("audio/x-wavpack")), "audio/x-wavpack");
+        // Nothing matches, so the graph decides
+        assert_eq!(tree_magic::from_u8_priority(b"Hello, world!\n"), "text/plain");
+        assert_eq!(
+            tree_magic::from_u8_mode(include_bytes!("image/png"), SniffMode::Priority),
+            "image/png"
+        );
+    }
+
+    #[test]
+    fn fast() {
+        assert_eq!(tree_magic::from_u8_fast(include_bytes!("image/png")), Some("image/png".into()));
+        assert_eq!(tree_magic::from_u8_fast(b"Hello, world!\n"), Some("text/plain".into()));
+        // Subtypes stop at the common type
+        assert_eq!(
+            tree_magic::from_u8_fast(include_bytes!("application/x-ipynb+json")),
+            Some("application/json".into())
+        );
+        assert_eq!(tree_magic::from_u8_fast(include_bytes!("application/dicom")), None);
+    }
+
+    // Data URI tests
+    #[test]
+    fn data_uri_roundtrip() {
+        let uri = tree_magic::to_data_uri(include_bytes!("image/png"));
+        assert!(uri.starts_with("data:image/png;base64,iVBORw0KGgo"));
+        assert_eq!(
+            tree_magic::from_data_uri(&uri),
+            Some(("image/png".to_string(), "image/png".into()))
+        );
+        assert!(tree_magic::match_data_uri(&uri));
+    }
+    #[test]
+    fn data_uri_mismatch() {
+        let uri = tree_magic::to_data_uri(include_bytes!("image/gif")).replacen("image/gif", "image/png", 1);
+        assert!(!tree_magic::match_data_uri(&uri));
+        assert_eq!(tree_magic::from_data_uri("not a data uri"), None);
+        assert_eq!(tree_magic::from_data_uri("data:;base64,*"), None);
+    }
+
+    // Report tests
+    #[test]
+    fn report() {
+        let report = tree_magic::from_u8_report(include_bytes!("text/vcard-utf16be"));
+        assert_eq!(report.mimetype, "text/vcard");
+        assert_eq!(report.path.first().map(|x| &**x), Some("all/all"));
+        assert!(report.path.iter().any(|x| x == "text/plain"));
+        // Found in the decoded text
+        let rule = report.rule.unwrap();
+        assert_eq!(rule.rule.value.len(), rule.len);
+
+        let report = tree_magic::from_u8_report(b"Hello, world!\n");
+        assert_eq!(report.mimetype, "text/plain");
+        assert_eq!(report.checker, "basetype");
+        assert_eq!(report.rule, None);
+    }
+    #[test]
+    fn report_matches_from_u8() {
+        fn visit(dir: &std::path::Path) {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    visit(&path);
+                    continue;
+                }
+                let bytes = std::fs::read(&path).unwrap();
+                let report = tree_magic::from_u8_report(&bytes);
+                assert_eq!(report.mimetype, tree_magic::from_u8(&bytes), "{}", path.display());
+                assert_eq!(report.path.last(), Some(&report.mimetype));
+            }
+        }
+        visit(std::path::Path::new("tests"));
+    }
+    #[test]
+    fn traced() {
+        use self::tree_magic::TraceEvent;
+
+        let mut events = Vec::new();
+        let report = tree_magic::from_u8_traced(include_bytes!("text/vcard-utf16be"), |x| events.push(x.clone()));
+        // Each step down is to the best match among what was just tried
+        let chosen: Vec<_> = events.iter()
+            .filter_map(|x| match x {
+                TraceEvent::Chose { mimetype, .. } => Some(mimetype.clone()),
+                _ => None
+            })
+            .collect();
+        assert_eq!(chosen, report.path[1..]);
+        assert!(events.contains(&TraceEvent::Decoded));
+        assert!(events.contains(&TraceEvent::Tried {
+            parent: "all/all".into(),
+            mimetype: "all/allfiles".into(),
+            priority: Some(50)
+        }));
+        assert_eq!(report, tree_magic::from_u8_report(include_bytes!("text/vcard-utf16be")));
+    }
 }
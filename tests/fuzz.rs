@@ -0,0 +1,84 @@
+#![cfg(all(feature="fuzz", feature="checker-fdo-magic"))]
+mod fuzz {
+
+    extern crate arbitrary;
+    extern crate tree_magic;
+
+    use self::arbitrary::{Arbitrary, Unstructured};
+    use self::tree_magic::fuzz::{detect, parse_magic, run_rules};
+    use self::tree_magic::MagicRule;
+
+    /// Same bytes every run
+    fn noise(seed: u64, len: usize) -> Vec<u8> {
+        let mut x = seed;
+        (0..len).map(|_| {
+            x = x.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (x >> 56) as u8
+        }).collect()
+    }
+
+    #[test]
+    fn detect_noise() {
+        for seed in 0..200 {
+            let data = noise(seed, (seed as usize * 37) % 600);
+            assert_eq!(detect(&data), detect(&data));
+        }
+        for (mimetype, header) in [("image/png", &b"\x89PNG\r\n\x1a\n"[..]), ("application/zip", b"PK\x03\x04")] {
+            for len in 0..=header.len() {
+                detect(&header[..len]);
+            }
+            assert_eq!(detect(header), mimetype);
+        }
+    }
+
+    #[test]
+    fn parse_mangled() {
+        let magic = b"MIME-Magic\0\n[50:image/png]\n>0=\0\x04\x89PNG\n1>4=\0\x02\r\n&\xff\xff~2+8\n[40:text/x-a]\n>0=\0\x01a\n";
+        assert_eq!(parse_magic(magic), Ok(3));
+        for len in 0..magic.len() {
+            let _ = parse_magic(&magic[..len]);
+        }
+        for i in 0..magic.len() {
+            for bit in 0..8 {
+                let mut x = magic.to_vec();
+                x[i] ^= 1 << bit;
+                let _ = parse_magic(&x);
+            }
+        }
+    }
+
+    #[test]
+    fn arbitrary_rules() {
+        for seed in 0..100 {
+            let bytes = noise(seed, 2048);
+            let mut u = Unstructured::new(&bytes);
+            let rules = Vec::<MagicRule>::arbitrary(&mut u).unwrap();
+            for rule in &rules {
+                assert!(rule.value.len() <= 256);
+                assert!(rule.mask.as_ref().is_none_or(|x| x.len() == rule.value.len()));
+            }
+            assert_eq!(run_rules(&rules, &bytes), run_rules(&rules, &bytes));
+        }
+    }
+
+    #[test]
+    fn odd_rules() {
+        let rule = |indent: u32, value: &'static [u8], mask: Option<&'static [u8]>| MagicRule {
+            indent,
+            offset: 0,
+            range: 0,
+            value: value.into(),
+            mask: mask.map(Into::into),
+            word_size: 1,
+            priority: 50
+        };
+        // A mask shorter than the value leaves the rest unmasked
+        assert!(run_rules(&[rule(0, b"ab", Some(b"\xdf"))], b"Ab"));
+        assert!(!run_rules(&[rule(0, b"ab", Some(b"\xdf"))], b"AB"));
+        // Every rule a child of the last
+        let deep: Vec<MagicRule> = (0..100_000).map(|x| rule(x, b"a", None)).collect();
+        assert!(run_rules(&deep, b"a"));
+        let far = MagicRule {offset: u32::MAX, range: u32::MAX, word_size: u32::MAX, ..rule(0, b"a", None)};
+        assert!(!run_rules(&[far], b"a"));
+    }
+}
@@ -0,0 +1,33 @@
+#![cfg(feature="checker-infer")]
+mod infer {
+
+    extern crate tree_magic;
+
+    use self::tree_magic::from_u8;
+
+    #[test]
+    fn fills_gaps() {
+        assert_eq!(from_u8(b"\0asm\x01\0\0\0"), "application/wasm");
+        assert_eq!(from_u8(&[0xcf, 0xfa, 0xed, 0xfe, 0x07, 0x00, 0x00, 0x01]), "application/x-mach-binary");
+
+        let wasm = tree_magic::supported_types().find(|x| x.mimetype == "application/wasm").unwrap();
+        assert_eq!(wasm.checker, "infer");
+    }
+
+    // Types the database already has stay with the checker that has them
+    #[test]
+    fn leaves_known_types() {
+        let find = |x: &str| tree_magic::supported_types().find(|y| y.mimetype == x).unwrap().checker;
+        assert_eq!(find("image/png"), "fdo-magic");
+        assert_eq!(find("font/woff2"), "fdo-magic");
+        assert_eq!(find("application/x-nes-rom"), "rom");
+        assert_eq!(from_u8(include_bytes!("image/png")), "image/png");
+        assert_eq!(from_u8(include_bytes!("application/zip")), "application/zip");
+    }
+
+    #[test]
+    fn under_container() {
+        let docx = tree_magic::TypeId::of("application/vnd.openxmlformats-officedocument.wordprocessingml.document").unwrap();
+        assert!(docx.parents().any(|x| x.name() == "application/zip"));
+    }
+}
@@ -0,0 +1,95 @@
+#![cfg(feature="tower")]
+mod layer {
+    extern crate tree_magic;
+    extern crate bytes;
+    extern crate http;
+    extern crate http_body;
+    extern crate http_body_util;
+    extern crate tower_layer;
+    extern crate tower_service;
+
+    use std::convert::Infallible;
+    use std::future::{Future, Ready, ready};
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+    use self::bytes::Bytes;
+    use self::http::{Request, Response};
+    use self::http::header::CONTENT_TYPE;
+    use self::http_body::Body;
+    use self::http_body_util::Full;
+    use self::tower_layer::Layer;
+    use self::tower_service::Service;
+    use self::tree_magic::layer::SniffContentTypeLayer;
+
+    /// Serves the same response to every request
+    #[derive(Clone)]
+    struct Fixed(Response<Full<Bytes>>);
+
+    impl Service<Request<()>> for Fixed {
+        type Response = Response<Full<Bytes>>;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Infallible>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: Request<()>) -> Self::Future {
+            ready(Ok(self.0.clone()))
+        }
+    }
+
+    fn run<F: Future>(future: F) -> F::Output {
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut future = pin!(future);
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(x) => x,
+            Poll::Pending => panic!("Future should be ready")
+        }
+    }
+
+    fn respond(layer: SniffContentTypeLayer, response: Response<Full<Bytes>>) -> (Option<String>, Vec<u8>) {
+        let mut service = layer.layer(Fixed(response));
+        let response = run(service.call(Request::new(()))).unwrap();
+        let content_type = response.headers().get(CONTENT_TYPE)
+            .map(|x| x.to_str().unwrap().to_string());
+
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut body = pin!(response.into_body());
+        let mut out = Vec::new();
+        while let Poll::Ready(Some(frame)) = body.as_mut().poll_frame(&mut cx) {
+            out.extend_from_slice(&frame.unwrap().into_data().unwrap());
+        }
+        (content_type, out)
+    }
+
+    #[test]
+    fn sets_content_type() {
+        let png = include_bytes!("image/png");
+        let response = Response::new(Full::new(Bytes::from_static(png)));
+        let (content_type, body) = respond(SniffContentTypeLayer::new(), response);
+        assert_eq!(content_type, Some("image/png".to_string()));
+        assert_eq!(body, png.to_vec());
+    }
+
+    #[test]
+    fn keeps_content_type() {
+        let response = Response::builder()
+            .header(CONTENT_TYPE, "application/x-custom")
+            .body(Full::new(Bytes::from_static(include_bytes!("image/png"))))
+            .unwrap();
+        let (content_type, _) = respond(SniffContentTypeLayer::new(), response.clone());
+        assert_eq!(content_type, Some("application/x-custom".to_string()));
+
+        let (content_type, _) = respond(SniffContentTypeLayer::new().overwrite(true), response);
+        assert_eq!(content_type, Some("image/png".to_string()));
+    }
+
+    #[test]
+    fn empty_body() {
+        let response = Response::new(Full::new(Bytes::new()));
+        let (content_type, body) = respond(SniffContentTypeLayer::new(), response);
+        assert_eq!(content_type, None);
+        assert!(body.is_empty());
+    }
+}
@@ -0,0 +1,103 @@
+#![cfg(all(feature="std", feature="checker-fdo-magic", feature="checker-mail"))]
+mod limit {
+
+    extern crate tree_magic;
+
+    use self::tree_magic::{DetectOptions, WorkLimit};
+
+    fn limited(max_bytes: usize, max_rules: usize) -> DetectOptions {
+        DetectOptions {
+            work_limit: Some(WorkLimit { max_bytes, max_rules }),
+            ..Default::default()
+        }
+    }
+
+    /// A multipart body with more parts than the mail checker parses
+    fn many_parts() -> Vec<u8> {
+        let mut out = b"--a\r\nContent-Type: text/plain\r\n\r\nx".to_vec();
+        for _ in 0..1 << 16 {
+            out.extend_from_slice(b"\r\n--a\r\nX: y\r\n\r\nz");
+        }
+        out
+    }
+
+    #[test]
+    fn unlimited() {
+        let input = include_bytes!("image/gif");
+        let options = DetectOptions::default();
+        assert_eq!(tree_magic::try_from_u8_with(input, &options), Ok("image/gif".into()));
+    }
+
+    #[test]
+    fn within_limit() {
+        let options = limited(1 << 20, 10_000);
+        for &(input, expected) in &[
+            (&include_bytes!("image/gif")[..], "image/gif"),
+            (&include_bytes!("image/png")[..], "image/png"),
+            (&include_bytes!("application/zip")[..], "application/zip"),
+            (&include_bytes!("text/plain")[..], "text/plain")
+        ] {
+            assert_eq!(tree_magic::try_from_u8_with(input, &options), Ok(expected.into()));
+            assert_eq!(tree_magic::from_u8_with(input, &options), expected);
+        }
+    }
+
+    #[test]
+    fn rules_exceeded() {
+        let input = include_bytes!("image/gif");
+        let result = tree_magic::try_from_u8_with(input, &limited(1 << 20, 1));
+        let found = result.unwrap_err().found;
+        assert_ne!(found, "image/gif");
+        // Without `try_`, it's what was found by then
+        assert_eq!(tree_magic::from_u8_with(input, &limited(1 << 20, 1)), found);
+    }
+
+    #[test]
+    fn bytes_exceeded() {
+        let input = include_bytes!("image/png");
+        assert!(tree_magic::try_from_u8_with(input, &limited(0, 10_000)).is_err());
+    }
+
+    #[test]
+    fn crafted_input() {
+        // Parsing stops at the scan extent, so the cost doesn't grow with
+        // the number of parts
+        let input = many_parts();
+        assert!(input.len() > 1 << 20);
+        assert_eq!(tree_magic::from_u8(&input), "multipart/mixed");
+        let result = tree_magic::try_from_u8_with(&input, &limited(1 << 20, 10_000));
+        assert_eq!(result, Ok("multipart/mixed".into()));
+    }
+
+    #[test]
+    #[cfg(feature="checker-json")]
+    fn large_json() {
+        // Only the start of the document is parsed, and only once
+        let mut input = b"[".to_vec();
+        for _ in 0..1 << 16 {
+            input.extend_from_slice(b"{\"a\": 1, \"b\": 2}, ");
+        }
+        input.extend_from_slice(b"null]");
+        assert!(input.len() > 1 << 20);
+        let result = tree_magic::try_from_u8_with(&input, &limited(1 << 20, 10_000));
+        assert_eq!(result, Ok("application/json".into()));
+    }
+
+    #[test]
+    fn limit_is_per_call() {
+        let input = include_bytes!("image/gif");
+        assert!(tree_magic::try_from_u8_with(input, &limited(0, 0)).is_err());
+        // Nothing is left over for later calls, with or without a limit
+        assert_eq!(tree_magic::from_u8(input), "image/gif");
+        assert!(tree_magic::match_u8("image/gif", input));
+        assert_eq!(tree_magic::try_from_u8_with(input, &limited(1 << 20, 10_000)), Ok("image/gif".into()));
+    }
+
+    #[test]
+    #[cfg(feature="fs")]
+    fn from_filepath() {
+        let path = std::path::Path::new("tests/image/gif");
+        assert_eq!(tree_magic::from_filepath_with(path, &limited(1 << 20, 10_000)), Some("image/gif".into()));
+        assert_ne!(tree_magic::from_filepath_with(path, &limited(1 << 20, 1)), Some("image/gif".into()));
+    }
+}
@@ -74,4 +74,326 @@ mod match_u8 {
     fn audio_wav() {
         assert!(tree_magic::match_u8("audio/wav", include_bytes!("audio/wav")));
     }
+
+    // OpenPGP tests
+    #[test]
+    fn application_pgp_encrypted() {
+        assert!(tree_magic::match_u8("application/pgp-encrypted", include_bytes!("application/pgp-encrypted")));
+    }
+    #[test]
+    fn application_pgp_keys() {
+        assert!(tree_magic::match_u8("application/pgp-keys", include_bytes!("application/pgp-keys")));
+    }
+    #[test]
+    fn application_pgp_signature() {
+        assert!(tree_magic::match_u8("application/pgp-signature", include_bytes!("application/pgp-signature")));
+    }
+
+    // Mail tests
+    #[test]
+    fn application_mbox() {
+        assert!(tree_magic::match_u8("application/mbox", include_bytes!("application/mbox")));
+    }
+    #[test]
+    fn message_rfc822() {
+        assert!(tree_magic::match_u8("message/rfc822", include_bytes!("message/rfc822")));
+    }
+
+    // Multipart tests
+    #[test]
+    fn multipart_alternative() {
+        assert!(tree_magic::match_u8("multipart/alternative", include_bytes!("multipart/alternative")));
+    }
+    #[test]
+    fn multipart_form_data() {
+        assert!(tree_magic::match_u8("multipart/form-data", include_bytes!("multipart/form-data")));
+    }
+    #[test]
+    fn multipart_mixed() {
+        assert!(tree_magic::match_u8("multipart/mixed", include_bytes!("multipart/mixed")));
+    }
+
+    // Medical imaging tests
+    #[test]
+    fn application_dicom() {
+        assert!(tree_magic::match_u8("application/dicom", include_bytes!("application/dicom")));
+    }
+
+    // Bioinformatics tests
+    #[test]
+    fn application_x_bam() {
+        assert!(tree_magic::match_u8("application/x-bam", include_bytes!("application/x-bam")));
+    }
+    #[test]
+    fn application_x_cram() {
+        assert!(tree_magic::match_u8("application/x-cram", include_bytes!("application/x-cram")));
+    }
+    #[test]
+    fn text_x_fasta() {
+        assert!(tree_magic::match_u8("text/x-fasta", include_bytes!("text/x-fasta")));
+    }
+    #[test]
+    fn text_x_fastq() {
+        assert!(tree_magic::match_u8("text/x-fastq", include_bytes!("text/x-fastq")));
+    }
+
+    // Columnar data tests
+    #[test]
+    fn application_parquet() {
+        assert!(tree_magic::match_u8("application/vnd.apache.parquet", include_bytes!("application/vnd.apache.parquet")));
+    }
+    #[test]
+    fn application_arrow_file() {
+        assert!(tree_magic::match_u8("application/vnd.apache.arrow.file", include_bytes!("application/vnd.apache.arrow.file")));
+    }
+    #[test]
+    fn application_x_avro() {
+        assert!(tree_magic::match_u8("application/x-avro", include_bytes!("application/x-avro")));
+    }
+    #[test]
+    fn application_x_orc() {
+        assert!(tree_magic::match_u8("application/x-orc", include_bytes!("application/x-orc")));
+    }
+
+    // Scientific data tests
+    #[test]
+    fn application_x_hdf5() {
+        assert!(tree_magic::match_u8("application/x-hdf5", include_bytes!("application/x-hdf5")));
+    }
+    #[test]
+    fn application_x_netcdf() {
+        assert!(tree_magic::match_u8("application/x-netcdf", include_bytes!("application/x-netcdf")));
+    }
+
+    // 3D model tests
+    #[test]
+    fn model_stl() {
+        assert!(tree_magic::match_u8("model/stl", include_bytes!("model/stl")));
+    }
+    #[test]
+    fn model_gltf_json() {
+        assert!(tree_magic::match_u8("model/gltf+json", include_bytes!("model/gltf+json")));
+    }
+    #[test]
+    fn model_gltf_binary() {
+        assert!(tree_magic::match_u8("model/gltf-binary", include_bytes!("model/gltf-binary")));
+    }
+    #[test]
+    fn model_obj() {
+        assert!(tree_magic::match_u8("model/obj", include_bytes!("model/obj")));
+    }
+    #[test]
+    fn model_x_ply() {
+        assert!(tree_magic::match_u8("model/x-ply", include_bytes!("model/x-ply")));
+    }
+
+    // CAD tests
+    #[test]
+    fn image_vnd_dxf() {
+        assert!(tree_magic::match_u8("image/vnd.dxf", include_bytes!("image/vnd.dxf")));
+    }
+    #[test]
+    fn image_vnd_dwg() {
+        assert!(tree_magic::match_u8("image/vnd.dwg", include_bytes!("image/vnd.dwg")));
+    }
+
+    // BitTorrent tests
+    #[test]
+    fn application_x_bittorrent() {
+        assert!(tree_magic::match_u8("application/x-bittorrent", include_bytes!("application/x-bittorrent")));
+    }
+
+    // Windows shortcut tests
+    #[test]
+    fn application_x_ms_shortcut() {
+        assert!(tree_magic::match_u8("application/x-ms-shortcut", include_bytes!("application/x-ms-shortcut")));
+    }
+    #[test]
+    fn application_x_mswinurl() {
+        assert!(tree_magic::match_u8("application/x-mswinurl", include_bytes!("application/x-mswinurl")));
+    }
+
+    // JSON tests
+    #[test]
+    fn application_json() {
+        assert!(tree_magic::match_u8("application/json", include_bytes!("application/json")));
+    }
+    #[test]
+    fn application_x_ipynb_json() {
+        assert!(tree_magic::match_u8("application/x-ipynb+json", include_bytes!("application/x-ipynb+json")));
+    }
+
+    // Property list tests
+    #[test]
+    fn application_x_bplist() {
+        assert!(tree_magic::match_u8("application/x-bplist", include_bytes!("application/x-bplist")));
+    }
+    #[test]
+    fn application_x_plist() {
+        assert!(tree_magic::match_u8("application/x-plist", include_bytes!("application/x-plist")));
+    }
+
+    // Windows CAB and registry tests
+    #[test]
+    fn application_vnd_ms_cab_compressed() {
+        assert!(tree_magic::match_u8("application/vnd.ms-cab-compressed", include_bytes!("application/vnd.ms-cab-compressed")));
+    }
+    #[test]
+    fn application_x_ms_registry_hive() {
+        assert!(tree_magic::match_u8("application/x-ms-registry-hive", include_bytes!("application/x-ms-registry-hive")));
+    }
+
+    // Firmware tests
+    #[test]
+    fn application_x_dtb() {
+        assert!(tree_magic::match_u8("application/x-dtb", include_bytes!("application/x-dtb")));
+    }
+    #[test]
+    fn application_x_uboot_fit_image() {
+        assert!(tree_magic::match_u8("application/x-uboot-fit-image", include_bytes!("application/x-uboot-fit-image")));
+    }
+    #[test]
+    fn application_x_uboot_image() {
+        assert!(tree_magic::match_u8("application/x-uboot-image", include_bytes!("application/x-uboot-image")));
+    }
+    #[test]
+    fn application_x_uefi_capsule() {
+        assert!(tree_magic::match_u8("application/x-uefi-capsule", include_bytes!("application/x-uefi-capsule")));
+    }
+    #[test]
+    fn application_x_uefi_firmware_volume() {
+        assert!(tree_magic::match_u8("application/x-uefi-firmware-volume", include_bytes!("application/x-uefi-firmware-volume")));
+    }
+
+    // Android image tests
+    #[test]
+    fn application_vnd_android_bootimg() {
+        assert!(tree_magic::match_u8("application/vnd.android.bootimg", include_bytes!("application/vnd.android.bootimg")));
+    }
+    #[test]
+    fn application_vnd_android_sparse_image() {
+        assert!(tree_magic::match_u8("application/vnd.android.sparse-image", include_bytes!("application/vnd.android.sparse-image")));
+    }
+
+    // ROM tests
+    #[test]
+    fn application_x_nes_rom() {
+        assert!(tree_magic::match_u8("application/x-nes-rom", include_bytes!("application/x-nes-rom")));
+    }
+    #[test]
+    fn application_x_gameboy_rom() {
+        assert!(tree_magic::match_u8("application/x-gameboy-rom", include_bytes!("application/x-gameboy-rom")));
+    }
+    #[test]
+    fn application_x_gameboy_color_rom() {
+        assert!(tree_magic::match_u8("application/x-gameboy-color-rom", include_bytes!("application/x-gameboy-color-rom")));
+    }
+    #[test]
+    fn application_vnd_nintendo_snes_rom() {
+        assert!(tree_magic::match_u8("application/vnd.nintendo.snes.rom", include_bytes!("application/vnd.nintendo.snes.rom")));
+    }
+    #[test]
+    fn application_x_genesis_rom() {
+        assert!(tree_magic::match_u8("application/x-genesis-rom", include_bytes!("application/x-genesis-rom")));
+    }
+
+    // Lossless audio tests
+    #[test]
+    fn audio_x_ape() {
+        assert!(tree_magic::match_u8("audio/x-ape", include_bytes!("audio/x-ape")));
+    }
+    #[test]
+    fn audio_x_wavpack() {
+        assert!(tree_magic::match_u8("audio/x-wavpack", include_bytes!("audio/x-wavpack")));
+    }
+    #[test]
+    fn audio_x_wavpack_correction() {
+        assert!(tree_magic::match_u8("audio/x-wavpack-correction", include_bytes!("audio/x-wavpack-correction")));
+    }
+    #[test]
+    fn audio_x_tta() {
+        assert!(tree_magic::match_u8("audio/x-tta", include_bytes!("audio/x-tta")));
+    }
+
+    // Partition table and filesystem tests
+    #[test]
+    fn application_x_mbr_partition_table() {
+        assert!(tree_magic::match_u8("application/x-mbr-partition-table", include_bytes!("application/x-mbr-partition-table")));
+    }
+    #[test]
+    fn application_x_gpt_partition_table() {
+        assert!(tree_magic::match_u8("application/x-gpt-partition-table", include_bytes!("application/x-gpt-partition-table")));
+    }
+    #[test]
+    fn application_x_ext2_filesystem() {
+        assert!(tree_magic::match_u8("application/x-ext2-filesystem", include_bytes!("application/x-ext2-filesystem")));
+    }
+    #[test]
+    fn application_x_ext3_filesystem() {
+        assert!(tree_magic::match_u8("application/x-ext3-filesystem", include_bytes!("application/x-ext3-filesystem")));
+    }
+    #[test]
+    fn application_x_ext4_filesystem() {
+        assert!(tree_magic::match_u8("application/x-ext4-filesystem", include_bytes!("application/x-ext4-filesystem")));
+    }
+    #[test]
+    fn application_x_ntfs_filesystem() {
+        assert!(tree_magic::match_u8("application/x-ntfs-filesystem", include_bytes!("application/x-ntfs-filesystem")));
+    }
+    #[test]
+    fn gpt_is_mbr() {
+        // Behind its protective MBR
+        assert!(tree_magic::match_u8("application/x-mbr-partition-table", include_bytes!("application/x-gpt-partition-table")));
+        // A filesystem's boot sector isn't a partition table
+        assert!(!tree_magic::match_u8("application/x-mbr-partition-table", include_bytes!("application/x-ntfs-filesystem")));
+    }
+
+    // Byte order mark tests
+    #[test]
+    fn text_plain_utf16le() {
+        assert!(tree_magic::match_u8("text/plain", include_bytes!("text/plain-utf16le")));
+    }
+    #[test]
+    fn text_plain_utf32be() {
+        assert!(tree_magic::match_u8("text/plain", include_bytes!("text/plain-utf32be")));
+    }
+    #[test]
+    fn text_vcard_utf16be() {
+        assert!(tree_magic::match_u8("text/vcard", include_bytes!("text/vcard-utf16be")));
+    }
+    #[test]
+    fn text_html_utf16_mark() {
+        // Decodes to "<html>", followed by control characters
+        let input = b"\xff\xfe<\x00h\x00t\x00m\x00l\x00>\x00\x01\x00\x00\x00\x10\x00\x03\x00";
+        assert!(!tree_magic::match_u8("text/html", input));
+        assert!(!tree_magic::match_u8("text/plain", input));
+    }
+
+    // Host-endian magic is in the byte order of the machine running it
+    #[test]
+    fn host_endian() {
+        let mut input = 0x0110u16.to_ne_bytes().to_vec();
+        input.resize(64, 0);
+        assert!(tree_magic::match_u8("application/x-executable", &input));
+        input[..2].copy_from_slice(&0x0110u16.swap_bytes().to_ne_bytes());
+        assert!(!tree_magic::match_u8("application/x-executable", &input));
+    }
+
+    // Same answers as one type at a time
+    #[test]
+    fn match_u8_many() {
+        let mut types: Vec<String> = tree_magic::TypeId::all().map(|x| x.to_string()).collect();
+        types.push("application/x-zip-compressed".to_string());
+        types.push("nonexistent/type".to_string());
+        let inputs: [&[u8]; 4] = [
+            include_bytes!("image/gif"),
+            include_bytes!("application/zip"),
+            include_bytes!("text/vcard-utf16be"),
+            b""
+        ];
+        for input in inputs.iter() {
+            let expected: Vec<bool> = types.iter().map(|x| tree_magic::match_u8(x, input)).collect();
+            assert_eq!(tree_magic::match_u8_many(&types, input), expected);
+        }
+    }
 }
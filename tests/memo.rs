@@ -0,0 +1,56 @@
+#![cfg(feature="result-cache")]
+mod memo {
+
+    extern crate tree_magic;
+
+    use std::fs;
+    use std::path::Path;
+    use self::tree_magic::memo::ResultCache;
+
+    #[test]
+    fn changed_file() {
+        let path = std::env::temp_dir().join(format!("tree_magic-memo-{}", std::process::id()));
+        let cache = ResultCache::new(16);
+
+        fs::copy("tests/image/gif", &path).unwrap();
+        let before = cache.from_filepath(&path);
+        assert_eq!(cache.len(), 1);
+        // A different size means a different file
+        fs::copy("tests/image/png", &path).unwrap();
+        let after = cache.from_filepath(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(before, Some("image/gif".into()));
+        assert_eq!(after, Some("image/png".into()));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn capacity() {
+        let cache = ResultCache::new(2);
+        let gif = Path::new("tests/image/gif");
+        let png = Path::new("tests/image/png");
+        let hdf5 = Path::new("tests/application/x-hdf5");
+
+        cache.from_filepath(gif);
+        cache.from_filepath(png);
+        cache.from_filepath(gif);
+        // Pushes out the PNG, as the GIF was used since
+        assert_eq!(cache.from_filepath(hdf5), Some("application/x-hdf5".into()));
+        assert_eq!(cache.len(), 2);
+
+        cache.invalidate(gif);
+        assert_eq!(cache.len(), 1);
+        cache.from_filepath(png);
+        cache.set_capacity(1);
+        assert_eq!(cache.capacity(), 1);
+        assert_eq!(cache.len(), 1);
+        cache.clear();
+        assert!(cache.is_empty());
+
+        // Files that can't be read aren't remembered
+        assert_eq!(cache.from_filepath(Path::new("nonexistent")), None);
+        assert!(cache.is_empty());
+    }
+
+}
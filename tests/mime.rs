@@ -0,0 +1,31 @@
+#![cfg(feature="mime")]
+mod mime {
+
+    extern crate tree_magic;
+    extern crate mime;
+
+    use std::convert::TryFrom;
+    use self::tree_magic::Mime;
+
+    #[test]
+    fn to_mime() {
+        let png = tree_magic::from_u8(include_bytes!("image/png"));
+        let converted = mime::Mime::try_from(&png).unwrap();
+        assert_eq!(converted, mime::IMAGE_PNG);
+        assert_eq!(png, converted);
+        assert!(mime::Mime::try_from(Mime::from("not a type")).is_err());
+
+        // Everything the database hands out converts
+        for x in tree_magic::TypeId::all() {
+            let x = Mime::from(x.name());
+            assert_eq!(x, mime::Mime::try_from(&x).unwrap());
+        }
+    }
+
+    #[test]
+    fn from_mime() {
+        let html: Mime = mime::TEXT_HTML_UTF_8.into();
+        assert_eq!(html, "text/html");
+        assert!(tree_magic::match_u8(&html, b"<html><body></body></html>"));
+    }
+}
@@ -0,0 +1,40 @@
+#![cfg(feature="mime_guess")]
+mod mime_guess {
+
+    extern crate tree_magic;
+
+    use self::tree_magic::from_u8_and_name;
+
+    #[test]
+    fn content_backs_guess() {
+        // application/json is under text/plain in the type graph
+        assert_eq!(from_u8_and_name(b"Hello, world!\n", "data.json"), "application/json");
+        assert_eq!(from_u8_and_name(include_bytes!("application/zip"), "archive.zip"), "application/zip");
+    }
+
+    #[test]
+    fn catch_all() {
+        assert_eq!(from_u8_and_name(b"a,b\n1,2\n", "table.csv"), "text/csv");
+        assert_eq!(from_u8_and_name(&[0x00, 0x80, 0x81, 0x82, 0x83], "data.wasm"), "application/wasm");
+        // Text types only stand in for text
+        assert_eq!(from_u8_and_name(b"Hello, world!\n", "font.woff2"), "text/plain");
+    }
+
+    #[test]
+    fn content_wins() {
+        assert_eq!(from_u8_and_name(include_bytes!("image/gif"), "image.png"), "image/gif");
+        assert_eq!(from_u8_and_name(include_bytes!("image/gif"), "noextension"), "image/gif");
+        assert_eq!(from_u8_and_name(b"Hello, world!\n", "image.png"), "text/plain");
+    }
+
+    #[cfg(feature="fs")]
+    #[test]
+    fn filepath() {
+        use std::path::Path;
+
+        assert_eq!(tree_magic::from_filepath_and_name(Path::new("tests/image/gif")), Some("image/gif".into()));
+        // Unreadable files only have their name to go on
+        assert_eq!(tree_magic::from_filepath_and_name(Path::new("nonexistent.png")), Some("image/png".into()));
+        assert_eq!(tree_magic::from_filepath_and_name(Path::new("nonexistent")), None);
+    }
+}
@@ -0,0 +1,150 @@
+#![cfg(feature="parallel")]
+mod parallel {
+
+    extern crate tree_magic;
+
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn from_filepaths() {
+        let paths: Vec<PathBuf> = ["image/gif", "image/png", "application/x-hdf5", "nonexistent"]
+            .iter()
+            .map(|x| Path::new("tests").join(x))
+            .collect();
+
+        let result = tree_magic::from_filepaths(paths.clone());
+        assert_eq!(result.len(), paths.len());
+        for (x, path) in result.iter().zip(paths.iter()) {
+            assert_eq!(&x.0, path);
+            assert_eq!(x.1, tree_magic::from_filepath(path));
+        }
+        assert_eq!(result[3].1, None);
+    }
+
+    #[test]
+    fn many_files() {
+        // Far more than can be open at once
+        let paths = vec![PathBuf::from("tests/image/gif"); 2000];
+        let result = tree_magic::from_filepaths(paths);
+        assert!(result.iter().all(|x| x.1 == Some("image/gif".into())));
+    }
+
+    #[test]
+    fn same_start() {
+        // Files that start the same but end differently
+        let dir = std::env::temp_dir().join(format!("tree_magic-start-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut b = b"PAR1".to_vec();
+        b.resize(tree_magic::max_scan_extent() + 4096, 0);
+        std::fs::write(dir.join("zeros"), &b).unwrap();
+        b.extend_from_slice(b"PAR1");
+        std::fs::write(dir.join("parquet"), &b).unwrap();
+
+        let paths = vec![dir.join("zeros"), dir.join("parquet"), dir.join("zeros"), dir.join("parquet")];
+        let result = tree_magic::from_filepaths(paths);
+        let expected: Vec<_> = result.iter().map(|x| tree_magic::from_filepath(&x.0)).collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(result.into_iter().map(|x| x.1).collect::<Vec<_>>(), expected);
+        assert_eq!(expected[1], Some("application/vnd.apache.parquet".into()));
+        assert_ne!(expected[0], expected[1]);
+    }
+
+    #[test]
+    fn test_files() {
+        // Everything under tests/, many times over, detects as it does alone
+        let paths: Vec<PathBuf> = tree_magic::dir::classify_dir(Path::new("tests"), &Default::default())
+            .map(|x| x.path)
+            .collect();
+        let expected: Vec<_> = paths.iter().map(|x| tree_magic::from_filepath(x)).collect();
+        let result = tree_magic::from_filepaths(paths.iter().chain(paths.iter()).cloned());
+        for (x, y) in result.iter().zip(expected.iter().chain(expected.iter())) {
+            assert_eq!(&x.1, y, "{}", x.0.display());
+        }
+    }
+
+    /// Makes a small tree to walk, under a fresh temp dir
+    fn make_tree(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tree_magic-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(dir.join("sub/deeper")).unwrap();
+        std::fs::copy("tests/image/gif", dir.join("a.gif")).unwrap();
+        std::fs::copy("tests/image/png", dir.join("sub/b.png")).unwrap();
+        std::fs::write(dir.join("sub/deeper/c.txt"), b"Hello, world!\n").unwrap();
+        dir
+    }
+
+    /// Types found under a dir, by path relative to it
+    fn walk(dir: &Path, options: &tree_magic::dir::WalkOptions) -> Vec<(String, Option<String>)> {
+        let mut out: Vec<_> = tree_magic::dir::classify_dir(dir, options)
+            .map(|x| (
+                x.path.strip_prefix(dir).unwrap().to_string_lossy().replace('\\', "/"),
+                x.mimetype.map(String::from)
+            ))
+            .collect();
+        out.sort();
+        out
+    }
+
+    #[test]
+    fn classify_dir() {
+        use self::tree_magic::dir::WalkOptions;
+
+        let dir = make_tree("classify");
+        let all = walk(&dir, &WalkOptions::default());
+        let shallow = walk(&dir, &WalkOptions { max_depth: Some(2), ..Default::default() });
+        let entry = tree_magic::dir::classify_dir(&dir.join("a.gif"), &WalkOptions::default())
+            .next()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let owned = |x: &[(&str, &str)]| -> Vec<(String, Option<String>)> {
+            x.iter().map(|y| (y.0.to_string(), Some(y.1.to_string()))).collect()
+        };
+        assert_eq!(all, owned(&[
+            ("a.gif", "image/gif"),
+            ("sub/b.png", "image/png"),
+            ("sub/deeper/c.txt", "text/plain")
+        ]));
+        assert_eq!(shallow, owned(&[("a.gif", "image/gif"), ("sub/b.png", "image/png")]));
+        assert_eq!(entry.mimetype, Some("image/gif".into()));
+        assert_eq!(entry.len, std::fs::metadata("tests/image/gif").unwrap().len());
+        assert_eq!(entry.depth, 0);
+    }
+
+    #[test]
+    fn type_stats() {
+        use self::tree_magic::dir::{TypeStats, WalkOptions};
+
+        let dir = make_tree("stats");
+        std::fs::copy("tests/image/gif", dir.join("sub/deeper/d.gif")).unwrap();
+        let stats = tree_magic::dir::type_stats(&dir, &WalkOptions::default());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let len = |x| std::fs::metadata(x).unwrap().len();
+        let gif = len("tests/image/gif");
+        let png = len("tests/image/png");
+        let mut expected = vec![
+            TypeStats { mimetype: Some("image/gif".into()), files: 2, bytes: 2 * gif },
+            TypeStats { mimetype: Some("image/png".into()), files: 1, bytes: png },
+            TypeStats { mimetype: Some("text/plain".into()), files: 1, bytes: 14 }
+        ];
+        expected.sort_by_key(|x| std::cmp::Reverse(x.bytes));
+        assert_eq!(stats, expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn classify_dir_links() {
+        use self::tree_magic::dir::WalkOptions;
+
+        let dir = make_tree("links");
+        std::os::unix::fs::symlink(dir.join("sub"), dir.join("link")).unwrap();
+        let unfollowed = walk(&dir, &WalkOptions::default());
+        let followed = walk(&dir, &WalkOptions { follow_links: true, ..Default::default() });
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!unfollowed.iter().any(|x| x.0.starts_with("link")));
+        assert!(followed.contains(&("link/b.png".to_string(), Some("image/png".to_string()))));
+    }
+
+}
@@ -0,0 +1,58 @@
+#![cfg(feature="serde")]
+mod serde {
+
+    extern crate tree_magic;
+    extern crate serde_json;
+
+    use self::tree_magic::{Coverage, Detection, MagicRule, TypeInfo};
+
+    #[test]
+    fn detection() {
+        let detection = tree_magic::from_u8_detailed(include_bytes!("image/png"));
+        let json = serde_json::to_string(&detection).unwrap();
+        assert!(json.contains("\"mimetype\":\"image/png\""));
+        assert_eq!(serde_json::from_str::<Detection>(&json).unwrap(), detection);
+    }
+
+    #[test]
+    fn report() {
+        let report = tree_magic::from_u8_report(include_bytes!("image/png"));
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(serde_json::from_str::<tree_magic::DetectionReport>(&json).unwrap(), report);
+    }
+
+    #[test]
+    fn mime() {
+        let png = tree_magic::from_u8(include_bytes!("image/png"));
+        assert_eq!(serde_json::to_string(&png).unwrap(), "\"image/png\"");
+        assert_eq!(serde_json::from_str::<tree_magic::Mime>("\"image/png\"").unwrap(), png);
+        assert!(serde_json::from_str::<tree_magic::Mime>("\"image png\"").is_err());
+    }
+
+    #[test]
+    fn magic_rule() {
+        let rule = tree_magic::rules_for("image/png").remove(0);
+        let json = serde_json::to_string(&rule).unwrap();
+        assert_eq!(serde_json::from_str::<MagicRule>(&json).unwrap(), rule);
+    }
+
+    #[test]
+    fn type_info() {
+        let types: Vec<TypeInfo> = tree_magic::supported_types().collect();
+        let json = serde_json::to_string(&types).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<TypeInfo>>(&json).unwrap(), types);
+
+        // Checkers are only known by the names this build has
+        let unknown = json.replacen("\"fdo-magic\"", "\"nonexistent\"", 1);
+        assert!(serde_json::from_str::<Vec<TypeInfo>>(&unknown).is_err());
+    }
+
+    #[test]
+    fn coverage() {
+        assert_eq!(serde_json::to_string(&Coverage::GlobOnly).unwrap(), "\"GlobOnly\"");
+        let db = tree_magic::db_version().unwrap();
+        let json = serde_json::to_string(&db).unwrap();
+        assert_eq!(serde_json::from_str::<tree_magic::DbVersion>(&json).unwrap(), db);
+    }
+
+}
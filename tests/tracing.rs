@@ -0,0 +1,52 @@
+#![cfg(feature="tracing")]
+mod tracing {
+
+    extern crate tree_magic;
+    extern crate tracing;
+
+    use std::fmt::Write;
+    use std::sync::{Arc, Mutex};
+    use self::tracing::{Event, Metadata, Subscriber};
+    use self::tracing::field::{Field, Visit};
+    use self::tracing::span::{Attributes, Id, Record};
+
+    /// Keeps the fields of every event, formatted
+    struct Collect(Arc<Mutex<Vec<String>>>);
+
+    struct Fields(String);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            let _ = write!(self.0, "{}={:?} ", field.name(), value);
+        }
+    }
+
+    impl Subscriber for Collect {
+        fn enabled(&self, _: &Metadata) -> bool { true }
+        fn new_span(&self, _: &Attributes) -> Id { Id::from_u64(1) }
+        fn record(&self, _: &Id, _: &Record) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event) {
+            let mut fields = Fields(String::new());
+            event.record(&mut fields);
+            self.0.lock().unwrap().push(fields.0);
+        }
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn walk_events() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        self::tracing::subscriber::with_default(Collect(events.clone()), || {
+            tree_magic::from_u8(include_bytes!("image/png"))
+        });
+        let events = events.lock().unwrap();
+        assert!(events.iter().any(|x| {
+            x.contains("walk chose type") &&
+            x.contains("parent=\"application/octet-stream\"") &&
+            x.contains("mimetype=\"image/png\"")
+        }), "{:?}", *events);
+    }
+
+}
@@ -0,0 +1,82 @@
+#![cfg(feature="axum")]
+mod upload {
+    extern crate tree_magic;
+    extern crate axum_core;
+    extern crate http;
+
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+    use self::axum_core::body::Body;
+    use self::axum_core::extract::FromRequest;
+    use self::http::Request;
+    use self::tree_magic::upload::{AllowList, SniffedUpload, UploadRejection};
+
+    fn run<F: Future>(future: F) -> F::Output {
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut future = pin!(future);
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(x) => x,
+            Poll::Pending => panic!("Future should be ready")
+        }
+    }
+
+    /// Builds a form with a text field and a file that claims to be a PNG
+    fn request(allow: Option<AllowList>) -> Request<Body> {
+        let mut body = b"--XyZ\r\n\
+            Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+            Holiday\r\n\
+            --XyZ\r\n\
+            Content-Disposition: form-data; name=\"photo\"; filename=\"photo.png\"\r\n\
+            Content-Type: image/png\r\n\r\n".to_vec();
+        body.extend_from_slice(include_bytes!("image/gif"));
+        body.extend_from_slice(b"\r\n--XyZ--\r\n");
+
+        let mut req = Request::builder()
+            .header("Content-Type", "multipart/form-data; boundary=XyZ")
+            .body(Body::from(body))
+            .unwrap();
+        if let Some(x) = allow {
+            req.extensions_mut().insert(x);
+        }
+        req
+    }
+
+    #[test]
+    fn sniffs_parts() {
+        let SniffedUpload(parts) = run(SniffedUpload::from_request(request(None), &())).unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].name, Some("title".to_string()));
+        assert_eq!(parts[0].file_name, None);
+        assert_eq!(parts[0].detected, "text/plain");
+        assert_eq!(&parts[0].data[..], b"Holiday");
+        assert_eq!(parts[1].file_name, Some("photo.png".to_string()));
+        assert_eq!(parts[1].declared, Some("image/png".to_string()));
+        assert_eq!(parts[1].detected, "image/gif");
+        assert_eq!(&parts[1].data[..], &include_bytes!("image/gif")[..]);
+    }
+
+    #[test]
+    fn allow_list() {
+        let allow = AllowList::new(&["image/*"]);
+        assert!(run(SniffedUpload::from_request(request(Some(allow)), &())).is_ok());
+
+        let allow = AllowList::new(&["image/png", "application/pdf"]);
+        match run(SniffedUpload::from_request(request(Some(allow)), &())) {
+            Err(UploadRejection::NotAllowed{detected, ..}) => assert_eq!(detected, "image/gif"),
+            _ => panic!("GIF should not be allowed")
+        }
+    }
+
+    #[test]
+    fn not_multipart() {
+        let req = Request::builder()
+            .header("Content-Type", "application/json")
+            .body(Body::from("{}"))
+            .unwrap();
+        assert!(matches!(
+            run(SniffedUpload::from_request(req, &())),
+            Err(UploadRejection::NotMultipart)
+        ));
+    }
+}
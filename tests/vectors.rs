@@ -0,0 +1,40 @@
+#![cfg(feature="test-vectors")]
+mod vectors {
+
+    extern crate tree_magic;
+
+    use self::tree_magic::vectors;
+
+    #[test]
+    fn constants() {
+        assert_eq!(tree_magic::from_u8(vectors::PNG), "image/png");
+        assert_eq!(tree_magic::from_u8(vectors::JPEG), "image/jpeg");
+        assert_eq!(tree_magic::from_u8(vectors::GIF), "image/gif");
+        assert_eq!(tree_magic::from_u8(vectors::PDF), "application/pdf");
+        assert_eq!(tree_magic::from_u8(vectors::ZIP), "application/zip");
+        assert_eq!(tree_magic::from_u8(vectors::GZIP), "application/gzip");
+        assert_eq!(tree_magic::from_u8(vectors::TEXT), "text/plain");
+    }
+
+    #[test]
+    fn header() {
+        // Made from magic, on top of the parent's header
+        let svg = vectors::header("image/svg+xml").unwrap();
+        assert_eq!(tree_magic::from_u8(&svg), "image/svg+xml");
+        // Written by hand, for a type without magic
+        let mixed = vectors::header("multipart/mixed").unwrap();
+        assert_eq!(tree_magic::from_u8(&mixed), "multipart/mixed");
+        // Aliases give the header of the type they stand for
+        assert_eq!(vectors::header("image/x-bmp"), vectors::header("image/bmp"));
+        assert_eq!(vectors::header("application/x-nonexistent"), None);
+    }
+
+    #[test]
+    fn all() {
+        let all = vectors::all();
+        assert!(all.len() > 400, "{}", all.len());
+        for (mimetype, header) in &all {
+            assert_eq!(tree_magic::from_u8(header), mimetype.as_str());
+        }
+    }
+}
@@ -0,0 +1,87 @@
+mod whatwg {
+    extern crate tree_magic;
+    use self::tree_magic::SniffMode;
+
+    fn sniff(bytes: &[u8], supplied: Option<&str>, no_sniff: bool) -> String {
+        tree_magic::from_u8_mode(bytes, SniffMode::Whatwg{supplied, no_sniff})
+    }
+
+    /// Unknown type tests
+    #[test]
+    fn unknown_html() {
+        assert_eq!(sniff(b"  <HTML><body></body></html>", None, false), "text/html");
+        assert_eq!(sniff(b"<htmlx>", None, false), "text/plain");
+    }
+    #[test]
+    fn unknown_scriptable_nosniff() {
+        assert_eq!(sniff(b"<?xml version=\"1.0\"?><a/>", None, true), "text/plain");
+        assert_eq!(sniff(b"%PDF-1.7", Some("application/unknown"), true), "text/plain");
+    }
+    #[test]
+    fn unknown_image() {
+        assert_eq!(sniff(include_bytes!("image/png"), None, false), "image/png");
+        assert_eq!(sniff(include_bytes!("image/gif"), Some("*/*"), false), "image/gif");
+    }
+    #[test]
+    fn unknown_media() {
+        assert_eq!(sniff(include_bytes!("audio/ogg"), None, false), "application/ogg");
+        assert_eq!(sniff(include_bytes!("audio/wav"), None, false), "audio/wave");
+    }
+    #[test]
+    fn unknown_webm() {
+        let padded = b"\x1a\x45\xdf\xa3\x42\x82\x88\x00\x00webm\x00\x00";
+        assert_eq!(sniff(padded, None, false), "video/webm");
+        let second = b"\x1a\x45\xdf\xa3\x42\x82\x84matr\x42\x82\x84webm\x00\x00";
+        assert_eq!(sniff(second, None, false), "video/webm");
+        let other = b"\x1a\x45\xdf\xa3\x42\x82\x88matroska\x00\x00";
+        assert_ne!(sniff(other, None, false), "video/webm");
+    }
+    #[test]
+    fn unknown_archive() {
+        assert_eq!(sniff(include_bytes!("application/zip"), None, false), "application/zip");
+    }
+    #[test]
+    fn unknown_binary() {
+        assert_eq!(sniff(include_bytes!("application/x-7z-compressed"), None, false), "application/octet-stream");
+        assert_eq!(sniff(include_bytes!("text/plain"), None, false), "text/plain");
+    }
+
+    /// Supplied type tests
+    #[test]
+    fn supplied_nosniff() {
+        assert_eq!(sniff(include_bytes!("image/png"), Some("text/css"), true), "text/css");
+    }
+    #[test]
+    fn supplied_apache_bug() {
+        assert_eq!(sniff(include_bytes!("image/png"), Some("text/plain"), false), "image/png");
+        assert_eq!(sniff(b"<html>", Some("text/plain"), false), "text/plain");
+    }
+    #[test]
+    fn supplied_xml() {
+        assert_eq!(sniff(b"<html>", Some("image/svg+xml"), false), "image/svg+xml");
+    }
+    #[test]
+    fn supplied_feed() {
+        assert_eq!(sniff(b"<?xml version=\"1.0\"?>\n<rss version=\"2.0\">", Some("text/html"), false), "application/rss+xml");
+        assert_eq!(sniff(b"<!-- x --><feed xmlns=\"http://www.w3.org/2005/Atom\">", Some("text/html"), false), "application/atom+xml");
+        assert_eq!(sniff(b"<!DOCTYPE html>", Some("text/html; charset=utf-8"), false), "text/html; charset=utf-8");
+    }
+    #[test]
+    fn supplied_image() {
+        assert_eq!(sniff(include_bytes!("image/gif"), Some("image/png"), false), "image/gif");
+        assert_eq!(sniff(b"not an image", Some("image/png"), false), "image/png");
+    }
+    #[test]
+    fn supplied_media() {
+        assert_eq!(sniff(include_bytes!("audio/mpeg"), Some("video/mp4"), false), "audio/mpeg");
+    }
+
+    /// Mode selection tests
+    #[test]
+    fn freedesktop_mode() {
+        assert_eq!(
+            tree_magic::from_u8_mode(include_bytes!("image/gif"), SniffMode::Freedesktop),
+            "image/gif"
+        );
+    }
+}
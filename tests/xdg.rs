@@ -0,0 +1,66 @@
+#![cfg(all(feature="fs", feature="checker-fdo-magic"))]
+mod xdg {
+
+    extern crate tree_magic;
+
+    use self::tree_magic::xdg;
+    use std::path::Path;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    #[test]
+    fn from_filename() {
+        assert_eq!(xdg::from_filename("Makefile"), ["text/x-makefile"]);
+        assert_eq!(xdg::from_filename("archive.tar.gz"), ["application/x-compressed-tar"]);
+        // Case only matters where the database says so
+        assert_eq!(xdg::from_filename("a.C"), ["text/x-c++src"]);
+        assert_eq!(xdg::from_filename("a.c"), ["text/x-csrc"]);
+        assert_eq!(xdg::from_filename("IMAGE.PNG"), ["image/png"]);
+        assert!(xdg::from_filename("notes").is_empty());
+    }
+
+    #[test]
+    fn from_u8() {
+        assert_eq!(xdg::from_u8(PNG), "image/png");
+        assert_eq!(xdg::from_u8(b""), "application/x-zerosize");
+        assert_eq!(xdg::from_u8(b"\x01\x02\x03"), "application/octet-stream");
+    }
+
+    #[test]
+    fn guess() {
+        // One type for the name settles it, whatever the content
+        assert_eq!(xdg::guess(Some("fake.txt"), Some(PNG)), ("text/plain".into(), false));
+        assert_eq!(xdg::guess(Some("png_noext"), Some(PNG)), ("image/png".into(), false));
+        assert_eq!(xdg::guess(None, Some(b"hello\n")), ("text/plain".into(), false));
+        // Desktop entries can't hide behind a name
+        assert_eq!(xdg::guess(Some("sneaky"), Some(b"[Desktop Entry]\nType=Application\n")), ("text/plain".into(), false));
+        assert_eq!(xdg::guess(None, Some(b"[Desktop Entry]\nType=Application\n")), ("application/x-desktop".into(), false));
+        // Only the start is checked for text
+        let mut late = vec![b'a'; 128];
+        late.push(0);
+        assert_eq!(xdg::guess(None, Some(&late)), ("text/plain".into(), false));
+        assert_eq!(xdg::guess(None, Some(&late[1..])), ("application/octet-stream".into(), true));
+    }
+
+    #[test]
+    fn from_filepath() {
+        let dir = std::env::temp_dir().join(format!("tree_magic-xdg-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cases: &[(&str, &[u8], &str)] = &[
+            ("empty", b"", "text/plain"),
+            ("blob", b"\x01\x02\x03", "application/octet-stream"),
+            ("notes", b"hello\n", "text/plain"),
+            ("img.png", include_bytes!("image/gif"), "image/png"),
+            ("lib.so.1", b"\x7fELF", "application/x-troff-man")
+        ];
+        for &(name, bytes, expected) in cases {
+            std::fs::write(dir.join(name), bytes).unwrap();
+            assert_eq!(xdg::from_filepath(&dir.join(name)), Some(expected.into()), "{}", name);
+        }
+        assert_eq!(xdg::from_filepath(&dir), Some("inode/directory".into()));
+        assert_eq!(xdg::from_filepath(&dir.join("nonexistent")), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(xdg::from_filepath(Path::new("tests/image/gif")), Some("image/gif".into()));
+    }
+}
@@ -0,0 +1,212 @@
+//! Picks the files to embed, and turns the magic file into static tables so
+//! it doesn't have to be parsed at runtime.
+//!
+//! Setting `TREE_MAGIC_DB` to a magic file at build time embeds that file in
+//! place of the bundled one. If `aliases`, `subclasses` and `version` files
+//! sit next to it, as they do in `/usr/share/mime`, those are embedded too.
+
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const HEADER: &[u8] = b"MIME-Magic\0\n";
+
+/// Passes a path on to `include_bytes!`/`include_str!` through an env var
+fn export(name: &str, path: &Path) {
+    println!("cargo:rerun-if-changed={}", path.display());
+    println!("cargo:rustc-env={}={}", name, path.display());
+}
+
+/// Singular magic rule, as in `tree_magic_db::Rule`
+struct Rule<'a> {
+    indent_level: u32,
+    start_off: u32,
+    val: &'a [u8],
+    mask: Option<&'a [u8]>,
+    word_len: u32,
+    region_len: u32
+}
+
+/// Reads the decimal number at the cursor, if there is one
+fn read_number(b: &[u8], pos: &mut usize) -> Option<u32> {
+    let start = *pos;
+    let mut out: u32 = 0;
+    while let Some(x) = b.get(*pos).filter(|x| x.is_ascii_digit()) {
+        out = out.saturating_mul(10).saturating_add((x - b'0') as u32);
+        *pos += 1;
+    }
+    if *pos == start { None } else { Some(out) }
+}
+
+/// Takes the given number of bytes at the cursor
+fn take<'a>(b: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8], String> {
+    match b.get(*pos..*pos + len) {
+        Some(x) => {
+            *pos += len;
+            Ok(x)
+        },
+        None => Err(format!("unexpected end of magic file at byte {}", *pos))
+    }
+}
+
+/// Consumes the given byte at the cursor, if it's there
+fn eat(b: &[u8], pos: &mut usize, c: u8) -> bool {
+    if b.get(*pos) == Some(&c) {
+        *pos += 1;
+        true
+    } else {
+        false
+    }
+}
+
+/// Fails unless the given byte is at the cursor
+fn expect(b: &[u8], pos: &mut usize, c: u8) -> Result<(), String> {
+    match eat(b, pos, c) {
+        true => Ok(()),
+        false => Err(format!("expected {:?} at byte {} of magic file", c as char, *pos))
+    }
+}
+
+/// Section header, giving the priority and MIME type of the following rules
+/// Format: [priority:mime]
+fn mime<'a>(b: &'a [u8], pos: &mut usize) -> Result<(u32, &'a str), String> {
+    expect(b, pos, b'[')?;
+    let priority = read_number(b, pos).unwrap_or(50);
+    expect(b, pos, b':')?;
+    let len = match b[*pos..].iter().position(|&x| x == b']') {
+        Some(x) => x,
+        None => return Err(format!("unterminated section at byte {} of magic file", *pos))
+    };
+    let out = std::str::from_utf8(&b[*pos..*pos + len]).map_err(|e| e.to_string())?;
+    *pos += len;
+    expect(b, pos, b']')?;
+    expect(b, pos, b'\n')?;
+    Ok((priority, out))
+}
+
+/// Singular magic rule
+/// Format: [indent]>start-offset=value-length value [&mask] [~word-size] [+range-length]
+fn magic_rule<'a>(b: &'a [u8], pos: &mut usize) -> Result<Rule<'a>, String> {
+    let indent_level = read_number(b, pos).unwrap_or(0);
+    expect(b, pos, b'>')?;
+    let start_off = read_number(b, pos).unwrap_or(0);
+    expect(b, pos, b'=')?;
+    let len = take(b, pos, 2)?;
+    let val_len = (len[0] as usize) << 8 | len[1] as usize;
+    let val = take(b, pos, val_len)?;
+    let mask = match eat(b, pos, b'&') {
+        true => Some(take(b, pos, val_len)?),
+        false => None
+    };
+    let word_len = match eat(b, pos, b'~') {
+        true => read_number(b, pos).unwrap_or(1),
+        false => 1
+    };
+    let region_len = match eat(b, pos, b'+') {
+        true => read_number(b, pos).unwrap_or(0),
+        false => 0
+    };
+    expect(b, pos, b'\n')?;
+
+    Ok(Rule{indent_level, start_off, val, mask, word_len, region_len})
+}
+
+/// Section of a magic file: MIME type, priority and rules
+type Section<'a> = (&'a str, u32, Vec<Rule<'a>>);
+
+/// Parses a magic file into sections
+fn parse(b: &[u8]) -> Result<Vec<Section<'_>>, String> {
+    if !b.starts_with(HEADER) {
+        return Err("not a shared-mime-info magic file".to_string());
+    }
+
+    let mut pos = HEADER.len();
+    let mut out = Vec::new();
+    while pos < b.len() {
+        let (priority, mimetype) = mime(b, &mut pos)?;
+        let mut rules = Vec::new();
+        while pos < b.len() && b[pos] != b'[' {
+            rules.push(magic_rule(b, &mut pos)?);
+        }
+        out.push((mimetype, priority, rules));
+    }
+    Ok(out)
+}
+
+/// Writes bytes as a byte string literal
+fn byte_literal(out: &mut String, b: &[u8]) {
+    out.push_str("b\"");
+    for &x in b {
+        write!(out, "\\x{:02x}", x).unwrap();
+    }
+    out.push('"');
+}
+
+/// Writes the rules as a Rust expression for `tree_magic_db::RULES`
+fn codegen(entries: &[Section<'_>]) -> String {
+    let mut out = String::from("&[\n");
+    for (mimetype, priority, rules) in entries {
+        writeln!(out, "    ({:?}, {}, &[", mimetype, priority).unwrap();
+        for rule in rules {
+            write!(
+                out, "        Rule {{ indent_level: {}, start_off: {}, val: ",
+                rule.indent_level, rule.start_off
+            ).unwrap();
+            byte_literal(&mut out, rule.val);
+            out.push_str(", mask: ");
+            match rule.mask {
+                Some(x) => {
+                    out.push_str("Some(");
+                    byte_literal(&mut out, x);
+                    out.push(')');
+                },
+                None => out.push_str("None")
+            }
+            writeln!(out, ", word_len: {}, region_len: {} }},", rule.word_len, rule.region_len).unwrap();
+        }
+        out.push_str("    ]),\n");
+    }
+    out.push(']');
+    out
+}
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=TREE_MAGIC_DB");
+
+    let bundled = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("data");
+    let magic = match env::var_os("TREE_MAGIC_DB") {
+        Some(x) => PathBuf::from(x),
+        None => bundled.join("magic")
+    };
+
+    let bytes = match fs::read(&magic) {
+        Ok(x) => x,
+        Err(e) => panic!("TREE_MAGIC_DB: can't read {}: {}", magic.display(), e)
+    };
+    let entries = match parse(&bytes) {
+        Ok(x) => x,
+        Err(e) => panic!("TREE_MAGIC_DB: {}: {}", magic.display(), e)
+    };
+    export("TREE_MAGIC_DB_MAGIC", &magic);
+
+    let rules = PathBuf::from(env::var("OUT_DIR").unwrap()).join("rules.rs");
+    fs::write(&rules, codegen(&entries)).unwrap();
+
+    let dir = magic.parent().unwrap_or(&bundled);
+    for (name, file) in [("TREE_MAGIC_DB_ALIASES", "aliases"), ("TREE_MAGIC_DB_SUBCLASSES", "subclasses")] {
+        let path = dir.join(file);
+        if path.is_file() {
+            export(name, &path);
+        } else {
+            export(name, &bundled.join(file));
+        }
+    }
+
+    // The version is optional, and goes in as text rather than a path
+    let version = dir.join("version");
+    if let Ok(x) = fs::read_to_string(&version) {
+        println!("cargo:rerun-if-changed={}", version.display());
+        println!("cargo:rustc-env=TREE_MAGIC_DB_VERSION={}", x.trim());
+    }
+}
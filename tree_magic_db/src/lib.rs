@@ -0,0 +1,57 @@
+//! The freedesktop.org [shared-mime-info](https://gitlab.freedesktop.org/xdg/shared-mime-info)
+//! database that `tree_magic` falls back on when it can't read the one
+//! installed on the system.
+//!
+//! This is kept out of `tree_magic` itself so it can be packaged, and
+//! licensed, separately from the matching engine. To have `tree_magic` use
+//! another database, replace this crate with one exposing the same items:
+//!
+//! ```toml
+//! [patch.crates-io]
+//! tree_magic_db = { path = "my_magic_db" }
+//! ```
+//!
+//! Or, to embed another magic file without touching the source tree, point
+//! `TREE_MAGIC_DB` at it when building:
+//!
+//! ```sh
+//! TREE_MAGIC_DB=/usr/share/mime/magic cargo build
+//! ```
+//!
+//! `aliases`, `subclasses` and `version` files in the same directory are
+//! embedded along with it; otherwise the bundled lists are kept.
+#![no_std]
+
+/// Magic rules, in the binary `magic` format that `update-mime-database` writes
+pub static MAGIC: &[u8] = include_bytes!(env!("TREE_MAGIC_DB_MAGIC"));
+
+/// Singular magic rule, decoded from `MAGIC` at build time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule {
+    /// Nesting depth; a rule only applies if the rule it's nested under does
+    pub indent_level: u32,
+    /// Offset to start looking for the value at
+    pub start_off: u32,
+    /// Bytes to compare against
+    pub val: &'static [u8],
+    /// Bits of the value to compare, if not all of them
+    pub mask: Option<&'static [u8]>,
+    /// Word size, for byte swapping
+    pub word_len: u32,
+    /// How far past the offset the value may start
+    pub region_len: u32
+}
+
+/// Magic rules from `MAGIC`, already parsed, so they can be used without
+/// reading the magic file at runtime. Each section gives its MIME type, its
+/// priority, and its rules in file order. A type may have several sections.
+pub static RULES: &[(&str, u32, &[Rule])] = include!(concat!(env!("OUT_DIR"), "/rules.rs"));
+
+/// Alias list, one `alias canonical` pair per line
+pub static ALIASES: &str = include_str!(env!("TREE_MAGIC_DB_ALIASES"));
+
+/// Subclass list, one `child parent` pair per line
+pub static SUBCLASSES: &str = include_str!(env!("TREE_MAGIC_DB_SUBCLASSES"));
+
+/// Version of shared-mime-info that wrote the database, if known
+pub static VERSION: Option<&str> = option_env!("TREE_MAGIC_DB_VERSION");
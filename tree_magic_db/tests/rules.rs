@@ -0,0 +1,11 @@
+extern crate tree_magic_db;
+
+#[test]
+fn rules_match_magic() {
+    let png = tree_magic_db::RULES.iter()
+        .find(|x| x.0 == "image/png")
+        .expect("image/png has rules");
+    assert_eq!(png.1, 50);
+    assert_eq!(png.2[0].val, b"\x89PNG");
+    assert_eq!(png.2[0].start_off, 0);
+}